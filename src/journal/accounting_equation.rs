@@ -0,0 +1,603 @@
+use crate::journal::balance_sheet::BalanceSheetSnapshot;
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+///
+/// Assets = Liabilities + Owner's Equity
+///
+pub const BASIC_EQUATION: &str = "Assets = Liabilities + Owner's Equity";
+
+///
+/// Assets = Liabilities + Owner's Equity - Dividends + Revenue - Expenses
+///
+pub const EXPANDED_EQUATION: &str =
+    "Assets = Liabilities + Owner's Equity - Dividends + Revenue - Expenses";
+
+///
+/// The DEALER form: Dividends + Expenses + Assets = Liabilities + Equity + Revenue
+///
+pub const DEALER_EQUATION: &str =
+    "Dividends + Expenses + Assets = Liabilities + Owner's Equity + Revenue";
+
+///
+/// Tolerance used when none is given, half of the smallest currency unit
+///
+pub const DEFAULT_TOLERANCE: f64 = 0.005;
+
+///
+/// Sign of a term of an `AccountingEquation`
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sign {
+    Plus,
+    Minus,
+}
+
+impl Sign {
+    fn apply(&self, amount: f64) -> f64 {
+        match self {
+            Sign::Plus => amount,
+            Sign::Minus => -amount,
+        }
+    }
+}
+
+///
+/// Side of the `=` a term is on
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EquationSide {
+    Lhs,
+    Rhs,
+}
+
+///
+/// A signed reference to an account, by name or by path (e.g. `"Assets:Current Assets"`)
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    sign: Sign,
+    account: String,
+}
+
+impl Term {
+    pub fn new(sign: Sign, account: &str) -> Self {
+        Term {
+            sign,
+            account: account.to_owned(),
+        }
+    }
+
+    pub fn sign(&self) -> Sign {
+        self.sign
+    }
+
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+}
+
+///
+/// Errors raised while parsing or checking an `AccountingEquation`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum EquationError {
+    ///
+    /// The expression could not be parsed
+    ///
+    Parse(String),
+    ///
+    /// A term refers to an account that is not on the balance sheet
+    ///
+    UnknownAccount(String),
+    ///
+    /// Both sides differ by more than the tolerance
+    ///
+    Unbalanced(EquationCheck),
+}
+
+impl Display for EquationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquationError::Parse(message) => write!(f, "Invalid equation: {}", message),
+            EquationError::UnknownAccount(account) => write!(
+                f,
+                "Account name: {:?} has no associated account node.",
+                account
+            ),
+            EquationError::Unbalanced(check) => write!(f, "{}", check),
+        }
+    }
+}
+
+impl Error for EquationError {}
+
+///
+/// `AccountingEquation` such as `Assets = Liabilities + Owner's Equity`.
+///
+/// Terms are account names or account paths joined with `+` or `-` (`−` is accepted too).
+/// An operator has to be surrounded by whitespace, so names such as `FREIGHT-IN` are read as
+/// a single account. Names can also be wrapped in double quotes.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountingEquation {
+    lhs: Vec<Term>,
+    rhs: Vec<Term>,
+}
+
+impl AccountingEquation {
+    pub fn new(lhs: Vec<Term>, rhs: Vec<Term>) -> Self {
+        AccountingEquation { lhs, rhs }
+    }
+
+    ///
+    /// Parse an equation such as `Assets = Liabilities + Owner's Equity`
+    ///
+    pub fn parse(expression: &str) -> Result<Self, EquationError> {
+        // Split on the '=' signs outside quoted account names
+        let mut quoted = false;
+        let sides: Vec<&str> = expression
+            .split(|c| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                c == '=' && !quoted
+            })
+            .collect();
+
+        if quoted {
+            return Err(EquationError::Parse(format!(
+                "unterminated quote in {:?}",
+                expression
+            )));
+        }
+
+        if sides.len() != 2 {
+            return Err(EquationError::Parse(format!(
+                "expected exactly one '=' in {:?}",
+                expression
+            )));
+        }
+
+        Ok(AccountingEquation {
+            lhs: Self::parse_side(sides[0])?,
+            rhs: Self::parse_side(sides[1])?,
+        })
+    }
+
+    ///
+    /// Parse one side of the equation into its terms
+    ///
+    fn parse_side(side: &str) -> Result<Vec<Term>, EquationError> {
+        let mut terms = Vec::new();
+        let mut sign = Sign::Plus;
+        let mut name = String::new();
+        let mut expecting_term = true;
+        let mut sign_pending = false;
+        let mut chars = side.trim().chars().peekable();
+        let mut previous_is_space = true;
+
+        while let Some(c) = chars.next() {
+            let next_is_space = chars.peek().is_none_or(|next| next.is_whitespace());
+
+            match c {
+                '"' => {
+                    if !expecting_term {
+                        return Err(EquationError::Parse(format!(
+                            "unexpected quote after {:?}",
+                            name.trim()
+                        )));
+                    }
+
+                    name = chars.by_ref().take_while(|c| *c != '"').collect();
+                    expecting_term = false;
+                    sign_pending = false;
+                    previous_is_space = false;
+                    continue;
+                }
+                '+' | '-' | '−' if previous_is_space && next_is_space => {
+                    if sign_pending {
+                        return Err(EquationError::Parse(format!(
+                            "two operators in a row in {:?}",
+                            side.trim()
+                        )));
+                    }
+
+                    // Close the term before the operator, unless this is a leading sign
+                    if !expecting_term {
+                        terms.push(Term::new(sign, name.trim()));
+                        name.clear();
+                    }
+
+                    sign = if c == '+' { Sign::Plus } else { Sign::Minus };
+                    sign_pending = true;
+                    expecting_term = true;
+                }
+                _ => {
+                    name.push(c);
+                    if !c.is_whitespace() {
+                        expecting_term = false;
+                        sign_pending = false;
+                    }
+                }
+            }
+
+            previous_is_space = c.is_whitespace();
+        }
+
+        if expecting_term || name.trim().is_empty() {
+            return Err(EquationError::Parse(format!(
+                "missing account name in {:?}",
+                side.trim()
+            )));
+        }
+
+        terms.push(Term::new(sign, name.trim()));
+
+        Ok(terms)
+    }
+
+    pub fn lhs(&self) -> &Vec<Term> {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Vec<Term> {
+        &self.rhs
+    }
+
+    ///
+    /// Evaluate every term against a built balance sheet
+    ///
+    pub fn evaluate(
+        &self,
+        snapshot: &BalanceSheetSnapshot,
+        tolerance: f64,
+    ) -> Result<EquationCheck, EquationError> {
+        let mut terms = Vec::new();
+
+        for (side, side_terms) in [
+            (EquationSide::Lhs, &self.lhs),
+            (EquationSide::Rhs, &self.rhs),
+        ] {
+            for term in side_terms.iter() {
+                let node = snapshot
+                    .lookup(term.account())
+                    .ok_or_else(|| EquationError::UnknownAccount(term.account().to_owned()))?;
+
+                terms.push(TermValue {
+                    side,
+                    term: term.clone(),
+                    amount: node.amount(),
+                });
+            }
+        }
+
+        Ok(EquationCheck { terms, tolerance })
+    }
+
+    ///
+    /// Evaluate the equation and fail with a breakdown of every term if it does not hold
+    ///
+    pub fn check(
+        &self,
+        snapshot: &BalanceSheetSnapshot,
+        tolerance: f64,
+    ) -> Result<EquationCheck, EquationError> {
+        let check = self.evaluate(snapshot, tolerance)?;
+
+        if check.is_balanced() {
+            Ok(check)
+        } else {
+            Err(EquationError::Unbalanced(check))
+        }
+    }
+}
+
+impl FromStr for AccountingEquation {
+    type Err = EquationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AccountingEquation::parse(s)
+    }
+}
+
+///
+/// Check whether `account` must be quoted to be parsed back as a single account name,
+/// e.g. `"FREIGHT - IN"`
+///
+fn needs_quotes(account: &str) -> bool {
+    account.contains('=')
+        || AccountingEquation::parse_side(account).ok()
+            != Some(vec![Term::new(Sign::Plus, account)])
+}
+
+impl Display for AccountingEquation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let side = |terms: &Vec<Term>| {
+            let mut text = String::new();
+            for (index, term) in terms.iter().enumerate() {
+                match (index, term.sign()) {
+                    (0, Sign::Plus) => {}
+                    (0, Sign::Minus) => text.push_str("- "),
+                    (_, Sign::Plus) => text.push_str(" + "),
+                    (_, Sign::Minus) => text.push_str(" - "),
+                }
+                if needs_quotes(term.account()) {
+                    text.push_str(&format!("\"{}\"", term.account()));
+                } else {
+                    text.push_str(term.account());
+                }
+            }
+            text
+        };
+
+        write!(f, "{} = {}", side(&self.lhs), side(&self.rhs))
+    }
+}
+
+///
+/// The value a term evaluated to
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TermValue {
+    side: EquationSide,
+    term: Term,
+    amount: f64,
+}
+
+impl TermValue {
+    pub fn side(&self) -> EquationSide {
+        self.side
+    }
+
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+
+    ///
+    /// Get the account's amount, before the term's sign is applied
+    ///
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    ///
+    /// Get the amount with the term's sign applied
+    ///
+    pub fn signed_amount(&self) -> f64 {
+        self.term.sign().apply(self.amount)
+    }
+}
+
+///
+/// Result of evaluating an `AccountingEquation` against a balance sheet
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct EquationCheck {
+    terms: Vec<TermValue>,
+    tolerance: f64,
+}
+
+impl EquationCheck {
+    pub fn terms(&self) -> &Vec<TermValue> {
+        &self.terms
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    fn side_total(&self, side: EquationSide) -> f64 {
+        self.terms
+            .iter()
+            .filter(|term| term.side() == side)
+            .map(|term| term.signed_amount())
+            .sum()
+    }
+
+    pub fn lhs_total(&self) -> f64 {
+        self.side_total(EquationSide::Lhs)
+    }
+
+    pub fn rhs_total(&self) -> f64 {
+        self.side_total(EquationSide::Rhs)
+    }
+
+    ///
+    /// Get `lhs - rhs`
+    ///
+    pub fn difference(&self) -> f64 {
+        self.lhs_total() - self.rhs_total()
+    }
+
+    ///
+    /// Check whether both sides are equal within the tolerance
+    ///
+    pub fn is_balanced(&self) -> bool {
+        self.difference().abs() <= self.tolerance
+    }
+}
+
+impl Display for EquationCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (side, label) in [(EquationSide::Lhs, "LHS"), (EquationSide::Rhs, "RHS")] {
+            writeln!(f, "{}:", label)?;
+            for term in self.terms.iter().filter(|term| term.side() == side) {
+                let sign = match term.term().sign() {
+                    Sign::Plus => '+',
+                    Sign::Minus => '-',
+                };
+                writeln!(
+                    f,
+                    "  {} {:<40}{:>16.2}",
+                    sign,
+                    term.term().account(),
+                    term.amount()
+                )?;
+            }
+        }
+
+        writeln!(f, "LHS total: {:.2}", self.lhs_total())?;
+        writeln!(f, "RHS total: {:.2}", self.rhs_total())?;
+        write!(
+            f,
+            "Difference: {:.2} (tolerance {})",
+            self.difference(),
+            self.tolerance
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AccountingEquation, EquationError, Sign, Term, DEFAULT_TOLERANCE};
+    use crate::journal::balance_sheet::BalanceSheet;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    #[test]
+    fn test_parse_equation() {
+        let equation = AccountingEquation::parse(
+            "Assets = Liabilities + Owner's Equity − Dividends + \"FREIGHT - IN\" - COST-OF-SALES",
+        )
+        .unwrap();
+
+        assert_eq!(equation.lhs(), &vec![Term::new(Sign::Plus, "Assets")]);
+        assert_eq!(
+            equation.rhs(),
+            &vec![
+                Term::new(Sign::Plus, "Liabilities"),
+                Term::new(Sign::Plus, "Owner's Equity"),
+                Term::new(Sign::Minus, "Dividends"),
+                Term::new(Sign::Plus, "FREIGHT - IN"),
+                Term::new(Sign::Minus, "COST-OF-SALES"),
+            ]
+        );
+
+        let leading = AccountingEquation::parse("- Assets:Current Assets = Equity").unwrap();
+        assert_eq!(
+            leading.lhs(),
+            &vec![Term::new(Sign::Minus, "Assets:Current Assets")]
+        );
+        assert_eq!(leading.to_string(), "- Assets:Current Assets = Equity");
+        assert_eq!(
+            equation.to_string(),
+            "Assets = Liabilities + Owner's Equity - Dividends + \"FREIGHT - IN\" - COST-OF-SALES"
+        );
+
+        assert!(AccountingEquation::parse("Assets Liabilities").is_err());
+        assert!(AccountingEquation::parse("Assets = Liabilities +").is_err());
+        assert!(AccountingEquation::parse("Assets = + + Liabilities").is_err());
+    }
+
+    #[test]
+    fn test_unterminated_quote_is_rejected() {
+        for expression in [
+            "Assets = Liabilities + \"FREIGHT - IN",
+            "\"Assets = Liabilities",
+            "Assets = \"Owner's\" Equity\"",
+        ] {
+            assert_eq!(
+                AccountingEquation::parse(expression).unwrap_err(),
+                EquationError::Parse(format!("unterminated quote in {:?}", expression))
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_equation() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let stock = chart.account("Common Stock");
+        let sales = chart.account("Sales Revenue");
+        let rent = chart.account("Rent Expense");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 2),
+            "Share capital",
+            &[
+                (&cash, 1_000.0, EntryType::Debit),
+                (&stock, 1_000.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 2, 2),
+            "Cash sales",
+            &[
+                (&cash, 300.1, EntryType::Debit),
+                (&sales, 300.1, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 3, 2),
+            "Rent",
+            &[
+                (&rent, 100.2, EntryType::Debit),
+                (&cash, 100.2, EntryType::Credit),
+            ],
+        ));
+
+        let snapshot = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger,
+        )
+        .build()
+        .unwrap();
+
+        let expanded: AccountingEquation =
+            "Assets:Current Assets = Liabilities + Equity + Revenue - Expenses"
+                .parse()
+                .unwrap();
+        let check = expanded.check(&snapshot, DEFAULT_TOLERANCE).unwrap();
+        assert!((check.lhs_total() - 1_199.9).abs() < DEFAULT_TOLERANCE);
+
+        let basic = AccountingEquation::parse("Assets = Liabilities + Equity").unwrap();
+        match basic.check(&snapshot, DEFAULT_TOLERANCE) {
+            Err(EquationError::Unbalanced(check)) => {
+                assert!((check.difference() - 199.9).abs() < DEFAULT_TOLERANCE);
+                assert_eq!(check.terms().len(), 3);
+                assert!(check.to_string().contains("Difference: 199.90"));
+            }
+            other => panic!("Expected an unbalanced equation, got {:?}", other),
+        }
+
+        assert_eq!(
+            AccountingEquation::parse("Assets = Goodwill")
+                .unwrap()
+                .evaluate(&snapshot, DEFAULT_TOLERANCE)
+                .unwrap_err(),
+            EquationError::UnknownAccount("Goodwill".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_display_round_trips() {
+        let equation = AccountingEquation::new(
+            vec![
+                Term::new(Sign::Plus, "Assets"),
+                Term::new(Sign::Minus, "Contra - Assets"),
+            ],
+            vec![
+                Term::new(Sign::Plus, "Owner's Equity"),
+                Term::new(Sign::Plus, "-"),
+                Term::new(Sign::Minus, "Gain = Loss"),
+                Term::new(Sign::Plus, "+ Padded"),
+            ],
+        );
+
+        let text = equation.to_string();
+        assert_eq!(
+            text,
+            "Assets - \"Contra - Assets\" = Owner's Equity + \"-\" - \"Gain = Loss\" + \"+ Padded\""
+        );
+        assert_eq!(AccountingEquation::parse(&text).unwrap(), equation);
+    }
+}
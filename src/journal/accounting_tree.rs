@@ -1,4 +1,5 @@
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use crate::journal::audit::{AuditAction, AuditSubject, Auditor};
+use std::{cell::RefCell, collections::HashMap, fmt::Debug, rc::Rc};
 
 #[derive(Debug, PartialEq)]
 pub enum ActionType {
@@ -199,7 +200,7 @@ pub struct RootNode {
 
 impl AccountTreeNode for RootNode {
     fn level(&self) -> usize {
-        return self.level;
+        self.level
     }
 
     fn set_level(&mut self, level: usize) {
@@ -207,7 +208,7 @@ impl AccountTreeNode for RootNode {
     }
 
     fn name(&self) -> &str {
-        return self.name.as_str();
+        self.name.as_str()
     }
 
     fn set_name(&mut self, name: &str) {
@@ -215,7 +216,7 @@ impl AccountTreeNode for RootNode {
     }
 
     fn account_type(&self) -> &Option<Rc<PrimaryAccountType>> {
-        return &None;
+        &None
     }
 
     // Used to set a child node's parent's
@@ -225,7 +226,7 @@ impl AccountTreeNode for RootNode {
 
     // Use to get the child node's parentOption
     fn parent(&self) -> &Option<Rc<RefCell<dyn ParentNode>>> {
-        return &self.parent;
+        &self.parent
     }
 
     fn amount(&self) -> f64 {
@@ -249,7 +250,13 @@ impl ParentNodeT for RootNode {
     /// Get the children for this `AccountTagNode`
     ///
     fn children(&self) -> &Vec<ParentNodeRef> {
-        return &self.children;
+        &self.children
+    }
+}
+
+impl Default for RootNode {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -287,7 +294,7 @@ impl Debug for AccountTagNode {
 
 impl AccountTreeNode for AccountTagNode {
     fn level(&self) -> usize {
-        return self.level;
+        self.level
     }
 
     fn set_level(&mut self, level: usize) {
@@ -298,7 +305,7 @@ impl AccountTreeNode for AccountTagNode {
     /// Get the name of the `AccountTagNode`
     ///
     fn name(&self) -> &str {
-        return self.name.as_str();
+        self.name.as_str()
     }
 
     ///
@@ -312,7 +319,7 @@ impl AccountTreeNode for AccountTagNode {
     /// Get the `PrimaryAccountType` of this tag node
     ///
     fn account_type(&self) -> &Option<Rc<PrimaryAccountType>> {
-        return &self.account_type;
+        &self.account_type
     }
 
     // Used to set a child node's parent's
@@ -322,11 +329,11 @@ impl AccountTreeNode for AccountTagNode {
 
     // Use to get the child node's parentOption
     fn parent(&self) -> &Option<Rc<RefCell<dyn ParentNode>>> {
-        return &self.parent;
+        &self.parent
     }
 
     fn amount(&self) -> f64 {
-        return self.amount;
+        self.amount
     }
 
     fn set_amount(&mut self, amount: f64) {
@@ -349,7 +356,7 @@ impl ParentNodeT for AccountTagNode {
     /// Get the children for this `AccountTagNode`
     ///
     fn children(&self) -> &Vec<ParentNodeRef> {
-        return &self.children;
+        &self.children
     }
 }
 
@@ -378,17 +385,12 @@ impl AccountTagNode {
                     let parent_ref_borrowed = _parent_ref.as_ref().borrow();
                     // Get the parent
                     let parent = parent_ref_borrowed.parent().clone();
-                    let parent_to_return: ParentNodeRef;
                     match parent {
                         None => {
                             panic!("");
                         }
-                        Some(parent_unwrapped) => {
-                            parent_to_return = parent_unwrapped.clone();
-                        }
+                        Some(parent_unwrapped) => parent_unwrapped.clone(),
                     }
-
-                    parent_to_return
                 }
             }
 
@@ -420,7 +422,7 @@ impl AccountTagNode {
             }
         }
 
-        return account_tag_node;
+        account_tag_node
     }
 }
 
@@ -431,6 +433,7 @@ impl AccountTagNode {
 pub struct AccountNode {
     level: usize,
     name: String,
+    code: String,
     amount: f64,
     parent: Option<ParentNodeRef>,
     children: Vec<ParentNodeRef>,
@@ -439,13 +442,17 @@ pub struct AccountNode {
 
 impl Debug for AccountNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        write!(
+            f,
+            "AccountNode {{ level: {}, name: {}, account_type: {:?}, amount: {:?} }}",
+            self.level, self.name, self.account_type, self.amount
+        )
     }
 }
 
 impl AccountTreeNode for AccountNode {
     fn level(&self) -> usize {
-        return self.level;
+        self.level
     }
 
     fn set_level(&mut self, level: usize) {
@@ -456,7 +463,7 @@ impl AccountTreeNode for AccountNode {
     /// Used to get the name of the `AccountNode`
     ///
     fn name(&self) -> &str {
-        return &self.name;
+        &self.name
     }
 
     ///
@@ -477,14 +484,14 @@ impl AccountTreeNode for AccountNode {
     ///  Use to get the child node's parentOption
     ///
     fn parent(&self) -> &Option<ParentNodeRef> {
-        return &self.parent;
+        &self.parent
     }
 
     ///
     /// Get the `PrimaryAccountType` of this tag node
     ///
     fn account_type(&self) -> &Option<Rc<PrimaryAccountType>> {
-        return &self.account_type;
+        &self.account_type
     }
 
     ///
@@ -498,7 +505,7 @@ impl AccountTreeNode for AccountNode {
     /// Function used to get the `subtotal amount` for an `AccountTagNode`
     ///
     fn amount(&self) -> f64 {
-        return self.amount;
+        self.amount
     }
 }
 
@@ -508,7 +515,7 @@ impl ParentNodeT for AccountNode {
     }
 
     fn children(&self) -> &Vec<ParentNodeRef> {
-        return &self.children;
+        &self.children
     }
 }
 
@@ -525,6 +532,7 @@ impl AccountNode {
         AccountNode {
             level,
             name: name.to_owned(),
+            code: name.to_owned(),
             amount: 0f64,
             parent,
             children: Vec::new(),
@@ -532,6 +540,14 @@ impl AccountNode {
         }
     }
 
+    ///
+    /// Get the code identifying the account: its name when it was created, kept when the
+    /// account is renamed
+    ///
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
     ///
     /// Used to set the amount in the `AccountNode`
     ///
@@ -543,16 +559,22 @@ impl AccountNode {
     /// Used to get the amount in the `AccountNode`
     ///
     pub fn amount(&self) -> f64 {
-        return self.amount;
+        self.amount
     }
 }
 
 ///
 /// `AccountTree` that holds the entire structure of the relation between different accounts.
-/// It contains a single reference to the `RootNode` of the particular account structure
+/// It contains a single reference to the `RootNode` of the particular account structure.
+/// Cloning an `AccountTree` shares the underlying nodes.
+///
+/// Changes made through the tree are recorded by its `Auditor`, if any; changes made
+/// directly on the nodes are not.
 ///
+#[derive(Clone)]
 pub struct AccountTree {
     root: RootNodeRef,
+    auditor: Option<Auditor>,
 }
 
 impl AccountTree {
@@ -561,7 +583,43 @@ impl AccountTree {
     /// It takes a reference to the root node of `RootNodeRef` type.
     ///
     pub fn new(root: RootNodeRef) -> Self {
-        AccountTree { root: root.clone() }
+        AccountTree {
+            root: root.clone(),
+            auditor: None,
+        }
+    }
+
+    ///
+    /// Get the `Auditor` recording the changes made through the tree, if any
+    ///
+    pub fn auditor(&self) -> Option<&Auditor> {
+        self.auditor.as_ref()
+    }
+
+    ///
+    /// Record every later change made through the tree with `auditor`
+    ///
+    pub fn set_auditor(&mut self, auditor: Option<Auditor>) {
+        self.auditor = auditor;
+    }
+
+    fn audit(
+        &self,
+        name: &str,
+        action: AuditAction,
+        field: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) {
+        if let Some(auditor) = self.auditor.as_ref() {
+            auditor.record(
+                AuditSubject::Account(name.to_owned()),
+                action,
+                field,
+                before,
+                after,
+            );
+        }
     }
 
     ///
@@ -575,12 +633,68 @@ impl AccountTree {
     /// Used to set the `root` of the `AccountTree`.
     ///
     pub fn set_root(&mut self, root: RootNodeRef) {
+        let before = self.root.borrow().name().to_owned();
+        let after = root.borrow().name().to_owned();
+        self.audit(
+            &after,
+            AuditAction::Update,
+            "root",
+            Some(before),
+            Some(after.clone()),
+        );
         self.root = root.clone()
     }
 
     pub fn get_node_by_name(&self, name: &str) -> Option<Rc<RefCell<dyn ParentNode>>> {
         let mut dfs = DFS::new(self.root.clone());
-        return dfs.traverse(name);
+        dfs.traverse(name)
+    }
+
+    ///
+    /// Add `child` under the node named `parent_name`.
+    /// Returns `false`, leaving the tree untouched, when there is no such node.
+    ///
+    pub fn add_child(&mut self, parent_name: &str, child: ParentNodeRef) -> bool {
+        let parent = match self.get_node_by_name(parent_name) {
+            None => return false,
+            Some(parent) => parent,
+        };
+
+        let name = child.borrow().name().to_owned();
+        child.borrow_mut().set_parent(Some(parent.clone()));
+        parent.borrow_mut().add_child(child);
+        self.audit(
+            &name,
+            AuditAction::Insert,
+            "parent",
+            None,
+            Some(parent_name.to_owned()),
+        );
+
+        true
+    }
+
+    ///
+    /// Rename the node named `name` to `new_name`.
+    /// Returns `false`, leaving the tree untouched, when there is no such node.
+    ///
+    pub fn rename_node(&mut self, name: &str, new_name: &str) -> bool {
+        let node = match self.get_node_by_name(name) {
+            None => return false,
+            Some(node) => node,
+        };
+
+        let before = node.borrow().name().to_owned();
+        node.borrow_mut().set_name(new_name);
+        self.audit(
+            new_name,
+            AuditAction::Update,
+            "name",
+            Some(before),
+            Some(new_name.to_owned()),
+        );
+
+        true
     }
 }
 
@@ -620,7 +734,7 @@ impl Iterator for Ancestors {
             }
         }
 
-        return Some(self.source.clone());
+        Some(self.source.clone())
     }
 }
 
@@ -653,7 +767,7 @@ impl Descendants {
     /// Used to mark the `Descendants` Iterator as having consumed all the values
     ///
     fn finished(&self) -> bool {
-        return self.children.is_empty();
+        self.children.is_empty()
     }
 }
 
@@ -677,7 +791,7 @@ impl Iterator for Descendants {
 
         self.update_children(next_children);
 
-        return Some(children);
+        Some(children)
     }
 }
 
@@ -785,9 +899,21 @@ impl AmountAggregator {
 
     ///
     /// Function used to perform the aggregation. This updates the
-    /// tree nodes' amount
+    /// tree nodes' amount.
+    ///
+    /// Only the leaves' amounts are taken as inputs; every parent node's amount is
+    /// overwritten with the sum of its children, so running the aggregation more than
+    /// once yields the same subtotals.
     ///
     pub fn aggregate(&mut self) -> Rc<RefCell<dyn ParentNode>> {
+        self.node = self.root.clone();
+        self.aggregate_node()
+    }
+
+    ///
+    /// Post-order step of the aggregation starting at the current `node`
+    ///
+    fn aggregate_node(&mut self) -> Rc<RefCell<dyn ParentNode>> {
         // If root has no child, return
         let node_clone = self.node.clone();
         let mut borrowed_node = node_clone.borrow_mut();
@@ -798,32 +924,177 @@ impl AmountAggregator {
             return node_clone.clone();
         }
 
-        // Variable to store the total amount retrieved from a node's children
-        let mut total_from_children = borrowed_node.amount();
+        // Variable to store the total amount retrieved from a node's children.
+        // The node's own (possibly stale) amount is deliberately not part of the total.
+        let mut total_from_children = 0f64;
 
         for child_node in children.iter() {
             self.node = child_node.clone();
-            let result_node: Rc<RefCell<dyn ParentNode>> = self.aggregate();
+            let result_node: Rc<RefCell<dyn ParentNode>> = self.aggregate_node();
             total_from_children += result_node.borrow().amount();
         }
 
         borrowed_node.set_amount(total_from_children);
-        // {
-        //     let mut mutable_node = node_clone.borrow_mut();
-        //     mutable_node.set_amount(total_from_children);
-        // }
 
         node_clone.clone()
     }
 }
 
+///
+/// Separator used when an account is referred to by its path from a level 1 node,
+/// e.g. `"Assets:Current Assets:Cash"`
+///
+pub const ACCOUNT_PATH_SEPARATOR: &str = ":";
+
+///
+/// `SubtotalNode` is a detached, owned copy of a node of an `AccountTree` that carries
+/// a computed amount. A tree of `SubtotalNode`s is the result of evaluating a set of
+/// amounts against an `AccountTree` without touching the amounts stored on the tree itself.
+///
+/// Leaves carry their own amount while every parent carries the sum of its children.
+///
+#[derive(Debug, Clone)]
+pub struct SubtotalNode {
+    level: usize,
+    name: String,
+    account_type: Option<Rc<PrimaryAccountType>>,
+    amount: f64,
+    children: Vec<SubtotalNode>,
+}
+
+impl SubtotalNode {
+    ///
+    /// Build a `SubtotalNode` tree mirroring the tree under `root`.
+    /// Leaf amounts are looked up in `amounts` by account name; leaves without an entry
+    /// get `0`. Parent amounts are the sum of their children's amounts.
+    ///
+    pub fn from_tree(root: ParentNodeRef, amounts: &HashMap<String, f64>) -> Self {
+        let node = root.as_ref().borrow();
+        let children: Vec<SubtotalNode> = node
+            .children()
+            .iter()
+            .map(|child| SubtotalNode::from_tree(child.clone(), amounts))
+            .collect();
+
+        let amount = if children.is_empty() {
+            amounts.get(node.name()).copied().unwrap_or(0f64)
+        } else {
+            children.iter().map(|child| child.amount()).sum()
+        };
+
+        SubtotalNode {
+            level: node.level(),
+            name: node.name().to_owned(),
+            account_type: node.account_type().clone(),
+            amount,
+            children,
+        }
+    }
+
+    ///
+    /// Get the level of the node this subtotal was computed for
+    ///
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    ///
+    /// Get the name of the node this subtotal was computed for
+    ///
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///
+    /// Get the `PrimaryAccountType` of the node this subtotal was computed for
+    ///
+    pub fn account_type(&self) -> &Option<Rc<PrimaryAccountType>> {
+        &self.account_type
+    }
+
+    ///
+    /// Get the computed amount
+    ///
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    ///
+    /// Get the children of this node
+    ///
+    pub fn children(&self) -> &Vec<SubtotalNode> {
+        &self.children
+    }
+
+    ///
+    /// Check whether this node has no children
+    ///
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    ///
+    /// Get the leaves under this node in depth first order
+    ///
+    pub fn leaves(&self) -> Vec<&SubtotalNode> {
+        if self.is_leaf() {
+            return vec![self];
+        }
+
+        self.children
+            .iter()
+            .flat_map(|child| child.leaves())
+            .collect()
+    }
+
+    ///
+    /// Depth first search for the first node whose `name` matches the passed `name`.
+    /// The comparison ignores ASCII case, in the same way `DFS::traverse` does.
+    ///
+    pub fn find(&self, name: &str) -> Option<&SubtotalNode> {
+        if self.name.eq_ignore_ascii_case(name) {
+            return Some(self);
+        }
+
+        self.children.iter().find_map(|child| child.find(name))
+    }
+
+    ///
+    /// Follow `path` down from this node's children, e.g. `["Assets", "Current Assets", "Cash"]`.
+    /// Each segment is compared ignoring ASCII case.
+    ///
+    pub fn find_path(&self, path: &[&str]) -> Option<&SubtotalNode> {
+        match path.split_first() {
+            None => Some(self),
+            Some((first, rest)) => self
+                .children
+                .iter()
+                .find(|child| child.name.eq_ignore_ascii_case(first.trim()))
+                .and_then(|child| child.find_path(rest)),
+        }
+    }
+
+    ///
+    /// Look a node up either by a path joined with `ACCOUNT_PATH_SEPARATOR`
+    /// (e.g. `"Assets:Current Assets:Cash"`) or, when there is no separator, by name.
+    ///
+    pub fn lookup(&self, name_or_path: &str) -> Option<&SubtotalNode> {
+        if name_or_path.contains(ACCOUNT_PATH_SEPARATOR) {
+            let path: Vec<&str> = name_or_path.split(ACCOUNT_PATH_SEPARATOR).collect();
+            self.find_path(&path)
+        } else {
+            self.find(name_or_path.trim())
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::{
         AccountNode, AccountTagNode, ActionType, AmountAggregator, ParentNodeT, PrimaryAccountType,
-        RootNode, RootNodeRef, DFS,
+        RootNode, RootNodeRef, SubtotalNode, DFS,
     };
-    use std::{cell::RefCell, rc::Rc};
+    use std::{cell::RefCell, collections::HashMap, rc::Rc};
 
     fn get_root_node() -> RootNodeRef {
         // Create a tree instance
@@ -991,7 +1262,7 @@ mod test {
 
         let mut dfs = DFS::new(root.clone());
         let query = "Cost of Sales";
-        let result = dfs.traverse(&query);
+        let result = dfs.traverse(query);
 
         match result {
             None => {
@@ -1015,7 +1286,7 @@ mod test {
 
         let mut dfs = DFS::new(root.clone());
         let query = "Owner's Equity";
-        let result = dfs.traverse(&query);
+        let result = dfs.traverse(query);
 
         match result {
             None => {
@@ -1035,6 +1306,40 @@ mod test {
 
         assert_eq!(true, true);
     }
+
+    #[test]
+    fn test_amount_aggregator_is_repeatable() {
+        let root = get_root_node().clone();
+
+        let mut amount_aggregator = AmountAggregator::new(root.clone());
+        amount_aggregator.aggregate();
+        amount_aggregator.aggregate();
+
+        let mut dfs = DFS::new(root.clone());
+        let asset = dfs.traverse("Asset").unwrap();
+
+        assert_eq!(asset.borrow().amount(), 2000f64);
+    }
+
+    #[test]
+    fn test_subtotal_node_from_tree() {
+        let root = get_root_node().clone();
+
+        let mut amounts = HashMap::new();
+        amounts.insert("Cash".to_owned(), 100f64);
+        amounts.insert("Short Term Loan".to_owned(), 40f64);
+
+        let subtotals = SubtotalNode::from_tree(root.clone(), &amounts);
+
+        assert_eq!(subtotals.find("asset").unwrap().amount(), 100f64);
+        assert_eq!(subtotals.find("Inventory").unwrap().amount(), 0f64);
+        assert_eq!(subtotals.find("Liabilities").unwrap().amount(), 40f64);
+        assert!(subtotals.find("Goodwill").is_none());
+
+        // The amounts on the tree itself are left as they were
+        let mut dfs = DFS::new(root.clone());
+        assert_eq!(dfs.traverse("Cash").unwrap().borrow().amount(), 1200f64);
+    }
 }
 
 // fn main() {
@@ -0,0 +1,445 @@
+use chrono::{DateTime, Utc};
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// What an `AuditEvent` is about
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AuditSubject {
+    ///
+    /// A property of the `Ledger` with the given id
+    ///
+    Ledger(usize),
+    ///
+    /// The `JournalEntry` with the given id
+    ///
+    JournalEntry(usize),
+    ///
+    /// The node of the `AccountTree` with the given name
+    ///
+    Account(String),
+}
+
+impl Display for AuditSubject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditSubject::Ledger(id) => write!(f, "Ledger {}", id),
+            AuditSubject::JournalEntry(id) => write!(f, "Journal entry {}", id),
+            AuditSubject::Account(name) => write!(f, "Account {:?}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Insert,
+    Update,
+    Delete,
+}
+
+///
+/// `AuditEvent` records who changed what and when, with the values before and after
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    sequence: usize,
+    actor: String,
+    timestamp: DateTime<Utc>,
+    subject: AuditSubject,
+    action: AuditAction,
+    field: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl AuditEvent {
+    ///
+    /// Get the position of the event in the log, starting at `1`
+    ///
+    pub fn sequence(&self) -> usize {
+        self.sequence
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    pub fn subject(&self) -> &AuditSubject {
+        &self.subject
+    }
+
+    pub fn action(&self) -> AuditAction {
+        self.action
+    }
+
+    ///
+    /// Get the name of the property that changed
+    ///
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn before(&self) -> Option<&str> {
+        self.before.as_deref()
+    }
+
+    pub fn after(&self) -> Option<&str> {
+        self.after.as_deref()
+    }
+}
+
+impl Display for AuditEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} {} {:?} {} {}: {} -> {}",
+            self.sequence,
+            self.timestamp.to_rfc3339(),
+            self.actor,
+            self.action,
+            self.subject,
+            self.field,
+            self.before.as_deref().unwrap_or("-"),
+            self.after.as_deref().unwrap_or("-")
+        )
+    }
+}
+
+///
+/// `AuditLog` is an append-only list of `AuditEvent`s. Events can be recorded and read
+/// but never changed or removed.
+///
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    events: Vec<AuditEvent>,
+    clock: fn() -> DateTime<Utc>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        AuditLog::new()
+    }
+}
+
+impl AuditLog {
+    ///
+    /// Create an empty `AuditLog` timestamping its events with the current time
+    ///
+    pub fn new() -> Self {
+        AuditLog {
+            events: Vec::new(),
+            clock: Utc::now,
+        }
+    }
+
+    ///
+    /// Create an empty `AuditLog` behind a shared reference, ready to be attached to
+    /// ledgers and account trees
+    ///
+    pub fn shared() -> AuditLogRef {
+        Rc::new(RefCell::new(AuditLog::new()))
+    }
+
+    ///
+    /// Set the clock timestamping the events
+    ///
+    pub fn set_clock(&mut self, clock: fn() -> DateTime<Utc>) {
+        self.clock = clock;
+    }
+
+    ///
+    /// Append an event and return its sequence number
+    ///
+    pub fn record(
+        &mut self,
+        actor: &str,
+        subject: AuditSubject,
+        action: AuditAction,
+        field: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> usize {
+        let sequence = self.events.len() + 1;
+
+        self.events.push(AuditEvent {
+            sequence,
+            actor: actor.to_owned(),
+            timestamp: (self.clock)(),
+            subject,
+            action,
+            field: field.to_owned(),
+            before,
+            after,
+        });
+
+        sequence
+    }
+
+    pub fn events(&self) -> &Vec<AuditEvent> {
+        &self.events
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    ///
+    /// Get the events about `subject`
+    ///
+    pub fn events_for(&self, subject: &AuditSubject) -> Vec<&AuditEvent> {
+        self.query(Some(subject), None, None)
+    }
+
+    ///
+    /// Get the events timestamped between `from` and `to` (inclusive)
+    ///
+    pub fn events_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<&AuditEvent> {
+        self.query(None, Some(from), Some(to))
+    }
+
+    ///
+    /// Get the events about `subject`, if any, timestamped on or after `from` and on or
+    /// before `to`, if any
+    ///
+    pub fn query(
+        &self,
+        subject: Option<&AuditSubject>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<&AuditEvent> {
+        self.events
+            .iter()
+            .filter(|event| subject.is_none_or(|subject| &event.subject == subject))
+            .filter(|event| from.is_none_or(|from| event.timestamp >= from))
+            .filter(|event| to.is_none_or(|to| event.timestamp <= to))
+            .collect()
+    }
+}
+
+///
+/// A wrapper for a shared reference to an `AuditLog`, so several ledgers and account
+/// trees can record to the same log
+///
+pub type AuditLogRef = Rc<RefCell<AuditLog>>;
+
+///
+/// `Auditor` is attached to an audited structure: the log it records to and the actor
+/// it records as
+///
+#[derive(Debug, Clone)]
+pub struct Auditor {
+    log: AuditLogRef,
+    actor: String,
+}
+
+impl Auditor {
+    pub fn new(log: &AuditLogRef, actor: &str) -> Self {
+        Auditor {
+            log: log.clone(),
+            actor: actor.to_owned(),
+        }
+    }
+
+    pub fn log(&self) -> &AuditLogRef {
+        &self.log
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_owned();
+    }
+
+    ///
+    /// Record an event as the actor
+    ///
+    pub fn record(
+        &self,
+        subject: AuditSubject,
+        action: AuditAction,
+        field: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) -> usize {
+        self.log
+            .borrow_mut()
+            .record(&self.actor, subject, action, field, before, after)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AuditAction, AuditLog, AuditSubject, Auditor};
+    use crate::journal::accounting_tree::{AccountNode, AccountTreeNode};
+    use crate::journal::dimensions::{Dimension, Dimensions};
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+    use chrono::{Duration, Utc};
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn test_ledger_and_tree_mutations_are_recorded() {
+        let chart = test_chart();
+        let log = AuditLog::shared();
+        let start = Utc::now();
+
+        let mut ledger = Ledger::new(7, date(2024, 1, 1), date(2024, 1, 31));
+        ledger.set_auditor(Some(Auditor::new(&log, "alice")));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 10),
+            "Cash sale",
+            &[
+                (&chart.account("Cash"), 250.0, EntryType::Debit),
+                (&chart.account("Sales Revenue"), 250.0, EntryType::Credit),
+            ],
+        ));
+
+        ledger.set_actor("bob");
+        ledger.void_journal_entry(1).unwrap();
+        ledger.set_to_date(date(2024, 2, 29));
+
+        let mut tree = chart.tree();
+        tree.set_auditor(Some(Auditor::new(&log, "carol")));
+        let current_assets = tree.get_node_by_name("Current Assets");
+        let petty_cash = Rc::new(RefCell::new(AccountNode::new(
+            3,
+            "Petty Cash",
+            current_assets,
+        )));
+        assert!(tree.add_child("Current Assets", petty_cash.clone()));
+        assert!(!tree.add_child("Missing", petty_cash.clone()));
+        assert!(tree.rename_node("Petty Cash", "Cash on Hand"));
+        assert_eq!(petty_cash.borrow().name(), "Cash on Hand");
+
+        let log = log.borrow();
+        assert_eq!(log.len(), 6);
+
+        let history = log.events_for(&AuditSubject::JournalEntry(1));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].actor(), "alice");
+        assert_eq!(history[0].action(), AuditAction::Insert);
+        assert_eq!(
+            history[0].after(),
+            Some("#1 2024-01-10 \"Cash sale\": Dr Cash 250.00, Cr Sales Revenue 250.00")
+        );
+        assert_eq!(history[1].actor(), "bob");
+        assert_eq!(history[1].action(), AuditAction::Update);
+        assert_eq!(history[1].field(), "status");
+        assert_eq!(history[1].before(), Some("Posted"));
+        assert_eq!(history[1].after(), Some("Voided"));
+
+        let ledger_events = log.events_for(&AuditSubject::Ledger(7));
+        assert_eq!(ledger_events.len(), 2);
+        assert_eq!(ledger_events[0].field(), "auditor");
+        assert_eq!(ledger_events[0].after(), Some("alice"));
+        assert_eq!(ledger_events[1].field(), "to_date");
+        assert_eq!(ledger_events[1].before(), Some("2024-01-31T00:00:00+00:00"));
+
+        let renamed = log.events_for(&AuditSubject::Account("Cash on Hand".to_owned()));
+        assert_eq!(renamed[0].actor(), "carol");
+        assert_eq!(renamed[0].before(), Some("Petty Cash"));
+
+        assert_eq!(log.events_between(start, Utc::now()).len(), 6);
+        assert!(log
+            .events_between(start - Duration::days(2), start - Duration::days(1))
+            .is_empty());
+        assert_eq!(
+            log.events()
+                .iter()
+                .map(|e| e.sequence())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_voiding_records_the_linked_reversal() {
+        let chart = test_chart();
+        let log = AuditLog::shared();
+
+        let mut ledger = Ledger::new(7, date(2024, 1, 1), date(2024, 2, 29));
+        let mut accrual = journal_entry(
+            1,
+            date(2024, 1, 31),
+            "Accrued wages",
+            &[
+                (&chart.account("Wages Expense"), 400.0, EntryType::Debit),
+                (
+                    &chart.account("Accrued Liabilities"),
+                    400.0,
+                    EntryType::Credit,
+                ),
+            ],
+        );
+        accrual.set_auto_reversing(true);
+        ledger.add_journal_entry(accrual);
+        let reversal_id = ledger.get_reversal_of(1).unwrap().id();
+
+        ledger.set_auditor(Some(Auditor::new(&log, "alice")));
+        ledger.void_journal_entry(1).unwrap();
+
+        let log = log.borrow();
+        assert_eq!(log.len(), 3);
+        for id in [1, reversal_id] {
+            let history = log.events_for(&AuditSubject::JournalEntry(id));
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].field(), "status");
+            assert_eq!(history[0].after(), Some("Voided"));
+        }
+    }
+
+    #[test]
+    fn test_ledger_settings_are_recorded() {
+        let log = AuditLog::shared();
+        let other_log = AuditLog::shared();
+
+        let mut ledger = Ledger::new(7, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.set_auditor(Some(Auditor::new(&log, "alice")));
+        ledger.set_clock(|| date(2024, 6, 30));
+        ledger.set_fiscal_year_start_month(7);
+        let mut dimensions = Dimensions::new();
+        dimensions
+            .add_dimension(Dimension::new("Region", &["North"]))
+            .unwrap();
+        ledger.set_dimensions(Some(dimensions)).unwrap();
+
+        // Moving to another log is recorded in both, switching auditing off in the last one
+        ledger.set_auditor(Some(Auditor::new(&other_log, "bob")));
+        ledger.set_auditor(None);
+
+        let log = log.borrow();
+        let fields: Vec<&str> = log.events().iter().map(|e| e.field()).collect();
+        assert_eq!(
+            fields,
+            vec![
+                "auditor",
+                "clock",
+                "fiscal_year_start_month",
+                "dimensions",
+                "auditor"
+            ]
+        );
+        assert_eq!(log.events()[1].after(), Some("2024-06-30T00:00:00+00:00"));
+        assert_eq!(log.events()[2].before(), Some("1"));
+        assert_eq!(log.events()[2].after(), Some("7"));
+        assert_eq!(log.events()[4].after(), Some("bob"));
+
+        let other_log = other_log.borrow();
+        assert_eq!(other_log.len(), 2);
+        assert_eq!(other_log.events()[1].before(), Some("bob"));
+        assert_eq!(other_log.events()[1].after(), None);
+    }
+}
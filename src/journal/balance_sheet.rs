@@ -1,8 +1,12 @@
-use crate::journal::accounting_tree::{AccountTree, ActionType, AmountAggregator, DFS};
-use crate::journal::ledger::{EntryType, Ledger, LedgerReader, TransactionEntry};
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountTree, SubtotalNode};
+use crate::journal::ledger::{Ledger, LedgerReader, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
 use chrono::{DateTime, Utc};
-use std::borrow::Borrow;
-use std::{collections::HashMap, rc::Rc};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
 
 ///
 /// `Balance Sheet` structure
@@ -25,8 +29,6 @@ impl BalanceSheet {
         accounting_tree: AccountTree,
         ledger: Ledger,
     ) -> Self {
-        //todo!("Filter by from_date and to_date");
-
         BalanceSheet {
             id,
             from_date,
@@ -132,178 +134,490 @@ impl BalanceSheet {
     }
 
     ///
-    /// Build the AccountTree by populating it with values from the ledger
-    ///    - Call this method build_tree or generate or execute etc
-    ///    - Should return a Result<Ok, Err>
+    /// Build the balance sheet by aggregating the ledger's transaction entries per account
+    /// and propagating the totals up a copy of the `AccountTree`.
+    /// Only journal entries dated between `from_date` and `to_date` (inclusive) are considered.
+    ///
+    /// The `AccountTree` itself is left untouched, so a balance sheet can be built any number
+    /// of times and a single tree can be shared across several balance sheets.
     ///
-    pub fn build(&self) {
-        // Retieve all transaction entries
+    pub fn build(&self) -> Result<BalanceSheetSnapshot, BalanceSheetError> {
+        ReportingPeriod::range(self.from_date, self.to_date)?;
+
+        // Retieve all transaction entries whose journal entries fall within the balance sheet's dates
         let transaction_entries: Vec<&Rc<TransactionEntry>> = self
             .ledger
-            .journal_entries()
-            .iter()
-            .map(|journal_entry| journal_entry.transaction_entries())
-            .flatten()
+            .active_journal_entries()
+            .filter(|journal_entry| {
+                journal_entry.date_of_entry() >= self.from_date
+                    && journal_entry.date_of_entry() <= self.to_date
+            })
+            .flat_map(|journal_entry| journal_entry.transaction_entries())
             .collect();
 
-        // Group by account type
-        // Collect all the unique account types by name
-        let mut accounts_aggregate_map: HashMap<String, f64> = HashMap::new();
-
-        // Perform aggregates for each account type taking credit/debit into consideration
-        for transaction_entry in transaction_entries.iter() {
-            // Perform the aggregations based on the Credit/Debit rule per account type
-            let acc_type_ref = transaction_entry.account_type();
-            let account_type_option = acc_type_ref.borrow().as_ref();
-            let account_type;
-            let acc_name = transaction_entry.account_name().clone();
-
-            // Attempt to retieve the primary account type
-            // This helps in knowing whether to increase/decrease the amount of an account
-            // when the transaction is a credit/debit.
-            match account_type_option {
-                None => {
-                    panic!(
-                        "Transaction with id: {:?} and account name: {:?} has account_type of None",
-                        transaction_entry.id(),
-                        acc_name,
-                    );
-                }
-                Some(account_type_result) => {
-                    account_type = account_type_result.clone().to_owned();
-                }
-            }
+        let root = build_subtotals(&self.accounting_tree, transaction_entries)?;
 
-            // Get the amount associated with the account name under the transaction entry or
-            // initialize it to 0
-            if !accounts_aggregate_map.contains_key(&acc_name) {
-                accounts_aggregate_map.insert(acc_name.clone(), 0f64);
-            }
+        Ok(BalanceSheetSnapshot {
+            id: self.id,
+            from_date: self.from_date,
+            to_date: self.to_date,
+            root,
+        })
+    }
 
-            // Get the sign that'll be assigned to the amount
-            let signum: f64;
-
-            // Increase of decrease the amounts accordingly
-            match transaction_entry.entry_type() {
-                EntryType::Credit => match account_type.on_credit() {
-                    ActionType::Decrease => {
-                        signum = -1f64;
-                    }
-                    ActionType::Increase => {
-                        signum = 1f64;
-                    }
-                },
-                EntryType::Debit => match account_type.on_debit() {
-                    ActionType::Decrease => {
-                        signum = -1f64;
-                    }
-                    ActionType::Increase => {
-                        signum = 1f64;
-                    }
-                },
+    // Consider adding a method to return the CashflowStatement
+    //
+}
+
+///
+/// Aggregate `transaction_entries` per account and propagate the totals up a copy of
+/// `accounting_tree`. The tree itself is left untouched.
+///
+pub fn build_subtotals<'a>(
+    accounting_tree: &AccountTree,
+    transaction_entries: impl IntoIterator<Item = &'a Rc<TransactionEntry>>,
+) -> Result<SubtotalNode, BalanceSheetError> {
+    // Group by account name
+    let mut accounts_aggregate_map: HashMap<String, f64> = HashMap::new();
+
+    // Perform aggregates for each account taking credit/debit into consideration
+    for transaction_entry in transaction_entries {
+        let acc_name = transaction_entry.account_name();
+
+        // The primary account type decides whether the amount increases or decreases the account
+        let transaction_amount = match transaction_entry.signed_amount() {
+            None => {
+                return Err(BalanceSheetError::MissingAccountType {
+                    transaction_entry_id: transaction_entry.id(),
+                    account_name: acc_name,
+                });
             }
+            Some(amount) => amount,
+        };
 
-            // Apply the signum to the amount
-            let transaction_amount = transaction_entry.amount() * signum;
+        *accounts_aggregate_map.entry(acc_name).or_insert(0f64) += transaction_amount;
+    }
 
-            // Apply this amount delta in the accounts aggregate map
-            accounts_aggregate_map.insert(
-                acc_name.clone(),
-                accounts_aggregate_map.get(&acc_name).unwrap() + transaction_amount,
-            );
+    // Copy the tree, filling in the leaves and propagating the subtotals upwards
+    let root = SubtotalNode::from_tree(accounting_tree.root(), &accounts_aggregate_map);
+
+    // Every account referenced by the ledger must be a leaf of the tree with the exact
+    // same name: only the leaves are filled in
+    let leaves: HashSet<&str> = root.leaves().iter().map(|leaf| leaf.name()).collect();
+    let mut account_names: Vec<&String> = accounts_aggregate_map.keys().collect();
+    account_names.sort();
+
+    for account_name in account_names {
+        if leaves.contains(account_name.as_str()) {
+            continue;
         }
 
-        // Create a DFS instance
-        let mut dfs = DFS::new(self.accounting_tree.root().clone());
-
-        for (account_name, amount) in accounts_aggregate_map.iter() {
-            // Fetch the account associated with the account name
-            let account = dfs.traverse(account_name.as_str());
-
-            match account {
-                None => {
-                    panic!(
-                        "Account name: {:?} has no associated account node.",
-                        account_name
-                    )
-                }
-                Some(acc) => {
-                    // Update the amount set for the current node with the one associated to account name
-                    let mut borrowed_account_node = acc.borrow_mut();
-                    borrowed_account_node.set_amount(amount.to_owned());
-                }
+        let is_parent = root
+            .find(account_name)
+            .is_some_and(|node| node.name() == account_name && !node.is_leaf());
+        return Err(if is_parent {
+            BalanceSheetError::ParentAccount(account_name.to_owned())
+        } else {
+            BalanceSheetError::UnknownAccount(account_name.to_owned())
+        });
+    }
+
+    Ok(root)
+}
+
+///
+/// Errors raised while building or querying a `BalanceSheet`
+///
+#[derive(Debug, PartialEq)]
+pub enum BalanceSheetError {
+    ///
+    /// A transaction entry's account has no `PrimaryAccountType`, so the direction of
+    /// the amount cannot be decided
+    ///
+    MissingAccountType {
+        transaction_entry_id: usize,
+        account_name: String,
+    },
+    ///
+    /// An account name that has no associated node on the `AccountTree`
+    ///
+    UnknownAccount(String),
+    ///
+    /// An amount posted to a node with children, whose amount is the sum of its children
+    ///
+    ParentAccount(String),
+    ///
+    /// A reporting period whose `from_date` is after its `to_date`
+    ///
+    InvalidPeriod {
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    },
+}
+
+impl Display for BalanceSheetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BalanceSheetError::MissingAccountType {
+                transaction_entry_id,
+                account_name,
+            } => write!(
+                f,
+                "Transaction with id: {:?} and account name: {:?} has account_type of None",
+                transaction_entry_id, account_name
+            ),
+            BalanceSheetError::UnknownAccount(account_name) => write!(
+                f,
+                "Account name: {:?} has no associated account node.",
+                account_name
+            ),
+            BalanceSheetError::ParentAccount(account_name) => write!(
+                f,
+                "Account name: {:?} has sub-accounts and cannot hold an amount.",
+                account_name
+            ),
+            BalanceSheetError::InvalidPeriod { from_date, to_date } => write!(
+                f,
+                "The period starts on {} after it ends on {}.",
+                from_date.format("%Y-%m-%d"),
+                to_date.format("%Y-%m-%d")
+            ),
+        }
+    }
+}
+
+impl Error for BalanceSheetError {}
+
+///
+/// `BalanceSheetSnapshot` is the result of building a `BalanceSheet`.
+/// It holds its own tree of computed subtotals and is independent of the `AccountTree`
+/// it was built from.
+///
+#[derive(Debug, Clone)]
+pub struct BalanceSheetSnapshot {
+    id: usize,
+    from_date: DateTime<Utc>,
+    to_date: DateTime<Utc>,
+    root: SubtotalNode,
+}
+
+impl BalanceSheetSnapshot {
+    ///
+    /// Get the id of the `BalanceSheet` this snapshot was built from
+    ///
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    ///
+    /// Get the `from_date` of the `BalanceSheet` this snapshot was built from
+    ///
+    pub fn from_date(&self) -> &DateTime<Utc> {
+        &self.from_date
+    }
+
+    ///
+    /// Get the `to_date` of the `BalanceSheet` this snapshot was built from
+    ///
+    pub fn to_date(&self) -> &DateTime<Utc> {
+        &self.to_date
+    }
+
+    ///
+    /// Get the `ReportingPeriod` covered by this snapshot
+    ///
+    pub fn period(&self) -> ReportingPeriod {
+        if self.from_date == DateTime::<Utc>::MIN_UTC {
+            ReportingPeriod::as_of(self.to_date)
+        } else {
+            // The dates were checked when the snapshot was built
+            ReportingPeriod::Range {
+                from_date: self.from_date,
+                to_date: self.to_date,
             }
         }
+    }
+
+    ///
+    /// Get the root of the computed subtotals tree
+    ///
+    pub fn root(&self) -> &SubtotalNode {
+        &self.root
+    }
 
-        // At this point, we can assume that all leaf nodes that are touched on by the
-        // transaction entries have their aggregate amounts set.
-        // Use the aggregates to compute amounts that'll be propagated up the tree
-        //  - Propagate the amounts up the tree.
-        let mut amount_aggregator = AmountAggregator::new(self.accounting_tree.root().clone());
-        _ = amount_aggregator.aggregate();
+    ///
+    /// Find the node with the passed account `name`
+    ///
+    pub fn find(&self, name: &str) -> Option<&SubtotalNode> {
+        self.root.find(name)
+    }
+
+    ///
+    /// Find a node by its account name or by its path, e.g. `"Assets:Current Assets:Cash"`
+    ///
+    pub fn lookup(&self, name_or_path: &str) -> Option<&SubtotalNode> {
+        self.root.lookup(name_or_path)
     }
 
     ///
     /// Function to check whether the balance sheet is balanced.
     /// Takes 2 parameters:
-    ///     - `lhs`: __left hand side__ vector comprising of the names of accounts
+    ///     - `lhs`: __left hand side__ slice comprising of the names of accounts
     ///        that make up the left side of the accounting equation.
-    ///     - `rhs`: __right hand side__ vector comprising of the names of accounts
+    ///     - `rhs`: __right hand side__ slice comprising of the names of accounts
     ///        that make up the right hand side of the accounting equation.
     /// A common equation is assets = liabilities + owner's equity.
+    /// Both totals are compared within `DEFAULT_TOLERANCE`; use an `AccountingEquation`
+    /// for other forms of the equation or a breakdown of the terms.
     ///
     /// # Example
     ///
-    /// ```
+    /// ```ignore
     /// let lhs = vec!["assets"];
-    /// let rhs = vec!["liabilities", "owner's equity"]
+    /// let rhs = vec!["liabilities", "owner's equity"];
     ///
     /// // Create a new balance sheet instance
     /// let balance_sheet = ...;
-    /// // Build the balance sheet to fill in the account tree amount field
-    /// balance_sheet.build();
+    /// // Build the balance sheet to compute the subtotals
+    /// let snapshot = balance_sheet.build()?;
     ///
-    /// let is_balanced = balance_sheet.is_balanced(&lhs, &rhs);
+    /// let is_balanced = snapshot.is_balanced(&lhs, &rhs)?;
     ///
     /// println!("Is the balance sheet balanced? {:?}", is_balanced);
     /// ```
-    pub fn is_balanced(&self, lhs: &Vec<&str>, rhs: &Vec<&str>) -> bool {
-        let lhs_total: f64 = self.accounts_total(lhs);
-        let rhs_total = self.accounts_total(rhs);
+    pub fn is_balanced(&self, lhs: &[&str], rhs: &[&str]) -> Result<bool, BalanceSheetError> {
+        let lhs_total: f64 = self.accounts_total(lhs)?;
+        let rhs_total = self.accounts_total(rhs)?;
 
-        lhs_total == rhs_total
+        Ok((lhs_total - rhs_total).abs() <= DEFAULT_TOLERANCE)
     }
 
     ///
     /// Function used to return the total for a set of accounts represented by their account name
+    /// or account path
     ///
-    pub fn accounts_total(&self, account_names: &Vec<&str>) -> f64 {
+    pub fn accounts_total(&self, account_names: &[&str]) -> Result<f64, BalanceSheetError> {
         let mut total = 0f64;
 
-        // DFS instance useful for traversal of accounts
-        let mut dfs = DFS::new(self.accounting_tree.root().clone());
-
-        //
         for account_name in account_names {
-            // Get the account with the name from the tree
-            let account_option = dfs.traverse(account_name);
-
-            match account_option {
-                None => {
-                    panic!("No account exists with account name: {:?}", account_name)
-                }
-                Some(account_node) => {
-                    let account = account_node.as_ref().borrow();
-                    total += account.amount();
-                }
+            match self.lookup(account_name) {
+                None => return Err(BalanceSheetError::UnknownAccount(account_name.to_string())),
+                Some(account) => total += account.amount(),
             }
         }
 
-        total
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BalanceSheet, BalanceSheetError};
+    use crate::journal::accounting_tree::AccountNode;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, journal_entry, test_chart, TestChart};
+    use std::{cell::RefCell, rc::Rc};
+
+    fn get_ledger(chart: &TestChart) -> Ledger {
+        let cash = chart.account("Cash");
+        let loan = chart.account("Short Term Loan");
+        let inventory = chart.account("Inventory");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 2, 1),
+            "Short term loan",
+            &[
+                (&cash, 400.0, EntryType::Debit),
+                (&loan, 400.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 2, 10),
+            "Purchase of inventory",
+            &[
+                (&inventory, 250.0, EntryType::Debit),
+                (&cash, 250.0, EntryType::Credit),
+            ],
+        ));
+
+        ledger
     }
 
-    // Consider adding methods to retrieve account trees with subtotals
-    // Consider adding a method to return the IncomeStatement
-    // Consider adding a method to return the CashflowStatement
-    //
+    #[test]
+    fn test_build_computes_subtotals() {
+        let chart = test_chart();
+        let balance_sheet = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            get_ledger(&chart),
+        );
+
+        let snapshot = balance_sheet.build().unwrap();
+
+        assert_eq!(snapshot.find("Cash").unwrap().amount(), 150.0);
+        assert_eq!(snapshot.find("Current Assets").unwrap().amount(), 400.0);
+        assert_eq!(snapshot.find("Assets").unwrap().amount(), 400.0);
+        assert_eq!(snapshot.find("Liabilities").unwrap().amount(), 400.0);
+        assert!(snapshot
+            .is_balanced(&["Assets"], &["Liabilities", "Equity"])
+            .unwrap());
+    }
+
+    #[test]
+    fn test_build_is_repeatable_and_leaves_the_tree_untouched() {
+        let chart = test_chart();
+        let tree = chart.tree();
+
+        let first = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            tree.clone(),
+            get_ledger(&chart),
+        );
+        let second = BalanceSheet::new(
+            2,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            tree.clone(),
+            get_ledger(&chart),
+        );
+
+        let first_build = first.build().unwrap();
+        let first_rebuild = first.build().unwrap();
+        let second_build = second.build().unwrap();
+
+        for snapshot in [&first_build, &first_rebuild, &second_build] {
+            assert_eq!(snapshot.find("Assets").unwrap().amount(), 400.0);
+        }
+
+        // The chart of accounts itself carries no amounts
+        let assets = tree.get_node_by_name("Assets").unwrap();
+        assert_eq!(assets.borrow().amount(), 0.0);
+        assert_eq!(chart.account("Cash").borrow().amount(), 0.0);
+    }
+
+    #[test]
+    fn test_build_reports_unknown_accounts() {
+        let chart = test_chart();
+        let other_chart = test_chart();
+
+        // An account that is not on the balance sheet's tree
+        let orphan = Rc::new(RefCell::new(AccountNode::new(
+            3,
+            "Petty Cash",
+            other_chart
+                .tree()
+                .get_node_by_name("Current Assets")
+                .clone(),
+        )));
+
+        let mut ledger = get_ledger(&chart);
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 3, 1),
+            "Petty cash float",
+            &[
+                (&orphan, 20.0, EntryType::Debit),
+                (&chart.account("Cash"), 20.0, EntryType::Credit),
+            ],
+        ));
+
+        let balance_sheet = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger,
+        );
+
+        assert_eq!(
+            balance_sheet.build().unwrap_err(),
+            BalanceSheetError::UnknownAccount("Petty Cash".to_owned())
+        );
+
+        let snapshot = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            get_ledger(&chart),
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            snapshot.accounts_total(&["Goodwill"]).unwrap_err(),
+            BalanceSheetError::UnknownAccount("Goodwill".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_build_requires_exact_leaf_accounts() {
+        let chart = test_chart();
+        let current_assets = chart.tree().get_node_by_name("Current Assets");
+
+        for (name, error) in [
+            ("cash", BalanceSheetError::UnknownAccount("cash".to_owned())),
+            (
+                "Current Assets",
+                BalanceSheetError::ParentAccount("Current Assets".to_owned()),
+            ),
+        ] {
+            let account = Rc::new(RefCell::new(AccountNode::new(
+                3,
+                name,
+                current_assets.clone(),
+            )));
+            let mut ledger = get_ledger(&chart);
+            ledger.add_journal_entry(journal_entry(
+                3,
+                date(2024, 3, 1),
+                "Misposted float",
+                &[
+                    (&account, 20.0, EntryType::Debit),
+                    (&chart.account("Cash"), 20.0, EntryType::Credit),
+                ],
+            ));
+
+            let balance_sheet = BalanceSheet::new(
+                1,
+                date(2024, 1, 1),
+                date(2024, 12, 31),
+                chart.tree(),
+                ledger,
+            );
+            assert_eq!(balance_sheet.build().unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn test_reversed_period_is_rejected() {
+        let chart = test_chart();
+        let error = BalanceSheetError::InvalidPeriod {
+            from_date: date(2024, 12, 31),
+            to_date: date(2024, 1, 1),
+        };
+
+        assert_eq!(
+            ReportingPeriod::range(date(2024, 12, 31), date(2024, 1, 1)).unwrap_err(),
+            error
+        );
+        assert!(ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 1)).is_ok());
+
+        let balance_sheet = BalanceSheet::new(
+            1,
+            date(2024, 12, 31),
+            date(2024, 1, 1),
+            chart.tree(),
+            get_ledger(&chart),
+        );
+        assert_eq!(balance_sheet.build().unwrap_err(), error);
+    }
 }
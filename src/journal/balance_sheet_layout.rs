@@ -0,0 +1,538 @@
+use crate::journal::accounting_tree::SubtotalNode;
+use crate::journal::balance_sheet::{BalanceSheetError, BalanceSheetSnapshot};
+use crate::journal::period::ReportingPeriod;
+use std::fmt::Display;
+
+///
+/// Width of the label column when rendering a layout as text
+///
+const LABEL_WIDTH: usize = 40;
+
+///
+/// Width of the amount column when rendering a layout as text
+///
+const AMOUNT_WIDTH: usize = 16;
+
+///
+/// The form a balance sheet is presented in
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutForm {
+    ///
+    /// Assets on the left, liabilities and equity on the right
+    ///
+    AccountForm,
+    ///
+    /// Assets stacked above liabilities and equity
+    ///
+    ReportForm,
+}
+
+///
+/// `Side` of the balance sheet a level 1 node is presented on
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+///
+/// Header printed at the top of a balance sheet
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BalanceSheetHeader {
+    company_name: String,
+    currency_symbol: String,
+}
+
+impl BalanceSheetHeader {
+    pub fn new(company_name: &str, currency_symbol: &str) -> Self {
+        BalanceSheetHeader {
+            company_name: company_name.to_owned(),
+            currency_symbol: currency_symbol.to_owned(),
+        }
+    }
+
+    pub fn company_name(&self) -> &str {
+        &self.company_name
+    }
+
+    pub fn currency_symbol(&self) -> &str {
+        &self.currency_symbol
+    }
+}
+
+///
+/// A level 1 node of the `AccountTree` wrapped with the `Side` it goes on.
+/// Sections on the same side appear in the order they were added unless the layout's
+/// `Ordinal` ranks them.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    name: String,
+    side: Side,
+}
+
+impl Section {
+    pub fn new(name: &str, side: Side) -> Self {
+        Section {
+            name: name.to_owned(),
+            side,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn side(&self) -> Side {
+        self.side
+    }
+}
+
+///
+/// `Ordinal` ranking used to order the children of every node, e.g. by liquidity.
+/// Nodes named in the ranking come first in the ranking's order; all other nodes follow
+/// in the order they have on the `AccountTree`.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Ordinal {
+    ranking: Vec<String>,
+}
+
+impl Ordinal {
+    pub fn new(ranking: Vec<&str>) -> Self {
+        Ordinal {
+            ranking: ranking.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    ///
+    /// Get the rank of `name`, if it is ranked
+    ///
+    pub fn rank(&self, name: &str) -> Option<usize> {
+        self.ranking
+            .iter()
+            .position(|ranked| ranked.eq_ignore_ascii_case(name))
+    }
+
+    ///
+    /// Order `nodes` by rank; unranked nodes keep their relative order after the ranked ones
+    ///
+    fn sort<'a>(&self, nodes: &'a [SubtotalNode]) -> Vec<&'a SubtotalNode> {
+        let mut sorted: Vec<&SubtotalNode> = nodes.iter().collect();
+        sorted.sort_by_key(|node| self.rank(node.name()).unwrap_or(usize::MAX));
+        sorted
+    }
+}
+
+///
+/// `BalanceSheetLayout` is the presentation configuration applied to a built balance sheet
+///
+#[derive(Debug, Clone)]
+pub struct BalanceSheetLayout {
+    form: LayoutForm,
+    header: BalanceSheetHeader,
+    sections: Vec<Section>,
+    ordinal: Ordinal,
+}
+
+impl BalanceSheetLayout {
+    pub fn new(form: LayoutForm, header: BalanceSheetHeader) -> Self {
+        BalanceSheetLayout {
+            form,
+            header,
+            sections: Vec::new(),
+            ordinal: Ordinal::default(),
+        }
+    }
+
+    pub fn form(&self) -> LayoutForm {
+        self.form
+    }
+
+    pub fn set_form(&mut self, form: LayoutForm) {
+        self.form = form;
+    }
+
+    pub fn header(&self) -> &BalanceSheetHeader {
+        &self.header
+    }
+
+    pub fn set_header(&mut self, header: BalanceSheetHeader) {
+        self.header = header;
+    }
+
+    pub fn sections(&self) -> &Vec<Section> {
+        &self.sections
+    }
+
+    ///
+    /// Present the level 1 node called `name` on `side`
+    ///
+    pub fn add_section(&mut self, name: &str, side: Side) {
+        self.sections.push(Section::new(name, side));
+    }
+
+    pub fn ordinal(&self) -> &Ordinal {
+        &self.ordinal
+    }
+
+    ///
+    /// Set the ranking used to order sections, groups and accounts
+    ///
+    pub fn set_ordinal(&mut self, ordinal: Ordinal) {
+        self.ordinal = ordinal;
+    }
+
+    ///
+    /// Lay a built balance sheet out according to this configuration
+    ///
+    pub fn apply(
+        &self,
+        snapshot: &BalanceSheetSnapshot,
+    ) -> Result<RenderedBalanceSheet, BalanceSheetError> {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut left_sections = Vec::new();
+        let mut right_sections = Vec::new();
+
+        // Sections are ranked amongst the sections on the same side
+        let mut sections: Vec<&Section> = self.sections.iter().collect();
+        sections.sort_by_key(|section| self.ordinal.rank(section.name()).unwrap_or(usize::MAX));
+
+        for section in sections {
+            let node = snapshot
+                .root()
+                .children()
+                .iter()
+                .find(|node| node.name().eq_ignore_ascii_case(section.name()))
+                .ok_or_else(|| BalanceSheetError::UnknownAccount(section.name().to_owned()))?;
+
+            let (lines, names) = match section.side() {
+                Side::Left => (&mut left, &mut left_sections),
+                Side::Right => (&mut right, &mut right_sections),
+            };
+
+            self.push_node(lines, node, 0);
+            names.push(node);
+        }
+
+        for (lines, sections) in [(&mut left, left_sections), (&mut right, right_sections)] {
+            // A single section's total already is the side's total
+            if sections.len() > 1 {
+                let label = sections
+                    .iter()
+                    .map(|node| node.name())
+                    .collect::<Vec<&str>>()
+                    .join(" and ");
+
+                lines.push(LayoutLine::new(
+                    LineKind::Total,
+                    0,
+                    &format!("Total {}", label),
+                    Some(sections.iter().map(|node| node.amount()).sum()),
+                ));
+            }
+        }
+
+        Ok(RenderedBalanceSheet {
+            form: self.form,
+            header: self.header.clone(),
+            period: snapshot.period(),
+            left,
+            right,
+        })
+    }
+
+    ///
+    /// Append the lines of `node` and its descendants, followed by the node's subtotal
+    ///
+    fn push_node(&self, lines: &mut Vec<LayoutLine>, node: &SubtotalNode, depth: usize) {
+        if node.is_leaf() {
+            lines.push(LayoutLine::new(
+                LineKind::Account,
+                depth,
+                node.name(),
+                Some(node.amount()),
+            ));
+            return;
+        }
+
+        let kind = if depth == 0 {
+            LineKind::Section
+        } else {
+            LineKind::Group
+        };
+        lines.push(LayoutLine::new(kind, depth, node.name(), None));
+
+        for child in self.ordinal.sort(node.children()) {
+            self.push_node(lines, child, depth + 1);
+        }
+
+        lines.push(LayoutLine::new(
+            LineKind::Subtotal,
+            depth,
+            &format!("Total {}", node.name()),
+            Some(node.amount()),
+        ));
+    }
+}
+
+///
+/// What a `LayoutLine` represents
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineKind {
+    Section,
+    Group,
+    Account,
+    Subtotal,
+    Total,
+}
+
+///
+/// A single presented line of a balance sheet
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutLine {
+    kind: LineKind,
+    depth: usize,
+    label: String,
+    amount: Option<f64>,
+}
+
+impl LayoutLine {
+    fn new(kind: LineKind, depth: usize, label: &str, amount: Option<f64>) -> Self {
+        LayoutLine {
+            kind,
+            depth,
+            label: label.to_owned(),
+            amount,
+        }
+    }
+
+    pub fn kind(&self) -> LineKind {
+        self.kind
+    }
+
+    ///
+    /// Get the indentation depth, `0` for sections
+    ///
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn amount(&self) -> Option<f64> {
+        self.amount
+    }
+
+    fn render(&self, currency_symbol: &str) -> String {
+        let label = format!("{}{}", "  ".repeat(self.depth), self.label);
+        let amount = match self.amount {
+            None => String::new(),
+            Some(amount) if amount < 0f64 => format!("-{}{:.2}", currency_symbol, -amount),
+            Some(amount) => format!("{}{:.2}", currency_symbol, amount),
+        };
+
+        format!(
+            "{:<label_width$}{:>amount_width$}",
+            label,
+            amount,
+            label_width = LABEL_WIDTH,
+            amount_width = AMOUNT_WIDTH
+        )
+    }
+}
+
+///
+/// A balance sheet laid out for presentation.
+/// In report form the `right` lines are presented below the `left` lines.
+///
+#[derive(Debug, Clone)]
+pub struct RenderedBalanceSheet {
+    form: LayoutForm,
+    header: BalanceSheetHeader,
+    period: ReportingPeriod,
+    left: Vec<LayoutLine>,
+    right: Vec<LayoutLine>,
+}
+
+impl RenderedBalanceSheet {
+    pub fn form(&self) -> LayoutForm {
+        self.form
+    }
+
+    pub fn header(&self) -> &BalanceSheetHeader {
+        &self.header
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn left(&self) -> &Vec<LayoutLine> {
+        &self.left
+    }
+
+    pub fn right(&self) -> &Vec<LayoutLine> {
+        &self.right
+    }
+}
+
+impl Display for RenderedBalanceSheet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = self.header.currency_symbol();
+
+        writeln!(f, "{}", self.header.company_name())?;
+        writeln!(f, "Balance Sheet")?;
+        writeln!(f, "{} ({})", self.period, symbol)?;
+        writeln!(f)?;
+
+        match self.form {
+            LayoutForm::AccountForm => {
+                let blank = " ".repeat(LABEL_WIDTH + AMOUNT_WIDTH);
+                let rows = self.left.len().max(self.right.len());
+
+                for index in 0..rows {
+                    let left = self
+                        .left
+                        .get(index)
+                        .map(|line| line.render(symbol))
+                        .unwrap_or_else(|| blank.clone());
+                    let right = self
+                        .right
+                        .get(index)
+                        .map(|line| line.render(symbol))
+                        .unwrap_or_default();
+
+                    writeln!(f, "{}  |  {}", left, right.trim_end())?;
+                }
+            }
+            LayoutForm::ReportForm => {
+                for line in self.left.iter() {
+                    writeln!(f, "{}", line.render(symbol))?;
+                }
+                writeln!(f)?;
+                for line in self.right.iter() {
+                    writeln!(f, "{}", line.render(symbol))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BalanceSheetHeader, BalanceSheetLayout, LayoutForm, LineKind, Ordinal, Side};
+    use crate::journal::balance_sheet::{BalanceSheet, BalanceSheetError, BalanceSheetSnapshot};
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    fn get_snapshot() -> BalanceSheetSnapshot {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let inventory = chart.account("Inventory");
+        let equipment = chart.account("Equipment");
+        let loan = chart.account("Notes Payable");
+        let stock = chart.account("Common Stock");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 2),
+            "Opening balances",
+            &[
+                (&cash, 500.0, EntryType::Debit),
+                (&inventory, 200.0, EntryType::Debit),
+                (&equipment, 300.0, EntryType::Debit),
+                (&loan, 400.0, EntryType::Credit),
+                (&stock, 600.0, EntryType::Credit),
+            ],
+        ));
+
+        BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger,
+        )
+        .build()
+        .unwrap()
+    }
+
+    fn get_layout(form: LayoutForm) -> BalanceSheetLayout {
+        let mut layout = BalanceSheetLayout::new(form, BalanceSheetHeader::new("Acme Ltd", "$"));
+        layout.add_section("Assets", Side::Left);
+        layout.add_section("Liabilities", Side::Right);
+        layout.add_section("Equity", Side::Right);
+        layout
+    }
+
+    #[test]
+    fn test_account_form_layout() {
+        let rendered = get_layout(LayoutForm::AccountForm)
+            .apply(&get_snapshot())
+            .unwrap();
+
+        assert_eq!(rendered.left().first().unwrap().label(), "Assets");
+        assert_eq!(rendered.left().last().unwrap().label(), "Total Assets");
+        assert_eq!(rendered.left().last().unwrap().amount(), Some(1_000.0));
+
+        let right_total = rendered.right().last().unwrap();
+        assert_eq!(right_total.kind(), LineKind::Total);
+        assert_eq!(right_total.label(), "Total Liabilities and Equity");
+        assert_eq!(right_total.amount(), Some(1_000.0));
+
+        let text = rendered.to_string();
+        assert!(text.starts_with("Acme Ltd\nBalance Sheet\n2024-01-01 - 2024-12-31 ($)\n"));
+        assert!(text.contains("|"));
+    }
+
+    #[test]
+    fn test_report_form_ordering_by_liquidity() {
+        let mut layout = get_layout(LayoutForm::ReportForm);
+        layout.set_ordinal(Ordinal::new(vec![
+            "Equity",
+            "Inventory",
+            "Cash",
+            "Non Current Assets",
+        ]));
+
+        let rendered = layout.apply(&get_snapshot()).unwrap();
+        let labels: Vec<&str> = rendered.left().iter().map(|line| line.label()).collect();
+
+        assert_eq!(
+            labels[..5],
+            [
+                "Assets",
+                "Non Current Assets",
+                "Equipment",
+                "Accumulated Depreciation",
+                "Total Non Current Assets"
+            ]
+        );
+        assert_eq!(labels[6..8], ["Inventory", "Cash"]);
+        assert_eq!(rendered.right().first().unwrap().label(), "Equity");
+        assert!(!rendered.to_string().contains("|"));
+    }
+
+    #[test]
+    fn test_layout_with_unknown_section() {
+        let mut layout = get_layout(LayoutForm::ReportForm);
+        layout.add_section("Goodwill", Side::Left);
+
+        assert_eq!(
+            layout.apply(&get_snapshot()).unwrap_err(),
+            BalanceSheetError::UnknownAccount("Goodwill".to_owned())
+        );
+    }
+}
@@ -0,0 +1,1165 @@
+use crate::journal::accounting_tree::AccountNodeRef;
+use crate::journal::bank_reconciliation::{BankStatement, StatementLine};
+use crate::journal::ledger::{EntryType, JournalEntry, Ledger};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+
+///
+/// Errors raised while importing a bank statement
+///
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    ///
+    /// The statement file could not be read
+    ///
+    Io(String),
+    ///
+    /// A CSV column named in the `CsvMapping` is not in the header
+    ///
+    MissingColumn(String),
+    ///
+    /// A record (line of a CSV file, transaction of an OFX or CAMT file) is invalid
+    ///
+    Parse { record: usize, message: String },
+    ///
+    /// The file is not structured as expected
+    ///
+    Format(String),
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Io(message) => write!(f, "Could not read the statement: {}", message),
+            ImportError::MissingColumn(column) => write!(f, "Missing column {:?}", column),
+            ImportError::Parse { record, message } => {
+                write!(f, "Invalid record {}: {}", record, message)
+            }
+            ImportError::Format(message) => write!(f, "Invalid statement: {}", message),
+        }
+    }
+}
+
+impl Error for ImportError {}
+
+///
+/// `ImportedStatement` is the normalized result of every importer
+///
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ImportedStatement {
+    account_id: Option<String>,
+    currency: Option<String>,
+    from_date: Option<DateTime<Utc>>,
+    to_date: Option<DateTime<Utc>>,
+    opening_balance: Option<f64>,
+    closing_balance: Option<f64>,
+    lines: Vec<StatementLine>,
+}
+
+impl ImportedStatement {
+    ///
+    /// Get the bank's identifier of the account (account number, IBAN, ...)
+    ///
+    pub fn account_id(&self) -> Option<&str> {
+        self.account_id.as_deref()
+    }
+
+    pub fn currency(&self) -> Option<&str> {
+        self.currency.as_deref()
+    }
+
+    pub fn from_date(&self) -> Option<DateTime<Utc>> {
+        self.from_date
+    }
+
+    pub fn to_date(&self) -> Option<DateTime<Utc>> {
+        self.to_date
+    }
+
+    pub fn opening_balance(&self) -> Option<f64> {
+        self.opening_balance
+    }
+
+    pub fn closing_balance(&self) -> Option<f64> {
+        self.closing_balance
+    }
+
+    pub fn lines(&self) -> &Vec<StatementLine> {
+        &self.lines
+    }
+
+    ///
+    /// Turn the import into a `BankStatement` of `account`.
+    /// The period defaults to the dates of the first and last lines. A missing balance
+    /// is derived from the other one and the lines; the opening balance defaults to `0`.
+    ///
+    pub fn to_bank_statement(&self, account: AccountNodeRef) -> Result<BankStatement, ImportError> {
+        let from_date = self
+            .from_date
+            .or_else(|| self.lines.iter().map(|line| *line.date()).min())
+            .ok_or_else(|| ImportError::Format("the statement has no dates".to_owned()))?;
+        let to_date = self
+            .to_date
+            .or_else(|| self.lines.iter().map(|line| *line.date()).max())
+            .unwrap_or(from_date);
+
+        let period = ReportingPeriod::range(from_date, to_date).map_err(|_| {
+            ImportError::Format(format!(
+                "the statement starts on {} after it ends on {}",
+                from_date.format("%Y-%m-%d"),
+                to_date.format("%Y-%m-%d")
+            ))
+        })?;
+
+        let movement: f64 = self.lines.iter().map(|line| line.amount()).sum();
+        let (opening_balance, closing_balance) = match (self.opening_balance, self.closing_balance)
+        {
+            (Some(opening), Some(closing)) => (opening, closing),
+            (Some(opening), None) => (opening, opening + movement),
+            (None, Some(closing)) => (closing - movement, closing),
+            (None, None) => (0f64, movement),
+        };
+
+        let mut statement = BankStatement::new(account, period, opening_balance, closing_balance);
+        statement.set_lines(self.lines.clone());
+
+        Ok(statement)
+    }
+}
+
+///
+/// `StatementImporter` trait implemented by every bank statement format
+///
+pub trait StatementImporter {
+    ///
+    /// Import a statement from the contents of a file
+    ///
+    fn import(&self, contents: &str) -> Result<ImportedStatement, ImportError>;
+
+    ///
+    /// Import a statement from the file at `path`
+    ///
+    fn import_file(&self, path: &str) -> Result<ImportedStatement, ImportError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|error| ImportError::Io(format!("{}: {}", path, error)))?;
+
+        self.import(&contents)
+    }
+}
+
+///
+/// Build draft journal entries for imported statement lines: deposits debit
+/// `bank_account` and credit `suspense_account`, withdrawals the other way around.
+/// The entries are returned for review rather than added to `ledger`, which gives
+/// them their ids.
+///
+pub fn draft_journal_entries(
+    lines: &[StatementLine],
+    bank_account: &AccountNodeRef,
+    suspense_account: &AccountNodeRef,
+    ledger: &mut Ledger,
+) -> Vec<JournalEntry> {
+    lines
+        .iter()
+        .filter(|line| line.amount() != 0f64)
+        .map(|line| {
+            let amount = line.amount().abs();
+            let (bank_entry_type, suspense_entry_type) = if line.amount() > 0f64 {
+                (EntryType::Debit, EntryType::Credit)
+            } else {
+                (EntryType::Credit, EntryType::Debit)
+            };
+
+            JournalEntry::from_lines(
+                ledger.id_sequence(),
+                *line.date(),
+                line.description(),
+                &[
+                    (bank_account, amount, bank_entry_type),
+                    (suspense_account, amount, suspense_entry_type),
+                ],
+            )
+        })
+        .collect()
+}
+
+///
+/// A CSV column, by position (starting at `0`) or by header name
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Index(usize),
+    Name(String),
+}
+
+impl Column {
+    pub fn name(name: &str) -> Self {
+        Column::Name(name.to_owned())
+    }
+}
+
+///
+/// How the amount of a line is laid out in a CSV export
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum AmountColumns {
+    ///
+    /// A single signed column. Set `negate` when the bank shows withdrawals as
+    /// positive amounts (e.g. credit card exports).
+    ///
+    Signed { column: Column, negate: bool },
+    ///
+    /// A deposit column and a withdrawal column, one of them empty on each line
+    ///
+    Split { deposit: Column, withdrawal: Column },
+    ///
+    /// An unsigned amount with a debit/credit indicator column. The line is a
+    /// withdrawal when the indicator is one of `withdrawal_values` (case-insensitive).
+    ///
+    Indicator {
+        amount: Column,
+        indicator: Column,
+        withdrawal_values: Vec<String>,
+    },
+}
+
+///
+/// `CsvMapping` describes the column layout of a bank's CSV export
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvMapping {
+    delimiter: char,
+    has_header: bool,
+    date_column: Column,
+    date_formats: Vec<String>,
+    description_column: Column,
+    reference_column: Option<Column>,
+    amount_columns: AmountColumns,
+    decimal_separator: char,
+}
+
+impl CsvMapping {
+    ///
+    /// Create a `CsvMapping` for a comma delimited file with a header row,
+    /// `YYYY-MM-DD` dates and `.` as the decimal separator
+    ///
+    pub fn new(
+        date_column: Column,
+        description_column: Column,
+        amount_columns: AmountColumns,
+    ) -> Self {
+        CsvMapping {
+            delimiter: ',',
+            has_header: true,
+            date_column,
+            date_formats: vec!["%Y-%m-%d".to_owned()],
+            description_column,
+            reference_column: None,
+            amount_columns,
+            decimal_separator: '.',
+        }
+    }
+
+    pub fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    pub fn set_delimiter(&mut self, delimiter: char) {
+        self.delimiter = delimiter;
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.has_header
+    }
+
+    pub fn set_has_header(&mut self, has_header: bool) {
+        self.has_header = has_header;
+    }
+
+    pub fn date_formats(&self) -> &Vec<String> {
+        &self.date_formats
+    }
+
+    ///
+    /// Set the `chrono` formats dates are tried against, in order
+    ///
+    pub fn set_date_formats(&mut self, date_formats: Vec<&str>) {
+        self.date_formats = date_formats.iter().map(|f| f.to_string()).collect();
+    }
+
+    pub fn reference_column(&self) -> Option<&Column> {
+        self.reference_column.as_ref()
+    }
+
+    pub fn set_reference_column(&mut self, reference_column: Option<Column>) {
+        self.reference_column = reference_column;
+    }
+
+    pub fn amount_columns(&self) -> &AmountColumns {
+        &self.amount_columns
+    }
+
+    pub fn decimal_separator(&self) -> char {
+        self.decimal_separator
+    }
+
+    ///
+    /// Set the decimal separator; the other of `.` and `,` is then read as a
+    /// thousands separator
+    ///
+    pub fn set_decimal_separator(&mut self, decimal_separator: char) {
+        self.decimal_separator = decimal_separator;
+    }
+}
+
+///
+/// `StatementImporter` for CSV exports
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvImporter {
+    mapping: CsvMapping,
+}
+
+impl CsvImporter {
+    pub fn new(mapping: CsvMapping) -> Self {
+        CsvImporter { mapping }
+    }
+
+    pub fn mapping(&self) -> &CsvMapping {
+        &self.mapping
+    }
+
+    fn parse_amount(&self, value: &str, record: usize) -> Result<Option<f64>, ImportError> {
+        parse_amount(value, self.mapping.decimal_separator)
+            .map_err(|message| ImportError::Parse { record, message })
+    }
+
+    fn parse_date(&self, value: &str, record: usize) -> Result<DateTime<Utc>, ImportError> {
+        for format in self.mapping.date_formats.iter() {
+            if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+                return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+            }
+            if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+                return Ok(date_time.and_utc());
+            }
+        }
+
+        Err(ImportError::Parse {
+            record,
+            message: format!("invalid date {:?}", value),
+        })
+    }
+}
+
+impl StatementImporter for CsvImporter {
+    fn import(&self, contents: &str) -> Result<ImportedStatement, ImportError> {
+        let mut records = parse_csv(contents, self.mapping.delimiter)?.into_iter();
+
+        let header = if self.mapping.has_header {
+            records.next().map(|(_, fields)| fields).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let position = |column: &Column| -> Result<usize, ImportError> {
+            match column {
+                Column::Index(index) => Ok(*index),
+                Column::Name(name) => header
+                    .iter()
+                    .position(|field| field.trim().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| ImportError::MissingColumn(name.to_owned())),
+            }
+        };
+
+        let date_column = position(&self.mapping.date_column)?;
+        let description_column = position(&self.mapping.description_column)?;
+        let reference_column = match &self.mapping.reference_column {
+            Some(column) => Some(position(column)?),
+            None => None,
+        };
+        let amount_columns = match &self.mapping.amount_columns {
+            AmountColumns::Signed { column, .. } => vec![position(column)?],
+            AmountColumns::Split {
+                deposit,
+                withdrawal,
+            } => vec![position(deposit)?, position(withdrawal)?],
+            AmountColumns::Indicator {
+                amount, indicator, ..
+            } => vec![position(amount)?, position(indicator)?],
+        };
+
+        let mut statement = ImportedStatement::default();
+
+        for (record, fields) in records {
+            if fields.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+
+            let field = |index: usize| -> Result<&str, ImportError> {
+                fields
+                    .get(index)
+                    .map(|field| field.trim())
+                    .ok_or_else(|| ImportError::Parse {
+                        record,
+                        message: format!("expected at least {} fields", index + 1),
+                    })
+            };
+
+            let amount = match &self.mapping.amount_columns {
+                AmountColumns::Signed { negate, .. } => {
+                    let amount = self
+                        .parse_amount(field(amount_columns[0])?, record)?
+                        .unwrap_or(0f64);
+                    if *negate {
+                        -amount
+                    } else {
+                        amount
+                    }
+                }
+                AmountColumns::Split { .. } => {
+                    let deposit = self.parse_amount(field(amount_columns[0])?, record)?;
+                    let withdrawal = self.parse_amount(field(amount_columns[1])?, record)?;
+                    deposit.unwrap_or(0f64).abs() - withdrawal.unwrap_or(0f64).abs()
+                }
+                AmountColumns::Indicator {
+                    withdrawal_values, ..
+                } => {
+                    let amount = self
+                        .parse_amount(field(amount_columns[0])?, record)?
+                        .unwrap_or(0f64)
+                        .abs();
+                    let indicator = field(amount_columns[1])?;
+                    if withdrawal_values
+                        .iter()
+                        .any(|value| value.eq_ignore_ascii_case(indicator))
+                    {
+                        -amount
+                    } else {
+                        amount
+                    }
+                }
+            };
+
+            let mut line = StatementLine::new(
+                statement.lines.len() + 1,
+                self.parse_date(field(date_column)?, record)?,
+                amount,
+                field(description_column)?,
+            );
+            if let Some(reference_column) = reference_column {
+                let reference = field(reference_column)?;
+                line.set_reference((!reference.is_empty()).then_some(reference));
+            }
+
+            statement.lines.push(line);
+        }
+
+        Ok(statement)
+    }
+}
+
+///
+/// Split CSV contents into records of fields, keeping the line each record starts on.
+/// Fields may be quoted with `"`, in which case they can hold delimiters, new lines
+/// and escaped (doubled) quotes.
+///
+fn parse_csv(contents: &str, delimiter: char) -> Result<Vec<(usize, Vec<String>)>, ImportError> {
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+    let mut chars = contents.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            '\n' if !in_quotes => {
+                fields.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut fields)));
+                line += 1;
+                record_line = line;
+            }
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(ImportError::Parse {
+            record: record_line,
+            message: "unterminated quoted field".to_owned(),
+        });
+    }
+
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        records.push((record_line, fields));
+    }
+
+    Ok(records)
+}
+
+///
+/// Parse an amount such as `1,234.50`, `-12`, `(12.00)` or `1.234,50` with a `,`
+/// decimal separator. Empty values are `None`.
+///
+fn parse_amount(value: &str, decimal_separator: char) -> Result<Option<f64>, String> {
+    let mut value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let negative = value.starts_with('(') && value.ends_with(')');
+    if negative {
+        value = &value[1..value.len() - 1];
+    }
+
+    let thousands_separator = if decimal_separator == ',' { '.' } else { ',' };
+    let normalized: String = value
+        .chars()
+        .filter(|c| *c != thousands_separator && !c.is_whitespace())
+        .map(|c| if c == decimal_separator { '.' } else { c })
+        .collect();
+
+    let amount: f64 = normalized
+        .parse()
+        .map_err(|_| format!("invalid amount {:?}", value))?;
+
+    Ok(Some(if negative { -amount } else { amount }))
+}
+
+///
+/// A tag or text of an OFX (SGML or XML) or CAMT (XML) document
+///
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+        empty: bool,
+    },
+    End(String),
+    Text(String),
+}
+
+///
+/// Strip the namespace prefix of a tag name
+///
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let decoded = entity.and_then(|(entity, _)| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        });
+
+        match (decoded, entity) {
+            (Some(c), Some((_, end))) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+fn parse_attributes(mut rest: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim();
+        let value = rest[equals + 1..].trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            break;
+        };
+        let Some(end) = value[1..].find(quote) else {
+            break;
+        };
+
+        attributes.push((local_name(name).to_owned(), unescape(&value[1..end + 1])));
+        rest = &value[end + 2..];
+    }
+
+    attributes
+}
+
+///
+/// Split a markup document into tags and non blank text.
+/// Processing instructions, comments and declarations are skipped.
+///
+fn tokenize(contents: &str) -> Result<Vec<Token>, ImportError> {
+    let mut tokens = Vec::new();
+    let mut rest = contents;
+
+    let unterminated = |what: &str| ImportError::Format(format!("unterminated {}", what));
+
+    while let Some(start) = rest.find('<') {
+        let text = rest[..start].trim();
+        if !text.is_empty() {
+            tokens.push(Token::Text(unescape(text)));
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").ok_or_else(|| unterminated("comment"))?;
+            rest = &comment[end + 3..];
+        } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let end = cdata
+                .find("]]>")
+                .ok_or_else(|| unterminated("CDATA section"))?;
+            if !cdata[..end].trim().is_empty() {
+                tokens.push(Token::Text(cdata[..end].trim().to_owned()));
+            }
+            rest = &cdata[end + 3..];
+        } else {
+            let end = rest.find('>').ok_or_else(|| unterminated("tag"))?;
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+
+            if let Some(name) = tag.strip_prefix('/') {
+                tokens.push(Token::End(local_name(name.trim()).to_owned()));
+                continue;
+            }
+
+            let empty = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+
+            tokens.push(Token::Start {
+                name: local_name(&tag[..name_end]).to_owned(),
+                attributes: parse_attributes(&tag[name_end..]),
+                empty,
+            });
+        }
+    }
+
+    if !rest.trim().is_empty() {
+        tokens.push(Token::Text(unescape(rest.trim())));
+    }
+
+    Ok(tokens)
+}
+
+///
+/// `StatementImporter` for OFX and QFX files, in both the SGML (1.x) and XML (2.x)
+/// flavours. The lines come from the `STMTTRN` aggregates and the closing balance
+/// from `LEDGERBAL`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OfxImporter;
+
+impl OfxImporter {
+    pub fn new() -> Self {
+        OfxImporter
+    }
+}
+
+///
+/// Parse an OFX date such as `20240105`, `20240105120000` or
+/// `20240105120000.000[-5:EST]`. The time zone is ignored.
+///
+fn parse_ofx_date(value: &str, record: usize) -> Result<DateTime<Utc>, ImportError> {
+    let digits: String = value.chars().take_while(|c| c.is_ascii_digit()).collect();
+
+    let date_time = match digits.len() {
+        8 => NaiveDate::parse_from_str(&digits, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0)),
+        12 => NaiveDateTime::parse_from_str(&format!("{}00", digits), "%Y%m%d%H%M%S").ok(),
+        14 => NaiveDateTime::parse_from_str(&digits, "%Y%m%d%H%M%S").ok(),
+        _ => None,
+    };
+
+    date_time
+        .map(|date_time| date_time.and_utc())
+        .ok_or_else(|| ImportError::Parse {
+            record,
+            message: format!("invalid date {:?}", value),
+        })
+}
+
+impl StatementImporter for OfxImporter {
+    fn import(&self, contents: &str) -> Result<ImportedStatement, ImportError> {
+        let start = contents
+            .find("<OFX>")
+            .ok_or_else(|| ImportError::Format("no <OFX> element".to_owned()))?;
+        let tokens = tokenize(&contents[start..])?;
+
+        let mut statement = ImportedStatement::default();
+        let mut transaction: Option<HashMap<String, String>> = None;
+        let mut in_ledger_balance = false;
+        let mut record = 0;
+
+        for (index, token) in tokens.iter().enumerate() {
+            match token {
+                Token::Start { name, .. } if name == "STMTTRN" => {
+                    record += 1;
+                    transaction = Some(HashMap::new());
+                }
+                Token::Start { name, .. } if name == "LEDGERBAL" => in_ledger_balance = true,
+                Token::End(name) if name == "LEDGERBAL" => in_ledger_balance = false,
+                Token::End(name) if name == "STMTTRN" => {
+                    let fields = transaction.take().unwrap_or_default();
+                    let field = |name: &str| -> Result<&str, ImportError> {
+                        fields.get(name).map(|value| value.as_str()).ok_or_else(|| {
+                            ImportError::Parse {
+                                record,
+                                message: format!("missing {}", name),
+                            }
+                        })
+                    };
+
+                    let amount = parse_amount(field("TRNAMT")?, '.')
+                        .map_err(|message| ImportError::Parse { record, message })?
+                        .unwrap_or(0f64);
+                    let description = match (fields.get("NAME"), fields.get("MEMO")) {
+                        (Some(name), Some(memo)) if name != memo => format!("{} {}", name, memo),
+                        (Some(name), _) => name.to_owned(),
+                        (None, Some(memo)) => memo.to_owned(),
+                        (None, None) => String::new(),
+                    };
+
+                    let mut line = StatementLine::new(
+                        statement.lines.len() + 1,
+                        parse_ofx_date(field("DTPOSTED")?, record)?,
+                        amount,
+                        &description,
+                    );
+                    line.set_reference(
+                        fields
+                            .get("CHECKNUM")
+                            .or_else(|| fields.get("FITID"))
+                            .map(|reference| reference.as_str()),
+                    );
+                    statement.lines.push(line);
+                }
+                Token::Start { name, .. } => {
+                    let Some(Token::Text(value)) = tokens.get(index + 1) else {
+                        continue;
+                    };
+
+                    if let Some(fields) = transaction.as_mut() {
+                        fields.insert(name.to_owned(), value.to_owned());
+                    } else if in_ledger_balance && name == "BALAMT" {
+                        statement.closing_balance =
+                            parse_amount(value, '.').map_err(ImportError::Format)?;
+                    } else {
+                        match name.as_str() {
+                            "CURDEF" => statement.currency = Some(value.to_owned()),
+                            "ACCTID" => statement.account_id = Some(value.to_owned()),
+                            "DTSTART" => statement.from_date = Some(parse_ofx_date(value, 0)?),
+                            "DTEND" => statement.to_date = Some(parse_ofx_date(value, 0)?),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(statement)
+    }
+}
+
+///
+/// `StatementImporter` for ISO 20022 CAMT.053 bank to customer statements.
+/// Entries of every `Stmt` of the document are imported; the opening (`OPBD`) and
+/// closing (`CLBD`) booked balances are read from the `Bal` elements.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Camt053Importer;
+
+impl Camt053Importer {
+    pub fn new() -> Self {
+        Camt053Importer
+    }
+}
+
+///
+/// Parse a CAMT date (`2024-01-05`) or date time (`2024-01-05T10:00:00+01:00`)
+///
+fn parse_iso_date(value: &str, record: usize) -> Result<DateTime<Utc>, ImportError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date_time| date_time.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S").map(|d| d.and_utc()))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        })
+        .map_err(|_| ImportError::Parse {
+            record,
+            message: format!("invalid date {:?}", value),
+        })
+}
+
+///
+/// Sign a CAMT amount by its `CdtDbtInd`: credits are deposits and debits withdrawals
+///
+fn camt_amount(fields: &HashMap<String, String>, record: usize) -> Result<f64, ImportError> {
+    let amount = fields
+        .get("Amt")
+        .ok_or_else(|| ImportError::Parse {
+            record,
+            message: "missing Amt".to_owned(),
+        })
+        .and_then(|amount| {
+            parse_amount(amount, '.').map_err(|message| ImportError::Parse { record, message })
+        })?
+        .unwrap_or(0f64);
+
+    match fields.get("CdtDbtInd").map(|indicator| indicator.as_str()) {
+        Some("CRDT") => Ok(amount),
+        Some("DBIT") => Ok(-amount),
+        indicator => Err(ImportError::Parse {
+            record,
+            message: format!("invalid CdtDbtInd {:?}", indicator),
+        }),
+    }
+}
+
+impl StatementImporter for Camt053Importer {
+    fn import(&self, contents: &str) -> Result<ImportedStatement, ImportError> {
+        let tokens = tokenize(contents)?;
+
+        let mut statement = ImportedStatement::default();
+        let mut stack: Vec<String> = Vec::new();
+        // The `Ntry` or `Bal` being read, its depth and its fields by relative path
+        let mut element: Option<(String, usize, HashMap<String, String>)> = None;
+        let mut found_statement = false;
+        let mut record = 0;
+
+        for token in tokens.iter() {
+            match token {
+                Token::Start {
+                    name,
+                    attributes,
+                    empty,
+                } => {
+                    if *empty {
+                        continue;
+                    }
+                    stack.push(name.to_owned());
+
+                    let in_statement = stack.len() >= 2 && stack[stack.len() - 2] == "Stmt";
+                    if in_statement && (name == "Ntry" || name == "Bal") && element.is_none() {
+                        element = Some((name.to_owned(), stack.len(), HashMap::new()));
+                    } else if name == "Stmt" {
+                        found_statement = true;
+                    }
+
+                    if let Some((_, depth, fields)) = element.as_mut() {
+                        if name == "Amt" && stack.len() == *depth + 1 {
+                            if let Some((_, currency)) = attributes.iter().find(|(a, _)| a == "Ccy")
+                            {
+                                statement
+                                    .currency
+                                    .get_or_insert_with(|| currency.to_owned());
+                                fields.insert("Amt@Ccy".to_owned(), currency.to_owned());
+                            }
+                        }
+                    }
+                }
+                Token::Text(value) => {
+                    if let Some((_, depth, fields)) = element.as_mut() {
+                        fields
+                            .entry(stack[*depth..].join("/"))
+                            .or_insert_with(|| value.to_owned());
+                        continue;
+                    }
+
+                    let Some(statement_depth) = stack.iter().rposition(|name| name == "Stmt")
+                    else {
+                        continue;
+                    };
+                    match stack[statement_depth + 1..].join("/").as_str() {
+                        "Acct/Id/IBAN" | "Acct/Id/Othr/Id" => {
+                            statement.account_id.get_or_insert_with(|| value.to_owned());
+                        }
+                        "Acct/Ccy" => statement.currency = Some(value.to_owned()),
+                        "FrToDt/FrDtTm" => {
+                            statement.from_date = Some(parse_iso_date(value, record)?);
+                        }
+                        "FrToDt/ToDtTm" => {
+                            statement.to_date = Some(parse_iso_date(value, record)?);
+                        }
+                        _ => {}
+                    }
+                }
+                Token::End(name) => {
+                    let closes_element =
+                        element.as_ref().is_some_and(|(element_name, depth, _)| {
+                            element_name == name && stack.len() == *depth
+                        });
+                    stack.pop();
+
+                    if !closes_element {
+                        continue;
+                    }
+
+                    let (element_name, _, fields) = element.take().unwrap();
+                    if element_name == "Bal" {
+                        let amount = camt_amount(&fields, record)?;
+                        match fields.get("Tp/CdOrPrtry/Cd").map(|code| code.as_str()) {
+                            Some("OPBD") if statement.opening_balance.is_none() => {
+                                statement.opening_balance = Some(amount);
+                            }
+                            Some("CLBD") => statement.closing_balance = Some(amount),
+                            _ => {}
+                        }
+                        continue;
+                    }
+
+                    record += 1;
+                    let date = fields
+                        .get("BookgDt/Dt")
+                        .or_else(|| fields.get("BookgDt/DtTm"))
+                        .or_else(|| fields.get("ValDt/Dt"))
+                        .or_else(|| fields.get("ValDt/DtTm"))
+                        .ok_or_else(|| ImportError::Parse {
+                            record,
+                            message: "missing BookgDt".to_owned(),
+                        })?;
+                    let description = fields
+                        .get("NtryDtls/TxDtls/RmtInf/Ustrd")
+                        .or_else(|| fields.get("AddtlNtryInf"))
+                        .or_else(|| fields.get("NtryDtls/TxDtls/AddtlTxInf"))
+                        .map(|description| description.as_str())
+                        .unwrap_or("");
+
+                    let mut line = StatementLine::new(
+                        statement.lines.len() + 1,
+                        parse_iso_date(date, record)?,
+                        camt_amount(&fields, record)?,
+                        description,
+                    );
+                    line.set_reference(
+                        fields
+                            .get("AcctSvcrRef")
+                            .or_else(|| fields.get("NtryRef"))
+                            .map(|reference| reference.as_str()),
+                    );
+                    statement.lines.push(line);
+                }
+            }
+        }
+
+        if !found_statement {
+            return Err(ImportError::Format("no Stmt element".to_owned()));
+        }
+
+        Ok(statement)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        draft_journal_entries, AmountColumns, Camt053Importer, Column, CsvImporter, CsvMapping,
+        ImportError, OfxImporter, StatementImporter,
+    };
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_csv_import() {
+        let mut mapping = CsvMapping::new(
+            Column::name("Booking date"),
+            Column::name("Details"),
+            AmountColumns::Split {
+                deposit: Column::name("Credit"),
+                withdrawal: Column::name("Debit"),
+            },
+        );
+        mapping.set_delimiter(';');
+        mapping.set_decimal_separator(',');
+        mapping.set_date_formats(vec!["%d.%m.%Y"]);
+        mapping.set_reference_column(Some(Column::Index(1)));
+
+        let contents = "Booking date;Ref;Details;Debit;Credit\r\n\
+            02.01.2024;A1;\"ACME; invoice \"\"42\"\"\";;1.250,50\r\n\
+            05.01.2024;;Rent;300,00;\r\n";
+        let statement = CsvImporter::new(mapping.clone()).import(contents).unwrap();
+
+        assert_eq!(statement.lines().len(), 2);
+        assert_eq!(statement.lines()[0].description(), "ACME; invoice \"42\"");
+        assert_eq!(statement.lines()[0].amount(), 1_250.5);
+        assert_eq!(statement.lines()[0].reference(), Some("A1"));
+        assert_eq!(*statement.lines()[1].date(), date(2024, 1, 5));
+        assert_eq!(statement.lines()[1].amount(), -300.0);
+        assert_eq!(statement.lines()[1].reference(), None);
+
+        let mut signed = CsvMapping::new(
+            Column::Index(0),
+            Column::Index(1),
+            AmountColumns::Signed {
+                column: Column::Index(2),
+                negate: true,
+            },
+        );
+        signed.set_has_header(false);
+        let statement = CsvImporter::new(signed)
+            .import("2024-01-03,Card payment,(15.00)\n2024-01-04,Coffee,4.5")
+            .unwrap();
+        assert_eq!(statement.lines()[0].amount(), 15.0);
+        assert_eq!(statement.lines()[1].amount(), -4.5);
+
+        assert_eq!(
+            CsvImporter::new(mapping)
+                .import("Date;Details;Debit;Credit\n")
+                .unwrap_err(),
+            ImportError::MissingColumn("Booking date".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_ofx_import() {
+        let contents = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>USD
+<BANKACCTFROM><BANKID>121000248<ACCTID>123456789<ACCTTYPE>CHECKING</BANKACCTFROM>
+<BANKTRANLIST>
+<DTSTART>20240101<DTEND>20240131235959.000[-5:EST]
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240103<TRNAMT>1000.00<FITID>T1<NAME>DEPOSIT</STMTTRN>
+<STMTTRN><TRNTYPE>CHECK<DTPOSTED>20240120120000<TRNAMT>-300.00<FITID>T2<CHECKNUM>1001
+<NAME>CHQ 1001<MEMO>Rent &amp; service</STMTTRN>
+</BANKTRANLIST>
+<LEDGERBAL><BALAMT>700.00<DTASOF>20240131</LEDGERBAL>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>";
+
+        let statement = OfxImporter::new().import(contents).unwrap();
+
+        assert_eq!(statement.account_id(), Some("123456789"));
+        assert_eq!(statement.currency(), Some("USD"));
+        assert_eq!(statement.closing_balance(), Some(700.0));
+        assert_eq!(statement.lines().len(), 2);
+        assert_eq!(statement.lines()[1].amount(), -300.0);
+        assert_eq!(
+            statement.lines()[1].description(),
+            "CHQ 1001 Rent & service"
+        );
+        assert_eq!(statement.lines()[1].reference(), Some("1001"));
+
+        let bank_statement = statement
+            .to_bank_statement(test_chart().account("Cash"))
+            .unwrap();
+        assert_eq!(bank_statement.opening_balance(), 0.0);
+        assert_eq!(bank_statement.period().from_date(), date(2024, 1, 1));
+    }
+
+    #[test]
+    fn test_camt053_import_and_draft_entries() {
+        let contents = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG1</MsgId></GrpHdr>
+    <Stmt>
+      <Id>STMT1</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id><Ccy>EUR</Ccy></Acct>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>OPBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">100.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-01-01</Dt></Dt>
+      </Bal>
+      <Bal>
+        <Tp><CdOrPrtry><Cd>CLBD</Cd></CdOrPrtry></Tp>
+        <Amt Ccy="EUR">330.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <Dt><Dt>2024-01-31</Dt></Dt>
+      </Bal>
+      <Ntry>
+        <NtryRef>1</NtryRef>
+        <Amt Ccy="EUR">250.00</Amt><CdtDbtInd>CRDT</CdtDbtInd>
+        <BookgDt><Dt>2024-01-05</Dt></BookgDt>
+        <AcctSvcrRef>REF-1</AcctSvcrRef>
+        <NtryDtls><TxDtls><RmtInf><Ustrd>Invoice 42</Ustrd></RmtInf></TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">20.00</Amt><CdtDbtInd>DBIT</CdtDbtInd>
+        <BookgDt><DtTm>2024-01-31T18:00:00+01:00</DtTm></BookgDt>
+        <AddtlNtryInf>Account fee</AddtlNtryInf>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>"#;
+
+        let statement = Camt053Importer::new().import(contents).unwrap();
+
+        assert_eq!(statement.account_id(), Some("DE89370400440532013000"));
+        assert_eq!(statement.currency(), Some("EUR"));
+        assert_eq!(statement.opening_balance(), Some(100.0));
+        assert_eq!(statement.closing_balance(), Some(330.0));
+        assert_eq!(statement.lines().len(), 2);
+        assert_eq!(statement.lines()[0].description(), "Invoice 42");
+        assert_eq!(statement.lines()[0].reference(), Some("REF-1"));
+        assert_eq!(statement.lines()[1].amount(), -20.0);
+
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let suspense = chart.account("Accounts Payable");
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        let entries = draft_journal_entries(statement.lines(), &cash, &suspense, &mut ledger);
+
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.validate()));
+        assert_eq!(entries[1].id(), 4);
+        assert_eq!(
+            entries[1].transaction_entries()[0].entry_type(),
+            &EntryType::Credit
+        );
+        assert_eq!(ledger.id_sequence().peek(), 7);
+    }
+}
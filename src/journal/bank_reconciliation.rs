@@ -0,0 +1,918 @@
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTreeNode};
+use crate::journal::ledger::{EntryType, Ledger, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// A single line of a bank statement. Deposits are positive amounts and
+/// withdrawals negative ones.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementLine {
+    id: usize,
+    date: DateTime<Utc>,
+    amount: f64,
+    description: String,
+    reference: Option<String>,
+}
+
+impl StatementLine {
+    pub fn new(id: usize, date: DateTime<Utc>, amount: f64, description: &str) -> Self {
+        StatementLine {
+            id,
+            date,
+            amount,
+            description: description.to_owned(),
+            reference: None,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    ///
+    /// Get the bank's reference of the line (cheque number, transaction id, ...)
+    ///
+    pub fn reference(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+
+    pub fn set_reference(&mut self, reference: Option<&str>) {
+        self.reference = reference.map(|r| r.to_owned());
+    }
+}
+
+///
+/// `BankStatement` of the bank account behind a cash `AccountNode`
+///
+#[derive(Debug, Clone)]
+pub struct BankStatement {
+    account: AccountNodeRef,
+    period: ReportingPeriod,
+    opening_balance: f64,
+    closing_balance: f64,
+    lines: Vec<StatementLine>,
+}
+
+impl BankStatement {
+    pub fn new(
+        account: AccountNodeRef,
+        period: ReportingPeriod,
+        opening_balance: f64,
+        closing_balance: f64,
+    ) -> Self {
+        BankStatement {
+            account,
+            period,
+            opening_balance,
+            closing_balance,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn account(&self) -> AccountNodeRef {
+        self.account.clone()
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn opening_balance(&self) -> f64 {
+        self.opening_balance
+    }
+
+    pub fn closing_balance(&self) -> f64 {
+        self.closing_balance
+    }
+
+    pub fn add_line(&mut self, line: StatementLine) {
+        self.lines.push(line);
+    }
+
+    pub fn set_lines(&mut self, lines: Vec<StatementLine>) {
+        self.lines = lines;
+    }
+
+    pub fn lines(&self) -> &Vec<StatementLine> {
+        &self.lines
+    }
+
+    pub fn get_line_by_id(&self, id: usize) -> Option<&StatementLine> {
+        self.lines.iter().find(|line| line.id() == id)
+    }
+}
+
+///
+/// A posting to the reconciled account as recorded in the books.
+/// Debits are positive amounts and credits negative ones, as on the bank statement.
+///
+#[derive(Debug, Clone)]
+pub struct BookItem {
+    journal_entry_id: usize,
+    date: DateTime<Utc>,
+    transaction_entry: Rc<TransactionEntry>,
+}
+
+impl BookItem {
+    pub fn journal_entry_id(&self) -> usize {
+        self.journal_entry_id
+    }
+
+    pub fn transaction_entry_id(&self) -> usize {
+        self.transaction_entry.id()
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn amount(&self) -> f64 {
+        match self.transaction_entry.entry_type() {
+            EntryType::Debit => self.transaction_entry.amount(),
+            EntryType::Credit => -self.transaction_entry.amount(),
+        }
+    }
+
+    pub fn description(&self) -> &str {
+        self.transaction_entry.description()
+    }
+
+    pub fn transaction_entry(&self) -> &Rc<TransactionEntry> {
+        &self.transaction_entry
+    }
+}
+
+///
+/// How a statement line was paired with a book item
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchKind {
+    Auto,
+    Manual,
+}
+
+///
+/// A statement line paired with a book item
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconciliationMatch {
+    statement_line_id: usize,
+    transaction_entry_id: usize,
+    kind: MatchKind,
+}
+
+impl ReconciliationMatch {
+    pub fn statement_line_id(&self) -> usize {
+        self.statement_line_id
+    }
+
+    pub fn transaction_entry_id(&self) -> usize {
+        self.transaction_entry_id
+    }
+
+    pub fn kind(&self) -> MatchKind {
+        self.kind
+    }
+}
+
+///
+/// Rules used when automatically matching statement lines to book items
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchRules {
+    date_window_days: i64,
+    amount_tolerance: f64,
+    min_description_similarity: f64,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules {
+            date_window_days: 3,
+            amount_tolerance: DEFAULT_TOLERANCE,
+            min_description_similarity: 0f64,
+        }
+    }
+}
+
+impl MatchRules {
+    pub fn new(
+        date_window_days: i64,
+        amount_tolerance: f64,
+        min_description_similarity: f64,
+    ) -> Self {
+        MatchRules {
+            date_window_days,
+            amount_tolerance,
+            min_description_similarity,
+        }
+    }
+
+    ///
+    /// Get how many days apart a statement line and a book item may be dated
+    ///
+    pub fn date_window_days(&self) -> i64 {
+        self.date_window_days
+    }
+
+    pub fn amount_tolerance(&self) -> f64 {
+        self.amount_tolerance
+    }
+
+    ///
+    /// Get the lowest description similarity, between 0 and 1, a match may have
+    ///
+    pub fn min_description_similarity(&self) -> f64 {
+        self.min_description_similarity
+    }
+}
+
+///
+/// Similarity of two descriptions between 0 and 1: the Dice coefficient of their
+/// lowercased alphanumeric words.
+///
+pub fn description_similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(|word| word.to_lowercase())
+            .collect()
+    };
+
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1f64;
+    }
+
+    2f64 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+///
+/// Errors raised while matching statement lines to book items
+///
+#[derive(Debug, PartialEq)]
+pub enum ReconciliationError {
+    UnknownStatementLine(usize),
+    UnknownTransactionEntry(usize),
+    StatementLineAlreadyMatched(usize),
+    TransactionEntryAlreadyMatched(usize),
+}
+
+impl Display for ReconciliationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconciliationError::UnknownStatementLine(id) => {
+                write!(f, "Statement line {} is not on the bank statement.", id)
+            }
+            ReconciliationError::UnknownTransactionEntry(id) => write!(
+                f,
+                "Transaction entry {} is not a posting to the reconciled account.",
+                id
+            ),
+            ReconciliationError::StatementLineAlreadyMatched(id) => {
+                write!(f, "Statement line {} is already matched.", id)
+            }
+            ReconciliationError::TransactionEntryAlreadyMatched(id) => {
+                write!(f, "Transaction entry {} is already matched.", id)
+            }
+        }
+    }
+}
+
+impl Error for ReconciliationError {}
+
+///
+/// `BankReconciliation` pairs the lines of a `BankStatement` with the postings made to
+/// its account in a `Ledger` up to the end of the statement period that were not
+/// reconciled on an earlier statement.
+///
+#[derive(Debug, Clone)]
+pub struct BankReconciliation {
+    statement: BankStatement,
+    book_items: Vec<BookItem>,
+    book_balance: f64,
+    matches: Vec<ReconciliationMatch>,
+    reconciled: HashSet<usize>,
+}
+
+impl BankReconciliation {
+    ///
+    /// Start reconciling the account with its first statement. The postings dated
+    /// before the statement period are taken as cleared by its opening balance.
+    ///
+    pub fn new(statement: BankStatement, ledger: &Ledger) -> Self {
+        let from_date = statement.period().from_date();
+        let reconciled = Self::postings(&statement, ledger)
+            .filter(|item| item.date < from_date)
+            .map(|item| item.transaction_entry_id())
+            .collect();
+
+        Self::with_reconciled(statement, ledger, reconciled)
+    }
+
+    ///
+    /// Reconcile the statement following this one. The postings left unmatched on this
+    /// statement, such as cheques that clear later, are carried over.
+    ///
+    pub fn next(&self, statement: BankStatement, ledger: &Ledger) -> Self {
+        let mut reconciled = self.reconciled.clone();
+        reconciled.extend(self.matches.iter().map(|m| m.transaction_entry_id));
+
+        Self::with_reconciled(statement, ledger, reconciled)
+    }
+
+    ///
+    /// Reconcile `statement` against the postings to its account except the
+    /// transaction entries in `reconciled`, which were reconciled on earlier statements
+    ///
+    pub fn with_reconciled(
+        statement: BankStatement,
+        ledger: &Ledger,
+        reconciled: HashSet<usize>,
+    ) -> Self {
+        let mut book_items = Vec::new();
+        let mut book_balance = 0f64;
+
+        for book_item in Self::postings(&statement, ledger) {
+            book_balance += book_item.amount();
+            if !reconciled.contains(&book_item.transaction_entry_id()) {
+                book_items.push(book_item);
+            }
+        }
+
+        BankReconciliation {
+            statement,
+            book_items,
+            book_balance,
+            matches: Vec::new(),
+            reconciled,
+        }
+    }
+
+    ///
+    /// Get the postings to the account of `statement` up to the end of its period
+    ///
+    fn postings<'a>(
+        statement: &BankStatement,
+        ledger: &'a Ledger,
+    ) -> impl Iterator<Item = BookItem> + 'a {
+        let account_name = statement.account().borrow().name().to_owned();
+        let to_date = statement.period().to_date();
+
+        ledger
+            .active_journal_entries()
+            .filter(move |journal_entry| journal_entry.date_of_entry() <= to_date)
+            .flat_map(move |journal_entry| {
+                let account_name = account_name.clone();
+                journal_entry
+                    .transaction_entries()
+                    .iter()
+                    .filter(move |transaction_entry| {
+                        transaction_entry.account_name() == account_name
+                    })
+                    .map(move |transaction_entry| BookItem {
+                        journal_entry_id: journal_entry.id(),
+                        date: journal_entry.date_of_entry(),
+                        transaction_entry: transaction_entry.clone(),
+                    })
+            })
+    }
+
+    ///
+    /// Get the ids of the transaction entries reconciled on earlier statements
+    ///
+    pub fn reconciled(&self) -> &HashSet<usize> {
+        &self.reconciled
+    }
+
+    pub fn statement(&self) -> &BankStatement {
+        &self.statement
+    }
+
+    ///
+    /// Get the postings to the account up to the end of the statement period that were
+    /// not reconciled on an earlier statement
+    ///
+    pub fn book_items(&self) -> &Vec<BookItem> {
+        &self.book_items
+    }
+
+    ///
+    /// Get the balance of the account in the books at the end of the statement period
+    ///
+    pub fn book_balance(&self) -> f64 {
+        self.book_balance
+    }
+
+    pub fn matches(&self) -> &Vec<ReconciliationMatch> {
+        &self.matches
+    }
+
+    fn is_line_matched(&self, statement_line_id: usize) -> bool {
+        self.matches
+            .iter()
+            .any(|m| m.statement_line_id == statement_line_id)
+    }
+
+    fn is_item_matched(&self, transaction_entry_id: usize) -> bool {
+        self.matches
+            .iter()
+            .any(|m| m.transaction_entry_id == transaction_entry_id)
+    }
+
+    ///
+    /// Match the unmatched statement lines to unmatched book items of the same amount
+    /// dated within the date window. When several pairs qualify, the ones with the most
+    /// similar descriptions, then the closest dates, are matched first.
+    /// Returns the number of new matches.
+    ///
+    pub fn auto_match(&mut self, rules: &MatchRules) -> usize {
+        let mut candidates: Vec<(f64, i64, usize, usize)> = Vec::new();
+
+        for line in self
+            .statement
+            .lines()
+            .iter()
+            .filter(|line| !self.is_line_matched(line.id()))
+        {
+            for item in self
+                .book_items
+                .iter()
+                .filter(|item| !self.is_item_matched(item.transaction_entry_id()))
+            {
+                if (line.amount() - item.amount()).abs() > rules.amount_tolerance() {
+                    continue;
+                }
+
+                let days_apart = (*line.date() - *item.date()).num_days().abs();
+                if days_apart > rules.date_window_days() {
+                    continue;
+                }
+
+                let similarity = description_similarity(line.description(), item.description());
+                if similarity < rules.min_description_similarity() {
+                    continue;
+                }
+
+                candidates.push((
+                    similarity,
+                    days_apart,
+                    line.id(),
+                    item.transaction_entry_id(),
+                ));
+            }
+        }
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut matched = 0;
+        for (_, _, statement_line_id, transaction_entry_id) in candidates {
+            if self.is_line_matched(statement_line_id) || self.is_item_matched(transaction_entry_id)
+            {
+                continue;
+            }
+
+            self.matches.push(ReconciliationMatch {
+                statement_line_id,
+                transaction_entry_id,
+                kind: MatchKind::Auto,
+            });
+            matched += 1;
+        }
+
+        matched
+    }
+
+    ///
+    /// Match a statement line to a book item by hand. The amounts may differ; the
+    /// difference is carried to the adjusted book balance of the report.
+    ///
+    pub fn add_manual_match(
+        &mut self,
+        statement_line_id: usize,
+        transaction_entry_id: usize,
+    ) -> Result<(), ReconciliationError> {
+        if self.statement.get_line_by_id(statement_line_id).is_none() {
+            return Err(ReconciliationError::UnknownStatementLine(statement_line_id));
+        }
+        if !self
+            .book_items
+            .iter()
+            .any(|item| item.transaction_entry_id() == transaction_entry_id)
+        {
+            return Err(ReconciliationError::UnknownTransactionEntry(
+                transaction_entry_id,
+            ));
+        }
+        if self.is_line_matched(statement_line_id) {
+            return Err(ReconciliationError::StatementLineAlreadyMatched(
+                statement_line_id,
+            ));
+        }
+        if self.is_item_matched(transaction_entry_id) {
+            return Err(ReconciliationError::TransactionEntryAlreadyMatched(
+                transaction_entry_id,
+            ));
+        }
+
+        self.matches.push(ReconciliationMatch {
+            statement_line_id,
+            transaction_entry_id,
+            kind: MatchKind::Manual,
+        });
+
+        Ok(())
+    }
+
+    ///
+    /// Remove the match of a statement line, if any
+    ///
+    pub fn remove_match(&mut self, statement_line_id: usize) {
+        self.matches
+            .retain(|m| m.statement_line_id != statement_line_id);
+    }
+
+    pub fn unmatched_statement_lines(&self) -> Vec<&StatementLine> {
+        self.statement
+            .lines()
+            .iter()
+            .filter(|line| !self.is_line_matched(line.id()))
+            .collect()
+    }
+
+    pub fn unmatched_book_items(&self) -> Vec<&BookItem> {
+        self.book_items
+            .iter()
+            .filter(|item| !self.is_item_matched(item.transaction_entry_id()))
+            .collect()
+    }
+
+    ///
+    /// Produce the report tying the bank's closing balance to the book balance
+    ///
+    pub fn report(&self) -> ReconciliationReport {
+        let unmatched_items = self.unmatched_book_items();
+        let deposits_in_transit: f64 = unmatched_items
+            .iter()
+            .filter(|item| item.amount() > 0f64)
+            .map(|item| item.amount())
+            .sum();
+        let outstanding_payments: f64 = unmatched_items
+            .iter()
+            .filter(|item| item.amount() < 0f64)
+            .map(|item| -item.amount())
+            .sum();
+
+        let bank_only_items: f64 = self
+            .unmatched_statement_lines()
+            .iter()
+            .map(|line| line.amount())
+            .sum();
+
+        let match_differences: f64 = self
+            .matches
+            .iter()
+            .map(|m| {
+                let line = self.statement.get_line_by_id(m.statement_line_id).unwrap();
+                let item = self
+                    .book_items
+                    .iter()
+                    .find(|item| item.transaction_entry_id() == m.transaction_entry_id)
+                    .unwrap();
+                line.amount() - item.amount()
+            })
+            .sum();
+
+        ReconciliationReport {
+            period: *self.statement.period(),
+            bank_balance: self.statement.closing_balance(),
+            deposits_in_transit,
+            outstanding_payments,
+            book_balance: self.book_balance,
+            bank_only_items,
+            match_differences,
+        }
+    }
+}
+
+///
+/// `ReconciliationReport` ties the bank balance to the book balance:
+///
+/// `bank balance + deposits in transit - outstanding payments`
+/// equals
+/// `book balance + bank only items + match differences`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconciliationReport {
+    period: ReportingPeriod,
+    bank_balance: f64,
+    deposits_in_transit: f64,
+    outstanding_payments: f64,
+    book_balance: f64,
+    bank_only_items: f64,
+    match_differences: f64,
+}
+
+impl ReconciliationReport {
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn bank_balance(&self) -> f64 {
+        self.bank_balance
+    }
+
+    ///
+    /// Get the unmatched book debits, not yet seen by the bank
+    ///
+    pub fn deposits_in_transit(&self) -> f64 {
+        self.deposits_in_transit
+    }
+
+    ///
+    /// Get the unmatched book credits, not yet seen by the bank
+    ///
+    pub fn outstanding_payments(&self) -> f64 {
+        self.outstanding_payments
+    }
+
+    pub fn book_balance(&self) -> f64 {
+        self.book_balance
+    }
+
+    ///
+    /// Get the net of the unmatched statement lines (fees, interest, ...) not yet booked
+    ///
+    pub fn bank_only_items(&self) -> f64 {
+        self.bank_only_items
+    }
+
+    ///
+    /// Get the net difference between the matched statement lines and book items
+    ///
+    pub fn match_differences(&self) -> f64 {
+        self.match_differences
+    }
+
+    pub fn adjusted_bank_balance(&self) -> f64 {
+        self.bank_balance + self.deposits_in_transit - self.outstanding_payments
+    }
+
+    pub fn adjusted_book_balance(&self) -> f64 {
+        self.book_balance + self.bank_only_items + self.match_differences
+    }
+
+    pub fn difference(&self) -> f64 {
+        self.adjusted_bank_balance() - self.adjusted_book_balance()
+    }
+
+    pub fn is_reconciled(&self) -> bool {
+        self.difference().abs() <= DEFAULT_TOLERANCE
+    }
+}
+
+impl Display for ReconciliationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Bank Reconciliation {}", self.period)?;
+        writeln!(f, "{:<30}{:>15.2}", "Balance per bank", self.bank_balance)?;
+        writeln!(
+            f,
+            "{:<30}{:>15.2}",
+            "Add: deposits in transit", self.deposits_in_transit
+        )?;
+        writeln!(
+            f,
+            "{:<30}{:>15.2}",
+            "Less: outstanding payments", self.outstanding_payments
+        )?;
+        writeln!(
+            f,
+            "{:<30}{:>15.2}",
+            "Adjusted bank balance",
+            self.adjusted_bank_balance()
+        )?;
+        writeln!(f, "{:<30}{:>15.2}", "Balance per books", self.book_balance)?;
+        writeln!(f, "{:<30}{:>15.2}", "Bank only items", self.bank_only_items)?;
+        writeln!(
+            f,
+            "{:<30}{:>15.2}",
+            "Match differences", self.match_differences
+        )?;
+        writeln!(
+            f,
+            "{:<30}{:>15.2}",
+            "Adjusted book balance",
+            self.adjusted_book_balance()
+        )?;
+        write!(f, "{:<30}{:>15.2}", "Difference", self.difference())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        description_similarity, BankReconciliation, BankStatement, MatchKind, MatchRules,
+        ReconciliationError, StatementLine,
+    };
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    #[test]
+    fn test_description_similarity() {
+        assert_eq!(
+            description_similarity("ACME Ltd invoice 42", "acme ltd"),
+            4.0 / 6.0
+        );
+        assert_eq!(description_similarity("Rent", "Wages"), 0.0);
+    }
+
+    #[test]
+    fn test_bank_reconciliation() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let sales = chart.account("Sales Revenue");
+        let rent = chart.account("Rent Expense");
+        let wages = chart.account("Wages Expense");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        for (id, day, description, account, amount, cash_entry_type) in [
+            (1, 2, "Opening deposit", &sales, 1_000.0, EntryType::Debit),
+            (2, 5, "ACME Ltd invoice 42", &sales, 400.0, EntryType::Debit),
+            (3, 5, "Globex invoice 43", &sales, 400.0, EntryType::Debit),
+            (4, 10, "March rent", &rent, 300.0, EntryType::Credit),
+            (5, 30, "Wages January", &wages, 250.0, EntryType::Credit),
+            (6, 31, "Cash sales", &sales, 150.0, EntryType::Debit),
+        ] {
+            let other_entry_type = match cash_entry_type {
+                EntryType::Debit => EntryType::Credit,
+                EntryType::Credit => EntryType::Debit,
+            };
+            ledger.add_journal_entry(journal_entry(
+                id,
+                date(2024, 1, day),
+                description,
+                &[
+                    (&cash, amount, cash_entry_type),
+                    (account, amount, other_entry_type),
+                ],
+            ));
+        }
+
+        let mut statement = BankStatement::new(
+            cash.clone(),
+            ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap(),
+            0.0,
+            1_480.0,
+        );
+        statement.set_lines(vec![
+            StatementLine::new(1, date(2024, 1, 3), 1_000.0, "DEPOSIT"),
+            StatementLine::new(2, date(2024, 1, 6), 400.0, "GLOBEX PAYMENT"),
+            StatementLine::new(3, date(2024, 1, 7), 400.0, "ACME LTD"),
+            StatementLine::new(4, date(2024, 1, 20), -300.0, "CHQ 1001"),
+            StatementLine::new(5, date(2024, 1, 31), -20.0, "Service fee"),
+        ]);
+
+        let mut reconciliation = BankReconciliation::new(statement, &ledger);
+        assert_eq!(reconciliation.auto_match(&MatchRules::default()), 3);
+
+        let matched_to = |line_id: usize| {
+            reconciliation
+                .matches()
+                .iter()
+                .find(|m| m.statement_line_id() == line_id)
+                .map(|m| m.transaction_entry_id())
+        };
+        assert_eq!(matched_to(2), Some(300));
+        assert_eq!(matched_to(3), Some(200));
+        assert_eq!(matched_to(4), None);
+
+        // The cheque cleared outside of the date window
+        reconciliation.add_manual_match(4, 400).unwrap();
+        assert_eq!(reconciliation.matches()[3].kind(), MatchKind::Manual);
+        assert_eq!(
+            reconciliation.add_manual_match(5, 400),
+            Err(ReconciliationError::TransactionEntryAlreadyMatched(400))
+        );
+
+        assert_eq!(reconciliation.unmatched_statement_lines().len(), 1);
+        assert_eq!(reconciliation.unmatched_book_items().len(), 2);
+
+        let report = reconciliation.report();
+        assert_eq!(report.book_balance(), 1_400.0);
+        assert_eq!(report.deposits_in_transit(), 150.0);
+        assert_eq!(report.outstanding_payments(), 250.0);
+        assert_eq!(report.bank_only_items(), -20.0);
+        assert_eq!(report.adjusted_bank_balance(), 1_380.0);
+        assert_eq!(report.adjusted_book_balance(), 1_380.0);
+        assert!(report.is_reconciled());
+    }
+
+    #[test]
+    fn test_outstanding_cheque_clears_the_next_month() {
+        let chart = test_chart();
+        let (cash, sales, rent) = (
+            chart.account("Cash"),
+            chart.account("Sales Revenue"),
+            chart.account("Rent Expense"),
+        );
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        for (id, date_of_entry, description, account, amount, cash_entry_type) in [
+            (
+                1,
+                date(2024, 1, 2),
+                "Deposit",
+                &sales,
+                1_000.0,
+                EntryType::Debit,
+            ),
+            (
+                2,
+                date(2024, 1, 29),
+                "CHQ 1002",
+                &rent,
+                300.0,
+                EntryType::Credit,
+            ),
+            (
+                3,
+                date(2024, 2, 10),
+                "Deposit",
+                &sales,
+                500.0,
+                EntryType::Debit,
+            ),
+        ] {
+            let other_entry_type = match cash_entry_type {
+                EntryType::Debit => EntryType::Credit,
+                EntryType::Credit => EntryType::Debit,
+            };
+            ledger.add_journal_entry(journal_entry(
+                id,
+                date_of_entry,
+                description,
+                &[
+                    (&cash, amount, cash_entry_type),
+                    (account, amount, other_entry_type),
+                ],
+            ));
+        }
+
+        let mut january = BankStatement::new(
+            cash.clone(),
+            ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap(),
+            0.0,
+            1_000.0,
+        );
+        january.add_line(StatementLine::new(1, date(2024, 1, 3), 1_000.0, "Deposit"));
+        let mut january = BankReconciliation::new(january, &ledger);
+        january.auto_match(&MatchRules::default());
+        assert_eq!(january.report().outstanding_payments(), 300.0);
+        assert!(january.report().is_reconciled());
+
+        let mut february = BankStatement::new(
+            cash.clone(),
+            ReportingPeriod::range(date(2024, 2, 1), date(2024, 2, 29)).unwrap(),
+            1_000.0,
+            1_200.0,
+        );
+        february.set_lines(vec![
+            StatementLine::new(1, date(2024, 2, 1), -300.0, "CHQ 1002"),
+            StatementLine::new(2, date(2024, 2, 11), 500.0, "Deposit"),
+        ]);
+        let mut february = january.next(february, &ledger);
+        assert!(february.reconciled().contains(&100));
+        assert_eq!(february.book_items().len(), 2);
+        assert_eq!(february.auto_match(&MatchRules::default()), 2);
+
+        let report = february.report();
+        assert_eq!(report.book_balance(), 1_200.0);
+        assert_eq!(report.bank_only_items(), 0.0);
+        assert_eq!(report.outstanding_payments(), 0.0);
+        assert!(report.is_reconciled());
+    }
+}
@@ -0,0 +1,684 @@
+use crate::journal::accounting_tree::{
+    AccountNodeRef, AccountTree, AccountTreeNode, ActionType, SubtotalNode, ACCOUNT_PATH_SEPARATOR,
+};
+use crate::journal::balance_sheet::{build_subtotals, BalanceSheetError};
+use crate::journal::ledger::{round_to_cents, Ledger, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// Errors raised while loading a `Budget` or building a `VarianceReport`
+///
+#[derive(Debug, PartialEq)]
+pub enum BudgetError {
+    ///
+    /// The budget source could not be read
+    ///
+    Io(String),
+    ///
+    /// A line of the budget source could not be parsed
+    ///
+    Parse { line: usize, message: String },
+    ///
+    /// A budget line refers to an account that is not on the `AccountTree`
+    ///
+    UnknownAccount(String),
+    ///
+    /// A budget line refers to an account with sub-accounts, whose amount is the sum of
+    /// its sub-accounts
+    ///
+    ParentAccount(String),
+    ///
+    /// A budget line without a start date only partly overlaps the report period, so it
+    /// cannot be prorated
+    ///
+    UnboundedLine(String),
+    ///
+    /// The actuals could not be aggregated
+    ///
+    Statement(BalanceSheetError),
+}
+
+impl Display for BudgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetError::Io(message) => write!(f, "Could not read the budget: {}", message),
+            BudgetError::Parse { line, message } => {
+                write!(f, "Invalid budget line {}: {}", line, message)
+            }
+            BudgetError::UnknownAccount(account_name) => write!(
+                f,
+                "Account name: {:?} has no associated account node.",
+                account_name
+            ),
+            BudgetError::ParentAccount(account_name) => write!(
+                f,
+                "Account name: {:?} has sub-accounts and cannot be budgeted.",
+                account_name
+            ),
+            BudgetError::UnboundedLine(account_name) => write!(
+                f,
+                "The budget line of {:?} has no start date and cannot be prorated.",
+                account_name
+            ),
+            BudgetError::Statement(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for BudgetError {}
+
+impl From<BalanceSheetError> for BudgetError {
+    fn from(error: BalanceSheetError) -> Self {
+        BudgetError::Statement(error)
+    }
+}
+
+///
+/// The amount planned for an account over a period
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetLine {
+    account_name: String,
+    period: ReportingPeriod,
+    amount: f64,
+}
+
+impl BudgetLine {
+    pub fn new(account_name: &str, period: ReportingPeriod, amount: f64) -> Self {
+        BudgetLine {
+            account_name: account_name.to_owned(),
+            period,
+            amount,
+        }
+    }
+
+    pub fn account_name(&self) -> &str {
+        &self.account_name
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+}
+
+///
+/// `Budget` holding the planned amounts per account per period
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Budget {
+    id: usize,
+    name: String,
+    lines: Vec<BudgetLine>,
+}
+
+impl Budget {
+    pub fn new(id: usize, name: &str) -> Self {
+        Budget {
+            id,
+            name: name.to_owned(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn set_id(&mut self, id: usize) {
+        self.id = id;
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_owned();
+    }
+
+    ///
+    /// Plan `amount` for `account` over `period`
+    ///
+    pub fn add_line(&mut self, account: &AccountNodeRef, period: ReportingPeriod, amount: f64) {
+        let account_name = account.borrow().name().to_owned();
+        self.lines
+            .push(BudgetLine::new(&account_name, period, amount));
+    }
+
+    ///
+    /// Add an already built `BudgetLine`
+    ///
+    pub fn add_budget_line(&mut self, line: BudgetLine) {
+        self.lines.push(line);
+    }
+
+    pub fn lines(&self) -> &Vec<BudgetLine> {
+        &self.lines
+    }
+
+    ///
+    /// Sum the planned amounts per account over `period`. A line that only partly
+    /// overlaps `period` counts for the share of its time that falls within it.
+    ///
+    pub fn amounts_for(
+        &self,
+        period: &ReportingPeriod,
+    ) -> Result<HashMap<String, f64>, BudgetError> {
+        let mut amounts = HashMap::new();
+
+        for line in self.lines.iter() {
+            let (from_date, to_date) = (line.period().from_date(), line.period().to_date());
+            let start = from_date.max(period.from_date());
+            let end = to_date.min(period.to_date());
+            if start > end {
+                continue;
+            }
+
+            let amount = if start == from_date && end == to_date {
+                line.amount()
+            } else if from_date == DateTime::<Utc>::MIN_UTC {
+                return Err(BudgetError::UnboundedLine(line.account_name().to_owned()));
+            } else {
+                // Both ends are inclusive
+                let overlap = (end - start).num_seconds() + 1;
+                let length = (to_date - from_date).num_seconds() + 1;
+                round_to_cents(line.amount() * overlap as f64 / length as f64)
+            };
+
+            *amounts
+                .entry(line.account_name().to_owned())
+                .or_insert(0f64) += amount;
+        }
+
+        Ok(amounts)
+    }
+
+    ///
+    /// Check that every budgeted account is a leaf of `accounting_tree` with the exact
+    /// same name: only the leaves are filled in
+    ///
+    pub fn validate(&self, accounting_tree: &AccountTree) -> Result<(), BudgetError> {
+        let root = SubtotalNode::from_tree(accounting_tree.root(), &HashMap::new());
+        let leaves: HashSet<&str> = root.leaves().iter().map(|leaf| leaf.name()).collect();
+
+        for line in self.lines.iter() {
+            let account_name = line.account_name();
+            if leaves.contains(account_name) {
+                continue;
+            }
+
+            let is_parent = root
+                .find(account_name)
+                .is_some_and(|node| node.name() == account_name && !node.is_leaf());
+            return Err(if is_parent {
+                BudgetError::ParentAccount(account_name.to_owned())
+            } else {
+                BudgetError::UnknownAccount(account_name.to_owned())
+            });
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Parse a budget from tab separated lines of `Account`, `From`, `To` and `Amount`,
+    /// with dates formatted as `YYYY-MM-DD`. The first line is a header; blank lines and
+    /// lines starting with `#` are skipped. The `To` date covers the whole day.
+    ///
+    pub fn parse(id: usize, name: &str, contents: &str) -> Result<Self, BudgetError> {
+        let mut budget = Budget::new(id, name);
+
+        for (index, line) in contents.lines().enumerate().skip(1) {
+            let line_number = index + 1;
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split('\t').map(|column| column.trim()).collect();
+            if columns.len() != 4 {
+                return Err(BudgetError::Parse {
+                    line: line_number,
+                    message: format!("expected 4 columns, found {}", columns.len()),
+                });
+            }
+
+            let from_date = parse_date(columns[1], line_number)?;
+            let to_date = parse_date(columns[2], line_number)?
+                .date_naive()
+                .and_hms_opt(23, 59, 59)
+                .unwrap()
+                .and_utc();
+
+            let period =
+                ReportingPeriod::range(from_date, to_date).map_err(|_| BudgetError::Parse {
+                    line: line_number,
+                    message: format!("{} is after {}", columns[1], columns[2]),
+                })?;
+
+            let amount: f64 = columns[3].parse().map_err(|_| BudgetError::Parse {
+                line: line_number,
+                message: format!("invalid amount {:?}", columns[3]),
+            })?;
+
+            budget.add_budget_line(BudgetLine::new(columns[0], period, amount));
+        }
+
+        Ok(budget)
+    }
+}
+
+fn parse_date(value: &str, line: usize) -> Result<DateTime<Utc>, BudgetError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| BudgetError::Parse {
+            line,
+            message: format!("invalid date {:?}", value),
+        })
+}
+
+///
+/// `BudgetReader` trait used to read a budget from
+/// particular sources.
+///
+pub trait BudgetReader {
+    ///
+    /// Read a budget by id
+    ///
+    fn read_by_id(&self, id: usize) -> Result<Budget, BudgetError>;
+}
+
+///
+/// `BudgetReader` over a tab separated file in the format read by `Budget::parse`
+///
+pub struct FileBudgetReader {
+    path: String,
+}
+
+impl FileBudgetReader {
+    pub fn new(path: &str) -> Self {
+        FileBudgetReader {
+            path: path.to_owned(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl BudgetReader for FileBudgetReader {
+    fn read_by_id(&self, id: usize) -> Result<Budget, BudgetError> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|error| BudgetError::Io(format!("{}: {}", self.path, error)))?;
+
+        Budget::parse(id, &self.path, &contents)
+    }
+}
+
+///
+/// Whether a variance is good or bad news
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Favourability {
+    Favourable,
+    Unfavourable,
+    OnBudget,
+}
+
+///
+/// A single account (or account tag) of a `VarianceReport`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarianceRow {
+    path: Vec<String>,
+    level: usize,
+    budget: f64,
+    actual: f64,
+    favourability: Favourability,
+}
+
+impl VarianceRow {
+    pub fn path(&self) -> &Vec<String> {
+        &self.path
+    }
+
+    pub fn name(&self) -> &str {
+        self.path.last().map(|name| name.as_str()).unwrap_or("")
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn budget(&self) -> f64 {
+        self.budget
+    }
+
+    pub fn actual(&self) -> f64 {
+        self.actual
+    }
+
+    ///
+    /// Get `actual - budget`
+    ///
+    pub fn variance(&self) -> f64 {
+        self.actual - self.budget
+    }
+
+    ///
+    /// Get the variance as a percentage of the budget, `None` when nothing was budgeted
+    ///
+    pub fn variance_percentage(&self) -> Option<f64> {
+        if self.budget == 0f64 {
+            None
+        } else {
+            Some(self.variance() / self.budget.abs() * 100f64)
+        }
+    }
+
+    pub fn favourability(&self) -> Favourability {
+        self.favourability
+    }
+}
+
+///
+/// `VarianceReport` comparing a `Budget` to the actuals of a `Ledger` over a period.
+/// Both the budget and the actuals are rolled up through the `AccountTree`.
+///
+/// Accounts that increase on a debit (expenses, assets) are favourable when they come in
+/// under budget; accounts that increase on a credit (revenue, liabilities, equity) are
+/// favourable when they come in over budget.
+///
+#[derive(Debug, Clone)]
+pub struct VarianceReport {
+    period: ReportingPeriod,
+    rows: Vec<VarianceRow>,
+}
+
+impl VarianceReport {
+    pub fn build(
+        budget: &Budget,
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        period: ReportingPeriod,
+    ) -> Result<Self, BudgetError> {
+        budget.validate(accounting_tree)?;
+
+        let transaction_entries: Vec<&Rc<TransactionEntry>> = ledger
+            .active_journal_entries()
+            .filter(|journal_entry| period.contains(journal_entry.date_of_entry()))
+            .flat_map(|journal_entry| journal_entry.transaction_entries())
+            .collect();
+
+        let actuals = build_subtotals(accounting_tree, transaction_entries)?;
+        let budgeted =
+            SubtotalNode::from_tree(accounting_tree.root(), &budget.amounts_for(&period)?);
+
+        let mut rows = Vec::new();
+        let mut path = Vec::new();
+        for (budget_node, actual_node) in budgeted.children().iter().zip(actuals.children()) {
+            Self::push_rows(&mut rows, &mut path, budget_node, actual_node);
+        }
+
+        Ok(VarianceReport { period, rows })
+    }
+
+    ///
+    /// Pre-order walk of the budget and actual trees, which share the same shape
+    ///
+    fn push_rows(
+        rows: &mut Vec<VarianceRow>,
+        path: &mut Vec<String>,
+        budget_node: &SubtotalNode,
+        actual_node: &SubtotalNode,
+    ) {
+        path.push(budget_node.name().to_owned());
+
+        let variance = actual_node.amount() - budget_node.amount();
+        let favourability = if variance == 0f64 {
+            Favourability::OnBudget
+        } else {
+            let debit_increases = budget_node
+                .account_type()
+                .as_ref()
+                .map(|account_type| account_type.on_debit() == &ActionType::Increase)
+                .unwrap_or(true);
+
+            match (debit_increases, variance > 0f64) {
+                (true, true) | (false, false) => Favourability::Unfavourable,
+                (true, false) | (false, true) => Favourability::Favourable,
+            }
+        };
+
+        rows.push(VarianceRow {
+            path: path.clone(),
+            level: budget_node.level(),
+            budget: budget_node.amount(),
+            actual: actual_node.amount(),
+            favourability,
+        });
+
+        for (budget_child, actual_child) in
+            budget_node.children().iter().zip(actual_node.children())
+        {
+            Self::push_rows(rows, path, budget_child, actual_child);
+        }
+
+        path.pop();
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn rows(&self) -> &Vec<VarianceRow> {
+        &self.rows
+    }
+
+    ///
+    /// Find a row by account name or by its path, e.g. `"Expenses:Operating Expenses"`
+    ///
+    pub fn row(&self, name_or_path: &str) -> Option<&VarianceRow> {
+        if name_or_path.contains(ACCOUNT_PATH_SEPARATOR) {
+            let path: Vec<&str> = name_or_path
+                .split(ACCOUNT_PATH_SEPARATOR)
+                .map(|segment| segment.trim())
+                .collect();
+
+            self.rows.iter().find(|row| {
+                row.path.len() == path.len()
+                    && row
+                        .path
+                        .iter()
+                        .zip(path.iter())
+                        .all(|(a, b)| a.eq_ignore_ascii_case(b))
+            })
+        } else {
+            self.rows
+                .iter()
+                .find(|row| row.name().eq_ignore_ascii_case(name_or_path.trim()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Budget, BudgetError, Favourability, VarianceReport};
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    const BUDGET: &str = "Account\tFrom\tTo\tAmount
+Sales Revenue\t2024-01-01\t2024-01-31\t1000
+Sales Revenue\t2024-02-01\t2024-02-29\t1000
+# Rent is fixed
+Rent Expense\t2024-01-01\t2024-01-31\t300
+Rent Expense\t2024-02-01\t2024-02-29\t300
+Wages Expense\t2024-01-01\t2024-01-31\t500
+";
+
+    #[test]
+    fn test_budget_parse() {
+        let budget = Budget::parse(1, "2024", BUDGET).unwrap();
+
+        assert_eq!(budget.lines().len(), 5);
+        let january = budget
+            .amounts_for(
+                &ReportingPeriod::range(
+                    date(2024, 1, 1),
+                    date(2024, 1, 31)
+                        .date_naive()
+                        .and_hms_opt(23, 59, 59)
+                        .unwrap()
+                        .and_utc(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(january.get("Rent Expense"), Some(&300.0));
+        assert_eq!(january.get("Sales Revenue"), Some(&1000.0));
+
+        assert_eq!(
+            Budget::parse(
+                1,
+                "2024",
+                "Account\tFrom\tTo\tAmount\nRent\t2024-13-01\t2024-01-31\t1"
+            )
+            .unwrap_err(),
+            BudgetError::Parse {
+                line: 2,
+                message: "invalid date \"2024-13-01\"".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn test_variance_report() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let sales = chart.account("Sales Revenue");
+        let rent = chart.account("Rent Expense");
+        let wages = chart.account("Wages Expense");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 31),
+            "January activity",
+            &[
+                (&cash, 1_200.0, EntryType::Debit),
+                (&sales, 1_200.0, EntryType::Credit),
+                (&rent, 300.0, EntryType::Debit),
+                (&wages, 650.0, EntryType::Debit),
+                (&cash, 950.0, EntryType::Credit),
+            ],
+        ));
+
+        let budget = Budget::parse(1, "2024", BUDGET).unwrap();
+        let report = VarianceReport::build(
+            &budget,
+            &chart.tree(),
+            &ledger,
+            ReportingPeriod::range(date(2024, 1, 1), date(2024, 2, 1)).unwrap(),
+        )
+        .unwrap();
+
+        let sales_row = report.row("Sales Revenue").unwrap();
+        assert_eq!(sales_row.variance(), 200.0);
+        assert_eq!(sales_row.favourability(), Favourability::Favourable);
+
+        let wages_row = report.row("Wages Expense").unwrap();
+        assert_eq!(wages_row.variance(), 150.0);
+        assert_eq!(wages_row.variance_percentage(), Some(30.0));
+        assert_eq!(wages_row.favourability(), Favourability::Unfavourable);
+
+        assert_eq!(
+            report.row("Rent Expense").unwrap().favourability(),
+            Favourability::OnBudget
+        );
+
+        let expenses = report.row("Expenses:Operating Expenses").unwrap();
+        assert_eq!(expenses.budget(), 800.0);
+        assert_eq!(expenses.actual(), 950.0);
+
+        let mut unknown = Budget::new(2, "Unknown");
+        unknown.add_budget_line(super::BudgetLine::new(
+            "Travel",
+            ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap(),
+            10.0,
+        ));
+        assert_eq!(
+            VarianceReport::build(
+                &unknown,
+                &chart.tree(),
+                &ledger,
+                ReportingPeriod::as_of(date(2024, 12, 31))
+            )
+            .unwrap_err(),
+            BudgetError::UnknownAccount("Travel".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_budget_lines_on_leaves_and_prorated() {
+        let chart = test_chart();
+        let january = ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap();
+
+        for (account_name, error) in [
+            (
+                "Operating Expenses",
+                BudgetError::ParentAccount("Operating Expenses".to_owned()),
+            ),
+            (
+                "rent expense",
+                BudgetError::UnknownAccount("rent expense".to_owned()),
+            ),
+        ] {
+            let mut budget = Budget::new(1, "2024");
+            budget.add_budget_line(super::BudgetLine::new(account_name, january, 10.0));
+            assert_eq!(budget.validate(&chart.tree()).unwrap_err(), error);
+        }
+
+        // A quarterly line counts for the share of the quarter a month covers
+        let budget = Budget::parse(
+            1,
+            "2024",
+            "Account\tFrom\tTo\tAmount\nRent Expense\t2024-01-01\t2024-03-31\t910\n",
+        )
+        .unwrap();
+        let amounts = budget
+            .amounts_for(
+                &ReportingPeriod::range(
+                    date(2024, 2, 1),
+                    date(2024, 2, 29)
+                        .date_naive()
+                        .and_hms_opt(23, 59, 59)
+                        .unwrap()
+                        .and_utc(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(amounts.get("Rent Expense"), Some(&290.0));
+
+        let mut unbounded = Budget::new(2, "Unbounded");
+        unbounded.add_budget_line(super::BudgetLine::new(
+            "Rent Expense",
+            ReportingPeriod::as_of(date(2024, 3, 31)),
+            100.0,
+        ));
+        assert_eq!(
+            unbounded.amounts_for(&january).unwrap_err(),
+            BudgetError::UnboundedLine("Rent Expense".to_owned())
+        );
+    }
+}
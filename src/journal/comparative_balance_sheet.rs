@@ -0,0 +1,466 @@
+use crate::journal::accounting_tree::{AccountTree, SubtotalNode, ACCOUNT_PATH_SEPARATOR};
+use crate::journal::balance_sheet::{BalanceSheet, BalanceSheetError, BalanceSheetSnapshot};
+use crate::journal::ledger::Ledger;
+use crate::journal::period::ReportingPeriod;
+use std::collections::HashMap;
+
+///
+/// `ComparativeBalanceSheet` builds one `BalanceSheet` per `ReportingPeriod` and lines
+/// them up next to each other, e.g. this year next to last year.
+///
+pub struct ComparativeBalanceSheet {
+    id: usize,
+    accounting_tree: AccountTree,
+    ledger: Ledger,
+    periods: Vec<ReportingPeriod>,
+}
+
+impl ComparativeBalanceSheet {
+    pub fn new(
+        id: usize,
+        accounting_tree: AccountTree,
+        ledger: Ledger,
+        periods: Vec<ReportingPeriod>,
+    ) -> Self {
+        ComparativeBalanceSheet {
+            id,
+            accounting_tree,
+            ledger,
+            periods,
+        }
+    }
+
+    ///
+    /// Get the `ComparativeBalanceSheet` id
+    ///
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    ///
+    /// Get the `AccountTree` every period is built against
+    ///
+    pub fn accounting_tree(&self) -> &AccountTree {
+        &self.accounting_tree
+    }
+
+    ///
+    /// Get the `Ledger` every period is built from
+    ///
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    ///
+    /// Get the periods, in the order their columns appear
+    ///
+    pub fn periods(&self) -> &Vec<ReportingPeriod> {
+        &self.periods
+    }
+
+    ///
+    /// Add a period as the right-most column
+    ///
+    pub fn add_period(&mut self, period: ReportingPeriod) {
+        self.periods.push(period);
+    }
+
+    ///
+    /// Build a `BalanceSheet` for every period and align the results by account
+    ///
+    pub fn build(&self) -> Result<ComparativeReport, BalanceSheetError> {
+        let mut snapshots = Vec::with_capacity(self.periods.len());
+
+        for period in self.periods.iter() {
+            let balance_sheet = BalanceSheet::new(
+                self.id,
+                period.from_date(),
+                period.to_date(),
+                self.accounting_tree.clone(),
+                self.ledger.clone(),
+            );
+
+            snapshots.push(balance_sheet.build()?);
+        }
+
+        Ok(ComparativeReport::from_snapshots(
+            self.periods.clone(),
+            &snapshots,
+        ))
+    }
+}
+
+///
+/// Change of a row's amount between a period and the period before it.
+/// `percentage` is `None` when the earlier amount is `0`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeriodChange {
+    absolute: f64,
+    percentage: Option<f64>,
+}
+
+impl PeriodChange {
+    ///
+    /// Compute the change going from `previous` to `current`.
+    /// Missing amounts are treated as `0`.
+    ///
+    pub fn between(previous: Option<f64>, current: Option<f64>) -> Self {
+        let previous = previous.unwrap_or(0f64);
+        let current = current.unwrap_or(0f64);
+        let absolute = current - previous;
+
+        let percentage = if previous == 0f64 {
+            None
+        } else {
+            Some(absolute / previous.abs() * 100f64)
+        };
+
+        PeriodChange {
+            absolute,
+            percentage,
+        }
+    }
+
+    pub fn absolute(&self) -> f64 {
+        self.absolute
+    }
+
+    pub fn percentage(&self) -> Option<f64> {
+        self.percentage
+    }
+}
+
+///
+/// A single account (or account tag) of a `ComparativeReport`
+///
+#[derive(Debug, Clone)]
+pub struct ComparativeRow {
+    path: Vec<String>,
+    level: usize,
+    amounts: Vec<Option<f64>>,
+    changes: Vec<PeriodChange>,
+}
+
+impl ComparativeRow {
+    ///
+    /// Get the names from the level 1 node down to this row's node
+    ///
+    pub fn path(&self) -> &Vec<String> {
+        &self.path
+    }
+
+    ///
+    /// Get the path joined with `ACCOUNT_PATH_SEPARATOR`
+    ///
+    pub fn path_string(&self) -> String {
+        self.path.join(ACCOUNT_PATH_SEPARATOR)
+    }
+
+    ///
+    /// Get the name of the row's account
+    ///
+    pub fn name(&self) -> &str {
+        self.path.last().map(|name| name.as_str()).unwrap_or("")
+    }
+
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    ///
+    /// Get the amount per period. `None` means the account did not exist in that period.
+    ///
+    pub fn amounts(&self) -> &Vec<Option<f64>> {
+        &self.amounts
+    }
+
+    ///
+    /// Get the amount of the period at `period_index`
+    ///
+    pub fn amount(&self, period_index: usize) -> Option<f64> {
+        self.amounts.get(period_index).copied().flatten()
+    }
+
+    ///
+    /// Get the changes between consecutive periods. The change at index `i` compares
+    /// period `i + 1` with period `i`.
+    ///
+    pub fn changes(&self) -> &Vec<PeriodChange> {
+        &self.changes
+    }
+
+    fn matches_path(&self, path: &[String]) -> bool {
+        self.path.len() == path.len()
+            && self
+                .path
+                .iter()
+                .zip(path.iter())
+                .all(|(a, b)| a.eq_ignore_ascii_case(b))
+    }
+}
+
+///
+/// A node of the union of the trees of several balance sheets, with its amount in each
+/// period and its children indexed by name
+///
+struct MergedNode {
+    name: String,
+    level: usize,
+    amounts: Vec<Option<f64>>,
+    children: Vec<MergedNode>,
+    positions: HashMap<String, usize>,
+}
+
+impl MergedNode {
+    fn new(name: &str, level: usize, number_of_periods: usize) -> Self {
+        MergedNode {
+            name: name.to_owned(),
+            level,
+            amounts: vec![None; number_of_periods],
+            children: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Merge the children of `node` into the children of this node, matching them by name
+    /// regardless of ASCII case. Children that are new in this period go after the
+    /// existing ones.
+    ///
+    fn merge_children(&mut self, node: &SubtotalNode, period_index: usize) {
+        for child in node.children() {
+            let key = child.name().to_ascii_lowercase();
+            let position = match self.positions.get(&key) {
+                Some(position) => *position,
+                None => {
+                    let number_of_periods = self.amounts.len();
+                    self.children.push(MergedNode::new(
+                        child.name(),
+                        child.level(),
+                        number_of_periods,
+                    ));
+                    self.positions.insert(key, self.children.len() - 1);
+                    self.children.len() - 1
+                }
+            };
+
+            let merged = &mut self.children[position];
+            merged.amounts[period_index] = Some(child.amount());
+            merged.merge_children(child, period_index);
+        }
+    }
+
+    ///
+    /// Append a row for each descendant of this node, in pre-order
+    ///
+    fn collect_rows(&self, path: &mut Vec<String>, rows: &mut Vec<ComparativeRow>) {
+        for child in self.children.iter() {
+            path.push(child.name.clone());
+            rows.push(ComparativeRow {
+                path: path.clone(),
+                level: child.level,
+                amounts: child.amounts.clone(),
+                changes: Vec::new(),
+            });
+            child.collect_rows(path, rows);
+            path.pop();
+        }
+    }
+}
+
+///
+/// `ComparativeReport` holds the rows of a comparative balance sheet, one column per period
+///
+#[derive(Debug, Clone)]
+pub struct ComparativeReport {
+    periods: Vec<ReportingPeriod>,
+    rows: Vec<ComparativeRow>,
+}
+
+impl ComparativeReport {
+    ///
+    /// Align already built balance sheets by account path.
+    /// `periods` and `snapshots` are expected to be in the same order.
+    ///
+    pub fn from_snapshots(
+        periods: Vec<ReportingPeriod>,
+        snapshots: &[BalanceSheetSnapshot],
+    ) -> Self {
+        let mut root = MergedNode::new("", 0, snapshots.len());
+        for (period_index, snapshot) in snapshots.iter().enumerate() {
+            root.merge_children(snapshot.root(), period_index);
+        }
+
+        let mut rows: Vec<ComparativeRow> = Vec::new();
+        root.collect_rows(&mut Vec::new(), &mut rows);
+
+        for row in rows.iter_mut() {
+            row.changes = row
+                .amounts
+                .windows(2)
+                .map(|pair| PeriodChange::between(pair[0], pair[1]))
+                .collect();
+        }
+
+        ComparativeReport { periods, rows }
+    }
+
+    ///
+    /// Get the periods, in column order
+    ///
+    pub fn periods(&self) -> &Vec<ReportingPeriod> {
+        &self.periods
+    }
+
+    ///
+    /// Get all rows in presentation order
+    ///
+    pub fn rows(&self) -> &Vec<ComparativeRow> {
+        &self.rows
+    }
+
+    ///
+    /// Find a row by account name or by its path, e.g. `"Assets:Current Assets:Cash"`
+    ///
+    pub fn row(&self, name_or_path: &str) -> Option<&ComparativeRow> {
+        if name_or_path.contains(ACCOUNT_PATH_SEPARATOR) {
+            let path: Vec<String> = name_or_path
+                .split(ACCOUNT_PATH_SEPARATOR)
+                .map(|segment| segment.trim().to_owned())
+                .collect();
+
+            self.rows.iter().find(|row| row.matches_path(&path))
+        } else {
+            self.rows
+                .iter()
+                .find(|row| row.name().eq_ignore_ascii_case(name_or_path.trim()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ComparativeBalanceSheet, ComparativeReport, PeriodChange};
+    use crate::journal::balance_sheet::BalanceSheet;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{build_chart, date, journal_entry, test_chart};
+
+    #[test]
+    fn test_comparative_balance_sheet_by_as_of_dates() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let stock = chart.account("Common Stock");
+        let equipment = chart.account("Equipment");
+
+        let mut ledger = Ledger::new(1, date(2023, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2023, 3, 1),
+            "Share capital",
+            &[
+                (&cash, 1_000.0, EntryType::Debit),
+                (&stock, 1_000.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 6, 1),
+            "Equipment purchase",
+            &[
+                (&equipment, 400.0, EntryType::Debit),
+                (&cash, 400.0, EntryType::Credit),
+            ],
+        ));
+
+        let comparative = ComparativeBalanceSheet::new(
+            1,
+            chart.tree(),
+            ledger,
+            vec![
+                ReportingPeriod::as_of(date(2023, 12, 31)),
+                ReportingPeriod::as_of(date(2024, 12, 31)),
+            ],
+        );
+
+        let report = comparative.build().unwrap();
+        let cash_row = report.row("Assets:Current Assets:Cash").unwrap();
+
+        assert_eq!(cash_row.amounts(), &vec![Some(1_000.0), Some(600.0)]);
+        assert_eq!(cash_row.changes()[0].absolute(), -400.0);
+        assert_eq!(cash_row.changes()[0].percentage(), Some(-40.0));
+
+        let equipment_row = report.row("Equipment").unwrap();
+        assert_eq!(equipment_row.changes()[0].absolute(), 400.0);
+        assert_eq!(equipment_row.changes()[0].percentage(), None);
+
+        assert_eq!(report.row("Assets").unwrap().amount(1), Some(1_000.0));
+    }
+
+    #[test]
+    fn test_comparative_report_includes_accounts_missing_from_some_periods() {
+        let prior_chart = build_chart(&[
+            ("Assets", true, &[("Current Assets", &["Cash"])]),
+            ("Equity", false, &[("Owner's Equity", &["Common Stock"])]),
+        ]);
+        let current_chart = build_chart(&[
+            (
+                "Assets",
+                true,
+                &[("Current Assets", &["Cash", "Inventory"])],
+            ),
+            ("Equity", false, &[("Owner's Equity", &["Common Stock"])]),
+        ]);
+
+        let prior = BalanceSheet::new(
+            1,
+            date(2023, 1, 1),
+            date(2023, 12, 31),
+            prior_chart.tree(),
+            Ledger::new(1, date(2023, 1, 1), date(2023, 12, 31)),
+        );
+        let current = BalanceSheet::new(
+            2,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            current_chart.tree(),
+            Ledger::new(2, date(2024, 1, 1), date(2024, 12, 31)),
+        );
+
+        let report = ComparativeReport::from_snapshots(
+            vec![
+                ReportingPeriod::range(date(2023, 1, 1), date(2023, 12, 31)).unwrap(),
+                ReportingPeriod::range(date(2024, 1, 1), date(2024, 12, 31)).unwrap(),
+            ],
+            &[prior.build().unwrap(), current.build().unwrap()],
+        );
+
+        let names: Vec<&str> = report.rows().iter().map(|row| row.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Assets",
+                "Current Assets",
+                "Cash",
+                "Inventory",
+                "Equity",
+                "Owner's Equity",
+                "Common Stock"
+            ]
+        );
+        assert_eq!(
+            report.row("Inventory").unwrap().amounts(),
+            &vec![None, Some(0.0)]
+        );
+    }
+
+    #[test]
+    fn test_period_change() {
+        let change = PeriodChange::between(Some(200.0), Some(250.0));
+
+        assert_eq!(change.absolute(), 50.0);
+        assert_eq!(change.percentage(), Some(25.0));
+        assert_eq!(PeriodChange::between(None, Some(10.0)).percentage(), None);
+    }
+}
@@ -0,0 +1,1191 @@
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTree, AccountTreeNode, SubtotalNode};
+use crate::journal::balance_sheet::{
+    build_subtotals, BalanceSheet, BalanceSheetError, BalanceSheetSnapshot,
+};
+use crate::journal::income_statement::{IncomeStatement, IncomeStatementSnapshot};
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError, TransactionEntry,
+};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// Transaction entries carrying this dimension, with the name of another entity of the
+/// group as its value, are intercompany and are eliminated on consolidation
+///
+pub const COUNTERPARTY_DIMENSION: &str = "Counterparty";
+
+///
+/// Errors raised by the `Consolidation`
+///
+#[derive(Debug, PartialEq)]
+pub enum ConsolidationError {
+    DuplicateEntity(usize),
+    ///
+    /// A transaction entry's account is not on the entity's `AccountTree`
+    ///
+    UnknownAccount {
+        entity_id: usize,
+        account_name: String,
+    },
+    ///
+    /// An account of the entity has no group account to consolidate into
+    ///
+    UnmappedAccount {
+        entity_id: usize,
+        account_name: String,
+    },
+    ///
+    /// The consolidated statements could not be built
+    ///
+    Statement(BalanceSheetError),
+    ///
+    /// The consolidated journal entries could not be posted
+    ///
+    Ledger(LedgerError),
+}
+
+impl Display for ConsolidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConsolidationError::DuplicateEntity(id) => write!(f, "Entity {} already exists.", id),
+            ConsolidationError::UnknownAccount {
+                entity_id,
+                account_name,
+            } => write!(
+                f,
+                "Account {:?} is not on the account tree of entity {}.",
+                account_name, entity_id
+            ),
+            ConsolidationError::UnmappedAccount {
+                entity_id,
+                account_name,
+            } => write!(
+                f,
+                "Account {:?} of entity {} is not mapped to a group account.",
+                account_name, entity_id
+            ),
+            ConsolidationError::Statement(error) => write!(f, "{}", error),
+            ConsolidationError::Ledger(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for ConsolidationError {}
+
+impl From<BalanceSheetError> for ConsolidationError {
+    fn from(error: BalanceSheetError) -> Self {
+        ConsolidationError::Statement(error)
+    }
+}
+
+impl From<LedgerError> for ConsolidationError {
+    fn from(error: LedgerError) -> Self {
+        ConsolidationError::Ledger(error)
+    }
+}
+
+///
+/// Rates translating an entity's currency into the group currency: the closing rate for
+/// assets and liabilities, the average rate for revenue and expenses and the historical
+/// rate for equity
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranslationRates {
+    closing: f64,
+    average: f64,
+    historical: f64,
+}
+
+impl TranslationRates {
+    pub fn new(closing: f64, average: f64, historical: f64) -> Self {
+        TranslationRates {
+            closing,
+            average,
+            historical,
+        }
+    }
+
+    ///
+    /// Rates of an entity reporting in the group currency
+    ///
+    pub fn unit() -> Self {
+        TranslationRates::new(1f64, 1f64, 1f64)
+    }
+
+    pub fn closing(&self) -> f64 {
+        self.closing
+    }
+
+    pub fn average(&self) -> f64 {
+        self.average
+    }
+
+    pub fn historical(&self) -> f64 {
+        self.historical
+    }
+}
+
+///
+/// `Entity` is a legal entity of the group with its own chart and books
+///
+pub struct Entity {
+    id: usize,
+    name: String,
+    currency: String,
+    accounting_tree: AccountTree,
+    ledger: Ledger,
+    ownership: f64,
+    rates: TranslationRates,
+    account_map: HashMap<String, AccountNodeRef>,
+}
+
+impl Entity {
+    ///
+    /// Create a wholly owned `Entity` reporting in `currency`, translated at unit rates
+    /// until set otherwise
+    ///
+    pub fn new(
+        id: usize,
+        name: &str,
+        currency: &str,
+        accounting_tree: AccountTree,
+        ledger: Ledger,
+    ) -> Self {
+        Entity {
+            id,
+            name: name.to_owned(),
+            currency: currency.to_owned(),
+            accounting_tree,
+            ledger,
+            ownership: 1f64,
+            rates: TranslationRates::unit(),
+            account_map: HashMap::new(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn currency(&self) -> &str {
+        &self.currency
+    }
+
+    pub fn accounting_tree(&self) -> &AccountTree {
+        &self.accounting_tree
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub fn ledger_mut(&mut self) -> &mut Ledger {
+        &mut self.ledger
+    }
+
+    ///
+    /// Get the share of the entity the group owns
+    ///
+    pub fn ownership(&self) -> f64 {
+        self.ownership
+    }
+
+    ///
+    /// Set the share of the entity the group owns, the rest being the non-controlling interest
+    ///
+    pub fn set_ownership(&mut self, ownership: f64) {
+        assert!(ownership > 0f64 && ownership <= 1f64);
+        self.ownership = ownership;
+    }
+
+    pub fn rates(&self) -> &TranslationRates {
+        &self.rates
+    }
+
+    pub fn set_rates(&mut self, rates: TranslationRates) {
+        self.rates = rates;
+    }
+
+    ///
+    /// Consolidate the entity's account named `account_name` into `group_account`
+    ///
+    pub fn map_account(&mut self, account_name: &str, group_account: &AccountNodeRef) {
+        self.account_map
+            .insert(account_name.to_owned(), group_account.clone());
+    }
+
+    pub fn mapped_account(&self, account_name: &str) -> Option<&AccountNodeRef> {
+        self.account_map.get(account_name)
+    }
+}
+
+///
+/// Group accounts the consolidation posts to besides the mapped accounts
+///
+pub struct ConsolidationAccounts {
+    translation_adjustment: AccountNodeRef,
+    elimination_difference: AccountNodeRef,
+    non_controlling_interest: AccountNodeRef,
+    non_controlling_interest_in_income: AccountNodeRef,
+}
+
+impl ConsolidationAccounts {
+    pub fn new(
+        translation_adjustment: &AccountNodeRef,
+        elimination_difference: &AccountNodeRef,
+        non_controlling_interest: &AccountNodeRef,
+        non_controlling_interest_in_income: &AccountNodeRef,
+    ) -> Self {
+        ConsolidationAccounts {
+            translation_adjustment: translation_adjustment.clone(),
+            elimination_difference: elimination_difference.clone(),
+            non_controlling_interest: non_controlling_interest.clone(),
+            non_controlling_interest_in_income: non_controlling_interest_in_income.clone(),
+        }
+    }
+}
+
+///
+/// How the amounts of a group account are translated
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AccountClass {
+    BalanceSheet,
+    IncomeStatement,
+    Equity,
+}
+
+///
+/// Reversed intercompany lines, signed with debits positive, by date and pair of entity ids
+///
+type IntercompanyLines = BTreeMap<(DateTime<Utc>, usize, usize), Vec<(AccountNodeRef, f64)>>;
+
+///
+/// A line of the consolidation worksheet. Amounts follow the normal side of the account.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsolidationRow {
+    account_name: String,
+    entity_amounts: Vec<f64>,
+    eliminations: f64,
+}
+
+impl ConsolidationRow {
+    pub fn account_name(&self) -> &str {
+        &self.account_name
+    }
+
+    ///
+    /// Get the translated amounts of each entity, in the order the entities were added
+    ///
+    pub fn entity_amounts(&self) -> &Vec<f64> {
+        &self.entity_amounts
+    }
+
+    ///
+    /// Get the intercompany eliminations and non-controlling interest adjustments
+    ///
+    pub fn eliminations(&self) -> f64 {
+        self.eliminations
+    }
+
+    pub fn consolidated(&self) -> f64 {
+        self.entity_amounts.iter().sum::<f64>() + self.eliminations
+    }
+}
+
+///
+/// `ConsolidationReport` is the result of consolidating the group over a period
+///
+pub struct ConsolidationReport {
+    period: ReportingPeriod,
+    entity_names: Vec<String>,
+    rows: Vec<ConsolidationRow>,
+    ledger: Ledger,
+    balance_sheet: BalanceSheetSnapshot,
+    income_statement: IncomeStatementSnapshot,
+    non_controlling_interest: f64,
+    non_controlling_interest_in_income: f64,
+}
+
+impl ConsolidationReport {
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn entity_names(&self) -> &Vec<String> {
+        &self.entity_names
+    }
+
+    pub fn rows(&self) -> &Vec<ConsolidationRow> {
+        &self.rows
+    }
+
+    pub fn row(&self, account_name: &str) -> Option<&ConsolidationRow> {
+        self.rows
+            .iter()
+            .find(|row| row.account_name.eq_ignore_ascii_case(account_name))
+    }
+
+    ///
+    /// Get the group ledger holding the translated entries, the eliminations and the
+    /// non-controlling interest adjustments
+    ///
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    pub fn balance_sheet(&self) -> &BalanceSheetSnapshot {
+        &self.balance_sheet
+    }
+
+    pub fn income_statement(&self) -> &IncomeStatementSnapshot {
+        &self.income_statement
+    }
+
+    ///
+    /// Get the non-controlling interest in the net assets of the subsidiaries
+    ///
+    pub fn non_controlling_interest(&self) -> f64 {
+        self.non_controlling_interest
+    }
+
+    ///
+    /// Get the non-controlling interest's share of the net income for the period
+    ///
+    pub fn non_controlling_interest_in_income(&self) -> f64 {
+        self.non_controlling_interest_in_income
+    }
+}
+
+impl Display for ConsolidationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Consolidation worksheet {}", self.period)?;
+        write!(f, "{:<34}", "Account")?;
+        for name in self.entity_names.iter() {
+            write!(f, "{:>14}", name)?;
+        }
+        writeln!(f, "{:>14}{:>14}", "Eliminations", "Consolidated")?;
+
+        for row in self.rows.iter() {
+            write!(f, "{:<34}", row.account_name)?;
+            for amount in row.entity_amounts.iter() {
+                write!(f, "{:>14.2}", amount)?;
+            }
+            writeln!(f, "{:>14.2}{:>14.2}", row.eliminations, row.consolidated())?;
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// `Consolidation` combines the books of the entities of a group into the group chart.
+///
+/// Each entity's journal entries are mapped into the group accounts and translated into
+/// the group currency, the translation differences going to the translation adjustment
+/// account. Transaction entries tagged with another entity in the `COUNTERPARTY_DIMENSION`
+/// are eliminated, pairing both sides by date; what the sides do not agree on goes to the
+/// elimination difference account. For entities not wholly owned, the non-controlling
+/// interest's share of the entity's net assets, net of the intercompany balances, is
+/// reclassified to the non-controlling interest account. Its share of the net income for
+/// the period, net of the intercompany results, is charged to the non-controlling interest
+/// in income account and its share of the results of earlier periods, which are never
+/// closed to equity, is charged there on the day before the period starts.
+///
+/// The entity ledgers are expected to cover the consolidation period, with the opening
+/// balances carried in balance sheet accounts.
+///
+pub struct Consolidation {
+    group_currency: String,
+    accounting_tree: AccountTree,
+    accounts: ConsolidationAccounts,
+    entities: Vec<Entity>,
+    income_statement_accounts: Vec<String>,
+    equity_accounts: Vec<String>,
+}
+
+impl Consolidation {
+    ///
+    /// Create a new `Consolidation` into the group chart `accounting_tree`. Accounts under
+    /// the `"Revenue"` and `"Expenses"` nodes are translated at the average rate and those
+    /// under the `"Equity"` node at the historical rate unless set otherwise.
+    ///
+    pub fn new(
+        group_currency: &str,
+        accounting_tree: AccountTree,
+        accounts: ConsolidationAccounts,
+    ) -> Self {
+        Consolidation {
+            group_currency: group_currency.to_owned(),
+            accounting_tree,
+            accounts,
+            entities: Vec::new(),
+            income_statement_accounts: vec!["Revenue".to_owned(), "Expenses".to_owned()],
+            equity_accounts: vec!["Equity".to_owned()],
+        }
+    }
+
+    pub fn group_currency(&self) -> &str {
+        &self.group_currency
+    }
+
+    pub fn accounting_tree(&self) -> &AccountTree {
+        &self.accounting_tree
+    }
+
+    ///
+    /// Set the names or paths of the group nodes whose accounts make the income statement
+    ///
+    pub fn set_income_statement_accounts(&mut self, income_statement_accounts: Vec<&str>) {
+        self.income_statement_accounts = income_statement_accounts
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+    }
+
+    ///
+    /// Set the names or paths of the group nodes whose accounts make the equity
+    ///
+    pub fn set_equity_accounts(&mut self, equity_accounts: Vec<&str>) {
+        self.equity_accounts = equity_accounts.iter().map(|a| a.to_string()).collect();
+    }
+
+    pub fn add_entity(&mut self, entity: Entity) -> Result<(), ConsolidationError> {
+        if self.get_entity(entity.id).is_some() {
+            return Err(ConsolidationError::DuplicateEntity(entity.id));
+        }
+
+        self.entities.push(entity);
+        Ok(())
+    }
+
+    pub fn get_entity(&self, id: usize) -> Option<&Entity> {
+        self.entities.iter().find(|entity| entity.id == id)
+    }
+
+    pub fn get_entity_mut(&mut self, id: usize) -> Option<&mut Entity> {
+        self.entities.iter_mut().find(|entity| entity.id == id)
+    }
+
+    pub fn entities(&self) -> &Vec<Entity> {
+        &self.entities
+    }
+
+    ///
+    /// Consolidate the group: the balance sheet as at the end of `period` and the income
+    /// statement over `period`
+    ///
+    pub fn consolidate(
+        &self,
+        period: &ReportingPeriod,
+    ) -> Result<ConsolidationReport, ConsolidationError> {
+        let classes = self.account_classes()?;
+
+        let from_date = self
+            .entities
+            .iter()
+            .map(|entity| *entity.ledger.from_date())
+            .fold(period.from_date(), |earliest, date| earliest.min(date));
+        let mut ledger = Ledger::new(0, from_date, period.to_date());
+        let ids = ledger.id_sequence();
+
+        // Translated journal entries of each entity
+        let mut entity_entries: Vec<Vec<JournalEntry>> = Vec::new();
+        let mut intercompany = IntercompanyLines::new();
+        // Non-controlling interest adjustments and intercompany eliminations
+        let mut adjustments: Vec<JournalEntry> = Vec::new();
+        let mut non_controlling_interest = 0f64;
+        let mut non_controlling_interest_in_income = 0f64;
+
+        for entity in self.entities.iter() {
+            let entity_accounts: HashSet<String> =
+                SubtotalNode::from_tree(entity.accounting_tree.root(), &HashMap::new())
+                    .leaves()
+                    .iter()
+                    .map(|leaf| leaf.name().to_owned())
+                    .collect();
+
+            let mut translated = Vec::new();
+            let mut equity: BTreeMap<String, (AccountNodeRef, f64)> = BTreeMap::new();
+            // Net assets, net income for the period and results of earlier periods, all
+            // without the intercompany lines
+            let mut net_assets = 0f64;
+            let mut net_income = 0f64;
+            let mut prior_income = 0f64;
+
+            for journal_entry in entity
+                .ledger
+                .active_journal_entries()
+                .filter(|journal_entry| journal_entry.date_of_entry() <= period.to_date())
+            {
+                let date = journal_entry.date_of_entry();
+                let mut group_entry =
+                    JournalEntry::new(ids.next_id(), date, journal_entry.description());
+                let mut difference = 0f64;
+
+                for entry in journal_entry.transaction_entries() {
+                    let account_name = entry.account_name();
+                    if !entity_accounts.contains(&account_name) {
+                        return Err(ConsolidationError::UnknownAccount {
+                            entity_id: entity.id,
+                            account_name,
+                        });
+                    }
+                    let group_account = entity.mapped_account(&account_name).ok_or(
+                        ConsolidationError::UnmappedAccount {
+                            entity_id: entity.id,
+                            account_name: account_name.clone(),
+                        },
+                    )?;
+
+                    let group_name = group_account.borrow().name().to_owned();
+                    let class = classes
+                        .get(&group_name)
+                        .cloned()
+                        .unwrap_or(AccountClass::BalanceSheet);
+                    let rate = match class {
+                        AccountClass::BalanceSheet => entity.rates.closing,
+                        AccountClass::IncomeStatement => entity.rates.average,
+                        AccountClass::Equity => entity.rates.historical,
+                    };
+                    let amount = round_to_cents(debit_positive(entry) * rate);
+                    difference += amount;
+
+                    let mut group_line =
+                        transaction_entry(ids, group_account, amount, date, entry.description());
+                    for (name, value) in entry.dimensions() {
+                        group_line.set_dimension(name, value);
+                    }
+                    group_entry.add_transaction_entry(Rc::new(group_line));
+
+                    let counterparty = entry
+                        .dimension(COUNTERPARTY_DIMENSION)
+                        .and_then(|name| self.entities.iter().find(|e| e.name == name))
+                        .filter(|counterparty| counterparty.id != entity.id);
+                    match counterparty {
+                        Some(counterparty) => {
+                            let key = (
+                                date,
+                                entity.id.min(counterparty.id),
+                                entity.id.max(counterparty.id),
+                            );
+                            intercompany
+                                .entry(key)
+                                .or_default()
+                                .push((group_account.clone(), -amount));
+                        }
+                        None => match class {
+                            AccountClass::BalanceSheet => net_assets += amount,
+                            AccountClass::Equity => {
+                                equity
+                                    .entry(group_name)
+                                    .or_insert((group_account.clone(), 0f64))
+                                    .1 += amount;
+                            }
+                            AccountClass::IncomeStatement if date < period.from_date() => {
+                                prior_income -= amount
+                            }
+                            AccountClass::IncomeStatement => net_income -= amount,
+                        },
+                    }
+                }
+
+                let difference = round_to_cents(difference);
+                if difference != 0f64 {
+                    let translation_adjustment = &self.accounts.translation_adjustment;
+                    let adjustment = transaction_entry(
+                        ids,
+                        translation_adjustment,
+                        -difference,
+                        date,
+                        "Translation difference",
+                    );
+                    group_entry.add_transaction_entry(Rc::new(adjustment));
+                }
+
+                translated.push(group_entry);
+            }
+
+            if entity.ownership < 1f64 {
+                let share = 1f64 - entity.ownership;
+                let description = format!("Non-controlling interest in {}", entity.name);
+                let interest = round_to_cents(share * net_assets);
+                let income_share = round_to_cents(share * net_income);
+                let prior_share = round_to_cents(share * prior_income);
+
+                if prior_share != 0f64 {
+                    let date = period.from_date() - Duration::days(1);
+                    let description = format!("{}, earlier periods", description);
+                    let mut adjustment = JournalEntry::new(ids.next_id(), date, &description);
+                    for (account, amount) in [
+                        (
+                            &self.accounts.non_controlling_interest_in_income,
+                            prior_share,
+                        ),
+                        (&self.accounts.non_controlling_interest, -prior_share),
+                    ] {
+                        adjustment.add_transaction_entry(Rc::new(transaction_entry(
+                            ids,
+                            account,
+                            amount,
+                            date,
+                            &description,
+                        )));
+                    }
+                    adjustments.push(adjustment);
+                }
+
+                // Reclassify the share of each equity account, the translation adjustment
+                // taking what is left of the share of the net assets
+                let translation_adjustment = &self.accounts.translation_adjustment;
+                let mut debits: BTreeMap<String, (AccountNodeRef, f64)> = equity
+                    .into_iter()
+                    .map(|(name, (account, balance))| {
+                        (name, (account, -round_to_cents(share * balance)))
+                    })
+                    .collect();
+                let residual = interest
+                    - prior_share
+                    - income_share
+                    - debits.values().map(|(_, amount)| amount).sum::<f64>();
+                debits
+                    .entry(translation_adjustment.borrow().name().to_owned())
+                    .or_insert((translation_adjustment.clone(), 0f64))
+                    .1 += residual;
+
+                let date = period.to_date();
+                let mut adjustment = JournalEntry::new(ids.next_id(), date, &description);
+                for (account, amount) in debits
+                    .into_values()
+                    .chain([
+                        (
+                            self.accounts.non_controlling_interest_in_income.clone(),
+                            income_share,
+                        ),
+                        (
+                            self.accounts.non_controlling_interest.clone(),
+                            prior_share - interest,
+                        ),
+                    ])
+                    .map(|(account, amount)| (account, round_to_cents(amount)))
+                    .filter(|(_, amount)| *amount != 0f64)
+                {
+                    adjustment.add_transaction_entry(Rc::new(transaction_entry(
+                        ids,
+                        &account,
+                        amount,
+                        date,
+                        &description,
+                    )));
+                }
+                if adjustment.number_of_transaction_entries() > 0 {
+                    adjustments.push(adjustment);
+                }
+
+                non_controlling_interest += interest;
+                non_controlling_interest_in_income += income_share;
+            }
+
+            entity_entries.push(translated);
+        }
+
+        for ((date, first, second), lines) in intercompany {
+            let description = format!(
+                "Intercompany elimination {} / {}",
+                self.get_entity(first).map(|e| e.name()).unwrap_or_default(),
+                self.get_entity(second)
+                    .map(|e| e.name())
+                    .unwrap_or_default()
+            );
+            let mut elimination = JournalEntry::new(ids.next_id(), date, &description);
+
+            // Net the lines by account so each account is eliminated once
+            let mut netted: Vec<(AccountNodeRef, f64)> = Vec::new();
+            for (account, amount) in lines {
+                match netted.iter_mut().find(|(a, _)| Rc::ptr_eq(a, &account)) {
+                    Some((_, total)) => *total += amount,
+                    None => netted.push((account, amount)),
+                }
+            }
+
+            let difference = round_to_cents(netted.iter().map(|(_, amount)| amount).sum());
+            if difference != 0f64 {
+                netted.push((self.accounts.elimination_difference.clone(), -difference));
+            }
+
+            for (account, amount) in netted {
+                let amount = round_to_cents(amount);
+                if amount != 0f64 {
+                    elimination.add_transaction_entry(Rc::new(transaction_entry(
+                        ids,
+                        &account,
+                        amount,
+                        date,
+                        &description,
+                    )));
+                }
+            }
+
+            if elimination.number_of_transaction_entries() > 0 {
+                adjustments.push(elimination);
+            }
+        }
+
+        for journal_entry in entity_entries.iter().flatten().chain(adjustments.iter()) {
+            ledger.post_journal_entry(journal_entry.clone())?;
+        }
+
+        let rows = self.worksheet(&entity_entries, &adjustments)?;
+
+        let balance_sheet = BalanceSheet::new(
+            0,
+            from_date,
+            period.to_date(),
+            self.accounting_tree.clone(),
+            ledger.clone(),
+        )
+        .build()?;
+        let income_statement = IncomeStatement::new(
+            0,
+            period.from_date(),
+            period.to_date(),
+            self.accounting_tree.clone(),
+            ledger.clone(),
+        )
+        .build()?;
+
+        Ok(ConsolidationReport {
+            period: *period,
+            entity_names: self.entities.iter().map(|e| e.name.clone()).collect(),
+            rows,
+            ledger,
+            balance_sheet,
+            income_statement,
+            non_controlling_interest: round_to_cents(non_controlling_interest),
+            non_controlling_interest_in_income: round_to_cents(non_controlling_interest_in_income),
+        })
+    }
+
+    ///
+    /// Classify the group accounts by how they are translated
+    ///
+    fn account_classes(&self) -> Result<HashMap<String, AccountClass>, ConsolidationError> {
+        let root = SubtotalNode::from_tree(self.accounting_tree.root(), &HashMap::new());
+        let mut classes = HashMap::new();
+
+        for (nodes, class) in [
+            (
+                &self.income_statement_accounts,
+                AccountClass::IncomeStatement,
+            ),
+            (&self.equity_accounts, AccountClass::Equity),
+        ] {
+            for name in nodes {
+                let node = root
+                    .lookup(name)
+                    .ok_or(BalanceSheetError::UnknownAccount(name.to_owned()))?;
+                for leaf in node.leaves() {
+                    classes.insert(leaf.name().to_owned(), class);
+                }
+            }
+        }
+
+        Ok(classes)
+    }
+
+    ///
+    /// Lay out the group accounts with a column per entity and the eliminations
+    ///
+    fn worksheet(
+        &self,
+        entity_entries: &[Vec<JournalEntry>],
+        adjustments: &[JournalEntry],
+    ) -> Result<Vec<ConsolidationRow>, BalanceSheetError> {
+        let subtotals = |journal_entries: &[JournalEntry]| {
+            build_subtotals(
+                &self.accounting_tree,
+                journal_entries
+                    .iter()
+                    .flat_map(|journal_entry| journal_entry.transaction_entries())
+                    .collect::<Vec<&Rc<TransactionEntry>>>(),
+            )
+        };
+
+        let columns = entity_entries
+            .iter()
+            .map(|journal_entries| subtotals(journal_entries))
+            .collect::<Result<Vec<SubtotalNode>, BalanceSheetError>>()?;
+        let eliminations = subtotals(adjustments)?;
+
+        let amount = |root: &SubtotalNode, name: &str| {
+            root.find(name).map(|node| node.amount()).unwrap_or(0f64)
+        };
+
+        Ok(eliminations
+            .leaves()
+            .iter()
+            .map(|leaf| ConsolidationRow {
+                account_name: leaf.name().to_owned(),
+                entity_amounts: columns
+                    .iter()
+                    .map(|column| amount(column, leaf.name()))
+                    .collect(),
+                eliminations: leaf.amount(),
+            })
+            .filter(|row| row.eliminations != 0f64 || row.entity_amounts.iter().any(|a| *a != 0f64))
+            .collect())
+    }
+}
+
+///
+/// Sign the amount of `entry` with debits positive and credits negative
+///
+fn debit_positive(entry: &TransactionEntry) -> f64 {
+    match entry.entry_type() {
+        EntryType::Debit => entry.amount(),
+        EntryType::Credit => -entry.amount(),
+    }
+}
+
+///
+/// Build a transaction entry from an amount signed with debits positive
+///
+fn transaction_entry(
+    ids: &mut IdSequence,
+    account: &AccountNodeRef,
+    amount: f64,
+    date: DateTime<Utc>,
+    description: &str,
+) -> TransactionEntry {
+    let entry_type = if amount >= 0f64 {
+        EntryType::Debit
+    } else {
+        EntryType::Credit
+    };
+
+    TransactionEntry::new(
+        ids.next_id(),
+        account.clone(),
+        amount.abs(),
+        entry_type,
+        date,
+        description,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Consolidation, ConsolidationAccounts, ConsolidationError, Entity, TranslationRates,
+        COUNTERPARTY_DIMENSION,
+    };
+    use crate::journal::accounting_tree::AccountNodeRef;
+    use crate::journal::ledger::{EntryType, JournalEntry, Ledger, TransactionEntry};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, journal_entry, test_chart, TestChart};
+    use chrono::{DateTime, Utc};
+    use std::rc::Rc;
+
+    ///
+    /// Build a journal entry whose transaction entries are all tagged with `counterparty`
+    ///
+    fn intercompany(
+        id: usize,
+        date_of_entry: DateTime<Utc>,
+        description: &str,
+        lines: &[(&AccountNodeRef, f64, EntryType)],
+        counterparty: &str,
+    ) -> JournalEntry {
+        let mut journal_entry = JournalEntry::new(id, date_of_entry, description);
+
+        for (index, (account, amount, entry_type)) in lines.iter().enumerate() {
+            let mut entry = TransactionEntry::new(
+                id * 100 + index,
+                (*account).clone(),
+                *amount,
+                *entry_type,
+                date_of_entry,
+                description,
+            );
+            entry.set_dimension(COUNTERPARTY_DIMENSION, counterparty);
+            journal_entry.add_transaction_entry(Rc::new(entry));
+        }
+
+        journal_entry
+    }
+
+    fn map_chart(entity: &mut Entity, group: &TestChart, names: &[&str]) {
+        for name in names {
+            entity.map_account(name, &group.account(name));
+        }
+    }
+
+    #[test]
+    fn test_consolidation() {
+        let group = test_chart();
+        let names = [
+            "Cash",
+            "Accounts Receivable",
+            "Accounts Payable",
+            "Common Stock",
+            "Sales Revenue",
+            "Cost of Goods Sold",
+        ];
+        let year = ReportingPeriod::range(date(2024, 1, 1), date(2024, 12, 31)).unwrap();
+
+        // The parent sells to the subsidiary on account
+        let parent_chart = test_chart();
+        let account = |name| parent_chart.account(name);
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 1),
+            "Share capital",
+            &[
+                (&account("Cash"), 1_000.0, EntryType::Debit),
+                (&account("Common Stock"), 1_000.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(intercompany(
+            2,
+            date(2024, 6, 30),
+            "Sale to subsidiary",
+            &[
+                (&account("Accounts Receivable"), 300.0, EntryType::Debit),
+                (&account("Sales Revenue"), 300.0, EntryType::Credit),
+            ],
+            "Sub",
+        ));
+        let mut parent = Entity::new(1, "Parent", "USD", parent_chart.tree(), ledger);
+        map_chart(&mut parent, &group, &names);
+
+        // The subsidiary is 80% owned and reports in euros
+        let sub_chart = test_chart();
+        let account = |name| sub_chart.account(name);
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 1),
+            "Share capital",
+            &[
+                (&account("Cash"), 500.0, EntryType::Debit),
+                (&account("Common Stock"), 500.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(intercompany(
+            2,
+            date(2024, 6, 30),
+            "Purchase from parent",
+            &[
+                (&account("Cost of Goods Sold"), 250.0, EntryType::Debit),
+                (&account("Accounts Payable"), 250.0, EntryType::Credit),
+            ],
+            "Parent",
+        ));
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 9, 30),
+            "Cash sales",
+            &[
+                (&account("Cash"), 400.0, EntryType::Debit),
+                (&account("Sales Revenue"), 400.0, EntryType::Credit),
+            ],
+        ));
+        let mut sub = Entity::new(2, "Sub", "EUR", sub_chart.tree(), ledger);
+        sub.set_ownership(0.8);
+        sub.set_rates(TranslationRates::new(1.2, 1.1, 1.0));
+        map_chart(&mut sub, &group, &names);
+
+        let mut consolidation = Consolidation::new(
+            "USD",
+            group.tree(),
+            ConsolidationAccounts::new(
+                &group.account("Translation Adjustment"),
+                &group.account("Translation Adjustment"),
+                &group.account("Non-Controlling Interest"),
+                &group.account("Non-Controlling Interest in Income"),
+            ),
+        );
+        consolidation.add_entity(parent).unwrap();
+        let duplicate = Entity::new(
+            1,
+            "Parent",
+            "USD",
+            group.tree(),
+            Ledger::new(2, year.from_date(), year.to_date()),
+        );
+        assert_eq!(
+            consolidation.add_entity(duplicate),
+            Err(ConsolidationError::DuplicateEntity(1))
+        );
+        consolidation.add_entity(sub).unwrap();
+
+        let report = consolidation.consolidate(&year).unwrap();
+
+        // Intercompany balances and sales are gone
+        let receivable = report.row("Accounts Receivable").unwrap();
+        assert_eq!(receivable.entity_amounts(), &vec![300.0, 0.0]);
+        assert_eq!(receivable.eliminations(), -300.0);
+        assert_eq!(receivable.consolidated(), 0.0);
+        assert_eq!(report.row("Accounts Payable").unwrap().consolidated(), 0.0);
+        assert_eq!(
+            report.row("Cost of Goods Sold").unwrap().consolidated(),
+            0.0
+        );
+        assert_eq!(report.row("Sales Revenue").unwrap().consolidated(), 440.0);
+
+        // The subsidiary's net assets of 1,080 once the intercompany payable is eliminated,
+        // 20% of it is outside the group, as is 20% of its sales of 440
+        assert_eq!(
+            report.row("Cash").unwrap().entity_amounts(),
+            &vec![1_000.0, 1_080.0]
+        );
+        assert_eq!(report.non_controlling_interest(), 216.0);
+        assert_eq!(report.non_controlling_interest_in_income(), 88.0);
+
+        let income_statement = report.income_statement();
+        assert_eq!(income_statement.revenue(), 440.0);
+        assert_eq!(income_statement.net_income(), 352.0);
+
+        let balance_sheet = report.balance_sheet();
+        let assets = balance_sheet.accounts_total(&["Assets"]).unwrap();
+        let claims = balance_sheet
+            .accounts_total(&["Liabilities", "Equity", "Revenue"])
+            .unwrap()
+            - balance_sheet.accounts_total(&["Expenses"]).unwrap();
+        assert_eq!(assets, 2_080.0);
+        assert_eq!(claims, 2_080.0);
+        assert_eq!(
+            balance_sheet
+                .find("Non-Controlling Interest")
+                .unwrap()
+                .amount(),
+            216.0
+        );
+    }
+
+    #[test]
+    fn test_non_controlling_interest_in_earlier_results() {
+        let group = test_chart();
+        let names = [
+            "Cash",
+            "Accounts Receivable",
+            "Accounts Payable",
+            "Common Stock",
+            "Sales Revenue",
+            "Cost of Goods Sold",
+        ];
+        let year = ReportingPeriod::range(date(2024, 1, 1), date(2024, 12, 31)).unwrap();
+
+        // The parent buys from the subsidiary on account
+        let parent_chart = test_chart();
+        let account = |name| parent_chart.account(name);
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(intercompany(
+            1,
+            date(2024, 3, 31),
+            "Purchase from subsidiary",
+            &[
+                (&account("Cost of Goods Sold"), 50.0, EntryType::Debit),
+                (&account("Accounts Payable"), 50.0, EntryType::Credit),
+            ],
+            "Sub",
+        ));
+        let mut parent = Entity::new(1, "Parent", "USD", parent_chart.tree(), ledger);
+        map_chart(&mut parent, &group, &names);
+
+        // The subsidiary is 75% owned and its 2023 sales were never closed to equity
+        let sub_chart = test_chart();
+        let account = |name| sub_chart.account(name);
+        let mut ledger = Ledger::new(1, date(2023, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2023, 1, 1),
+            "Share capital",
+            &[
+                (&account("Cash"), 500.0, EntryType::Debit),
+                (&account("Common Stock"), 500.0, EntryType::Credit),
+            ],
+        ));
+        for (id, date_of_entry, amount) in
+            [(2, date(2023, 6, 30), 100.0), (3, date(2024, 6, 30), 200.0)]
+        {
+            ledger.add_journal_entry(journal_entry(
+                id,
+                date_of_entry,
+                "Cash sales",
+                &[
+                    (&account("Cash"), amount, EntryType::Debit),
+                    (&account("Sales Revenue"), amount, EntryType::Credit),
+                ],
+            ));
+        }
+        ledger.add_journal_entry(intercompany(
+            4,
+            date(2024, 3, 31),
+            "Sale to parent",
+            &[
+                (&account("Accounts Receivable"), 50.0, EntryType::Debit),
+                (&account("Sales Revenue"), 50.0, EntryType::Credit),
+            ],
+            "Parent",
+        ));
+        let mut sub = Entity::new(2, "Sub", "USD", sub_chart.tree(), ledger);
+        sub.set_ownership(0.75);
+        map_chart(&mut sub, &group, &names);
+
+        let mut consolidation = Consolidation::new(
+            "USD",
+            group.tree(),
+            ConsolidationAccounts::new(
+                &group.account("Translation Adjustment"),
+                &group.account("Translation Adjustment"),
+                &group.account("Non-Controlling Interest"),
+                &group.account("Non-Controlling Interest in Income"),
+            ),
+        );
+        consolidation.add_entity(parent).unwrap();
+        consolidation.add_entity(sub).unwrap();
+
+        let report = consolidation.consolidate(&year).unwrap();
+
+        // A quarter of the net assets of 800 without the intercompany receivable, of the
+        // 2024 sales of 200 without the sale to the parent and of the 2023 sales of 100
+        assert_eq!(report.non_controlling_interest(), 200.0);
+        assert_eq!(report.non_controlling_interest_in_income(), 50.0);
+        assert_eq!(report.income_statement().net_income(), 150.0);
+
+        let balance_sheet = report.balance_sheet();
+        assert_eq!(
+            balance_sheet
+                .find("Non-Controlling Interest")
+                .unwrap()
+                .amount(),
+            200.0
+        );
+        assert_eq!(
+            balance_sheet
+                .find("Non-Controlling Interest in Income")
+                .unwrap()
+                .amount(),
+            75.0
+        );
+        assert_eq!(
+            balance_sheet
+                .find("Translation Adjustment")
+                .map(|node| node.amount())
+                .unwrap_or(0.0),
+            0.0
+        );
+    }
+}
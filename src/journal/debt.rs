@@ -0,0 +1,808 @@
+use crate::journal::accounting_tree::AccountNodeRef;
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError,
+};
+use chrono::{DateTime, Months, Utc};
+
+///
+/// `DebtInstrument` is implemented by the loans and bonds that post their
+/// interest and principal to a `Ledger`
+///
+pub trait DebtInstrument {
+    ///
+    /// Build every journal entry of the instrument, from inception to maturity.
+    /// Each entry carries an idempotency key naming the instrument and the period,
+    /// such as `loan:{name}:{period}`.
+    ///
+    fn journal_entries(&self, ids: &mut IdSequence) -> Vec<JournalEntry>;
+
+    ///
+    /// Post the journal entries dated within the ledger's dates to `ledger` under ids
+    /// the ledger allocates. The entries already posted are skipped, so posting the
+    /// instrument again is harmless. Returns the number of entries added.
+    ///
+    fn post_to_ledger(&self, ledger: &mut Ledger) -> Result<usize, LedgerError> {
+        let (from_date, to_date) = (*ledger.from_date(), *ledger.to_date());
+        let journal_entries: Vec<JournalEntry> = self
+            .journal_entries(&mut IdSequence::new(1))
+            .into_iter()
+            .filter(|j| j.date_of_entry() >= from_date && j.date_of_entry() <= to_date)
+            .collect();
+
+        let mut new_journal_entries = Vec::new();
+        for journal_entry in journal_entries {
+            let key = journal_entry.idempotency_key().unwrap_or_default();
+            match ledger.get_journal_entry_by_idempotency_key(key) {
+                None => new_journal_entries.push(journal_entry),
+                Some(posted) if posted.has_same_content(&journal_entry) => {}
+                Some(_) => return Err(LedgerError::IdempotencyConflict(key.to_owned())),
+            }
+        }
+
+        let posted = new_journal_entries.len();
+        for journal_entry in new_journal_entries {
+            ledger.post_new_journal_entry(journal_entry)?;
+        }
+
+        Ok(posted)
+    }
+}
+
+///
+/// Get the date of payment `period` (starting at `1`) of an instrument starting on
+/// `start_date` with `periods_per_year` payments a year
+///
+fn period_date(start_date: DateTime<Utc>, periods_per_year: u32, period: u32) -> DateTime<Utc> {
+    start_date
+        .checked_add_months(Months::new(period * 12 / periods_per_year))
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+///
+/// A period of a `Loan`'s amortization schedule
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmortizationLine {
+    period: u32,
+    date: DateTime<Utc>,
+    payment: f64,
+    interest: f64,
+    principal: f64,
+    balance: f64,
+}
+
+impl AmortizationLine {
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn payment(&self) -> f64 {
+        self.payment
+    }
+
+    pub fn interest(&self) -> f64 {
+        self.interest
+    }
+
+    pub fn principal(&self) -> f64 {
+        self.principal
+    }
+
+    ///
+    /// Get the principal left after the payment
+    ///
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+}
+
+///
+/// How a `Loan`'s principal is repaid
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Repayment {
+    ///
+    /// Equal payments of interest and principal
+    ///
+    Annuity,
+    ///
+    /// Interest every period and the whole principal with the last payment
+    ///
+    InterestOnly,
+}
+
+///
+/// `LoanTerms`: the principal borrowed, the annual interest rate (`0.12` for 12%)
+/// and the number of payments
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoanTerms {
+    principal: f64,
+    annual_rate: f64,
+    number_of_periods: u32,
+    periods_per_year: u32,
+}
+
+impl LoanTerms {
+    ///
+    /// Create monthly `LoanTerms`
+    ///
+    pub fn new(principal: f64, annual_rate: f64, number_of_periods: u32) -> Self {
+        LoanTerms {
+            principal,
+            annual_rate,
+            number_of_periods,
+            periods_per_year: 12,
+        }
+    }
+
+    pub fn principal(&self) -> f64 {
+        self.principal
+    }
+
+    pub fn annual_rate(&self) -> f64 {
+        self.annual_rate
+    }
+
+    pub fn number_of_periods(&self) -> u32 {
+        self.number_of_periods
+    }
+
+    pub fn periods_per_year(&self) -> u32 {
+        self.periods_per_year
+    }
+
+    ///
+    /// Set the number of payments a year; it must divide 12
+    ///
+    pub fn set_periods_per_year(&mut self, periods_per_year: u32) {
+        assert!(periods_per_year > 0 && 12 % periods_per_year == 0);
+
+        self.periods_per_year = periods_per_year;
+    }
+
+    pub fn periodic_rate(&self) -> f64 {
+        self.annual_rate / self.periods_per_year as f64
+    }
+}
+
+///
+/// The accounts a `Loan` posts to
+///
+#[derive(Debug, Clone)]
+pub struct LoanAccounts {
+    cash: AccountNodeRef,
+    liability: AccountNodeRef,
+    interest_expense: AccountNodeRef,
+}
+
+impl LoanAccounts {
+    pub fn new(
+        cash: AccountNodeRef,
+        liability: AccountNodeRef,
+        interest_expense: AccountNodeRef,
+    ) -> Self {
+        LoanAccounts {
+            cash,
+            liability,
+            interest_expense,
+        }
+    }
+
+    pub fn cash(&self) -> AccountNodeRef {
+        self.cash.clone()
+    }
+
+    pub fn liability(&self) -> AccountNodeRef {
+        self.liability.clone()
+    }
+
+    pub fn interest_expense(&self) -> AccountNodeRef {
+        self.interest_expense.clone()
+    }
+}
+
+///
+/// `Loan` received on `start_date` and repaid every period after it
+///
+#[derive(Debug, Clone)]
+pub struct Loan {
+    id: usize,
+    name: String,
+    terms: LoanTerms,
+    start_date: DateTime<Utc>,
+    repayment: Repayment,
+    accounts: LoanAccounts,
+}
+
+impl Loan {
+    pub fn new(
+        id: usize,
+        name: &str,
+        terms: LoanTerms,
+        start_date: DateTime<Utc>,
+        repayment: Repayment,
+        accounts: LoanAccounts,
+    ) -> Self {
+        Loan {
+            id,
+            name: name.to_owned(),
+            terms,
+            start_date,
+            repayment,
+            accounts,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn terms(&self) -> &LoanTerms {
+        &self.terms
+    }
+
+    pub fn start_date(&self) -> &DateTime<Utc> {
+        &self.start_date
+    }
+
+    pub fn repayment(&self) -> Repayment {
+        self.repayment
+    }
+
+    pub fn accounts(&self) -> &LoanAccounts {
+        &self.accounts
+    }
+
+    ///
+    /// Get the payment of each annuity period, interest included
+    ///
+    pub fn annuity_payment(&self) -> f64 {
+        let principal = self.terms.principal;
+        let rate = self.terms.periodic_rate();
+        let periods = self.terms.number_of_periods as f64;
+
+        if rate == 0f64 {
+            round_to_cents(principal / periods)
+        } else {
+            round_to_cents(principal * rate / (1f64 - (1f64 + rate).powf(-periods)))
+        }
+    }
+
+    ///
+    /// Get the amortization schedule. The last payment absorbs the rounding differences.
+    ///
+    pub fn schedule(&self) -> Vec<AmortizationLine> {
+        let rate = self.terms.periodic_rate();
+        let periods = self.terms.number_of_periods;
+        let mut balance = self.terms.principal;
+
+        (1..=periods)
+            .map(|period| {
+                let interest = round_to_cents(balance * rate);
+                let principal = if period == periods {
+                    balance
+                } else {
+                    match self.repayment {
+                        Repayment::Annuity => round_to_cents(self.annuity_payment() - interest),
+                        Repayment::InterestOnly => 0f64,
+                    }
+                };
+                balance = round_to_cents(balance - principal);
+
+                AmortizationLine {
+                    period,
+                    date: period_date(self.start_date, self.terms.periods_per_year, period),
+                    payment: round_to_cents(interest + principal),
+                    interest,
+                    principal,
+                    balance,
+                }
+            })
+            .collect()
+    }
+}
+
+impl DebtInstrument for Loan {
+    ///
+    /// The loan proceeds, keyed `loan:{name}:0`, then a payment entry per period
+    ///
+    fn journal_entries(&self, ids: &mut IdSequence) -> Vec<JournalEntry> {
+        let mut proceeds = JournalEntry::from_lines(
+            ids,
+            self.start_date,
+            &format!("{} - proceeds", self.name),
+            &[
+                (&self.accounts.cash, self.terms.principal, EntryType::Debit),
+                (
+                    &self.accounts.liability,
+                    self.terms.principal,
+                    EntryType::Credit,
+                ),
+            ],
+        );
+        proceeds.set_idempotency_key(Some(&format!("loan:{}:0", self.name)));
+        let mut journal_entries = vec![proceeds];
+
+        for line in self.schedule() {
+            let mut lines = Vec::new();
+            if line.interest > 0f64 {
+                lines.push((
+                    &self.accounts.interest_expense,
+                    line.interest,
+                    EntryType::Debit,
+                ));
+            }
+            if line.principal > 0f64 {
+                lines.push((&self.accounts.liability, line.principal, EntryType::Debit));
+            }
+            if lines.is_empty() {
+                continue;
+            }
+            lines.push((&self.accounts.cash, line.payment, EntryType::Credit));
+
+            let mut payment = JournalEntry::from_lines(
+                ids,
+                line.date,
+                &format!("{} - payment {}", self.name, line.period),
+                &lines,
+            );
+            payment.set_idempotency_key(Some(&format!("loan:{}:{}", self.name, line.period)));
+            journal_entries.push(payment);
+        }
+
+        journal_entries
+    }
+}
+
+///
+/// `BondTerms`: the face value, the annual coupon rate, the annual market rate
+/// (yield) at issue and the number of coupons
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BondTerms {
+    face_value: f64,
+    coupon_rate: f64,
+    market_rate: f64,
+    number_of_periods: u32,
+    periods_per_year: u32,
+}
+
+impl BondTerms {
+    ///
+    /// Create `BondTerms` with semi-annual coupons
+    ///
+    pub fn new(
+        face_value: f64,
+        coupon_rate: f64,
+        market_rate: f64,
+        number_of_periods: u32,
+    ) -> Self {
+        BondTerms {
+            face_value,
+            coupon_rate,
+            market_rate,
+            number_of_periods,
+            periods_per_year: 2,
+        }
+    }
+
+    pub fn face_value(&self) -> f64 {
+        self.face_value
+    }
+
+    pub fn coupon_rate(&self) -> f64 {
+        self.coupon_rate
+    }
+
+    pub fn market_rate(&self) -> f64 {
+        self.market_rate
+    }
+
+    pub fn number_of_periods(&self) -> u32 {
+        self.number_of_periods
+    }
+
+    pub fn periods_per_year(&self) -> u32 {
+        self.periods_per_year
+    }
+
+    ///
+    /// Set the number of coupons a year; it must divide 12
+    ///
+    pub fn set_periods_per_year(&mut self, periods_per_year: u32) {
+        assert!(periods_per_year > 0 && 12 % periods_per_year == 0);
+
+        self.periods_per_year = periods_per_year;
+    }
+
+    ///
+    /// Get the cash interest paid each period
+    ///
+    pub fn coupon(&self) -> f64 {
+        round_to_cents(self.face_value * self.coupon_rate / self.periods_per_year as f64)
+    }
+}
+
+///
+/// The accounts a `Bond` posts to. The discount or premium account holds the
+/// difference between the face value and the issue price: a debit balance for a
+/// discount, a credit balance for a premium.
+///
+#[derive(Debug, Clone)]
+pub struct BondAccounts {
+    cash: AccountNodeRef,
+    bonds_payable: AccountNodeRef,
+    discount_or_premium: AccountNodeRef,
+    interest_expense: AccountNodeRef,
+}
+
+impl BondAccounts {
+    pub fn new(
+        cash: AccountNodeRef,
+        bonds_payable: AccountNodeRef,
+        discount_or_premium: AccountNodeRef,
+        interest_expense: AccountNodeRef,
+    ) -> Self {
+        BondAccounts {
+            cash,
+            bonds_payable,
+            discount_or_premium,
+            interest_expense,
+        }
+    }
+
+    pub fn cash(&self) -> AccountNodeRef {
+        self.cash.clone()
+    }
+
+    pub fn bonds_payable(&self) -> AccountNodeRef {
+        self.bonds_payable.clone()
+    }
+
+    pub fn discount_or_premium(&self) -> AccountNodeRef {
+        self.discount_or_premium.clone()
+    }
+
+    pub fn interest_expense(&self) -> AccountNodeRef {
+        self.interest_expense.clone()
+    }
+}
+
+///
+/// A period of a `Bond`'s effective interest schedule
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BondScheduleLine {
+    period: u32,
+    date: DateTime<Utc>,
+    cash_interest: f64,
+    interest_expense: f64,
+    carrying_value: f64,
+}
+
+impl BondScheduleLine {
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn cash_interest(&self) -> f64 {
+        self.cash_interest
+    }
+
+    pub fn interest_expense(&self) -> f64 {
+        self.interest_expense
+    }
+
+    ///
+    /// Get the discount (positive) or premium (negative) amortized in the period
+    ///
+    pub fn amortization(&self) -> f64 {
+        round_to_cents(self.interest_expense - self.cash_interest)
+    }
+
+    ///
+    /// Get the carrying value after the period
+    ///
+    pub fn carrying_value(&self) -> f64 {
+        self.carrying_value
+    }
+}
+
+///
+/// `Bond` issued on `issue_date` and amortized with the effective interest method
+///
+#[derive(Debug, Clone)]
+pub struct Bond {
+    id: usize,
+    name: String,
+    terms: BondTerms,
+    issue_date: DateTime<Utc>,
+    accounts: BondAccounts,
+}
+
+impl Bond {
+    pub fn new(
+        id: usize,
+        name: &str,
+        terms: BondTerms,
+        issue_date: DateTime<Utc>,
+        accounts: BondAccounts,
+    ) -> Self {
+        Bond {
+            id,
+            name: name.to_owned(),
+            terms,
+            issue_date,
+            accounts,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn terms(&self) -> &BondTerms {
+        &self.terms
+    }
+
+    pub fn issue_date(&self) -> &DateTime<Utc> {
+        &self.issue_date
+    }
+
+    pub fn accounts(&self) -> &BondAccounts {
+        &self.accounts
+    }
+
+    ///
+    /// Get the issue price: the coupons and the face value discounted at the market rate
+    ///
+    pub fn issue_price(&self) -> f64 {
+        let face_value = self.terms.face_value;
+        let rate = self.terms.market_rate / self.terms.periods_per_year as f64;
+        let periods = self.terms.number_of_periods as f64;
+        let coupon = face_value * self.terms.coupon_rate / self.terms.periods_per_year as f64;
+
+        if rate == 0f64 {
+            round_to_cents(coupon * periods + face_value)
+        } else {
+            let discount_factor = (1f64 + rate).powf(-periods);
+            round_to_cents(coupon * (1f64 - discount_factor) / rate + face_value * discount_factor)
+        }
+    }
+
+    ///
+    /// Get the effective interest schedule. The last period absorbs the rounding
+    /// differences so the carrying value ends at the face value.
+    ///
+    pub fn schedule(&self) -> Vec<BondScheduleLine> {
+        let rate = self.terms.market_rate / self.terms.periods_per_year as f64;
+        let periods = self.terms.number_of_periods;
+        let coupon = self.terms.coupon();
+        let mut carrying_value = self.issue_price();
+
+        (1..=periods)
+            .map(|period| {
+                let interest_expense = if period == periods {
+                    round_to_cents(coupon + self.terms.face_value - carrying_value)
+                } else {
+                    round_to_cents(carrying_value * rate)
+                };
+                carrying_value = round_to_cents(carrying_value + interest_expense - coupon);
+
+                BondScheduleLine {
+                    period,
+                    date: period_date(self.issue_date, self.terms.periods_per_year, period),
+                    cash_interest: coupon,
+                    interest_expense,
+                    carrying_value,
+                }
+            })
+            .collect()
+    }
+}
+
+impl DebtInstrument for Bond {
+    ///
+    /// The issue, an interest entry per period and the repayment at maturity, keyed
+    /// `bond:{name}:0`, `bond:{name}:{period}` and `bond:{name}:redemption`
+    ///
+    fn journal_entries(&self, ids: &mut IdSequence) -> Vec<JournalEntry> {
+        let face_value = self.terms.face_value;
+        let price = self.issue_price();
+        let discount = round_to_cents(face_value - price);
+
+        let mut lines = vec![(&self.accounts.cash, price, EntryType::Debit)];
+        if discount > 0f64 {
+            lines.push((
+                &self.accounts.discount_or_premium,
+                discount,
+                EntryType::Debit,
+            ));
+        }
+        lines.push((&self.accounts.bonds_payable, face_value, EntryType::Credit));
+        if discount < 0f64 {
+            lines.push((
+                &self.accounts.discount_or_premium,
+                -discount,
+                EntryType::Credit,
+            ));
+        }
+
+        let mut issue = JournalEntry::from_lines(
+            ids,
+            self.issue_date,
+            &format!("{} - issue", self.name),
+            &lines,
+        );
+        issue.set_idempotency_key(Some(&format!("bond:{}:0", self.name)));
+        let mut journal_entries = vec![issue];
+
+        let schedule = self.schedule();
+        for line in schedule.iter() {
+            let amortization = line.amortization();
+
+            let mut lines = vec![(
+                &self.accounts.interest_expense,
+                line.interest_expense,
+                EntryType::Debit,
+            )];
+            if amortization < 0f64 {
+                lines.push((
+                    &self.accounts.discount_or_premium,
+                    -amortization,
+                    EntryType::Debit,
+                ));
+            }
+            lines.push((&self.accounts.cash, line.cash_interest, EntryType::Credit));
+            if amortization > 0f64 {
+                lines.push((
+                    &self.accounts.discount_or_premium,
+                    amortization,
+                    EntryType::Credit,
+                ));
+            }
+
+            let mut interest = JournalEntry::from_lines(
+                ids,
+                line.date,
+                &format!("{} - interest {}", self.name, line.period),
+                &lines,
+            );
+            interest.set_idempotency_key(Some(&format!("bond:{}:{}", self.name, line.period)));
+            journal_entries.push(interest);
+        }
+
+        if let Some(last) = schedule.last() {
+            let mut redemption = JournalEntry::from_lines(
+                ids,
+                last.date,
+                &format!("{} - redemption", self.name),
+                &[
+                    (&self.accounts.bonds_payable, face_value, EntryType::Debit),
+                    (&self.accounts.cash, face_value, EntryType::Credit),
+                ],
+            );
+            redemption.set_idempotency_key(Some(&format!("bond:{}:redemption", self.name)));
+            journal_entries.push(redemption);
+        }
+
+        journal_entries
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Bond, BondAccounts, BondTerms, DebtInstrument, Loan, LoanAccounts, LoanTerms, Repayment,
+    };
+    use crate::journal::ledger::{IdSequence, Ledger};
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_loan_schedules() {
+        let chart = test_chart();
+        let accounts = LoanAccounts::new(
+            chart.account("Cash"),
+            chart.account("Notes Payable"),
+            chart.account("Interest Expense"),
+        );
+        let terms = LoanTerms::new(10_000.0, 0.12, 12);
+
+        let annuity = Loan::new(
+            1,
+            "Term loan",
+            terms,
+            date(2024, 1, 1),
+            Repayment::Annuity,
+            accounts.clone(),
+        );
+        assert_eq!(annuity.annuity_payment(), 888.49);
+
+        let schedule = annuity.schedule();
+        assert_eq!(schedule[0].interest(), 100.0);
+        assert_eq!(schedule[0].principal(), 788.49);
+        assert_eq!(*schedule[0].date(), date(2024, 2, 1));
+        assert_eq!(schedule[11].balance(), 0.0);
+        let principal: f64 = schedule.iter().map(|l| l.principal()).sum();
+        assert!((principal - 10_000.0).abs() < 0.005);
+
+        let bullet = Loan::new(
+            2,
+            "Bullet loan",
+            terms,
+            date(2024, 1, 1),
+            Repayment::InterestOnly,
+            accounts,
+        );
+        let schedule = bullet.schedule();
+        assert!(schedule.iter().all(|l| l.interest() == 100.0));
+        assert_eq!(schedule[11].payment(), 10_100.0);
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 6, 30));
+        assert_eq!(bullet.post_to_ledger(&mut ledger), Ok(6));
+        assert!(ledger.journal_entries().iter().all(|j| j.validate()));
+        assert_eq!(bullet.post_to_ledger(&mut ledger), Ok(0));
+        assert_eq!(annuity.post_to_ledger(&mut ledger), Ok(6));
+        assert_eq!(ledger.number_of_journal_entries(), 12);
+        assert!(ledger
+            .get_journal_entry_by_idempotency_key("loan:Bullet loan:5")
+            .is_some());
+    }
+
+    #[test]
+    fn test_bond_effective_interest() {
+        let chart = test_chart();
+        let accounts = BondAccounts::new(
+            chart.account("Cash"),
+            chart.account("Bonds Payable"),
+            chart.account("Discount on Bonds Payable"),
+            chart.account("Interest Expense"),
+        );
+
+        let discount = Bond::new(
+            1,
+            "8% bonds",
+            BondTerms::new(100_000.0, 0.08, 0.10, 10),
+            date(2024, 1, 1),
+            accounts.clone(),
+        );
+        assert_eq!(discount.issue_price(), 92_278.27);
+
+        let schedule = discount.schedule();
+        assert_eq!(schedule[0].interest_expense(), 4_613.91);
+        assert_eq!(schedule[0].amortization(), 613.91);
+        assert_eq!(schedule[9].carrying_value(), 100_000.0);
+
+        let journal_entries = discount.journal_entries(&mut IdSequence::new(1));
+        assert_eq!(journal_entries.len(), 12);
+        assert!(journal_entries.iter().all(|j| j.validate()));
+
+        let premium = Bond::new(
+            2,
+            "12% bonds",
+            BondTerms::new(100_000.0, 0.12, 0.10, 10),
+            date(2024, 1, 1),
+            accounts,
+        );
+        assert!(premium.issue_price() > 100_000.0);
+        assert!(premium.schedule()[0].amortization() < 0.0);
+        assert_eq!(premium.schedule()[9].carrying_value(), 100_000.0);
+    }
+}
@@ -0,0 +1,626 @@
+use crate::journal::ledger::{EntryType, JournalEntry, Ledger, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+
+///
+/// Errors raised when validating the dimensions of transaction entries
+///
+#[derive(Debug, PartialEq)]
+pub enum DimensionError {
+    DuplicateDimension(String),
+    ///
+    /// A transaction entry uses a dimension that was not declared
+    ///
+    UnknownDimension {
+        transaction_entry_id: usize,
+        dimension: String,
+    },
+    ///
+    /// A transaction entry uses a value that was not declared for the dimension
+    ///
+    UnknownValue {
+        transaction_entry_id: usize,
+        dimension: String,
+        value: String,
+    },
+    ///
+    /// A transaction entry has no value for a required dimension
+    ///
+    MissingDimension {
+        transaction_entry_id: usize,
+        dimension: String,
+    },
+}
+
+impl Display for DimensionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DimensionError::DuplicateDimension(dimension) => {
+                write!(f, "Dimension {} already exists.", dimension)
+            }
+            DimensionError::UnknownDimension {
+                transaction_entry_id,
+                dimension,
+            } => write!(
+                f,
+                "Transaction entry {} uses the unknown dimension {}.",
+                transaction_entry_id, dimension
+            ),
+            DimensionError::UnknownValue {
+                transaction_entry_id,
+                dimension,
+                value,
+            } => write!(
+                f,
+                "Transaction entry {} uses the unknown value {} of dimension {}.",
+                transaction_entry_id, value, dimension
+            ),
+            DimensionError::MissingDimension {
+                transaction_entry_id,
+                dimension,
+            } => write!(
+                f,
+                "Transaction entry {} has no value for dimension {}.",
+                transaction_entry_id, dimension
+            ),
+        }
+    }
+}
+
+impl Error for DimensionError {}
+
+///
+/// `Dimension` such as a cost center, a department, a project, a region or a product line,
+/// and the values it can take
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dimension {
+    name: String,
+    values: Vec<String>,
+    required: bool,
+}
+
+impl Dimension {
+    pub fn new(name: &str, values: &[&str]) -> Self {
+        Dimension {
+            name: name.to_owned(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+            required: false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn values(&self) -> &Vec<String> {
+        &self.values
+    }
+
+    pub fn add_value(&mut self, value: &str) {
+        if !self.values.iter().any(|v| v == value) {
+            self.values.push(value.to_owned());
+        }
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    ///
+    /// Require every transaction entry to have a value for the dimension
+    ///
+    pub fn set_required(&mut self, required: bool) {
+        self.required = required;
+    }
+}
+
+///
+/// `Dimensions` declares the dimensions transaction entries may carry
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dimensions {
+    dimensions: Vec<Dimension>,
+}
+
+impl Dimensions {
+    pub fn new() -> Self {
+        Dimensions::default()
+    }
+
+    pub fn add_dimension(&mut self, dimension: Dimension) -> Result<(), DimensionError> {
+        if self.get_dimension(&dimension.name).is_some() {
+            return Err(DimensionError::DuplicateDimension(dimension.name));
+        }
+
+        self.dimensions.push(dimension);
+
+        Ok(())
+    }
+
+    pub fn dimensions(&self) -> &Vec<Dimension> {
+        &self.dimensions
+    }
+
+    pub fn get_dimension(&self, name: &str) -> Option<&Dimension> {
+        self.dimensions
+            .iter()
+            .find(|dimension| dimension.name == name)
+    }
+
+    pub fn get_dimension_mut(&mut self, name: &str) -> Option<&mut Dimension> {
+        self.dimensions
+            .iter_mut()
+            .find(|dimension| dimension.name == name)
+    }
+
+    ///
+    /// Check that every dimension of `transaction_entry` is declared with a declared
+    /// value, and that it has a value for every required dimension
+    ///
+    pub fn validate(&self, transaction_entry: &TransactionEntry) -> Result<(), DimensionError> {
+        let transaction_entry_id = transaction_entry.id();
+
+        for (name, value) in transaction_entry.dimensions() {
+            let dimension =
+                self.get_dimension(name)
+                    .ok_or_else(|| DimensionError::UnknownDimension {
+                        transaction_entry_id,
+                        dimension: name.to_owned(),
+                    })?;
+            if !dimension.values.contains(value) {
+                return Err(DimensionError::UnknownValue {
+                    transaction_entry_id,
+                    dimension: name.to_owned(),
+                    value: value.to_owned(),
+                });
+            }
+        }
+
+        for dimension in self.dimensions.iter().filter(|d| d.required) {
+            if transaction_entry.dimension(&dimension.name).is_none() {
+                return Err(DimensionError::MissingDimension {
+                    transaction_entry_id,
+                    dimension: dimension.name.to_owned(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_journal_entry(
+        &self,
+        journal_entry: &JournalEntry,
+    ) -> Result<(), DimensionError> {
+        journal_entry
+            .transaction_entries()
+            .iter()
+            .try_for_each(|entry| self.validate(entry))
+    }
+
+    ///
+    /// Check the transaction entries of the active journal entries of `ledger`
+    ///
+    pub fn validate_ledger(&self, ledger: &Ledger) -> Result<(), DimensionError> {
+        ledger
+            .active_journal_entries()
+            .try_for_each(|journal_entry| self.validate_journal_entry(journal_entry))
+    }
+}
+
+///
+/// `DimensionFilter` selects transaction entries by their dimension values.
+/// Values of the same dimension are alternatives; different dimensions must all match.
+/// A `None` value selects the entries without a value for the dimension.
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DimensionFilter {
+    criteria: BTreeMap<String, Vec<Option<String>>>,
+}
+
+impl DimensionFilter {
+    ///
+    /// Create a filter that selects every transaction entry
+    ///
+    pub fn new() -> Self {
+        DimensionFilter::default()
+    }
+
+    pub fn add(&mut self, dimension: &str, value: Option<&str>) {
+        self.criteria
+            .entry(dimension.to_owned())
+            .or_default()
+            .push(value.map(|value| value.to_owned()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.criteria.is_empty()
+    }
+
+    pub fn matches(&self, transaction_entry: &TransactionEntry) -> bool {
+        self.criteria.iter().all(|(dimension, values)| {
+            let value = transaction_entry.dimension(dimension);
+            values.iter().any(|v| v.as_deref() == value)
+        })
+    }
+
+    ///
+    /// Get a copy of the filter restricted to `value` of `dimension`, replacing the
+    /// values it selected for that dimension
+    ///
+    pub fn and(&self, dimension: &str, value: Option<&str>) -> Self {
+        let mut filter = self.clone();
+        filter.criteria.remove(dimension);
+        filter.add(dimension, value);
+
+        filter
+    }
+}
+
+///
+/// Get the values `dimension` takes in `ledger`, sorted, with `None` last when some
+/// transaction entries have no value for it
+///
+pub fn dimension_values(ledger: &Ledger, dimension: &str) -> Vec<Option<String>> {
+    let mut values: Vec<Option<String>> = Vec::new();
+
+    for entry in ledger
+        .active_journal_entries()
+        .flat_map(|journal_entry| journal_entry.transaction_entries())
+    {
+        let value = entry.dimension(dimension).map(|value| value.to_owned());
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    }
+
+    // `None` sorts first, move it last
+    values.sort();
+    if values.first() == Some(&None) {
+        values.rotate_left(1);
+    }
+
+    values
+}
+
+///
+/// A transaction entry of a `LedgerDetail`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerDetailRow {
+    journal_entry_id: usize,
+    transaction_entry_id: usize,
+    date: DateTime<Utc>,
+    account_name: String,
+    description: String,
+    debit: f64,
+    credit: f64,
+    dimensions: BTreeMap<String, String>,
+}
+
+impl LedgerDetailRow {
+    pub fn journal_entry_id(&self) -> usize {
+        self.journal_entry_id
+    }
+
+    pub fn transaction_entry_id(&self) -> usize {
+        self.transaction_entry_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn account_name(&self) -> &str {
+        &self.account_name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn debit(&self) -> f64 {
+        self.debit
+    }
+
+    pub fn credit(&self) -> f64 {
+        self.credit
+    }
+
+    pub fn dimensions(&self) -> &BTreeMap<String, String> {
+        &self.dimensions
+    }
+
+    pub fn dimension(&self, name: &str) -> Option<&str> {
+        self.dimensions.get(name).map(|value| value.as_str())
+    }
+}
+
+///
+/// `LedgerDetail` lists the transaction entries of a period, in date order
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerDetail {
+    period: ReportingPeriod,
+    rows: Vec<LedgerDetailRow>,
+}
+
+impl LedgerDetail {
+    ///
+    /// Build the detail of the transaction entries of `ledger` within `period` that
+    /// `filter` selects, optionally of a single account
+    ///
+    pub fn build(
+        ledger: &Ledger,
+        period: &ReportingPeriod,
+        account_name: Option<&str>,
+        filter: &DimensionFilter,
+    ) -> Self {
+        let mut rows: Vec<LedgerDetailRow> = ledger
+            .active_journal_entries()
+            .filter(|journal_entry| period.contains(journal_entry.date_of_entry()))
+            .flat_map(|journal_entry| {
+                journal_entry
+                    .transaction_entries()
+                    .iter()
+                    .map(move |entry| (journal_entry.id(), entry))
+            })
+            .filter(|(_, entry)| {
+                account_name
+                    .map(|name| entry.account_name().eq_ignore_ascii_case(name))
+                    .unwrap_or(true)
+            })
+            .filter(|(_, entry)| filter.matches(entry))
+            .map(|(journal_entry_id, entry)| {
+                let (debit, credit) = match entry.entry_type() {
+                    EntryType::Debit => (entry.amount(), 0f64),
+                    EntryType::Credit => (0f64, entry.amount()),
+                };
+
+                LedgerDetailRow {
+                    journal_entry_id,
+                    transaction_entry_id: entry.id(),
+                    date: *entry.date_of_entry(),
+                    account_name: entry.account_name(),
+                    description: entry.description().to_owned(),
+                    debit,
+                    credit,
+                    dimensions: entry.dimensions().clone(),
+                }
+            })
+            .collect();
+        rows.sort_by_key(|row| row.date);
+
+        LedgerDetail {
+            period: *period,
+            rows,
+        }
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn rows(&self) -> &Vec<LedgerDetailRow> {
+        &self.rows
+    }
+
+    pub fn total_debit(&self) -> f64 {
+        self.rows.iter().map(|row| row.debit).sum()
+    }
+
+    pub fn total_credit(&self) -> f64 {
+        self.rows.iter().map(|row| row.credit).sum()
+    }
+
+    ///
+    /// Group the rows by their value of `dimension`, with the rows without a value last
+    ///
+    pub fn group_by(&self, dimension: &str) -> Vec<(Option<String>, Vec<&LedgerDetailRow>)> {
+        let mut groups: Vec<(Option<String>, Vec<&LedgerDetailRow>)> = Vec::new();
+
+        for row in self.rows.iter() {
+            let value = row.dimension(dimension).map(|value| value.to_owned());
+            match groups.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, rows)) => rows.push(row),
+                None => groups.push((value, vec![row])),
+            }
+        }
+
+        groups.sort_by(|(a, _), (b, _)| match (a, b) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (Some(a), Some(b)) => a.cmp(b),
+        });
+
+        groups
+    }
+}
+
+impl Display for LedgerDetail {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Ledger detail {}", self.period)?;
+        writeln!(
+            f,
+            "{:<12}{:>8}  {:<25}{:<30}{:>12}{:>12}",
+            "Date", "Entry", "Account", "Description", "Debit", "Credit"
+        )?;
+
+        for row in self.rows.iter() {
+            writeln!(
+                f,
+                "{:<12}{:>8}  {:<25}{:<30}{:>12.2}{:>12.2}",
+                row.date.format("%Y-%m-%d"),
+                row.journal_entry_id,
+                row.account_name,
+                row.description,
+                row.debit,
+                row.credit
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{:<77}{:>12.2}{:>12.2}",
+            "Total",
+            self.total_debit(),
+            self.total_credit()
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        dimension_values, Dimension, DimensionError, DimensionFilter, Dimensions, LedgerDetail,
+    };
+    use crate::journal::accounting_tree::AccountNodeRef;
+    use crate::journal::income_statement::IncomeStatement;
+    use crate::journal::ledger::{EntryType, JournalEntry, Ledger, LedgerError, TransactionEntry};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, test_chart};
+    use crate::journal::trial_balance::TrialBalance;
+    use std::rc::Rc;
+
+    fn tagged_entry(
+        id: usize,
+        lines: &[(&AccountNodeRef, f64, EntryType, Option<&str>)],
+    ) -> JournalEntry {
+        let mut journal_entry = JournalEntry::new(id, date(2024, 1, 15), "Tagged");
+
+        for (index, (account, amount, entry_type, department)) in lines.iter().enumerate() {
+            let mut entry = TransactionEntry::new(
+                id * 100 + index,
+                (*account).clone(),
+                *amount,
+                *entry_type,
+                date(2024, 1, 15),
+                "Tagged",
+            );
+            if let Some(department) = department {
+                entry.set_dimension("Department", department);
+            }
+            journal_entry.add_transaction_entry(Rc::new(entry));
+        }
+
+        journal_entry
+    }
+
+    #[test]
+    fn test_dimensions_in_reports() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let sales = chart.account("Sales Revenue");
+        let rent = chart.account("Rent Expense");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 1, 31));
+        ledger.add_journal_entry(tagged_entry(
+            1,
+            &[
+                (&cash, 1_000.0, EntryType::Debit, None),
+                (&sales, 600.0, EntryType::Credit, Some("East")),
+                (&sales, 400.0, EntryType::Credit, Some("West")),
+            ],
+        ));
+        ledger.add_journal_entry(tagged_entry(
+            2,
+            &[
+                (&rent, 300.0, EntryType::Debit, Some("East")),
+                (&cash, 300.0, EntryType::Credit, None),
+            ],
+        ));
+
+        let mut dimensions = Dimensions::new();
+        dimensions
+            .add_dimension(Dimension::new("Department", &["East"]))
+            .unwrap();
+        assert_eq!(
+            dimensions.validate_ledger(&ledger),
+            Err(DimensionError::UnknownValue {
+                transaction_entry_id: 102,
+                dimension: "Department".to_owned(),
+                value: "West".to_owned()
+            })
+        );
+        let mut voided = ledger.clone();
+        voided.void_journal_entry(1).unwrap();
+        assert_eq!(dimensions.validate_ledger(&voided), Ok(()));
+
+        dimensions
+            .get_dimension_mut("Department")
+            .unwrap()
+            .add_value("West");
+        assert_eq!(dimensions.validate_ledger(&ledger), Ok(()));
+
+        assert_eq!(
+            ledger.set_dimensions(Some(Dimensions::new())),
+            Err(LedgerError::Dimension(DimensionError::UnknownDimension {
+                transaction_entry_id: 101,
+                dimension: "Department".to_owned()
+            }))
+        );
+        ledger.set_dimensions(Some(dimensions)).unwrap();
+        assert_eq!(
+            ledger
+                .post_journal_entry(tagged_entry(
+                    3,
+                    &[
+                        (&rent, 10.0, EntryType::Debit, Some("North")),
+                        (&cash, 10.0, EntryType::Credit, None),
+                    ],
+                ))
+                .unwrap_err(),
+            LedgerError::Dimension(DimensionError::UnknownValue {
+                transaction_entry_id: 300,
+                dimension: "Department".to_owned(),
+                value: "North".to_owned()
+            })
+        );
+
+        assert_eq!(
+            dimension_values(&ledger, "Department"),
+            vec![Some("East".to_owned()), Some("West".to_owned()), None]
+        );
+
+        let january = ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap();
+        let east = DimensionFilter::new().and("Department", Some("East"));
+
+        let trial_balance =
+            TrialBalance::build_filtered(&chart.tree(), &ledger, &january, &east).unwrap();
+        assert_eq!(
+            trial_balance.row("Sales Revenue").unwrap().adjusted(),
+            -600.0
+        );
+        assert!(trial_balance.row("Cash").is_none());
+
+        let groups =
+            TrialBalance::build_grouped(&chart.tree(), &ledger, &january, "Department").unwrap();
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[2].1.row("Cash").unwrap().adjusted(), 700.0);
+
+        let mut income_statement = IncomeStatement::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 1, 31),
+            chart.tree(),
+            ledger.clone(),
+        );
+        income_statement.set_filter(east);
+        assert_eq!(income_statement.build().unwrap().net_income(), 300.0);
+
+        let by_department = income_statement.build_grouped("Department").unwrap();
+        assert_eq!(by_department[1].0.as_deref(), Some("West"));
+        assert_eq!(by_department[1].1.net_income(), 400.0);
+
+        let detail = LedgerDetail::build(&ledger, &january, None, &DimensionFilter::new());
+        assert_eq!(detail.rows().len(), 5);
+        let groups = detail.group_by("Department");
+        assert_eq!(groups[0].1.len(), 2);
+        assert_eq!(groups[2].0, None);
+    }
+}
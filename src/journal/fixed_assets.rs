@@ -0,0 +1,701 @@
+use crate::journal::accounting_tree::AccountNodeRef;
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError,
+};
+use chrono::{DateTime, Months, Utc};
+use std::error::Error;
+use std::fmt::Display;
+
+///
+/// How the cost of a `FixedAsset` is spread over its useful life
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DepreciationMethod {
+    StraightLine,
+    ///
+    /// Book value times `multiplier / useful life` each year; `2.0` is the double
+    /// declining balance method. The last year brings the book value down to salvage.
+    ///
+    DecliningBalance {
+        multiplier: f64,
+    },
+    SumOfYearsDigits,
+    ///
+    /// Depreciation in proportion to the units used out of `total_units`
+    ///
+    UnitsOfProduction {
+        total_units: f64,
+    },
+}
+
+///
+/// Errors raised by the `FixedAssetRegister`
+///
+#[derive(Debug, PartialEq)]
+pub enum FixedAssetError {
+    DuplicateAsset(usize),
+    UnknownAsset(usize),
+    AlreadyDisposed(usize),
+    ///
+    /// The asset was depreciated past the disposal date
+    ///
+    DepreciatedPastDisposal(usize),
+    InvalidAsset(String),
+    ///
+    /// The ledger rejected a journal entry of the asset
+    ///
+    Ledger(LedgerError),
+}
+
+impl Display for FixedAssetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FixedAssetError::DuplicateAsset(id) => write!(f, "Asset {} already exists.", id),
+            FixedAssetError::UnknownAsset(id) => write!(f, "Asset {} does not exist.", id),
+            FixedAssetError::AlreadyDisposed(id) => {
+                write!(f, "Asset {} has already been disposed of.", id)
+            }
+            FixedAssetError::DepreciatedPastDisposal(id) => write!(
+                f,
+                "Asset {} has been depreciated past the disposal date.",
+                id
+            ),
+            FixedAssetError::InvalidAsset(message) => write!(f, "Invalid asset: {}", message),
+            FixedAssetError::Ledger(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for FixedAssetError {}
+
+impl From<LedgerError> for FixedAssetError {
+    fn from(error: LedgerError) -> Self {
+        FixedAssetError::Ledger(error)
+    }
+}
+
+///
+/// The accounts a `FixedAsset` posts to
+///
+#[derive(Debug, Clone)]
+pub struct AssetAccounts {
+    asset: AccountNodeRef,
+    accumulated_depreciation: AccountNodeRef,
+    depreciation_expense: AccountNodeRef,
+}
+
+impl AssetAccounts {
+    pub fn new(
+        asset: AccountNodeRef,
+        accumulated_depreciation: AccountNodeRef,
+        depreciation_expense: AccountNodeRef,
+    ) -> Self {
+        AssetAccounts {
+            asset,
+            accumulated_depreciation,
+            depreciation_expense,
+        }
+    }
+
+    pub fn asset(&self) -> AccountNodeRef {
+        self.asset.clone()
+    }
+
+    pub fn accumulated_depreciation(&self) -> AccountNodeRef {
+        self.accumulated_depreciation.clone()
+    }
+
+    pub fn depreciation_expense(&self) -> AccountNodeRef {
+        self.depreciation_expense.clone()
+    }
+}
+
+///
+/// A year of a `FixedAsset`'s depreciation schedule
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepreciationScheduleLine {
+    year: u32,
+    depreciation: f64,
+    accumulated_depreciation: f64,
+    book_value: f64,
+}
+
+impl DepreciationScheduleLine {
+    ///
+    /// Get the year of service, starting at `1`
+    ///
+    pub fn year(&self) -> u32 {
+        self.year
+    }
+
+    pub fn depreciation(&self) -> f64 {
+        self.depreciation
+    }
+
+    pub fn accumulated_depreciation(&self) -> f64 {
+        self.accumulated_depreciation
+    }
+
+    pub fn book_value(&self) -> f64 {
+        self.book_value
+    }
+}
+
+///
+/// `FixedAsset` of the register
+///
+#[derive(Debug, Clone)]
+pub struct FixedAsset {
+    id: usize,
+    name: String,
+    cost: f64,
+    salvage_value: f64,
+    in_service_date: DateTime<Utc>,
+    useful_life_years: u32,
+    method: DepreciationMethod,
+    accounts: AssetAccounts,
+    usage: Vec<(DateTime<Utc>, f64)>,
+    depreciated_through: Option<DateTime<Utc>>,
+    disposal_date: Option<DateTime<Utc>>,
+}
+
+impl FixedAsset {
+    pub fn new(
+        id: usize,
+        name: &str,
+        cost: f64,
+        in_service_date: DateTime<Utc>,
+        useful_life_years: u32,
+        method: DepreciationMethod,
+        accounts: AssetAccounts,
+    ) -> Self {
+        FixedAsset {
+            id,
+            name: name.to_owned(),
+            cost,
+            salvage_value: 0f64,
+            in_service_date,
+            useful_life_years,
+            method,
+            accounts,
+            usage: Vec::new(),
+            depreciated_through: None,
+            disposal_date: None,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+
+    pub fn salvage_value(&self) -> f64 {
+        self.salvage_value
+    }
+
+    pub fn set_salvage_value(&mut self, salvage_value: f64) {
+        self.salvage_value = salvage_value;
+    }
+
+    pub fn in_service_date(&self) -> &DateTime<Utc> {
+        &self.in_service_date
+    }
+
+    pub fn useful_life_years(&self) -> u32 {
+        self.useful_life_years
+    }
+
+    pub fn method(&self) -> &DepreciationMethod {
+        &self.method
+    }
+
+    pub fn accounts(&self) -> &AssetAccounts {
+        &self.accounts
+    }
+
+    ///
+    /// Record `units` of production on `date`, for the units of production method
+    ///
+    pub fn record_usage(&mut self, date: DateTime<Utc>, units: f64) {
+        self.usage.push((date, units));
+    }
+
+    ///
+    /// Get the date depreciation has been posted through, if any was
+    ///
+    pub fn depreciated_through(&self) -> Option<&DateTime<Utc>> {
+        self.depreciated_through.as_ref()
+    }
+
+    pub fn disposal_date(&self) -> Option<&DateTime<Utc>> {
+        self.disposal_date.as_ref()
+    }
+
+    ///
+    /// Get the cost less salvage value
+    ///
+    pub fn depreciable_base(&self) -> f64 {
+        self.cost - self.salvage_value
+    }
+
+    fn validate(&self) -> Result<(), FixedAssetError> {
+        if self.cost <= 0f64 || self.salvage_value < 0f64 || self.salvage_value > self.cost {
+            return Err(FixedAssetError::InvalidAsset(format!(
+                "{}: the cost must be positive and the salvage value between 0 and the cost",
+                self.name
+            )));
+        }
+        if self.useful_life_years == 0 {
+            return Err(FixedAssetError::InvalidAsset(format!(
+                "{}: the useful life must be at least a year",
+                self.name
+            )));
+        }
+        match self.method {
+            DepreciationMethod::DecliningBalance { multiplier } if multiplier <= 0f64 => Err(
+                FixedAssetError::InvalidAsset(format!("{}: invalid multiplier", self.name)),
+            ),
+            DepreciationMethod::UnitsOfProduction { total_units } if total_units <= 0f64 => Err(
+                FixedAssetError::InvalidAsset(format!("{}: invalid total units", self.name)),
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Get the depreciation of each year of service. Units of production assets
+    /// have no time based schedule and return the depreciation of the usage recorded
+    /// in each year of service instead.
+    ///
+    pub fn annual_depreciation(&self) -> Vec<f64> {
+        let years = self.useful_life_years as usize;
+        let base = self.depreciable_base();
+
+        match self.method {
+            DepreciationMethod::StraightLine => vec![base / years as f64; years],
+            DepreciationMethod::DecliningBalance { multiplier } => {
+                let rate = multiplier / years as f64;
+                let mut book_value = self.cost;
+
+                (0..years)
+                    .map(|year| {
+                        let depreciation = if year == years - 1 {
+                            book_value - self.salvage_value
+                        } else {
+                            (book_value * rate).min(book_value - self.salvage_value)
+                        }
+                        .max(0f64);
+                        book_value -= depreciation;
+                        depreciation
+                    })
+                    .collect()
+            }
+            DepreciationMethod::SumOfYearsDigits => {
+                let sum_of_digits = (years * (years + 1) / 2) as f64;
+                (0..years)
+                    .map(|year| base * (years - year) as f64 / sum_of_digits)
+                    .collect()
+            }
+            DepreciationMethod::UnitsOfProduction { .. } => (0..years)
+                .map(|year| {
+                    let start = self.month_start(year as u32 * 12);
+                    let end = self.month_start((year as u32 + 1) * 12);
+                    self.depreciation_through(end) - self.depreciation_through(start)
+                })
+                .collect(),
+        }
+    }
+
+    ///
+    /// Get the yearly schedule of depreciation, accumulated depreciation and book value
+    ///
+    pub fn schedule(&self) -> Vec<DepreciationScheduleLine> {
+        let mut accumulated_depreciation = 0f64;
+
+        self.annual_depreciation()
+            .into_iter()
+            .enumerate()
+            .map(|(year, depreciation)| {
+                let depreciation = round_to_cents(depreciation);
+                accumulated_depreciation += depreciation;
+                DepreciationScheduleLine {
+                    year: year as u32 + 1,
+                    depreciation,
+                    accumulated_depreciation: round_to_cents(accumulated_depreciation),
+                    book_value: round_to_cents(self.cost - accumulated_depreciation),
+                }
+            })
+            .collect()
+    }
+
+    fn month_start(&self, month: u32) -> DateTime<Utc> {
+        self.in_service_date
+            .checked_add_months(Months::new(month))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    ///
+    /// Get the accumulated depreciation through `date`. Time based methods charge a
+    /// twelfth of the year's depreciation for each month of service started by `date`.
+    ///
+    pub fn depreciation_through(&self, date: DateTime<Utc>) -> f64 {
+        let date = match self.disposal_date {
+            Some(disposal_date) if disposal_date < date => disposal_date,
+            _ => date,
+        };
+
+        let depreciation = match self.method {
+            DepreciationMethod::UnitsOfProduction { total_units } => {
+                let units: f64 = self
+                    .usage
+                    .iter()
+                    .filter(|(usage_date, _)| usage_date <= &date)
+                    .map(|(_, units)| units)
+                    .sum();
+                self.depreciable_base() * units.min(total_units) / total_units
+            }
+            _ => {
+                let annual_depreciation = self.annual_depreciation();
+                (0..self.useful_life_years * 12)
+                    .take_while(|month| self.month_start(*month) <= date)
+                    .map(|month| annual_depreciation[month as usize / 12] / 12f64)
+                    .sum()
+            }
+        };
+
+        round_to_cents(depreciation.min(self.depreciable_base()))
+    }
+
+    ///
+    /// Get the cost less the accumulated depreciation through `date`
+    ///
+    pub fn book_value(&self, date: DateTime<Utc>) -> f64 {
+        round_to_cents(self.cost - self.depreciation_through(date))
+    }
+
+    ///
+    /// Post the depreciation between the last posting and `date` to `ledger`, if there
+    /// is any, and only then mark the asset as depreciated through `date`. Returns the
+    /// posted entry.
+    ///
+    fn depreciate(
+        &mut self,
+        date: DateTime<Utc>,
+        ledger: &mut Ledger,
+    ) -> Result<Option<JournalEntry>, LedgerError> {
+        if self
+            .depreciated_through
+            .is_some_and(|through| through >= date)
+        {
+            return Ok(None);
+        }
+
+        let already_posted = self
+            .depreciated_through
+            .map(|through| self.depreciation_through(through))
+            .unwrap_or(0f64);
+        let amount = round_to_cents(self.depreciation_through(date) - already_posted);
+
+        let mut posted = None;
+        if amount > 0f64 {
+            let journal_entry = JournalEntry::from_lines(
+                &mut IdSequence::new(1),
+                date,
+                &format!("Depreciation - {}", self.name),
+                &[
+                    (
+                        &self.accounts.depreciation_expense,
+                        amount,
+                        EntryType::Debit,
+                    ),
+                    (
+                        &self.accounts.accumulated_depreciation,
+                        amount,
+                        EntryType::Credit,
+                    ),
+                ],
+            );
+            posted = Some(ledger.post_new_journal_entry(journal_entry)?.clone());
+        }
+        self.depreciated_through = Some(date);
+
+        Ok(posted)
+    }
+}
+
+///
+/// `FixedAssetRegister` keeps the fixed assets and posts their depreciation
+///
+#[derive(Debug, Clone, Default)]
+pub struct FixedAssetRegister {
+    assets: Vec<FixedAsset>,
+}
+
+impl FixedAssetRegister {
+    pub fn new() -> Self {
+        FixedAssetRegister { assets: Vec::new() }
+    }
+
+    pub fn add_asset(&mut self, asset: FixedAsset) -> Result<(), FixedAssetError> {
+        asset.validate()?;
+        if self.get_asset(asset.id()).is_some() {
+            return Err(FixedAssetError::DuplicateAsset(asset.id()));
+        }
+
+        self.assets.push(asset);
+        Ok(())
+    }
+
+    pub fn assets(&self) -> &Vec<FixedAsset> {
+        &self.assets
+    }
+
+    pub fn get_asset(&self, id: usize) -> Option<&FixedAsset> {
+        self.assets.iter().find(|a| a.id() == id)
+    }
+
+    ///
+    /// Get an asset to record its usage
+    ///
+    pub fn get_asset_mut(&mut self, id: usize) -> Option<&mut FixedAsset> {
+        self.assets.iter_mut().find(|a| a.id() == id)
+    }
+
+    ///
+    /// Post the depreciation of every asset in service from its last posting through
+    /// `period_end` to `ledger`, one entry per asset, and return the posted entries.
+    /// Running it twice for the same date posts nothing. An asset whose entry the ledger
+    /// rejects stays depreciated through its last posting.
+    ///
+    pub fn run_depreciation(
+        &mut self,
+        period_end: DateTime<Utc>,
+        ledger: &mut Ledger,
+    ) -> Result<Vec<JournalEntry>, FixedAssetError> {
+        let mut posted = Vec::new();
+        for asset in self
+            .assets
+            .iter_mut()
+            .filter(|asset| asset.disposal_date.is_none())
+        {
+            posted.extend(asset.depreciate(period_end, ledger)?);
+        }
+
+        Ok(posted)
+    }
+
+    ///
+    /// Dispose of an asset for `proceeds` received in `cash_account`. The depreciation
+    /// up to the disposal is posted to `ledger` first, then the cost and accumulated
+    /// depreciation are removed and the difference with the proceeds is booked to
+    /// `gain_loss_account`. Returns the posted entries. The asset is only disposed of
+    /// once the ledger accepts the disposal.
+    /// An asset cannot be disposed of before the date it was depreciated through.
+    ///
+    pub fn dispose(
+        &mut self,
+        asset_id: usize,
+        date: DateTime<Utc>,
+        proceeds: f64,
+        cash_account: &AccountNodeRef,
+        gain_loss_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<Vec<JournalEntry>, FixedAssetError> {
+        let asset = self
+            .get_asset_mut(asset_id)
+            .ok_or(FixedAssetError::UnknownAsset(asset_id))?;
+        if asset.disposal_date.is_some() {
+            return Err(FixedAssetError::AlreadyDisposed(asset_id));
+        }
+        if asset
+            .depreciated_through
+            .is_some_and(|depreciated_through| depreciated_through > date)
+        {
+            return Err(FixedAssetError::DepreciatedPastDisposal(asset_id));
+        }
+
+        let mut journal_entries: Vec<JournalEntry> =
+            asset.depreciate(date, ledger)?.into_iter().collect();
+
+        let accumulated_depreciation = asset.depreciation_through(date);
+        let gain = round_to_cents(proceeds - (asset.cost - accumulated_depreciation));
+
+        let mut lines = vec![];
+        if proceeds > 0f64 {
+            lines.push((cash_account, proceeds, EntryType::Debit));
+        }
+        if accumulated_depreciation > 0f64 {
+            lines.push((
+                &asset.accounts.accumulated_depreciation,
+                accumulated_depreciation,
+                EntryType::Debit,
+            ));
+        }
+        if gain < 0f64 {
+            lines.push((gain_loss_account, -gain, EntryType::Debit));
+        }
+        lines.push((&asset.accounts.asset, asset.cost, EntryType::Credit));
+        if gain > 0f64 {
+            lines.push((gain_loss_account, gain, EntryType::Credit));
+        }
+
+        let disposal = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Disposal - {}", asset.name),
+            &lines,
+        );
+        journal_entries.push(ledger.post_new_journal_entry(disposal)?.clone());
+        asset.disposal_date = Some(date);
+
+        Ok(journal_entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        AssetAccounts, DepreciationMethod, FixedAsset, FixedAssetError, FixedAssetRegister,
+    };
+    use crate::journal::ledger::{EntryType, Ledger, LedgerError};
+    use crate::journal::test_utils::{date, test_chart, TestChart};
+
+    fn equipment(chart: &TestChart, id: usize, method: DepreciationMethod) -> FixedAsset {
+        let mut asset = FixedAsset::new(
+            id,
+            "Forklift",
+            10_000.0,
+            date(2024, 1, 1),
+            5,
+            method,
+            AssetAccounts::new(
+                chart.account("Equipment"),
+                chart.account("Accumulated Depreciation"),
+                chart.account("Depreciation Expense"),
+            ),
+        );
+        asset.set_salvage_value(1_000.0);
+        asset
+    }
+
+    #[test]
+    fn test_depreciation_schedules() {
+        let chart = test_chart();
+        let yearly = |method| -> Vec<f64> {
+            equipment(&chart, 1, method)
+                .schedule()
+                .iter()
+                .map(|line| line.depreciation())
+                .collect()
+        };
+
+        assert_eq!(yearly(DepreciationMethod::StraightLine), vec![1_800.0; 5]);
+        assert_eq!(
+            yearly(DepreciationMethod::DecliningBalance { multiplier: 2.0 }),
+            vec![4_000.0, 2_400.0, 1_440.0, 864.0, 296.0]
+        );
+        assert_eq!(
+            yearly(DepreciationMethod::SumOfYearsDigits),
+            vec![3_000.0, 2_400.0, 1_800.0, 1_200.0, 600.0]
+        );
+
+        let mut press = equipment(
+            &chart,
+            2,
+            DepreciationMethod::UnitsOfProduction {
+                total_units: 100_000.0,
+            },
+        );
+        press.record_usage(date(2024, 6, 30), 12_000.0);
+        press.record_usage(date(2025, 6, 30), 20_000.0);
+        assert_eq!(press.depreciation_through(date(2024, 12, 31)), 1_080.0);
+        assert_eq!(press.schedule()[1].book_value(), 7_120.0);
+    }
+
+    #[test]
+    fn test_depreciation_run_and_disposal() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        let mut register = FixedAssetRegister::new();
+        register
+            .add_asset(equipment(&chart, 1, DepreciationMethod::StraightLine))
+            .unwrap();
+
+        let entries = register
+            .run_depreciation(date(2024, 3, 31), &mut ledger)
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].total_debit(), 450.0);
+        assert!(register
+            .run_depreciation(date(2024, 3, 31), &mut ledger)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            register
+                .dispose(
+                    1,
+                    date(2024, 2, 29),
+                    8_000.0,
+                    &chart.account("Cash"),
+                    &chart.account("Gain on Disposal"),
+                    &mut ledger,
+                )
+                .unwrap_err(),
+            FixedAssetError::DepreciatedPastDisposal(1)
+        );
+        let entries = register
+            .dispose(
+                1,
+                date(2024, 6, 30),
+                8_000.0,
+                &chart.account("Cash"),
+                &chart.account("Gain on Disposal"),
+                &mut ledger,
+            )
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].total_debit(), 450.0);
+
+        let disposal = &entries[1];
+        assert!(disposal.validate());
+        let loss = disposal
+            .transaction_entries()
+            .iter()
+            .find(|t| t.account_name() == "Gain on Disposal")
+            .unwrap();
+        assert_eq!(loss.amount(), 1_100.0);
+        assert_eq!(loss.entry_type(), &EntryType::Debit);
+
+        assert!(register
+            .run_depreciation(date(2024, 12, 31), &mut ledger)
+            .unwrap()
+            .is_empty());
+        assert_eq!(ledger.number_of_journal_entries(), 3);
+        assert!(ledger.verify_chain().is_ok());
+
+        // A depreciation the ledger rejects is not marked as done
+        register
+            .add_asset(equipment(&chart, 2, DepreciationMethod::StraightLine))
+            .unwrap();
+        assert_eq!(
+            register
+                .run_depreciation(date(2025, 3, 31), &mut ledger)
+                .unwrap_err(),
+            FixedAssetError::Ledger(LedgerError::DateOutOfRange(date(2025, 3, 31)))
+        );
+        assert!(register.get_asset(2).unwrap().depreciated_through.is_none());
+        let entries = register
+            .run_depreciation(date(2024, 12, 31), &mut ledger)
+            .unwrap();
+        assert_eq!(entries[0].date_of_entry(), date(2024, 12, 31));
+    }
+}
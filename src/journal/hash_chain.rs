@@ -0,0 +1,370 @@
+use crate::journal::ledger::{EntryType, JournalEntry};
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt::Display;
+use std::fmt::Write;
+
+///
+/// The previous hash of the first journal entry of a chain
+///
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+///
+/// Compute the SHA-256 digest of `data` as a lowercase hexadecimal string
+///
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        })
+}
+
+///
+/// Serialize everything that makes a `JournalEntry`, one field per line, in a form that
+/// only depends on its values. Strings are quoted and escaped and amounts keep every digit.
+/// Accounts are identified by their code, so renaming an account keeps the chain intact.
+/// The status and the voided date are left out: voiding an entry keeps the chain intact
+/// and is recorded by the audit trail, not the chain.
+///
+pub fn canonical_serialization(journal_entry: &JournalEntry) -> String {
+    let mut serialized = String::new();
+
+    let _ = writeln!(serialized, "id={}", journal_entry.id());
+    let _ = writeln!(
+        serialized,
+        "date={}",
+        journal_entry.date_of_entry().to_rfc3339()
+    );
+    let _ = writeln!(serialized, "description={:?}", journal_entry.description());
+    let _ = writeln!(
+        serialized,
+        "idempotency_key={:?}",
+        journal_entry.idempotency_key()
+    );
+    let _ = writeln!(
+        serialized,
+        "journal_type={:?} reference={:?}",
+        journal_entry.journal_type(),
+        journal_entry.reference()
+    );
+    let _ = writeln!(
+        serialized,
+        "recorded={:?}",
+        journal_entry.recorded_date().map(|date| date.to_rfc3339())
+    );
+    let _ = writeln!(
+        serialized,
+        "adjusting={} auto_reversing={} reversal_of={:?}",
+        journal_entry.is_adjusting(),
+        journal_entry.is_auto_reversing(),
+        journal_entry.reversal_of()
+    );
+
+    for entry in journal_entry.transaction_entries() {
+        let side = match entry.entry_type() {
+            EntryType::Debit => "Dr",
+            EntryType::Credit => "Cr",
+        };
+        let _ = writeln!(
+            serialized,
+            "entry={} {:?} {} {:?} {} {:?} tax_code={:?} dimensions={:?}",
+            entry.id(),
+            entry.account_code(),
+            side,
+            entry.amount(),
+            entry.date_of_entry().to_rfc3339(),
+            entry.description(),
+            entry.tax_code(),
+            entry.dimensions()
+        );
+    }
+
+    serialized
+}
+
+///
+/// Compute the hash sealing `journal_entry` after the entry whose hash is `previous_hash`
+///
+pub fn chain_hash(journal_entry: &JournalEntry, previous_hash: &str) -> String {
+    let mut data = String::from(previous_hash);
+    data.push('\n');
+    data.push_str(&canonical_serialization(journal_entry));
+
+    sha256_hex(data.as_bytes())
+}
+
+///
+/// How a link of the chain is broken
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrokenLinkKind {
+    ///
+    /// The entry was never sealed
+    ///
+    Unsealed,
+    ///
+    /// The entry does not point at the hash of the entry before it: an entry was
+    /// inserted, deleted or moved
+    ///
+    PreviousHashMismatch,
+    ///
+    /// The entry no longer matches its hash: it was modified after it was sealed
+    ///
+    HashMismatch,
+    ///
+    /// The chain does not end at the expected head: entries were removed from its end
+    ///
+    HeadMismatch,
+}
+
+///
+/// The first broken link found when verifying a chain
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenLink {
+    ///
+    /// The position of the entry in the chain, or the length of the chain when its
+    /// head does not match
+    ///
+    position: usize,
+    journal_entry_id: Option<usize>,
+    kind: BrokenLinkKind,
+}
+
+impl BrokenLink {
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn journal_entry_id(&self) -> Option<usize> {
+        self.journal_entry_id
+    }
+
+    pub fn kind(&self) -> &BrokenLinkKind {
+        &self.kind
+    }
+}
+
+impl Display for BrokenLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.journal_entry_id {
+            Some(id) => write!(
+                f,
+                "Chain broken at position {} (journal entry {}): {:?}",
+                self.position, id, self.kind
+            ),
+            None => write!(
+                f,
+                "Chain broken at position {}: {:?}",
+                self.position, self.kind
+            ),
+        }
+    }
+}
+
+impl Error for BrokenLink {}
+
+///
+/// Check every link of the chain formed by `journal_entries`, in order, and, if given,
+/// that the chain ends at `expected_head`. Returns the first broken link.
+///
+/// Voided entries are verified like the others: the status is not sealed, so voids do
+/// not break the chain and are not detected by it.
+///
+pub fn verify_chain(
+    journal_entries: &[JournalEntry],
+    expected_head: Option<&str>,
+) -> Result<(), BrokenLink> {
+    let mut previous_hash = GENESIS_HASH.to_owned();
+
+    for (position, journal_entry) in journal_entries.iter().enumerate() {
+        let broken = |kind| BrokenLink {
+            position,
+            journal_entry_id: Some(journal_entry.id()),
+            kind,
+        };
+
+        let (previous, hash) = match (journal_entry.previous_hash(), journal_entry.hash()) {
+            (Some(previous), Some(hash)) => (previous, hash),
+            _ => return Err(broken(BrokenLinkKind::Unsealed)),
+        };
+        if previous != previous_hash {
+            return Err(broken(BrokenLinkKind::PreviousHashMismatch));
+        }
+        if chain_hash(journal_entry, previous) != hash {
+            return Err(broken(BrokenLinkKind::HashMismatch));
+        }
+
+        previous_hash = hash.to_owned();
+    }
+
+    match expected_head {
+        Some(head) if head != previous_hash => Err(BrokenLink {
+            position: journal_entries.len(),
+            journal_entry_id: None,
+            kind: BrokenLinkKind::HeadMismatch,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sha256_hex, BrokenLinkKind};
+    use crate::journal::ledger::{EntryType, JournalEntry, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart, TestChart};
+
+    fn sale(chart: &TestChart, id: usize, day: u32, amount: f64) -> JournalEntry {
+        journal_entry(
+            id,
+            date(2024, 3, day),
+            "Cash sale",
+            &[
+                (&chart.account("Cash"), amount, EntryType::Debit),
+                (&chart.account("Sales Revenue"), amount, EntryType::Credit),
+            ],
+        )
+    }
+
+    ///
+    /// Load `journal_entries` into a new ledger
+    ///
+    fn load(journal_entries: Vec<JournalEntry>) -> Ledger {
+        let mut ledger = Ledger::new(1, date(2024, 3, 1), date(2024, 3, 31));
+        ledger.set_journal_entries(journal_entries).unwrap();
+        ledger
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+
+        // Messages around the padding boundaries of a block
+        for (length, digest) in [
+            (
+                55,
+                "9f4390f8d30c2dd92ec9f095b65e2b9ae9b0a925a5258e241c9f1e910f734318",
+            ),
+            (
+                56,
+                "b35439a4ac6f0948b6d6f9e3c6af0f5f590ce20f1bde7090ef7970686ec6738a",
+            ),
+            (
+                64,
+                "ffe054fe7ae0cb6dc65c3af9b61d5209f439851db43d0ba5997337df154668eb",
+            ),
+        ] {
+            assert_eq!(sha256_hex("a".repeat(length).as_bytes()), digest);
+        }
+    }
+
+    #[test]
+    fn test_verifier_reports_first_broken_link() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 3, 1), date(2024, 3, 31));
+        for id in 1..=4 {
+            ledger.add_journal_entry(sale(&chart, id, id as u32, 100.0 * id as f64));
+        }
+        assert!(ledger.verify_chain().is_ok());
+
+        // Modification
+        let mut tampered = ledger.journal_entries().clone();
+        tampered[2].set_description("Cash sale (edited)".to_owned());
+        let copy = load(tampered);
+        let broken = copy.verify_chain().unwrap_err();
+        assert_eq!(broken.position(), 2);
+        assert_eq!(broken.journal_entry_id(), Some(3));
+        assert_eq!(broken.kind(), &BrokenLinkKind::HashMismatch);
+
+        // Deletion
+        let mut deleted = ledger.journal_entries().clone();
+        deleted.remove(1);
+        let copy = load(deleted);
+        let broken = copy.verify_chain().unwrap_err();
+        assert_eq!(broken.journal_entry_id(), Some(3));
+        assert_eq!(broken.kind(), &BrokenLinkKind::PreviousHashMismatch);
+
+        // Insertion of a sealed entry taken from elsewhere
+        let mut other = Ledger::new(2, date(2024, 3, 1), date(2024, 3, 31));
+        other.add_journal_entry(sale(&chart, 9, 9, 1.0));
+        let mut inserted = ledger.journal_entries().clone();
+        inserted.insert(1, other.journal_entries()[0].clone());
+        let copy = load(inserted);
+        let broken = copy.verify_chain().unwrap_err();
+        assert_eq!(broken.position(), 1);
+        assert_eq!(broken.journal_entry_id(), Some(9));
+    }
+
+    #[test]
+    fn test_truncation_is_only_visible_against_a_known_head() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 3, 1), date(2024, 3, 31));
+        for id in 1..=3 {
+            ledger.add_journal_entry(sale(&chart, id, id as u32, 100.0 * id as f64));
+        }
+        let head = ledger.chain_head().unwrap().to_owned();
+        assert!(ledger.verify_chain_to(&head).is_ok());
+
+        // Dropping the last entries leaves a chain that is valid on its own
+        let mut truncated = ledger.journal_entries().clone();
+        truncated.pop();
+        let copy = load(truncated);
+        assert!(copy.verify_chain().is_ok());
+        let broken = copy.verify_chain_to(&head).unwrap_err();
+        assert_eq!(broken.kind(), &BrokenLinkKind::HeadMismatch);
+
+        let emptied = load(Vec::new());
+        assert!(emptied.verify_chain().is_ok());
+        assert_eq!(
+            emptied.verify_chain_to(&head).unwrap_err().kind(),
+            &BrokenLinkKind::HeadMismatch
+        );
+    }
+
+    #[test]
+    fn test_renames_and_voids_keep_the_chain() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 3, 1), date(2024, 3, 31));
+        for id in 1..=3 {
+            ledger.add_journal_entry(sale(&chart, id, id as u32, 100.0 * id as f64));
+        }
+        let hashes: Vec<Option<String>> = ledger
+            .journal_entries()
+            .iter()
+            .map(|j| j.hash().map(|hash| hash.to_owned()))
+            .collect();
+
+        let mut tree = chart.tree();
+        assert!(tree.rename_node("Cash", "Cash at Bank"));
+        assert_eq!(
+            ledger.journal_entries()[0].transaction_entries()[0].account_name(),
+            "Cash at Bank"
+        );
+        assert!(ledger.verify_chain().is_ok());
+
+        // The verifier does not see voids, which only the audit trail records
+        ledger.void_journal_entry(2).unwrap();
+        assert!(ledger.verify_chain().is_ok());
+        assert_eq!(
+            ledger
+                .journal_entries()
+                .iter()
+                .map(|j| j.hash().map(|hash| hash.to_owned()))
+                .collect::<Vec<_>>(),
+            hashes
+        );
+    }
+}
@@ -1,4 +1,313 @@
+use crate::journal::accounting_tree::{AccountTree, SubtotalNode};
+use crate::journal::balance_sheet::{build_subtotals, BalanceSheetError};
+use crate::journal::dimensions::{dimension_values, DimensionFilter};
+use crate::journal::ledger::{Ledger, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::rc::Rc;
+
 ///
 /// `Income Statement` structure
+/// Aggregates the ledger's activity over a period into revenue, expenses and net income
 ///
-pub struct IncomeStatement {}
+pub struct IncomeStatement {
+    id: usize,
+    from_date: DateTime<Utc>,
+    to_date: DateTime<Utc>,
+    accounting_tree: AccountTree,
+    ledger: Ledger,
+    revenue_accounts: Vec<String>,
+    expense_accounts: Vec<String>,
+    filter: DimensionFilter,
+}
+
+impl IncomeStatement {
+    ///
+    /// Create a new `IncomeStatement`. Revenue is read from the `"Revenue"` node and
+    /// expenses from the `"Expenses"` node unless set otherwise.
+    ///
+    pub fn new(
+        id: usize,
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+        accounting_tree: AccountTree,
+        ledger: Ledger,
+    ) -> Self {
+        IncomeStatement {
+            id,
+            from_date,
+            to_date,
+            accounting_tree,
+            ledger,
+            revenue_accounts: vec!["Revenue".to_owned()],
+            expense_accounts: vec!["Expenses".to_owned()],
+            filter: DimensionFilter::new(),
+        }
+    }
+
+    ///
+    /// Get the `IncomeStatement` id
+    ///
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    ///
+    /// Get the `IncomeStatement from_date`
+    ///
+    pub fn from_date(&self) -> &DateTime<Utc> {
+        &self.from_date
+    }
+
+    ///
+    /// Get the `IncomeStatement to_date`
+    ///
+    pub fn to_date(&self) -> &DateTime<Utc> {
+        &self.to_date
+    }
+
+    ///
+    /// Get the `IncomeStatement account_tree`
+    ///
+    pub fn accounting_tree(&self) -> &AccountTree {
+        &self.accounting_tree
+    }
+
+    ///
+    /// Get the `IncomeStatement ledger`
+    ///
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    ///
+    /// Get the names or paths of the accounts whose total is the revenue
+    ///
+    pub fn revenue_accounts(&self) -> &Vec<String> {
+        &self.revenue_accounts
+    }
+
+    ///
+    /// Set the names or paths of the accounts whose total is the revenue
+    ///
+    pub fn set_revenue_accounts(&mut self, revenue_accounts: Vec<&str>) {
+        self.revenue_accounts = revenue_accounts.iter().map(|a| a.to_string()).collect();
+    }
+
+    ///
+    /// Get the names or paths of the accounts whose total is the expenses
+    ///
+    pub fn expense_accounts(&self) -> &Vec<String> {
+        &self.expense_accounts
+    }
+
+    ///
+    /// Set the names or paths of the accounts whose total is the expenses
+    ///
+    pub fn set_expense_accounts(&mut self, expense_accounts: Vec<&str>) {
+        self.expense_accounts = expense_accounts.iter().map(|a| a.to_string()).collect();
+    }
+
+    ///
+    /// Get the filter selecting the transaction entries by their dimensions
+    ///
+    pub fn filter(&self) -> &DimensionFilter {
+        &self.filter
+    }
+
+    ///
+    /// Only include the transaction entries `filter` selects
+    ///
+    pub fn set_filter(&mut self, filter: DimensionFilter) {
+        self.filter = filter;
+    }
+
+    ///
+    /// Build the income statement from the journal entries dated between `from_date`
+    /// and `to_date` (inclusive). The `AccountTree` is left untouched.
+    ///
+    pub fn build(&self) -> Result<IncomeStatementSnapshot, BalanceSheetError> {
+        self.build_filtered(&self.filter)
+    }
+
+    ///
+    /// Build an income statement for each value `dimension` takes in the ledger,
+    /// within the filter
+    ///
+    pub fn build_grouped(
+        &self,
+        dimension: &str,
+    ) -> Result<Vec<(Option<String>, IncomeStatementSnapshot)>, BalanceSheetError> {
+        dimension_values(&self.ledger, dimension)
+            .into_iter()
+            .map(|value| {
+                let filter = self.filter.and(dimension, value.as_deref());
+                self.build_filtered(&filter)
+                    .map(|income_statement| (value, income_statement))
+            })
+            .collect()
+    }
+
+    fn build_filtered(
+        &self,
+        filter: &DimensionFilter,
+    ) -> Result<IncomeStatementSnapshot, BalanceSheetError> {
+        let period = ReportingPeriod::range(self.from_date, self.to_date)?;
+
+        let transaction_entries: Vec<&Rc<TransactionEntry>> = self
+            .ledger
+            .active_journal_entries()
+            .filter(|journal_entry| {
+                journal_entry.date_of_entry() >= self.from_date
+                    && journal_entry.date_of_entry() <= self.to_date
+            })
+            .flat_map(|journal_entry| journal_entry.transaction_entries())
+            .filter(|transaction_entry| filter.matches(transaction_entry))
+            .collect();
+
+        let root = build_subtotals(&self.accounting_tree, transaction_entries)?;
+
+        let revenue = accounts_total(&root, &self.revenue_accounts)?;
+        let expenses = accounts_total(&root, &self.expense_accounts)?;
+
+        Ok(IncomeStatementSnapshot {
+            id: self.id,
+            period,
+            root,
+            revenue,
+            expenses,
+        })
+    }
+}
+
+///
+/// Sum the amounts of the nodes named (or pathed) in `accounts`
+///
+fn accounts_total(root: &SubtotalNode, accounts: &[String]) -> Result<f64, BalanceSheetError> {
+    let mut total = 0f64;
+
+    for account in accounts {
+        match root.lookup(account) {
+            None => return Err(BalanceSheetError::UnknownAccount(account.to_owned())),
+            Some(node) => total += node.amount(),
+        }
+    }
+
+    Ok(total)
+}
+
+///
+/// `IncomeStatementSnapshot` is the result of building an `IncomeStatement`
+///
+#[derive(Debug, Clone)]
+pub struct IncomeStatementSnapshot {
+    id: usize,
+    period: ReportingPeriod,
+    root: SubtotalNode,
+    revenue: f64,
+    expenses: f64,
+}
+
+impl IncomeStatementSnapshot {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    ///
+    /// Get the `ReportingPeriod` the income statement covers
+    ///
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    ///
+    /// Get the root of the computed subtotals tree
+    ///
+    pub fn root(&self) -> &SubtotalNode {
+        &self.root
+    }
+
+    ///
+    /// Find a node by its account name or by its path
+    ///
+    pub fn lookup(&self, name_or_path: &str) -> Option<&SubtotalNode> {
+        self.root.lookup(name_or_path)
+    }
+
+    pub fn revenue(&self) -> f64 {
+        self.revenue
+    }
+
+    pub fn expenses(&self) -> f64 {
+        self.expenses
+    }
+
+    ///
+    /// Get revenue less expenses
+    ///
+    pub fn net_income(&self) -> f64 {
+        self.revenue - self.expenses
+    }
+
+    ///
+    /// Get the total of a set of accounts represented by their account name or path
+    ///
+    pub fn accounts_total(&self, account_names: &[&str]) -> Result<f64, BalanceSheetError> {
+        let account_names: Vec<String> = account_names.iter().map(|a| a.to_string()).collect();
+        accounts_total(&self.root, &account_names)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::IncomeStatement;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    #[test]
+    fn test_income_statement_build() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let sales = chart.account("Sales Revenue");
+        let rent = chart.account("Rent Expense");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 20),
+            "Cash sales",
+            &[
+                (&cash, 900.0, EntryType::Debit),
+                (&sales, 900.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 1, 31),
+            "January rent",
+            &[
+                (&rent, 250.0, EntryType::Debit),
+                (&cash, 250.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 2, 15),
+            "Cash sales",
+            &[
+                (&cash, 100.0, EntryType::Debit),
+                (&sales, 100.0, EntryType::Credit),
+            ],
+        ));
+
+        let january =
+            IncomeStatement::new(1, date(2024, 1, 1), date(2024, 1, 31), chart.tree(), ledger)
+                .build()
+                .unwrap();
+
+        assert_eq!(january.revenue(), 900.0);
+        assert_eq!(january.expenses(), 250.0);
+        assert_eq!(january.net_income(), 650.0);
+        assert_eq!(january.lookup("Rent Expense").unwrap().amount(), 250.0);
+    }
+}
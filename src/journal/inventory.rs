@@ -0,0 +1,1089 @@
+use crate::journal::accounting_tree::AccountNodeRef;
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError,
+};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fmt::Display;
+
+///
+/// Quantities below this are treated as zero
+///
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+
+///
+/// How the cost of the units sold is taken from an item's cost layers
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CostMethod {
+    ///
+    /// First in, first out: the oldest units are sold first
+    ///
+    Fifo,
+    ///
+    /// Last in, first out: the newest units are sold first
+    ///
+    Lifo,
+    ///
+    /// Every unit on hand carries the average cost, recomputed after each purchase
+    ///
+    MovingAverage,
+}
+
+///
+/// Errors raised by the `Inventory`
+///
+#[derive(Debug, PartialEq)]
+pub enum InventoryError {
+    DuplicateItem(usize),
+    UnknownItem(usize),
+    DuplicatePurchase(usize),
+    UnknownPurchase(usize),
+    ///
+    /// More units were sold or returned than are on hand
+    ///
+    InsufficientQuantity {
+        item_id: usize,
+        on_hand: f64,
+        requested: f64,
+    },
+    ///
+    /// A purchase or sale is dated before the last sale of the item, whose cost it
+    /// would change
+    ///
+    BackdatedEvent {
+        item_id: usize,
+        date: DateTime<Utc>,
+    },
+    ///
+    /// Quantities and amounts must be positive
+    ///
+    InvalidAmount(f64),
+    ///
+    /// The ledger rejected the journal entry of an event
+    ///
+    Ledger(LedgerError),
+}
+
+impl Display for InventoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InventoryError::DuplicateItem(id) => write!(f, "Item {} already exists.", id),
+            InventoryError::UnknownItem(id) => write!(f, "Item {} does not exist.", id),
+            InventoryError::DuplicatePurchase(id) => write!(f, "Purchase {} already exists.", id),
+            InventoryError::UnknownPurchase(id) => write!(f, "Purchase {} does not exist.", id),
+            InventoryError::InsufficientQuantity {
+                item_id,
+                on_hand,
+                requested,
+            } => write!(
+                f,
+                "Cannot take {} units of item {} with {} on hand.",
+                requested, item_id, on_hand
+            ),
+            InventoryError::BackdatedEvent { item_id, date } => write!(
+                f,
+                "Item {} was sold after {}.",
+                item_id,
+                date.format("%Y-%m-%d")
+            ),
+            InventoryError::InvalidAmount(amount) => write!(f, "{} must be positive.", amount),
+            InventoryError::Ledger(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for InventoryError {}
+
+impl From<LedgerError> for InventoryError {
+    fn from(error: LedgerError) -> Self {
+        InventoryError::Ledger(error)
+    }
+}
+
+fn positive(amount: f64) -> Result<f64, InventoryError> {
+    if amount > 0f64 {
+        Ok(amount)
+    } else {
+        Err(InventoryError::InvalidAmount(amount))
+    }
+}
+
+///
+/// Units of an item bought by a purchase and still on hand
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostLayer {
+    purchase_id: usize,
+    date: DateTime<Utc>,
+    quantity: f64,
+    unit_cost: f64,
+}
+
+impl CostLayer {
+    pub fn purchase_id(&self) -> usize {
+        self.purchase_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn quantity(&self) -> f64 {
+        self.quantity
+    }
+
+    ///
+    /// Get the cost of a unit, capitalized freight and discounts included
+    ///
+    pub fn unit_cost(&self) -> f64 {
+        self.unit_cost
+    }
+}
+
+///
+/// `InventoryItem` and its cost layers, in purchase date order
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryItem {
+    id: usize,
+    name: String,
+    method: CostMethod,
+    layers: Vec<CostLayer>,
+    last_sale: Option<DateTime<Utc>>,
+}
+
+impl InventoryItem {
+    pub fn new(id: usize, name: &str, method: CostMethod) -> Self {
+        InventoryItem {
+            id,
+            name: name.to_owned(),
+            method,
+            layers: Vec::new(),
+            last_sale: None,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn method(&self) -> CostMethod {
+        self.method
+    }
+
+    pub fn layers(&self) -> &Vec<CostLayer> {
+        &self.layers
+    }
+
+    pub fn quantity_on_hand(&self) -> f64 {
+        self.layers.iter().map(|layer| layer.quantity).sum()
+    }
+
+    ///
+    /// Get the date of the last sale, before which no purchase or sale can be dated
+    ///
+    pub fn last_sale(&self) -> Option<&DateTime<Utc>> {
+        self.last_sale.as_ref()
+    }
+
+    ///
+    /// Check that an event dated `date` does not come before the last sale
+    ///
+    fn check_date(&self, date: DateTime<Utc>) -> Result<(), InventoryError> {
+        match self.last_sale {
+            Some(last_sale) if date < last_sale => Err(InventoryError::BackdatedEvent {
+                item_id: self.id,
+                date,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Get the units on hand purchased up to `date`
+    ///
+    fn quantity_available(&self, date: DateTime<Utc>) -> f64 {
+        self.layers
+            .iter()
+            .filter(|layer| layer.date <= date)
+            .map(|layer| layer.quantity)
+            .sum()
+    }
+
+    ///
+    /// Get the cost of the units on hand
+    ///
+    pub fn value(&self) -> f64 {
+        round_to_cents(
+            self.layers
+                .iter()
+                .map(|layer| layer.quantity * layer.unit_cost)
+                .sum(),
+        )
+    }
+
+    fn layer_mut(&mut self, purchase_id: usize) -> Option<&mut CostLayer> {
+        self.layers
+            .iter_mut()
+            .find(|layer| layer.purchase_id == purchase_id)
+    }
+
+    ///
+    /// Spread `value` evenly over the units on hand
+    ///
+    fn average(&mut self, value: f64) {
+        let quantity = self.quantity_on_hand();
+        if quantity > QUANTITY_TOLERANCE {
+            self.layers
+                .iter_mut()
+                .for_each(|layer| layer.unit_cost = value / quantity);
+        }
+    }
+
+    ///
+    /// Take `quantity` units out of the layers purchased up to `date` in the order of
+    /// the cost method. Returns their cost.
+    ///
+    fn consume(&mut self, quantity: f64, date: DateTime<Utc>) -> f64 {
+        let value = self.value();
+        let mut remaining = quantity;
+        let mut cost = 0f64;
+
+        let purchased = self
+            .layers
+            .iter()
+            .take_while(|layer| layer.date <= date)
+            .count();
+        let order: Vec<usize> = match self.method {
+            CostMethod::Lifo => (0..purchased).rev().collect(),
+            CostMethod::Fifo | CostMethod::MovingAverage => (0..purchased).collect(),
+        };
+        for index in order {
+            let layer = &mut self.layers[index];
+            let taken = remaining.min(layer.quantity);
+
+            layer.quantity -= taken;
+            cost += taken * layer.unit_cost;
+            remaining -= taken;
+            if remaining <= QUANTITY_TOLERANCE {
+                break;
+            }
+        }
+        self.layers
+            .retain(|layer| layer.quantity > QUANTITY_TOLERANCE);
+
+        let cost = round_to_cents(cost);
+        if self.method == CostMethod::MovingAverage {
+            // Keep the units on hand worth exactly what is left in the inventory account
+            self.average(value - cost);
+        }
+
+        cost
+    }
+}
+
+///
+/// `Purchase` of `quantity` units of an item at an invoiced `unit_cost`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Purchase {
+    id: usize,
+    item_id: usize,
+    date: DateTime<Utc>,
+    quantity: f64,
+    unit_cost: f64,
+    returned: f64,
+}
+
+impl Purchase {
+    pub fn new(
+        id: usize,
+        item_id: usize,
+        date: DateTime<Utc>,
+        quantity: f64,
+        unit_cost: f64,
+    ) -> Self {
+        Purchase {
+            id,
+            item_id,
+            date,
+            quantity,
+            unit_cost,
+            returned: 0f64,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn item_id(&self) -> usize {
+        self.item_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn quantity(&self) -> f64 {
+        self.quantity
+    }
+
+    pub fn unit_cost(&self) -> f64 {
+        self.unit_cost
+    }
+
+    ///
+    /// Get the quantity returned to the vendor
+    ///
+    pub fn returned(&self) -> f64 {
+        self.returned
+    }
+}
+
+///
+/// An amount charged to cost of goods sold: the cost of units sold, or the share of
+/// a freight charge, discount or return that belongs to units already sold
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostOfSales {
+    item_id: usize,
+    date: DateTime<Utc>,
+    quantity: f64,
+    cost: f64,
+}
+
+impl CostOfSales {
+    pub fn item_id(&self) -> usize {
+        self.item_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    ///
+    /// Get the quantity sold; `0` for cost adjustments
+    ///
+    pub fn quantity(&self) -> f64 {
+        self.quantity
+    }
+
+    pub fn cost(&self) -> f64 {
+        self.cost
+    }
+}
+
+///
+/// The accounts the `Inventory` posts to
+///
+#[derive(Debug, Clone)]
+pub struct InventoryAccounts {
+    inventory: AccountNodeRef,
+    cost_of_goods_sold: AccountNodeRef,
+    accounts_payable: AccountNodeRef,
+}
+
+impl InventoryAccounts {
+    pub fn new(
+        inventory: AccountNodeRef,
+        cost_of_goods_sold: AccountNodeRef,
+        accounts_payable: AccountNodeRef,
+    ) -> Self {
+        InventoryAccounts {
+            inventory,
+            cost_of_goods_sold,
+            accounts_payable,
+        }
+    }
+
+    pub fn inventory(&self) -> AccountNodeRef {
+        self.inventory.clone()
+    }
+
+    pub fn cost_of_goods_sold(&self) -> AccountNodeRef {
+        self.cost_of_goods_sold.clone()
+    }
+
+    pub fn accounts_payable(&self) -> AccountNodeRef {
+        self.accounts_payable.clone()
+    }
+}
+
+///
+/// `Inventory` keeps the quantities and cost layers of its items up to date from
+/// purchase and sale events (a perpetual inventory system). Freight-in, purchase
+/// returns and purchase discounts change the cost of the units still on hand, and
+/// the share belonging to units already sold goes to cost of goods sold.
+/// Sales only take units purchased by their date, and the purchases and sales of an
+/// item cannot be dated before its last sale.
+/// Each event posts its journal entry to a `Ledger` and returns the posted entry; an
+/// event the ledger rejects leaves the inventory unchanged.
+///
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    accounts: InventoryAccounts,
+    items: Vec<InventoryItem>,
+    purchases: Vec<Purchase>,
+    cost_of_sales: Vec<CostOfSales>,
+    // Dated changes to the inventory account, to value the inventory as of a date
+    movements: Vec<(DateTime<Utc>, f64)>,
+}
+
+impl Inventory {
+    pub fn new(accounts: InventoryAccounts) -> Self {
+        Inventory {
+            accounts,
+            items: Vec::new(),
+            purchases: Vec::new(),
+            cost_of_sales: Vec::new(),
+            movements: Vec::new(),
+        }
+    }
+
+    pub fn accounts(&self) -> &InventoryAccounts {
+        &self.accounts
+    }
+
+    pub fn add_item(&mut self, item: InventoryItem) -> Result<(), InventoryError> {
+        if self.get_item(item.id).is_some() {
+            return Err(InventoryError::DuplicateItem(item.id));
+        }
+
+        self.items.push(item);
+
+        Ok(())
+    }
+
+    pub fn items(&self) -> &Vec<InventoryItem> {
+        &self.items
+    }
+
+    pub fn get_item(&self, id: usize) -> Option<&InventoryItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    fn item_mut(&mut self, id: usize) -> Result<&mut InventoryItem, InventoryError> {
+        self.items
+            .iter_mut()
+            .find(|item| item.id == id)
+            .ok_or(InventoryError::UnknownItem(id))
+    }
+
+    pub fn purchases(&self) -> &Vec<Purchase> {
+        &self.purchases
+    }
+
+    pub fn get_purchase(&self, id: usize) -> Option<&Purchase> {
+        self.purchases.iter().find(|purchase| purchase.id == id)
+    }
+
+    pub fn cost_of_sales(&self) -> &Vec<CostOfSales> {
+        &self.cost_of_sales
+    }
+
+    ///
+    /// Receive a purchase on account: debit inventory, credit accounts payable
+    ///
+    pub fn purchase(
+        &mut self,
+        purchase: Purchase,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        positive(purchase.quantity)?;
+        positive(purchase.unit_cost)?;
+        if self.get_purchase(purchase.id).is_some() {
+            return Err(InventoryError::DuplicatePurchase(purchase.id));
+        }
+
+        let before = self.clone();
+        let item = self.item_mut(purchase.item_id)?;
+        item.check_date(purchase.date)?;
+        let value = item.value();
+        let position = item
+            .layers
+            .iter()
+            .take_while(|layer| layer.date <= purchase.date)
+            .count();
+        item.layers.insert(
+            position,
+            CostLayer {
+                purchase_id: purchase.id,
+                date: purchase.date,
+                quantity: purchase.quantity,
+                unit_cost: purchase.unit_cost,
+            },
+        );
+
+        let amount = round_to_cents(purchase.quantity * purchase.unit_cost);
+        if item.method == CostMethod::MovingAverage {
+            item.average(value + amount);
+        }
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            purchase.date,
+            &format!("Purchase {} of {}", purchase.id, item.name),
+            &[
+                (&self.accounts.inventory, amount, EntryType::Debit),
+                (&self.accounts.accounts_payable, amount, EntryType::Credit),
+            ],
+        );
+        self.movements.push((purchase.date, amount));
+        self.purchases.push(purchase);
+
+        self.post(before, journal_entry, ledger)
+    }
+
+    ///
+    /// Change the cost of a purchase by `amount`, positive for freight and negative
+    /// for discounts. Units of the purchase still on hand take their share and the
+    /// rest goes to cost of goods sold. Returns the inventory and cost of goods sold shares.
+    ///
+    fn adjust_cost(
+        &mut self,
+        purchase_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+    ) -> Result<(f64, f64), InventoryError> {
+        let purchase = self
+            .get_purchase(purchase_id)
+            .ok_or(InventoryError::UnknownPurchase(purchase_id))?
+            .clone();
+        let kept = purchase.quantity - purchase.returned;
+
+        let item = self.item_mut(purchase.item_id)?;
+        let value = item.value();
+        let on_hand = item
+            .layers
+            .iter()
+            .find(|layer| layer.purchase_id == purchase_id)
+            .map(|layer| layer.quantity)
+            .unwrap_or(0f64);
+
+        let inventory_share = if kept > QUANTITY_TOLERANCE {
+            round_to_cents(amount * on_hand / kept)
+        } else {
+            0f64
+        };
+        let cost_of_sales_share = round_to_cents(amount - inventory_share);
+
+        match item.method {
+            CostMethod::MovingAverage => item.average(value + inventory_share),
+            CostMethod::Fifo | CostMethod::Lifo => {
+                if let Some(layer) = item.layer_mut(purchase_id) {
+                    layer.unit_cost += inventory_share / layer.quantity;
+                }
+            }
+        }
+
+        if cost_of_sales_share != 0f64 {
+            self.cost_of_sales.push(CostOfSales {
+                item_id: purchase.item_id,
+                date,
+                quantity: 0f64,
+                cost: cost_of_sales_share,
+            });
+        }
+        self.movements.push((date, inventory_share));
+
+        Ok((inventory_share, cost_of_sales_share))
+    }
+
+    ///
+    /// Add a line for a signed amount: positive amounts are posted as `positive`
+    /// and negative ones as the opposite entry type
+    ///
+    fn signed_line<'a>(
+        lines: &mut Vec<(&'a AccountNodeRef, f64, EntryType)>,
+        account: &'a AccountNodeRef,
+        amount: f64,
+        positive: EntryType,
+    ) {
+        let negative = match positive {
+            EntryType::Debit => EntryType::Credit,
+            EntryType::Credit => EntryType::Debit,
+        };
+
+        if amount > 0f64 {
+            lines.push((account, amount, positive));
+        } else if amount < 0f64 {
+            lines.push((account, -amount, negative));
+        }
+    }
+
+    ///
+    /// Capitalize a freight-in charge into the cost of a purchase, crediting
+    /// `credit_account` (cash or the carrier's payable)
+    ///
+    pub fn capitalize_freight(
+        &mut self,
+        purchase_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+        credit_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        positive(amount)?;
+        let before = self.clone();
+        let (inventory_share, cost_of_sales_share) = self.adjust_cost(purchase_id, date, amount)?;
+
+        let mut lines = Vec::new();
+        Self::signed_line(
+            &mut lines,
+            &self.accounts.inventory,
+            inventory_share,
+            EntryType::Debit,
+        );
+        Self::signed_line(
+            &mut lines,
+            &self.accounts.cost_of_goods_sold,
+            cost_of_sales_share,
+            EntryType::Debit,
+        );
+        lines.push((credit_account, amount, EntryType::Credit));
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Freight-in on purchase {}", purchase_id),
+            &lines,
+        );
+        self.post(before, journal_entry, ledger)
+    }
+
+    ///
+    /// Take a discount on a purchase: debit accounts payable, credit the cost of the units
+    ///
+    pub fn purchase_discount(
+        &mut self,
+        purchase_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        positive(amount)?;
+        let before = self.clone();
+        let (inventory_share, cost_of_sales_share) =
+            self.adjust_cost(purchase_id, date, -amount)?;
+
+        let mut lines = vec![(&self.accounts.accounts_payable, amount, EntryType::Debit)];
+        Self::signed_line(
+            &mut lines,
+            &self.accounts.inventory,
+            -inventory_share,
+            EntryType::Credit,
+        );
+        Self::signed_line(
+            &mut lines,
+            &self.accounts.cost_of_goods_sold,
+            -cost_of_sales_share,
+            EntryType::Credit,
+        );
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Discount on purchase {}", purchase_id),
+            &lines,
+        );
+        self.post(before, journal_entry, ledger)
+    }
+
+    ///
+    /// Return `quantity` units of a purchase to the vendor at the invoiced cost. Under
+    /// FIFO and LIFO the units leave their layer at its cost, and any capitalized freight
+    /// or discount on them goes to cost of goods sold.
+    ///
+    pub fn return_purchase(
+        &mut self,
+        purchase_id: usize,
+        date: DateTime<Utc>,
+        quantity: f64,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        positive(quantity)?;
+        let before = self.clone();
+        let purchase = self
+            .get_purchase(purchase_id)
+            .ok_or(InventoryError::UnknownPurchase(purchase_id))?
+            .clone();
+        let invoiced = round_to_cents(quantity * purchase.unit_cost);
+
+        let item = self.item_mut(purchase.item_id)?;
+        let value = item.value();
+        let method = item.method;
+        let on_hand = item
+            .layer_mut(purchase_id)
+            .map(|layer| layer.quantity)
+            .unwrap_or(0f64);
+        if on_hand + QUANTITY_TOLERANCE < quantity {
+            return Err(InventoryError::InsufficientQuantity {
+                item_id: purchase.item_id,
+                on_hand,
+                requested: quantity,
+            });
+        }
+
+        let layer = item
+            .layer_mut(purchase_id)
+            .ok_or(InventoryError::UnknownPurchase(purchase_id))?;
+        layer.quantity -= quantity;
+        let layer_cost = match method {
+            CostMethod::MovingAverage => invoiced,
+            CostMethod::Fifo | CostMethod::Lifo => round_to_cents(quantity * layer.unit_cost),
+        };
+        item.layers
+            .retain(|layer| layer.quantity > QUANTITY_TOLERANCE);
+        if method == CostMethod::MovingAverage {
+            item.average(value - invoiced);
+        }
+
+        let difference = round_to_cents(layer_cost - invoiced);
+        if difference != 0f64 {
+            self.cost_of_sales.push(CostOfSales {
+                item_id: purchase.item_id,
+                date,
+                quantity: 0f64,
+                cost: difference,
+            });
+        }
+        if let Some(purchase) = self.purchases.iter_mut().find(|p| p.id == purchase_id) {
+            purchase.returned += quantity;
+        }
+        self.movements.push((date, -layer_cost));
+
+        let mut lines = vec![(&self.accounts.accounts_payable, invoiced, EntryType::Debit)];
+        Self::signed_line(
+            &mut lines,
+            &self.accounts.cost_of_goods_sold,
+            difference,
+            EntryType::Debit,
+        );
+        lines.push((&self.accounts.inventory, layer_cost, EntryType::Credit));
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Return on purchase {}", purchase_id),
+            &lines,
+        );
+        self.post(before, journal_entry, ledger)
+    }
+
+    ///
+    /// Sell `quantity` units of an item: debit cost of goods sold, credit inventory
+    /// with the cost given by the item's cost method. Only the units purchased by
+    /// `date` can be sold.
+    ///
+    pub fn sell(
+        &mut self,
+        item_id: usize,
+        date: DateTime<Utc>,
+        quantity: f64,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        positive(quantity)?;
+
+        let before = self.clone();
+        let item = self.item_mut(item_id)?;
+        item.check_date(date)?;
+        let on_hand = item.quantity_available(date);
+        if on_hand + QUANTITY_TOLERANCE < quantity {
+            return Err(InventoryError::InsufficientQuantity {
+                item_id,
+                on_hand,
+                requested: quantity,
+            });
+        }
+
+        let cost = item.consume(quantity, date);
+        item.last_sale = Some(date);
+        let description = format!("Cost of {} units of {} sold", quantity, item.name);
+        self.cost_of_sales.push(CostOfSales {
+            item_id,
+            date,
+            quantity,
+            cost,
+        });
+        self.movements.push((date, -cost));
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &description,
+            &[
+                (&self.accounts.cost_of_goods_sold, cost, EntryType::Debit),
+                (&self.accounts.inventory, cost, EntryType::Credit),
+            ],
+        );
+        self.post(before, journal_entry, ledger)
+    }
+
+    ///
+    /// Post the journal entry of an event under ids the ledger allocates. When the
+    /// ledger rejects it, the inventory goes back to how it was `before` the event.
+    ///
+    fn post(
+        &mut self,
+        before: Inventory,
+        journal_entry: JournalEntry,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, InventoryError> {
+        match ledger.post_new_journal_entry(journal_entry) {
+            Ok(posted) => Ok(posted.clone()),
+            Err(error) => {
+                *self = before;
+                Err(error.into())
+            }
+        }
+    }
+
+    ///
+    /// Get the cost of goods sold over `period`
+    ///
+    pub fn cost_of_goods_sold(&self, period: &ReportingPeriod) -> f64 {
+        round_to_cents(
+            self.cost_of_sales
+                .iter()
+                .filter(|cost_of_sales| period.contains(cost_of_sales.date))
+                .map(|cost_of_sales| cost_of_sales.cost)
+                .sum(),
+        )
+    }
+
+    ///
+    /// Get the cost of every unit on hand as of a date
+    ///
+    pub fn ending_inventory(&self, as_of: DateTime<Utc>) -> f64 {
+        round_to_cents(
+            self.movements
+                .iter()
+                .filter(|(date, _)| *date <= as_of)
+                .map(|(_, amount)| amount)
+                .sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        CostMethod, Inventory, InventoryAccounts, InventoryError, InventoryItem, Purchase,
+    };
+    use crate::journal::ledger::{Ledger, LedgerError};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, test_chart, TestChart};
+
+    ///
+    /// Buy 10 units at 10 then 10 units at 12, and sell 15 units
+    ///
+    fn valuation(chart: &TestChart, method: CostMethod) -> Inventory {
+        let mut inventory = Inventory::new(InventoryAccounts::new(
+            chart.account("Inventory"),
+            chart.account("Cost of Goods Sold"),
+            chart.account("Accounts Payable"),
+        ));
+        inventory
+            .add_item(InventoryItem::new(1, "Widget", method))
+            .unwrap();
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        inventory
+            .purchase(
+                Purchase::new(1, 1, date(2024, 1, 5), 10.0, 10.0),
+                &mut ledger,
+            )
+            .unwrap();
+        inventory
+            .purchase(
+                Purchase::new(2, 1, date(2024, 1, 10), 10.0, 12.0),
+                &mut ledger,
+            )
+            .unwrap();
+        let journal_entry = inventory
+            .sell(1, date(2024, 1, 20), 15.0, &mut ledger)
+            .unwrap();
+        assert!(journal_entry.validate());
+
+        inventory
+    }
+
+    #[test]
+    fn test_cost_methods() {
+        let chart = test_chart();
+        let january = ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap();
+
+        let fifo = valuation(&chart, CostMethod::Fifo);
+        assert_eq!(fifo.cost_of_goods_sold(&january), 160.0);
+        assert_eq!(fifo.ending_inventory(date(2024, 1, 10)), 220.0);
+        assert_eq!(fifo.ending_inventory(date(2024, 1, 31)), 60.0);
+
+        let lifo = valuation(&chart, CostMethod::Lifo);
+        assert_eq!(lifo.cost_of_goods_sold(&january), 170.0);
+        assert_eq!(lifo.ending_inventory(date(2024, 1, 31)), 50.0);
+
+        let mut average = valuation(&chart, CostMethod::MovingAverage);
+        assert_eq!(average.cost_of_goods_sold(&january), 165.0);
+        assert_eq!(average.ending_inventory(date(2024, 1, 31)), 55.0);
+
+        assert_eq!(
+            average
+                .sell(
+                    1,
+                    date(2024, 1, 25),
+                    6.0,
+                    &mut Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31))
+                )
+                .unwrap_err(),
+            InventoryError::InsufficientQuantity {
+                item_id: 1,
+                on_hand: 5.0,
+                requested: 6.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_freight_returns_and_discounts() {
+        let chart = test_chart();
+        let january = ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap();
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+
+        // 5 units of purchase 2 are left on hand under FIFO
+        let mut fifo = valuation(&chart, CostMethod::Fifo);
+
+        // Half the freight belongs to the 5 units sold
+        let freight = fifo
+            .capitalize_freight(
+                2,
+                date(2024, 1, 21),
+                20.0,
+                &chart.account("Cash"),
+                &mut ledger,
+            )
+            .unwrap();
+        assert!(freight.validate());
+        assert_eq!(fifo.ending_inventory(date(2024, 1, 31)), 70.0);
+        assert_eq!(fifo.cost_of_goods_sold(&january), 170.0);
+
+        // Returned units leave at 14 a unit; 2 a unit of freight goes to cost of goods sold
+        let purchase_return = fifo
+            .return_purchase(2, date(2024, 1, 22), 2.0, &mut ledger)
+            .unwrap();
+        assert!(purchase_return.validate());
+        assert_eq!(purchase_return.total_credit(), 28.0);
+        assert_eq!(fifo.ending_inventory(date(2024, 1, 31)), 42.0);
+
+        let discount = fifo
+            .purchase_discount(2, date(2024, 1, 23), 8.0, &mut ledger)
+            .unwrap();
+        assert!(discount.validate());
+        assert_eq!(fifo.get_item(1).unwrap().quantity_on_hand(), 3.0);
+        assert_eq!(fifo.ending_inventory(date(2024, 1, 31)), 39.0);
+        assert_eq!(fifo.cost_of_goods_sold(&january), 169.0);
+    }
+
+    #[test]
+    fn test_sales_take_units_purchased_by_their_date() {
+        let chart = test_chart();
+        let mut inventory = Inventory::new(InventoryAccounts::new(
+            chart.account("Inventory"),
+            chart.account("Cost of Goods Sold"),
+            chart.account("Accounts Payable"),
+        ));
+        inventory
+            .add_item(InventoryItem::new(1, "Widget", CostMethod::Lifo))
+            .unwrap();
+
+        // Recorded out of order, the purchases are layered by date
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        inventory
+            .purchase(
+                Purchase::new(1, 1, date(2024, 1, 10), 10.0, 12.0),
+                &mut ledger,
+            )
+            .unwrap();
+        inventory
+            .purchase(
+                Purchase::new(2, 1, date(2024, 1, 5), 10.0, 10.0),
+                &mut ledger,
+            )
+            .unwrap();
+
+        assert_eq!(
+            inventory
+                .sell(1, date(2024, 1, 7), 15.0, &mut ledger)
+                .unwrap_err(),
+            InventoryError::InsufficientQuantity {
+                item_id: 1,
+                on_hand: 10.0,
+                requested: 15.0
+            }
+        );
+        let sale = inventory
+            .sell(1, date(2024, 1, 7), 5.0, &mut ledger)
+            .unwrap();
+        assert_eq!(sale.total_debit(), 50.0);
+
+        assert_eq!(
+            inventory
+                .purchase(
+                    Purchase::new(3, 1, date(2024, 1, 6), 1.0, 11.0),
+                    &mut ledger
+                )
+                .unwrap_err(),
+            InventoryError::BackdatedEvent {
+                item_id: 1,
+                date: date(2024, 1, 6)
+            }
+        );
+        assert_eq!(
+            inventory
+                .sell(1, date(2024, 1, 6), 1.0, &mut ledger)
+                .unwrap_err(),
+            InventoryError::BackdatedEvent {
+                item_id: 1,
+                date: date(2024, 1, 6)
+            }
+        );
+
+        assert_eq!(inventory.ending_inventory(date(2024, 1, 7)), 50.0);
+        assert_eq!(inventory.ending_inventory(date(2024, 1, 31)), 170.0);
+        assert_eq!(ledger.number_of_journal_entries(), 3);
+    }
+
+    #[test]
+    fn test_rejected_events_leave_the_inventory_unchanged() {
+        let chart = test_chart();
+        let mut inventory = valuation(&chart, CostMethod::Fifo);
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 1, 31));
+
+        assert_eq!(
+            inventory
+                .sell(1, date(2024, 2, 1), 2.0, &mut ledger)
+                .unwrap_err(),
+            InventoryError::Ledger(LedgerError::DateOutOfRange(date(2024, 2, 1)))
+        );
+        assert_eq!(inventory.get_item(1).unwrap().quantity_on_hand(), 5.0);
+        assert!(inventory
+            .get_item(1)
+            .unwrap()
+            .last_sale()
+            .is_none_or(|sale| *sale < date(2024, 2, 1)));
+        assert_eq!(inventory.cost_of_sales().len(), 1);
+        assert_eq!(inventory.ending_inventory(date(2024, 12, 31)), 60.0);
+
+        let sale = inventory
+            .sell(1, date(2024, 1, 31), 2.0, &mut ledger)
+            .unwrap();
+        assert_eq!(sale.total_debit(), 24.0);
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+    }
+}
@@ -1,13 +1,29 @@
-use crate::journal::accounting_tree::{AccountNodeRef, AccountTreeNode, PrimaryAccountType};
-use chrono::{DateTime, TimeZone, Utc};
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{
+    AccountNodeRef, AccountTreeNode, ActionType, PrimaryAccountType,
+};
+use crate::journal::audit::{AuditAction, AuditSubject, Auditor};
+use crate::journal::dimensions::{DimensionError, Dimensions};
+use crate::journal::hash_chain::{chain_hash, verify_chain, BrokenLink, GENESIS_HASH};
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Display;
 use std::{cell::Ref, cmp::Ordering, rc::Rc};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum EntryType {
     Credit,
     Debit,
 }
 
+///
+/// Round an amount to two decimal places
+///
+pub(crate) fn round_to_cents(amount: f64) -> f64 {
+    (amount * 100f64).round() / 100f64
+}
+
 ///
 /// `TransactionEntry` is a single row entry that makes up a JournalEntry
 ///
@@ -19,6 +35,8 @@ pub struct TransactionEntry {
     entry_type: EntryType,
     date_of_entry: DateTime<Utc>,
     description: String,
+    tax_code: Option<String>,
+    dimensions: BTreeMap<String, String>,
 }
 
 impl TransactionEntry {
@@ -37,6 +55,8 @@ impl TransactionEntry {
             entry_type,
             date_of_entry,
             description: description.to_owned(),
+            tax_code: None,
+            dimensions: BTreeMap::new(),
         }
     }
 
@@ -60,7 +80,14 @@ impl TransactionEntry {
         self.account.as_ref().borrow().name().to_owned()
     }
 
-    pub fn account_type(&self) -> Ref<Option<Rc<PrimaryAccountType>>> {
+    ///
+    /// Get the code of the account, which stays the same when the account is renamed
+    ///
+    pub fn account_code(&self) -> String {
+        self.account.as_ref().borrow().code().to_owned()
+    }
+
+    pub fn account_type(&self) -> Ref<'_, Option<Rc<PrimaryAccountType>>> {
         Ref::map(self.account.borrow(), |acc| acc.account_type())
     }
 
@@ -68,6 +95,26 @@ impl TransactionEntry {
         self.amount
     }
 
+    ///
+    /// Get the amount signed by its effect on the account: positive when the entry
+    /// increases the account and negative when it decreases it.
+    /// Returns `None` if the account has no `PrimaryAccountType`.
+    ///
+    pub fn signed_amount(&self) -> Option<f64> {
+        let account_type = self.account_type();
+        let account_type = account_type.as_ref()?;
+
+        let action = match self.entry_type {
+            EntryType::Credit => account_type.on_credit(),
+            EntryType::Debit => account_type.on_debit(),
+        };
+
+        match action {
+            ActionType::Increase => Some(self.amount),
+            ActionType::Decrease => Some(-self.amount),
+        }
+    }
+
     pub fn set_amount(&mut self, amount: f64) {
         self.amount = amount
     }
@@ -95,18 +142,110 @@ impl TransactionEntry {
     pub fn set_description(&mut self, description: &str) {
         self.description = description.to_owned()
     }
+
+    ///
+    /// Get the code of the tax the entry is a taxable base or a tax amount of
+    ///
+    pub fn tax_code(&self) -> Option<&str> {
+        self.tax_code.as_deref()
+    }
+
+    pub fn set_tax_code(&mut self, tax_code: Option<&str>) {
+        self.tax_code = tax_code.map(|code| code.to_owned());
+    }
+
+    ///
+    /// Get the analytic dimensions of the entry, e.g. `"Department"` to `"Sales"`
+    ///
+    pub fn dimensions(&self) -> &BTreeMap<String, String> {
+        &self.dimensions
+    }
+
+    ///
+    /// Get the value of a dimension
+    ///
+    pub fn dimension(&self, name: &str) -> Option<&str> {
+        self.dimensions.get(name).map(|value| value.as_str())
+    }
+
+    pub fn set_dimension(&mut self, name: &str, value: &str) {
+        self.dimensions.insert(name.to_owned(), value.to_owned());
+    }
+
+    pub fn remove_dimension(&mut self, name: &str) {
+        self.dimensions.remove(name);
+    }
+
+    ///
+    /// Copy the entry under the id `id`
+    ///
+    fn with_id(&self, id: usize) -> TransactionEntry {
+        TransactionEntry {
+            id,
+            account: self.account.clone(),
+            amount: self.amount,
+            entry_type: self.entry_type,
+            date_of_entry: self.date_of_entry,
+            description: self.description.clone(),
+            tax_code: self.tax_code.clone(),
+            dimensions: self.dimensions.clone(),
+        }
+    }
+}
+
+///
+/// Where a `JournalEntry` stands in its life cycle
+///
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JournalEntryStatus {
+    ///
+    /// Not posted yet: the entry can still be changed or deleted
+    ///
+    Draft,
+    ///
+    /// Posted to a `Ledger`: the entry can only be reversed or voided
+    ///
+    Posted,
+    ///
+    /// Posted then voided: the entry stays in the ledger but no longer counts
+    /// towards balances
+    ///
+    Voided,
 }
 
 ///
 /// `JournalEntry` structure that holds a set of related `TransactionEntries`.
 /// The sum of the `Credit` entries must equal the `Debit` entries.
 ///
-#[derive(Debug)]
+/// A `Ledger` seals each entry it posts with the hash of the entry and of the entry
+/// posted before it. Changing a sealed entry breaks the chain. Posted entries are
+/// corrected by reversing or voiding them, never by deleting them.
+///
+/// The `date_of_entry` is the effective date of the transaction, the date balances
+/// are reported at. The recorded date is when the ledger posted the entry, so that
+/// the books can be read as they stood at any time, before later restatements.
+///
+/// An entry of a journal type, such as `SJ` for the sales journal, is given a
+/// gapless legal reference within its journal and fiscal year when posted, such
+/// as `SJ-2026-000123`.
+///
+#[derive(Debug, Clone)]
 pub struct JournalEntry {
     id: usize,
     transaction_entries: Vec<Rc<TransactionEntry>>,
     date_of_entry: DateTime<Utc>,
     description: String,
+    adjusting: bool,
+    auto_reversing: bool,
+    reversal_of: Option<usize>,
+    idempotency_key: Option<String>,
+    journal_type: Option<String>,
+    reference: Option<String>,
+    recorded_date: Option<DateTime<Utc>>,
+    voided_date: Option<DateTime<Utc>>,
+    status: JournalEntryStatus,
+    previous_hash: Option<String>,
+    hash: Option<String>,
 }
 
 impl JournalEntry {
@@ -117,6 +256,17 @@ impl JournalEntry {
             transaction_entries,
             date_of_entry,
             description: description.to_owned(),
+            adjusting: false,
+            auto_reversing: false,
+            reversal_of: None,
+            idempotency_key: None,
+            journal_type: None,
+            reference: None,
+            recorded_date: None,
+            voided_date: None,
+            status: JournalEntryStatus::Draft,
+            previous_hash: None,
+            hash: None,
         }
     }
 
@@ -152,6 +302,201 @@ impl JournalEntry {
         &self.transaction_entries
     }
 
+    ///
+    /// Check whether the entry is an adjusting entry. Auto-reversing entries and
+    /// the reversals of adjusting entries are adjusting entries.
+    ///
+    pub fn is_adjusting(&self) -> bool {
+        self.adjusting || self.auto_reversing
+    }
+
+    ///
+    /// Mark the entry as an adjusting entry posted at the end of a period
+    ///
+    pub fn set_adjusting(&mut self, adjusting: bool) {
+        self.adjusting = adjusting
+    }
+
+    pub fn is_auto_reversing(&self) -> bool {
+        self.auto_reversing
+    }
+
+    ///
+    /// Mark the entry as auto-reversing: adding it to a `Ledger` also adds its
+    /// reversal at the start of the next period
+    ///
+    pub fn set_auto_reversing(&mut self, auto_reversing: bool) {
+        self.auto_reversing = auto_reversing
+    }
+
+    ///
+    /// Get the id of the entry this entry reverses
+    ///
+    pub fn reversal_of(&self) -> Option<usize> {
+        self.reversal_of
+    }
+
+    ///
+    /// Get the hash of the entry posted before this one, once sealed
+    ///
+    pub fn previous_hash(&self) -> Option<&str> {
+        self.previous_hash.as_deref()
+    }
+
+    ///
+    /// Get the hash sealing the entry, once posted
+    ///
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.hash.is_some()
+    }
+
+    pub fn status(&self) -> JournalEntryStatus {
+        self.status
+    }
+
+    ///
+    /// Get the key the client posting the entry supplied to make retries safe
+    ///
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    ///
+    /// Set the key identifying the entry across retries: posting an entry with the key
+    /// of a posted entry does nothing when their content is the same
+    ///
+    pub fn set_idempotency_key(&mut self, idempotency_key: Option<&str>) {
+        self.idempotency_key = idempotency_key.map(|key| key.to_owned());
+    }
+
+    ///
+    /// Get the journal the entry is recorded in, such as `SJ` for the sales journal
+    ///
+    pub fn journal_type(&self) -> Option<&str> {
+        self.journal_type.as_deref()
+    }
+
+    pub fn set_journal_type(&mut self, journal_type: Option<&str>) {
+        self.journal_type = journal_type.map(|journal_type| journal_type.to_owned());
+    }
+
+    ///
+    /// Get the legal reference the `Ledger` gave the entry when posting it, if the entry
+    /// has a journal type
+    ///
+    pub fn reference(&self) -> Option<&str> {
+        self.reference.as_deref()
+    }
+
+    ///
+    /// Get when the entry was recorded in the books
+    ///
+    pub fn recorded_date(&self) -> Option<DateTime<Utc>> {
+        self.recorded_date
+    }
+
+    ///
+    /// Set when the entry was recorded, for entries recorded before being loaded into
+    /// the `Ledger`. The ledger records the entries without one at the time it posts them;
+    /// sealed entries must already carry theirs.
+    ///
+    pub fn set_recorded_date(&mut self, recorded_date: Option<DateTime<Utc>>) {
+        self.recorded_date = recorded_date
+    }
+
+    ///
+    /// Get when the entry was voided, if it was
+    ///
+    pub fn voided_date(&self) -> Option<DateTime<Utc>> {
+        self.voided_date
+    }
+
+    ///
+    /// Check whether the books recorded at `recorded_at` hold the entry. The ledger
+    /// gives every entry it holds a recorded date.
+    ///
+    pub fn is_recorded_at(&self, recorded_at: DateTime<Utc>) -> bool {
+        self.recorded_date
+            .is_some_and(|recorded_date| recorded_date <= recorded_at)
+    }
+
+    ///
+    /// Check whether `other` records the same transaction: the same date, description,
+    /// journal, flags and lines. Ids, references, the recorded date and the posting
+    /// status are not compared.
+    ///
+    pub fn has_same_content(&self, other: &JournalEntry) -> bool {
+        self.date_of_entry == other.date_of_entry
+            && self.description == other.description
+            && self.journal_type == other.journal_type
+            && self.adjusting == other.adjusting
+            && self.auto_reversing == other.auto_reversing
+            && self.reversal_of == other.reversal_of
+            && self.transaction_entries.len() == other.transaction_entries.len()
+            && self
+                .transaction_entries
+                .iter()
+                .zip(other.transaction_entries.iter())
+                .all(|(a, b)| {
+                    a.account_name() == b.account_name()
+                        && a.entry_type == b.entry_type
+                        && a.amount == b.amount
+                        && a.date_of_entry == b.date_of_entry
+                        && a.description == b.description
+                        && a.tax_code == b.tax_code
+                        && a.dimensions == b.dimensions
+                })
+    }
+
+    pub fn is_voided(&self) -> bool {
+        self.status == JournalEntryStatus::Voided
+    }
+
+    ///
+    /// Seal the entry after the entry whose hash is `previous_hash`
+    ///
+    fn seal(&mut self, previous_hash: &str) {
+        self.hash = Some(chain_hash(self, previous_hash));
+        self.previous_hash = Some(previous_hash.to_owned());
+    }
+
+    ///
+    /// Build the mirror of the entry dated `date_of_entry`: the same lines with
+    /// debits and credits swapped, linked back to this entry
+    ///
+    pub fn reversal(&self, ids: &mut IdSequence, date_of_entry: DateTime<Utc>) -> JournalEntry {
+        let description = format!("Reversal of {}", self.description);
+        let mut reversal = JournalEntry::new(ids.next_id(), date_of_entry, &description);
+        reversal.reversal_of = Some(self.id);
+        reversal.adjusting = self.is_adjusting();
+        reversal.journal_type = self.journal_type.clone();
+
+        for entry in self.transaction_entries.iter() {
+            let entry_type = match entry.entry_type() {
+                EntryType::Credit => EntryType::Debit,
+                EntryType::Debit => EntryType::Credit,
+            };
+
+            let mut transaction_entry = TransactionEntry::new(
+                ids.next_id(),
+                entry.account(),
+                entry.amount(),
+                entry_type,
+                date_of_entry,
+                &description,
+            );
+            transaction_entry.set_tax_code(entry.tax_code());
+            transaction_entry.dimensions = entry.dimensions.clone();
+            reversal.add_transaction_entry(Rc::new(transaction_entry));
+        }
+
+        reversal
+    }
+
     pub fn number_of_transaction_entries(&self) -> usize {
         self.transaction_entries().len()
     }
@@ -159,23 +504,49 @@ impl JournalEntry {
     pub fn total_credit(&self) -> f64 {
         let total_credits = self
             .transaction_entries()
-            .into_iter()
+            .iter()
             .filter(|entry| entry.entry_type() == &EntryType::Credit)
             .map(|credit_entry| credit_entry.amount())
             .sum();
 
-        return total_credits;
+        total_credits
     }
 
     pub fn total_debit(&self) -> f64 {
         let total_debits = self
             .transaction_entries()
-            .into_iter()
+            .iter()
             .filter(|entry| entry.entry_type() == &EntryType::Debit)
             .map(|credit_entry| credit_entry.amount())
             .sum();
 
-        return total_debits;
+        total_debits
+    }
+
+    ///
+    /// Build a `JournalEntry` from `(account, amount, entry type)` lines, taking the
+    /// journal entry id and the transaction entry ids from `ids`
+    ///
+    pub fn from_lines(
+        ids: &mut IdSequence,
+        date_of_entry: DateTime<Utc>,
+        description: &str,
+        lines: &[(&AccountNodeRef, f64, EntryType)],
+    ) -> Self {
+        let mut journal_entry = JournalEntry::new(ids.next_id(), date_of_entry, description);
+
+        for (account, amount, entry_type) in lines {
+            journal_entry.add_transaction_entry(Rc::new(TransactionEntry::new(
+                ids.next_id(),
+                (*account).clone(),
+                *amount,
+                *entry_type,
+                date_of_entry,
+                description,
+            )));
+        }
+
+        journal_entry
     }
 
     // Should return a Result<Ok(), JournalEntryError()>
@@ -197,7 +568,149 @@ impl JournalEntry {
             }
         }
 
-        return debits == credits;
+        // Amounts are summed as floats, so the totals only match to the cent
+        (debits - credits).abs() <= DEFAULT_TOLERANCE
+    }
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {} {:?}:",
+            self.id,
+            self.date_of_entry.format("%Y-%m-%d"),
+            self.description
+        )?;
+
+        for (index, entry) in self.transaction_entries.iter().enumerate() {
+            let side = match entry.entry_type {
+                EntryType::Debit => "Dr",
+                EntryType::Credit => "Cr",
+            };
+            let separator = if index == 0 { "" } else { "," };
+            write!(
+                f,
+                "{} {} {} {:.2}",
+                separator,
+                side,
+                entry.account_name(),
+                entry.amount
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// Errors raised when correcting the journal entries of a `Ledger`
+///
+#[derive(Debug, PartialEq)]
+pub enum LedgerError {
+    UnknownJournalEntry(usize),
+    ///
+    /// Posted entries cannot be deleted, only reversed or voided
+    ///
+    PostedJournalEntry(usize),
+    AlreadyVoided(usize),
+    AlreadyReversed(usize),
+    ///
+    /// The date falls outside the dates of the ledger
+    ///
+    DateOutOfRange(DateTime<Utc>),
+    ///
+    /// A journal entry with the same idempotency key but a different content was posted
+    ///
+    IdempotencyConflict(String),
+    ///
+    /// The debits of the journal entry do not equal its credits
+    ///
+    UnbalancedJournalEntry(usize),
+    ///
+    /// A sealed journal entry was loaded without the date it was recorded
+    ///
+    MissingRecordedDate(usize),
+    DuplicateJournalEntryId(usize),
+    DuplicateTransactionEntryId(usize),
+    DuplicateReference(String),
+    ///
+    /// A loaded reference is not the next one of the legal sequence of its journal and
+    /// fiscal year
+    ///
+    ReferenceOutOfSequence(String),
+    ///
+    /// Entries can only be loaded into a ledger without posted entries
+    ///
+    LedgerNotEmpty(usize),
+    ///
+    /// A transaction entry does not carry the dimensions the ledger declares
+    ///
+    Dimension(DimensionError),
+}
+
+impl Display for LedgerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerError::UnknownJournalEntry(id) => {
+                write!(f, "Journal entry {} does not exist.", id)
+            }
+            LedgerError::PostedJournalEntry(id) => write!(
+                f,
+                "Journal entry {} is posted and can only be reversed or voided.",
+                id
+            ),
+            LedgerError::AlreadyVoided(id) => {
+                write!(f, "Journal entry {} has already been voided.", id)
+            }
+            LedgerError::AlreadyReversed(id) => {
+                write!(f, "Journal entry {} has already been reversed.", id)
+            }
+            LedgerError::DateOutOfRange(date) => write!(
+                f,
+                "Date {} falls outside the dates of the ledger.",
+                date.format("%Y-%m-%d")
+            ),
+            LedgerError::IdempotencyConflict(key) => write!(
+                f,
+                "Idempotency key {:?} was already posted with different content.",
+                key
+            ),
+            LedgerError::UnbalancedJournalEntry(id) => {
+                write!(f, "Journal entry {} does not balance.", id)
+            }
+            LedgerError::MissingRecordedDate(id) => {
+                write!(f, "Sealed journal entry {} has no recorded date.", id)
+            }
+            LedgerError::DuplicateJournalEntryId(id) => {
+                write!(f, "Journal entry id {} is already used.", id)
+            }
+            LedgerError::DuplicateTransactionEntryId(id) => {
+                write!(f, "Transaction entry id {} is already used.", id)
+            }
+            LedgerError::DuplicateReference(reference) => {
+                write!(f, "Reference {} is already used.", reference)
+            }
+            LedgerError::ReferenceOutOfSequence(reference) => write!(
+                f,
+                "Reference {} does not follow the legal sequence of its journal.",
+                reference
+            ),
+            LedgerError::LedgerNotEmpty(id) => write!(
+                f,
+                "Ledger {} already has posted journal entries and cannot load others.",
+                id
+            ),
+            LedgerError::Dimension(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for LedgerError {}
+
+impl From<DimensionError> for LedgerError {
+    fn from(error: DimensionError) -> Self {
+        LedgerError::Dimension(error)
     }
 }
 
@@ -206,12 +719,36 @@ impl JournalEntry {
 /// This is the structure that feeds into the `balance sheet`, the `income statement`
 /// and the `statemement of cashflow`.
 ///
-#[derive(Debug)]
+/// The ledger allocates the ids of the entries it generates, rejects ids already
+/// used by its posted, scheduled or draft entries and indexes the posted entries
+/// by id, idempotency key and reference.
+///
+#[derive(Debug, Clone)]
 pub struct Ledger {
     id: usize,
     from_date: DateTime<Utc>,
     to_date: DateTime<Utc>,
     journal_entries: Vec<JournalEntry>,
+    // Reversals dated after the to_date, by id of the entry they reverse
+    scheduled_reversals: BTreeMap<usize, JournalEntry>,
+    drafts: Vec<JournalEntry>,
+    draft_positions: HashMap<usize, usize>,
+    auditor: Option<Auditor>,
+    // Positions of the posted entries, which are never removed
+    positions_by_id: HashMap<usize, usize>,
+    positions_by_idempotency_key: HashMap<String, usize>,
+    positions_by_reference: HashMap<String, usize>,
+    // Positions of the posted reversals by id of the entry they reverse
+    reversal_positions: HashMap<usize, usize>,
+    journal_entry_ids: HashSet<usize>,
+    transaction_entry_ids: HashSet<usize>,
+    // Hands out the ids of the entries generated for the ledger
+    ids: IdSequence,
+    // Last legal sequence number per journal type and fiscal year
+    legal_sequences: HashMap<(String, i32), usize>,
+    fiscal_year_start_month: u32,
+    clock: fn() -> DateTime<Utc>,
+    dimensions: Option<Dimensions>,
 }
 
 impl Ledger {
@@ -227,9 +764,306 @@ impl Ledger {
             from_date,
             to_date,
             journal_entries: Vec::new(),
+            scheduled_reversals: BTreeMap::new(),
+            drafts: Vec::new(),
+            draft_positions: HashMap::new(),
+            auditor: None,
+            positions_by_id: HashMap::new(),
+            positions_by_idempotency_key: HashMap::new(),
+            positions_by_reference: HashMap::new(),
+            reversal_positions: HashMap::new(),
+            journal_entry_ids: HashSet::new(),
+            transaction_entry_ids: HashSet::new(),
+            ids: IdSequence::new(1),
+            legal_sequences: HashMap::new(),
+            fiscal_year_start_month: 1,
+            clock: Utc::now,
+            dimensions: None,
+        }
+    }
+
+    ///
+    /// Set the clock giving the recorded dates of the posted entries and the dates
+    /// entries are voided
+    ///
+    pub fn set_clock(&mut self, clock: fn() -> DateTime<Utc>) {
+        // The audit shows the time each clock reads
+        self.audit_date("clock", (self.clock)(), clock());
+        self.clock = clock;
+    }
+
+    ///
+    /// Get the dimensions the transaction entries posted to the ledger must carry, if any
+    ///
+    pub fn dimensions(&self) -> Option<&Dimensions> {
+        self.dimensions.as_ref()
+    }
+
+    ///
+    /// Validate the dimensions of every later posted entry with `dimensions`.
+    /// The active entries already posted must carry them too.
+    ///
+    pub fn set_dimensions(&mut self, dimensions: Option<Dimensions>) -> Result<(), LedgerError> {
+        if let Some(dimensions) = dimensions.as_ref() {
+            dimensions.validate_ledger(self)?;
+        }
+
+        self.audit(
+            AuditSubject::Ledger(self.id),
+            AuditAction::Update,
+            "dimensions",
+            self.dimensions.as_ref().map(|d| format!("{:?}", d)),
+            dimensions.as_ref().map(|d| format!("{:?}", d)),
+        );
+        self.dimensions = dimensions;
+        Ok(())
+    }
+
+    fn check_dimensions(&self, journal_entry: &JournalEntry) -> Result<(), LedgerError> {
+        match self.dimensions.as_ref() {
+            Some(dimensions) => Ok(dimensions.validate_journal_entry(journal_entry)?),
+            None => Ok(()),
         }
     }
 
+    ///
+    /// Get the `Auditor` recording the mutations of the ledger, if any
+    ///
+    pub fn auditor(&self) -> Option<&Auditor> {
+        self.auditor.as_ref()
+    }
+
+    ///
+    /// Record every later mutation of the ledger with `auditor`. The change is recorded
+    /// by the auditor replaced, if any, and by `auditor`, so that switching auditing off
+    /// leaves a trace.
+    ///
+    pub fn set_auditor(&mut self, auditor: Option<Auditor>) {
+        let actor = |auditor: Option<&Auditor>| auditor.map(|a| a.actor().to_owned());
+        let (before, after) = (actor(self.auditor.as_ref()), actor(auditor.as_ref()));
+        let same_log = match (self.auditor.as_ref(), auditor.as_ref()) {
+            (Some(old), Some(new)) => Rc::ptr_eq(old.log(), new.log()),
+            _ => false,
+        };
+
+        self.audit(
+            AuditSubject::Ledger(self.id),
+            AuditAction::Update,
+            "auditor",
+            before.clone(),
+            after.clone(),
+        );
+        self.auditor = auditor;
+        if !same_log {
+            self.audit(
+                AuditSubject::Ledger(self.id),
+                AuditAction::Update,
+                "auditor",
+                before,
+                after,
+            );
+        }
+    }
+
+    ///
+    /// Set the actor recorded for the later mutations, if the ledger is audited
+    ///
+    pub fn set_actor(&mut self, actor: &str) {
+        if let Some(auditor) = self.auditor.as_mut() {
+            auditor.set_actor(actor);
+        }
+    }
+
+    fn audit(
+        &self,
+        subject: AuditSubject,
+        action: AuditAction,
+        field: &str,
+        before: Option<String>,
+        after: Option<String>,
+    ) {
+        if let Some(auditor) = self.auditor.as_ref() {
+            auditor.record(subject, action, field, before, after);
+        }
+    }
+
+    fn audit_insert(&self, journal_entry: &JournalEntry) {
+        self.audit(
+            AuditSubject::JournalEntry(journal_entry.id),
+            AuditAction::Insert,
+            "journal_entry",
+            None,
+            Some(journal_entry.to_string()),
+        );
+    }
+
+    ///
+    /// Seal `journal_entry` after the last posted entry unless it is already sealed
+    ///
+    fn chain(&self, mut journal_entry: JournalEntry) -> JournalEntry {
+        if journal_entry.status == JournalEntryStatus::Draft {
+            journal_entry.status = JournalEntryStatus::Posted;
+        }
+        if !journal_entry.is_sealed() {
+            journal_entry.seal(self.chain_head().unwrap_or(GENESIS_HASH));
+        }
+        journal_entry
+    }
+
+    ///
+    /// Check that the ids and the reference of `journal_entry` are not used yet, other
+    /// than by the draft it replaces, if any
+    ///
+    fn check_unique(
+        &self,
+        journal_entry: &JournalEntry,
+        replaced: Option<&JournalEntry>,
+    ) -> Result<(), LedgerError> {
+        let (replaced_id, replaced_ids) = match replaced {
+            Some(draft) => (
+                Some(draft.id),
+                draft
+                    .transaction_entries
+                    .iter()
+                    .map(|entry| entry.id)
+                    .collect(),
+            ),
+            None => (None, HashSet::new()),
+        };
+
+        if self.journal_entry_ids.contains(&journal_entry.id)
+            && replaced_id != Some(journal_entry.id)
+        {
+            return Err(LedgerError::DuplicateJournalEntryId(journal_entry.id));
+        }
+
+        let mut ids = HashSet::new();
+        for entry in journal_entry.transaction_entries.iter() {
+            if (self.transaction_entry_ids.contains(&entry.id) && !replaced_ids.contains(&entry.id))
+                || !ids.insert(entry.id)
+            {
+                return Err(LedgerError::DuplicateTransactionEntryId(entry.id));
+            }
+        }
+
+        match journal_entry.reference() {
+            Some(reference) if self.positions_by_reference.contains_key(reference) => {
+                Err(LedgerError::DuplicateReference(reference.to_owned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    ///
+    /// Check that the reference `journal_entry` is loaded with, if any, is the next one
+    /// of the legal sequence of its journal and fiscal year
+    ///
+    fn check_reference(&self, journal_entry: &JournalEntry) -> Result<(), LedgerError> {
+        let reference = match journal_entry.reference() {
+            None => return Ok(()),
+            Some(reference) => reference,
+        };
+        let out_of_sequence = || LedgerError::ReferenceOutOfSequence(reference.to_owned());
+
+        let journal_type = journal_entry.journal_type().ok_or_else(out_of_sequence)?;
+        let fiscal_year = self.fiscal_year(journal_entry.date_of_entry);
+        let last = self
+            .legal_sequences
+            .get(&(journal_type.to_owned(), fiscal_year))
+            .copied()
+            .unwrap_or(0);
+        let number = reference
+            .strip_prefix(&format!("{}-{}-", journal_type, fiscal_year))
+            .and_then(|number| number.parse::<usize>().ok());
+        if number != Some(last + 1) {
+            return Err(out_of_sequence());
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Mark the ids of `journal_entry` as used
+    ///
+    fn register_ids(&mut self, journal_entry: &JournalEntry) {
+        self.journal_entry_ids.insert(journal_entry.id);
+        self.ids.skip_past(journal_entry.id);
+        for entry in journal_entry.transaction_entries.iter() {
+            self.transaction_entry_ids.insert(entry.id);
+            self.ids.skip_past(entry.id);
+        }
+    }
+
+    ///
+    /// Free the ids of a removed draft. No other entry uses them: `add_draft` and
+    /// `update_draft` only accept drafts with ids of their own.
+    ///
+    fn release_ids(&mut self, journal_entry: &JournalEntry) {
+        self.journal_entry_ids.remove(&journal_entry.id);
+        for entry in journal_entry.transaction_entries.iter() {
+            self.transaction_entry_ids.remove(&entry.id);
+        }
+    }
+
+    ///
+    /// Give `journal_entry` the next reference of its journal and fiscal year, unless
+    /// it already carries one, record it and append it to the posted entries.
+    /// Returns its position.
+    ///
+    fn push_posted(&mut self, mut journal_entry: JournalEntry) -> usize {
+        if let Some(journal_type) = journal_entry.journal_type.clone() {
+            let fiscal_year = self.fiscal_year(journal_entry.date_of_entry);
+            let last = self
+                .legal_sequences
+                .entry((journal_type.clone(), fiscal_year))
+                .or_insert(0);
+
+            match journal_entry.reference.as_deref() {
+                // Entries loaded with their reference keep it, checked by `check_reference`
+                Some(_) => *last += 1,
+                // Sealed entries cannot be given a reference without breaking the chain
+                None if !journal_entry.is_sealed() => {
+                    *last += 1;
+                    journal_entry.reference =
+                        Some(format!("{}-{}-{:06}", journal_type, fiscal_year, *last));
+                }
+                None => {}
+            }
+        }
+
+        if !journal_entry.is_sealed() && journal_entry.recorded_date.is_none() {
+            journal_entry.recorded_date = Some((self.clock)());
+        }
+
+        let journal_entry = self.chain(journal_entry);
+        let position = self.journal_entries.len();
+        self.positions_by_id.insert(journal_entry.id, position);
+        if let Some(key) = journal_entry.idempotency_key() {
+            self.positions_by_idempotency_key
+                .insert(key.to_owned(), position);
+        }
+        if let Some(reference) = journal_entry.reference() {
+            self.positions_by_reference
+                .insert(reference.to_owned(), position);
+        }
+        if let Some(reversal_of) = journal_entry.reversal_of {
+            self.reversal_positions.insert(reversal_of, position);
+        }
+        self.journal_entries.push(journal_entry);
+
+        position
+    }
+
+    fn audit_date(&self, field: &str, before: DateTime<Utc>, after: DateTime<Utc>) {
+        self.audit(
+            AuditSubject::Ledger(self.id),
+            AuditAction::Update,
+            field,
+            Some(before.to_rfc3339()),
+            Some(after.to_rfc3339()),
+        );
+    }
+
     ///
     /// Get the `Ledger` id
     ///
@@ -241,6 +1075,13 @@ impl Ledger {
     /// Set the `Ledger`'s id
     ///
     pub fn set_id(&mut self, id: usize) {
+        self.audit(
+            AuditSubject::Ledger(self.id),
+            AuditAction::Update,
+            "id",
+            Some(self.id.to_string()),
+            Some(id.to_string()),
+        );
         self.id = id
     }
 
@@ -248,6 +1089,7 @@ impl Ledger {
     /// Set the `from_date` - the date when the ledger entries begin
     ///
     pub fn set_from_date(&mut self, from_date: DateTime<Utc>) {
+        self.audit_date("from_date", self.from_date, from_date);
         self.from_date = from_date;
     }
 
@@ -262,7 +1104,53 @@ impl Ledger {
     /// Set the `to_date` - the date when the ledger entries end
     ///
     pub fn set_to_date(&mut self, to_date: DateTime<Utc>) {
+        self.audit_date("to_date", self.to_date, to_date);
         self.to_date = to_date;
+
+        // Post the scheduled reversals the ledger now covers
+        let (due, scheduled): (BTreeMap<usize, JournalEntry>, BTreeMap<usize, JournalEntry>) =
+            std::mem::take(&mut self.scheduled_reversals)
+                .into_iter()
+                .partition(|(_, j)| j.date_of_entry <= to_date);
+        self.scheduled_reversals = scheduled;
+        for reversal in due.into_values() {
+            self.audit_insert(&reversal);
+            self.push_posted(reversal);
+        }
+    }
+
+    ///
+    /// Get the month the fiscal years of the ledger start in, `1` for calendar years
+    ///
+    pub fn fiscal_year_start_month(&self) -> u32 {
+        self.fiscal_year_start_month
+    }
+
+    ///
+    /// Set the month the fiscal years start in. Legal references are numbered per
+    /// journal type and fiscal year.
+    ///
+    pub fn set_fiscal_year_start_month(&mut self, month: u32) {
+        assert!((1..=12).contains(&month));
+        self.audit(
+            AuditSubject::Ledger(self.id),
+            AuditAction::Update,
+            "fiscal_year_start_month",
+            Some(self.fiscal_year_start_month.to_string()),
+            Some(month.to_string()),
+        );
+        self.fiscal_year_start_month = month;
+    }
+
+    ///
+    /// Get the fiscal year `date` falls in, named after the calendar year it ends in
+    ///
+    pub fn fiscal_year(&self, date: DateTime<Utc>) -> i32 {
+        if self.fiscal_year_start_month > 1 && date.month() >= self.fiscal_year_start_month {
+            date.year() + 1
+        } else {
+            date.year()
+        }
     }
 
     ///
@@ -273,80 +1161,560 @@ impl Ledger {
     }
 
     ///
-    /// Used to validate that the dates of the journal entry are in sync
-    /// with the dates of the ledger
+    /// Check that `journal_entry` balances, falls within the dates of the ledger and,
+    /// if it was sealed elsewhere, carries the date it was recorded
+    ///
+    fn check_entry(&self, journal_entry: &JournalEntry) -> Result<(), LedgerError> {
+        if !journal_entry.validate() {
+            return Err(LedgerError::UnbalancedJournalEntry(journal_entry.id));
+        }
+        // The recorded date is part of the hash, so a sealed entry cannot be given one
+        if journal_entry.is_sealed() && journal_entry.recorded_date.is_none() {
+            return Err(LedgerError::MissingRecordedDate(journal_entry.id));
+        }
+
+        self.check_date(journal_entry.date_of_entry)
+    }
+
+    fn check_date(&self, date: DateTime<Utc>) -> Result<(), LedgerError> {
+        if date < self.from_date || date > self.to_date {
+            return Err(LedgerError::DateOutOfRange(date));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Add the reversal of the posted entry at `position` if it is auto-reversing and
+    /// has none yet. Reversals dated after the `to_date` wait in the scheduled reversals
+    /// until the ledger covers their date.
     ///
-    fn validate_journal_entry_dates(&self, journal_entry: &JournalEntry) {
-        assert!(&journal_entry.date_of_entry() >= self.from_date());
-        assert!(&journal_entry.date_of_entry() <= self.to_date());
+    fn schedule_reversal(&mut self, position: usize) {
+        let journal_entry = &self.journal_entries[position];
+        if !journal_entry.auto_reversing || self.get_reversal_of(journal_entry.id).is_some() {
+            return;
+        }
+
+        let reversal = journal_entry.reversal(
+            &mut self.ids,
+            next_period_start(journal_entry.date_of_entry),
+        );
+        let reversal_of = journal_entry.id;
+        self.register_ids(&reversal);
+
+        if reversal.date_of_entry <= self.to_date {
+            self.audit_insert(&reversal);
+            self.push_posted(reversal);
+        } else {
+            self.scheduled_reversals.insert(reversal_of, reversal);
+        }
     }
 
     ///
-    /// Add a single journal entry
+    /// Add a single journal entry. An entry carrying the idempotency key of a posted
+    /// entry with the same content is skipped.
+    ///
+    /// Panics when the idempotency key was posted with a different content; use
+    /// `post_journal_entry` to handle the conflict instead.
     ///
     pub fn add_journal_entry(&mut self, journal_entry: JournalEntry) {
-        self.validate_journal_entry_dates(&journal_entry);
-        self.journal_entries.push(journal_entry);
+        if let Err(error) = self.post_journal_entry(journal_entry) {
+            panic!("{}", error);
+        }
+    }
+
+    ///
+    /// Add multiple journal entries, skipping the ones already posted under their
+    /// idempotency key
+    ///
+    pub fn add_journal_entries(&mut self, journal_entries: &mut Vec<JournalEntry>) {
+        if let Err(error) = journal_entries
+            .iter()
+            .try_for_each(|entry| self.check_entry(entry))
+        {
+            panic!("{}", error);
+        }
+
+        for journal_entry in journal_entries.drain(..) {
+            self.add_journal_entry(journal_entry);
+        }
+    }
+
+    ///
+    /// Post a journal entry and return the posted entry. Posting again an entry with
+    /// the idempotency key and the content of a posted entry does nothing and returns
+    /// the original; posting the key with a different content is an error, as is
+    /// posting an entry that does not balance, falls outside the dates of the ledger,
+    /// reuses ids or a reference or carries dimensions the ledger does not declare.
+    ///
+    pub fn post_journal_entry(
+        &mut self,
+        journal_entry: JournalEntry,
+    ) -> Result<&JournalEntry, LedgerError> {
+        if let Some(position) = self.replayed(&journal_entry)? {
+            return Ok(&self.journal_entries[position]);
+        }
+
+        self.check_entry(&journal_entry)?;
+        self.check_dimensions(&journal_entry)?;
+        self.check_unique(&journal_entry, None)?;
+        self.check_reference(&journal_entry)?;
+        self.register_ids(&journal_entry);
+        self.audit_insert(&journal_entry);
+        let position = self.push_posted(journal_entry);
+        self.schedule_reversal(position);
+
+        Ok(&self.journal_entries[position])
+    }
+
+    ///
+    /// Post a journal entry under ids allocated by the ledger, replacing the ids the
+    /// entry and its transaction entries carry
+    ///
+    pub fn post_new_journal_entry(
+        &mut self,
+        mut journal_entry: JournalEntry,
+    ) -> Result<&JournalEntry, LedgerError> {
+        if let Some(position) = self.replayed(&journal_entry)? {
+            return Ok(&self.journal_entries[position]);
+        }
+
+        self.allocate_ids(&mut journal_entry);
+        self.post_journal_entry(journal_entry)
+    }
+
+    ///
+    /// Give `journal_entry` and its transaction entries the next free ids of the ledger
+    ///
+    pub fn allocate_ids(&mut self, journal_entry: &mut JournalEntry) {
+        journal_entry.id = self.ids.next_id();
+        journal_entry.transaction_entries = journal_entry
+            .transaction_entries
+            .iter()
+            .map(|entry| Rc::new(entry.with_id(self.ids.next_id())))
+            .collect();
+    }
+
+    ///
+    /// Get the position of the posted entry `journal_entry` replays, if any
+    ///
+    fn replayed(&self, journal_entry: &JournalEntry) -> Result<Option<usize>, LedgerError> {
+        let key = match journal_entry.idempotency_key() {
+            None => return Ok(None),
+            Some(key) => key,
+        };
+
+        match self.positions_by_idempotency_key.get(key).copied() {
+            None => Ok(None),
+            Some(position) if self.journal_entries[position].has_same_content(journal_entry) => {
+                Ok(Some(position))
+            }
+            Some(_) => Err(LedgerError::IdempotencyConflict(key.to_owned())),
+        }
+    }
+
+    ///
+    /// Get the posted journal entry carrying `idempotency_key`
+    ///
+    pub fn get_journal_entry_by_idempotency_key(
+        &self,
+        idempotency_key: &str,
+    ) -> Option<&JournalEntry> {
+        self.positions_by_idempotency_key
+            .get(idempotency_key)
+            .map(|position| &self.journal_entries[*position])
+    }
+
+    ///
+    /// Get the posted journal entry with the legal reference `reference`
+    ///
+    pub fn get_journal_entry_by_reference(&self, reference: &str) -> Option<&JournalEntry> {
+        self.positions_by_reference
+            .get(reference)
+            .map(|position| &self.journal_entries[*position])
+    }
+
+    ///
+    /// Load `journal_entries`, such as entries saved from another ledger, into a ledger
+    /// that has no posted entries. Sealed entries keep their hashes and references.
+    ///
+    /// Posted entries cannot be replaced, so the ledger must not have any. Nothing is
+    /// loaded when an entry falls outside the dates of the ledger or reuses an id or a
+    /// reference.
+    ///
+    pub fn set_journal_entries(
+        &mut self,
+        journal_entries: Vec<JournalEntry>,
+    ) -> Result<(), LedgerError> {
+        if !self.journal_entries.is_empty() {
+            return Err(LedgerError::LedgerNotEmpty(self.id));
+        }
+
+        // Load into a copy so that a rejected entry leaves the ledger untouched
+        let mut loaded = self.clone();
+        loaded.auditor = None;
+        for journal_entry in journal_entries {
+            loaded.check_entry(&journal_entry)?;
+            loaded.check_unique(&journal_entry, None)?;
+            loaded.check_reference(&journal_entry)?;
+            loaded.register_ids(&journal_entry);
+            loaded.push_posted(journal_entry);
+        }
+
+        loaded.auditor = self.auditor.take();
+        for journal_entry in loaded.journal_entries.iter() {
+            loaded.audit_insert(journal_entry);
+        }
+        for position in 0..loaded.journal_entries.len() {
+            loaded.schedule_reversal(position);
+        }
+        *self = loaded;
+
+        Ok(())
+    }
+
+    ///
+    /// Add a draft journal entry. Drafts do not count towards balances until posted.
+    /// Their ids are reserved like the ids of posted entries.
+    ///
+    pub fn add_draft(&mut self, journal_entry: JournalEntry) -> Result<(), LedgerError> {
+        self.check_unique(&journal_entry, None)?;
+        self.register_ids(&journal_entry);
+        self.audit(
+            AuditSubject::JournalEntry(journal_entry.id),
+            AuditAction::Insert,
+            "draft",
+            None,
+            Some(journal_entry.to_string()),
+        );
+        self.draft_positions
+            .insert(journal_entry.id, self.drafts.len());
+        self.drafts.push(journal_entry);
+        Ok(())
+    }
+
+    ///
+    /// Take the draft with id `id` out of the drafts
+    ///
+    fn take_draft(&mut self, id: usize) -> Result<JournalEntry, LedgerError> {
+        let position = self
+            .draft_positions
+            .remove(&id)
+            .ok_or(LedgerError::UnknownJournalEntry(id))?;
+        let draft = self.drafts.remove(position);
+        for later in self.drafts[position..].iter() {
+            self.draft_positions.insert(later.id, position);
+        }
+        self.release_ids(&draft);
+
+        Ok(draft)
+    }
+
+    ///
+    /// Get the draft journal entries
+    ///
+    pub fn drafts(&self) -> &Vec<JournalEntry> {
+        &self.drafts
+    }
+
+    pub fn get_draft_by_id(&self, id: usize) -> Option<&JournalEntry> {
+        self.draft_positions
+            .get(&id)
+            .map(|position| &self.drafts[*position])
+    }
+
+    ///
+    /// Replace the draft with id `id` by `journal_entry`, which may carry other ids as
+    /// long as no other entry uses them
+    ///
+    pub fn update_draft(
+        &mut self,
+        id: usize,
+        journal_entry: JournalEntry,
+    ) -> Result<(), LedgerError> {
+        let position = *self
+            .draft_positions
+            .get(&id)
+            .ok_or(LedgerError::UnknownJournalEntry(id))?;
+        self.check_unique(&journal_entry, Some(&self.drafts[position]))?;
+
+        self.audit(
+            AuditSubject::JournalEntry(journal_entry.id),
+            AuditAction::Update,
+            "draft",
+            Some(self.drafts[position].to_string()),
+            Some(journal_entry.to_string()),
+        );
+        let draft = std::mem::replace(&mut self.drafts[position], journal_entry);
+        self.release_ids(&draft);
+        self.draft_positions.remove(&id);
+        let journal_entry = self.drafts[position].clone();
+        self.register_ids(&journal_entry);
+        self.draft_positions.insert(journal_entry.id, position);
+        Ok(())
+    }
+
+    ///
+    /// Post the draft with id `id`. A draft that cannot be posted stays a draft.
+    ///
+    pub fn post_draft(&mut self, id: usize) -> Result<(), LedgerError> {
+        let draft = self
+            .get_draft_by_id(id)
+            .ok_or(LedgerError::UnknownJournalEntry(id))?;
+        self.check_entry(draft)?;
+        self.replayed(draft)?;
+        self.check_dimensions(draft)?;
+        self.check_unique(draft, Some(draft))?;
+        self.check_reference(draft)?;
+
+        let draft = self.take_draft(id)?;
+        self.post_journal_entry(draft)?;
+        Ok(())
+    }
+
+    ///
+    /// `Remove` a draft `journal entry` from the `ledger`. Posted entries cannot be
+    /// removed: reverse or void them instead.
+    ///
+    pub fn remove_journal_entry(&mut self, id: usize) -> Result<(), LedgerError> {
+        if self.get_journal_entry_by_id(id).is_some() {
+            return Err(LedgerError::PostedJournalEntry(id));
+        }
+
+        let draft = self.take_draft(id)?;
+        self.audit(
+            AuditSubject::JournalEntry(id),
+            AuditAction::Delete,
+            "draft",
+            Some(draft.to_string()),
+            None,
+        );
+        Ok(())
+    }
+
+    ///
+    /// `Remove` all draft `journal entries` from the `ledger`, keeping the posted ones
+    ///
+    pub fn remove_all_journal_entries(&mut self) {
+        self.draft_positions.clear();
+        for draft in std::mem::take(&mut self.drafts) {
+            self.release_ids(&draft);
+            self.audit(
+                AuditSubject::JournalEntry(draft.id),
+                AuditAction::Delete,
+                "draft",
+                Some(draft.to_string()),
+                None,
+            );
+        }
+    }
+
+    ///
+    /// Remove all drafts and set the id to `0`.
+    /// A ledger with posted journal entries cannot be reset.
+    ///
+    pub fn reset(&mut self) -> Result<(), LedgerError> {
+        if let Some(journal_entry) = self.journal_entries.first() {
+            return Err(LedgerError::PostedJournalEntry(journal_entry.id));
+        }
+
+        self.set_id(0);
+        self.set_from_date(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
+        self.set_to_date(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
+        self.remove_all_journal_entries();
+        self.scheduled_reversals.clear();
+        self.reversal_positions.clear();
+        self.journal_entry_ids.clear();
+        self.transaction_entry_ids.clear();
+        self.legal_sequences.clear();
+        self.ids = IdSequence::new(1);
+        Ok(())
+    }
+
+    ///
+    /// Post the reversal of the journal entry with id `id` dated `date_of_entry`:
+    /// a contra entry with the same lines and the debits and credits swapped, linked
+    /// back to the entry. Returns the id of the reversal.
+    ///
+    pub fn reverse_journal_entry(
+        &mut self,
+        id: usize,
+        date_of_entry: DateTime<Utc>,
+    ) -> Result<usize, LedgerError> {
+        let journal_entry = self.correctable(id)?;
+        if self.get_reversal_of(id).is_some() {
+            return Err(LedgerError::AlreadyReversed(id));
+        }
+        self.check_date(date_of_entry)?;
+
+        let reversal = journal_entry.reversal(&mut self.ids, date_of_entry);
+        Ok(self.post_journal_entry(reversal)?.id)
+    }
+
+    ///
+    /// Void the journal entry with id `id`: it stays in the ledger but no longer counts
+    /// towards balances. The reversal of an auto-reversing entry is voided with it.
+    ///
+    pub fn void_journal_entry(&mut self, id: usize) -> Result<(), LedgerError> {
+        let journal_entry = self.correctable(id)?;
+        if !journal_entry.auto_reversing && self.get_reversal_of(id).is_some() {
+            return Err(LedgerError::AlreadyReversed(id));
+        }
+
+        let voided_date = (self.clock)();
+        let mut voided = Vec::new();
+        for position in [
+            self.positions_by_id.get(&id),
+            self.reversal_positions.get(&id),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let journal_entry = &mut self.journal_entries[*position];
+            if !journal_entry.is_voided() {
+                journal_entry.status = JournalEntryStatus::Voided;
+                journal_entry.voided_date = Some(voided_date);
+                voided.push(journal_entry.id);
+            }
+        }
+        self.scheduled_reversals.remove(&id);
+        for voided_id in voided {
+            self.audit(
+                AuditSubject::JournalEntry(voided_id),
+                AuditAction::Update,
+                "status",
+                Some(format!("{:?}", JournalEntryStatus::Posted)),
+                Some(format!("{:?}", JournalEntryStatus::Voided)),
+            );
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Get the posted journal entry with id `id` unless it was voided
+    ///
+    fn correctable(&self, id: usize) -> Result<JournalEntry, LedgerError> {
+        let journal_entry = self
+            .get_journal_entry_by_id(id)
+            .ok_or(LedgerError::UnknownJournalEntry(id))?;
+        if journal_entry.is_voided() {
+            return Err(LedgerError::AlreadyVoided(id));
+        }
+
+        Ok(journal_entry.clone())
+    }
+
+    ///
+    /// Get all journal entries
+    ///
+    pub fn journal_entries(&self) -> &Vec<JournalEntry> {
+        &self.journal_entries
+    }
+
+    ///
+    /// Get the posted journal entries that count towards balances: all but the voided ones
+    ///
+    pub fn active_journal_entries(&self) -> impl Iterator<Item = &JournalEntry> {
+        self.journal_entries.iter().filter(|j| !j.is_voided())
+    }
+
+    ///
+    /// Get the books as they were recorded at `recorded_at`: a `Ledger` holding the
+    /// posted journal entries recorded by then, the entries voided later counting
+    /// again. Reports built on it show what the books said before later restatements.
+    ///
+    pub fn as_recorded_at(&self, recorded_at: DateTime<Utc>) -> Ledger {
+        let mut ledger = Ledger::new(self.id, self.from_date, self.to_date);
+        ledger.fiscal_year_start_month = self.fiscal_year_start_month;
+        ledger.clock = self.clock;
+        ledger.dimensions = self.dimensions.clone();
+
+        for journal_entry in self
+            .journal_entries
+            .iter()
+            .filter(|j| j.is_recorded_at(recorded_at))
+        {
+            let mut journal_entry = journal_entry.clone();
+            if journal_entry
+                .voided_date
+                .is_some_and(|voided_date| voided_date > recorded_at)
+            {
+                journal_entry.status = JournalEntryStatus::Posted;
+                journal_entry.voided_date = None;
+            }
+            ledger.register_ids(&journal_entry);
+            ledger.push_posted(journal_entry);
+        }
+
+        ledger
     }
 
     ///
-    /// Add multiple journal entries
+    /// Get the journal entries recorded between `start_date` and `end_date`, whatever
+    /// their effective dates
     ///
-    pub fn add_journal_entries(&mut self, journal_entries: &mut Vec<JournalEntry>) {
-        journal_entries
+    pub fn get_journal_entries_recorded_between(
+        &self,
+        start_date: DateTime<Utc>,
+        end_date: DateTime<Utc>,
+    ) -> Vec<&JournalEntry> {
+        self.journal_entries
             .iter()
-            .for_each(|entry| self.validate_journal_entry_dates(entry));
-
-        self.journal_entries.append(journal_entries);
+            .filter(|j| {
+                j.recorded_date
+                    .is_some_and(|date| date >= start_date && date <= end_date)
+            })
+            .collect()
     }
 
     ///
-    /// Replace all journal entries with the new one
+    /// Get the hash of the last posted journal entry. Keeping it aside allows
+    /// `verify_chain_to` to detect entries later removed from the end of the chain.
     ///
-    pub fn set_journal_entries(&mut self, journal_entries: Vec<JournalEntry>) {
-        journal_entries
-            .iter()
-            .for_each(|entry| self.validate_journal_entry_dates(entry));
-
-        self.journal_entries = journal_entries;
+    pub fn chain_head(&self) -> Option<&str> {
+        self.journal_entries.last().and_then(|j| j.hash())
     }
 
     ///
-    /// `Remove` a `journal entry` from the `ledger`
+    /// Check that no posted journal entry was modified, inserted or deleted since it
+    /// was sealed. Returns the first broken link.
     ///
-    pub fn remove_journal_entry(&mut self, id: usize) {
-        self.journal_entries.retain(|j| j.id() != id)
+    pub fn verify_chain(&self) -> Result<(), BrokenLink> {
+        verify_chain(&self.journal_entries, None)
     }
 
     ///
-    /// `Remove`` all `journal entries` from the `ledger`
+    /// Check the chain and that it still ends at `head`
     ///
-    pub fn remove_all_journal_entries(&mut self) {
-        self.journal_entries.clear()
+    pub fn verify_chain_to(&self, head: &str) -> Result<(), BrokenLink> {
+        verify_chain(&self.journal_entries, Some(head))
     }
 
     ///
-    /// Remove all journal entries and set the id to `0`
+    /// Get the reversals dated after the `to_date`, posted once the ledger covers their date
     ///
-    pub fn reset(&mut self) {
-        self.set_id(0);
-        self.set_from_date(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
-        self.set_to_date(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap());
-        self.remove_all_journal_entries();
+    pub fn scheduled_reversals(&self) -> Vec<&JournalEntry> {
+        self.scheduled_reversals.values().collect()
     }
 
     ///
-    /// Get all journal entries
+    /// Get the reversal of the journal entry `id`, posted or scheduled
     ///
-    pub fn journal_entries(&self) -> &Vec<JournalEntry> {
-        &self.journal_entries
+    pub fn get_reversal_of(&self, id: usize) -> Option<&JournalEntry> {
+        self.reversal_positions
+            .get(&id)
+            .map(|position| &self.journal_entries[*position])
+            .or_else(|| self.scheduled_reversals.get(&id))
     }
 
     ///
     /// Get a journal entry by id
     ///
     pub fn get_journal_entry_by_id(&self, id: usize) -> Option<&JournalEntry> {
-        self.journal_entries.iter().find(|j| j.id() == id)
+        self.positions_by_id
+            .get(&id)
+            .map(|position| &self.journal_entries[*position])
     }
 
     ///
@@ -389,6 +1757,65 @@ impl Ledger {
     pub fn number_of_journal_entries(&self) -> usize {
         self.journal_entries.len()
     }
+
+    ///
+    /// Get the `IdSequence` of the ledger, which starts after the highest journal entry
+    /// and transaction entry id the ledger has seen. The ids it hands out are reserved:
+    /// the ledger never allocates them again.
+    ///
+    pub fn id_sequence(&mut self) -> &mut IdSequence {
+        &mut self.ids
+    }
+}
+
+///
+/// Get the start of the period following `date`: the first day of the next month
+///
+fn next_period_start(date: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+///
+/// `IdSequence` hands out increasing ids to generated journal entries and
+/// their transaction entries
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdSequence {
+    next: usize,
+}
+
+impl IdSequence {
+    pub fn new(first: usize) -> Self {
+        IdSequence { next: first }
+    }
+
+    ///
+    /// Get the id the next call to `next_id` returns
+    ///
+    pub fn peek(&self) -> usize {
+        self.next
+    }
+
+    pub fn next_id(&mut self) -> usize {
+        let id = self.next;
+        self.next += 1;
+        id
+    }
+
+    ///
+    /// Never hand out `id` or the ids before it
+    ///
+    fn skip_past(&mut self, id: usize) {
+        self.next = self.next.max(id + 1);
+    }
 }
 
 ///
@@ -416,8 +1843,12 @@ mod test {
 
     use super::EntryType;
     use super::JournalEntry;
+    use super::JournalEntryStatus;
     use super::Ledger;
+    use super::LedgerError;
     use super::TransactionEntry;
+    use crate::journal::test_utils::{date, journal_entry, test_chart, TestChart};
+    use chrono::DateTime;
     use chrono::TimeZone;
     use chrono::Utc;
     use std::cmp::Ordering;
@@ -559,7 +1990,7 @@ mod test {
         accounts_map.insert("revenue".to_owned(), revenue.clone());
         accounts_map.insert("cost_of_sales".to_owned(), cost_of_sales.clone());
 
-        return accounts_map.to_owned();
+        accounts_map.to_owned()
     }
 
     ///
@@ -705,7 +2136,7 @@ mod test {
         sale_journal_entry.add_transaction_entry(cash_from_sale.clone());
 
         // This journal entry oughts to be false here
-        assert_eq!(sale_journal_entry.validate(), false);
+        assert!(!sale_journal_entry.validate());
 
         // The Debit should exceed the credit by 300.00/=
         let total_credit = sale_journal_entry.total_credit();
@@ -828,7 +2259,7 @@ mod test {
         let cash_from_sale = Rc::new(TransactionEntry::new(
             4,
             cash_node.clone(),
-            700.00,
+            400.00,
             EntryType::Debit,
             Utc::now(),
             "Cash received from the sale of the inventory",
@@ -848,7 +2279,7 @@ mod test {
             ledger.get_journal_entry_by_description("sale of the inventory");
         assert_eq!(journal_entries_with_sale_desciption.len(), 1);
         assert!(journal_entries_with_sale_desciption
-            .get(0)
+            .first()
             .unwrap()
             .description()
             .eq("Journal entry for the sale of the inventory."));
@@ -862,20 +2293,20 @@ mod test {
         assert_eq!(journal_entries_between_2024_01_15_and_2024_03_01.len(), 1);
         assert_eq!(
             journal_entries_between_2024_01_15_and_2024_03_01
-                .get(0)
+                .first()
                 .unwrap()
                 .date_of_entry()
                 .cmp(&Utc.with_ymd_and_hms(2024, 2, 28, 0, 0, 0).unwrap()),
             Ordering::Equal
         );
         assert!(journal_entries_between_2024_01_15_and_2024_03_01
-            .get(0)
+            .first()
             .unwrap()
             .description()
             .eq("Entry for loan used to purchase inventory"));
         assert_eq!(
             journal_entries_between_2024_01_15_and_2024_03_01
-                .get(0)
+                .first()
                 .unwrap()
                 .id(),
             1
@@ -886,51 +2317,112 @@ mod test {
         assert_eq!(journal_entries_on_2024_03_15.len(), 1);
         assert_eq!(
             journal_entries_on_2024_03_15
-                .get(0)
+                .first()
                 .unwrap()
                 .date_of_entry()
                 .cmp(&Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap()),
             Ordering::Equal
         );
         assert!(journal_entries_on_2024_03_15
-            .get(0)
+            .first()
             .unwrap()
             .description()
             .eq("Journal entry for the sale of the inventory."));
-        assert_eq!(journal_entries_on_2024_03_15.get(0).unwrap().id(), 2);
+        assert_eq!(journal_entries_on_2024_03_15.first().unwrap().id(), 2);
 
         assert_eq!(ledger.number_of_journal_entries(), 2);
 
-        // Remove journal entry with id == 1
-        ledger.remove_journal_entry(1);
+        // Posted journal entries cannot be removed, only voided or reversed
+        assert_eq!(
+            ledger.remove_journal_entry(1),
+            Err(LedgerError::PostedJournalEntry(1))
+        );
+        assert_eq!(ledger.void_journal_entry(1), Ok(()));
+        assert_eq!(
+            ledger.void_journal_entry(1),
+            Err(LedgerError::AlreadyVoided(1))
+        );
 
-        assert_eq!(ledger.number_of_journal_entries(), 1);
-        assert_eq!(ledger.journal_entries().get(0).unwrap().id(), 2);
+        assert_eq!(ledger.number_of_journal_entries(), 2);
+        assert!(ledger.get_journal_entry_by_id(1).unwrap().is_voided());
+        assert_eq!(ledger.active_journal_entries().count(), 1);
+        assert_eq!(ledger.active_journal_entries().next().unwrap().id(), 2);
 
-        // Remove all journal entries - maintains the ledger's id though
+        // Remove all draft journal entries - maintains the posted entries and the ledger's id
+        let draft = JournalEntry::new(
+            5,
+            Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap(),
+            "A draft journal entry",
+        );
+        assert_eq!(ledger.add_draft(draft), Ok(()));
+        assert_eq!(ledger.drafts().len(), 1);
         ledger.remove_all_journal_entries();
 
-        assert_eq!(ledger.number_of_journal_entries(), 0);
+        assert!(ledger.drafts().is_empty());
+        assert_eq!(ledger.number_of_journal_entries(), 2);
         assert_eq!(ledger.id(), 1);
 
-        // Recreate the journal entries
-        journal_entry = JournalEntry::new(3, Utc::now(), "A test journal entry");
+        // Recreate the journal entries: ids already used are rejected
+        journal_entry = JournalEntry::new(
+            3,
+            Utc.with_ymd_and_hms(2024, 4, 2, 0, 0, 0).unwrap(),
+            "A test journal entry",
+        );
         journal_entry.add_transaction_entry(loan_entry.clone());
         journal_entry.add_transaction_entry(cash_entry_from_loan.clone());
+        assert_eq!(
+            ledger
+                .post_journal_entry(journal_entry.clone())
+                .unwrap_err(),
+            LedgerError::DuplicateTransactionEntryId(1)
+        );
+        journal_entry.set_id(2);
+        assert_eq!(
+            ledger
+                .post_journal_entry(journal_entry.clone())
+                .unwrap_err(),
+            LedgerError::DuplicateJournalEntryId(2)
+        );
+        journal_entry.set_id(3);
 
-        sale_journal_entry = JournalEntry::new(4, Utc::now(), "Another test journal entry");
+        sale_journal_entry = JournalEntry::new(
+            4,
+            Utc.with_ymd_and_hms(2024, 4, 10, 0, 0, 0).unwrap(),
+            "Another test journal entry",
+        );
         sale_journal_entry.add_transaction_entry(inventory_sale.clone());
         sale_journal_entry.add_transaction_entry(cash_from_sale.clone());
 
-        // Add Journal Entries to the ledger
+        // Add Journal Entries to the ledger under ids it allocates, never reusing the
+        // ids of the removed draft
+        ledger.allocate_ids(&mut journal_entry);
+        ledger.allocate_ids(&mut sale_journal_entry);
+        assert_eq!(journal_entry.id(), 6);
+        assert_eq!(sale_journal_entry.transaction_entries()[1].id(), 11);
         ledger.add_journal_entries(&mut vec![journal_entry, sale_journal_entry]);
 
-        assert_eq!(ledger.number_of_journal_entries(), 2);
+        assert_eq!(ledger.number_of_journal_entries(), 4);
         assert_eq!(ledger.id(), 1);
 
-        // Reset the ledger
-        ledger.reset();
+        // Posted journal entries keep the ledger from being reset
+        assert_eq!(ledger.reset(), Err(LedgerError::PostedJournalEntry(1)));
 
+        // Reset a ledger holding drafts only
+        ledger = Ledger::new(
+            1,
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 5, 3, 12, 0, 0).unwrap(),
+        );
+        ledger
+            .add_draft(JournalEntry::new(
+                6,
+                Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap(),
+                "A draft journal entry",
+            ))
+            .unwrap();
+        assert_eq!(ledger.reset(), Ok(()));
+
+        assert!(ledger.drafts().is_empty());
         assert_eq!(ledger.id(), 0);
         assert_eq!(ledger.number_of_journal_entries(), 0);
         assert_eq!(
@@ -983,8 +2475,570 @@ mod test {
         sale_journal_entry.add_transaction_entry(inventory_sale.clone());
         sale_journal_entry.add_transaction_entry(cash_from_sale.clone());
 
-        ledger.set_journal_entries(vec![journal_entry, sale_journal_entry]);
+        ledger
+            .set_journal_entries(vec![journal_entry, sale_journal_entry])
+            .unwrap();
+
+        assert_eq!(ledger.number_of_journal_entries(), 2);
+    }
+
+    #[test]
+    fn test_corrections_through_reversals_and_drafts() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 6, 5),
+            "Cash sale",
+            &[
+                (&cash, 80.0, EntryType::Debit),
+                (&sales, 80.0, EntryType::Credit),
+            ],
+        ));
+
+        let reversal_id = ledger.reverse_journal_entry(1, date(2024, 6, 20)).unwrap();
+        let reversal = ledger.get_journal_entry_by_id(reversal_id).unwrap();
+        assert_eq!(reversal.reversal_of(), Some(1));
+        assert_eq!(reversal.date_of_entry(), date(2024, 6, 20));
+        assert!(!reversal.is_adjusting());
+        assert_eq!(
+            ledger.reverse_journal_entry(1, date(2024, 6, 21)),
+            Err(LedgerError::AlreadyReversed(1))
+        );
+        assert_eq!(
+            ledger.void_journal_entry(1),
+            Err(LedgerError::AlreadyReversed(1))
+        );
+        assert_eq!(
+            ledger.reverse_journal_entry(99, date(2024, 6, 21)),
+            Err(LedgerError::UnknownJournalEntry(99))
+        );
+
+        // Drafts can be changed and deleted until posted
+        ledger
+            .add_draft(journal_entry(
+                10,
+                date(2024, 7, 1),
+                "Next month's sale",
+                &[
+                    (&cash, 10.0, EntryType::Debit),
+                    (&sales, 10.0, EntryType::Credit),
+                ],
+            ))
+            .unwrap();
+        assert_eq!(
+            ledger.get_draft_by_id(10).unwrap().status(),
+            JournalEntryStatus::Draft
+        );
+        assert_eq!(
+            ledger.post_draft(10),
+            Err(LedgerError::DateOutOfRange(date(2024, 7, 1)))
+        );
+        let mut draft = ledger.get_draft_by_id(10).unwrap().clone();
+        draft.set_date_of_entry(date(2024, 6, 30));
+        ledger.update_draft(10, draft).unwrap();
+        assert_eq!(ledger.post_draft(10), Ok(()));
+        assert_eq!(
+            ledger.get_journal_entry_by_id(10).unwrap().status(),
+            JournalEntryStatus::Posted
+        );
+        assert_eq!(
+            ledger.remove_journal_entry(10),
+            Err(LedgerError::PostedJournalEntry(10))
+        );
+        assert!(ledger.verify_chain().is_ok());
+    }
+
+    #[test]
+    fn test_idempotent_posting() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let imported = |id: usize, amount: f64| {
+            let mut journal_entry = journal_entry(
+                id,
+                date(2024, 6, 5),
+                "Imported sale",
+                &[
+                    (&cash, amount, EntryType::Debit),
+                    (&sales, amount, EntryType::Credit),
+                ],
+            );
+            journal_entry.set_idempotency_key(Some("import-42"));
+            journal_entry
+        };
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+
+        assert_eq!(
+            ledger.post_journal_entry(imported(1, 75.0)).unwrap().id(),
+            1
+        );
+
+        // A retry is a no-op returning the original, whatever ids it was given
+        assert_eq!(
+            ledger.post_journal_entry(imported(2, 75.0)).unwrap().id(),
+            1
+        );
+        ledger.add_journal_entry(imported(3, 75.0));
+        ledger.add_journal_entries(&mut vec![imported(4, 75.0)]);
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+
+        assert_eq!(
+            ledger.post_journal_entry(imported(5, 76.0)).unwrap_err(),
+            LedgerError::IdempotencyConflict("import-42".to_owned())
+        );
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+        assert_eq!(
+            ledger
+                .get_journal_entry_by_idempotency_key("import-42")
+                .unwrap()
+                .total_debit(),
+            75.0
+        );
+    }
+
+    #[test]
+    fn test_allocated_ids_and_legal_references() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let sale = |date_of_entry, journal_type: &str| {
+            let mut journal_entry = journal_entry(
+                0,
+                date_of_entry,
+                "Cash sale",
+                &[
+                    (&cash, 50.0, EntryType::Debit),
+                    (&sales, 50.0, EntryType::Credit),
+                ],
+            );
+            journal_entry.set_journal_type(Some(journal_type));
+            journal_entry
+        };
+        let mut ledger = Ledger::new(1, date(2026, 6, 1), date(2026, 7, 31));
+        ledger.set_fiscal_year_start_month(7);
+
+        let references: Vec<String> = [
+            (date(2026, 6, 30), "SJ"),
+            (date(2026, 7, 1), "SJ"),
+            (date(2026, 7, 2), "PJ"),
+            (date(2026, 7, 3), "SJ"),
+        ]
+        .into_iter()
+        .map(|(date_of_entry, journal_type)| {
+            let posted = ledger
+                .post_new_journal_entry(sale(date_of_entry, journal_type))
+                .unwrap();
+            posted.reference().unwrap().to_owned()
+        })
+        .collect();
+        assert_eq!(
+            references,
+            vec![
+                "SJ-2026-000001",
+                "SJ-2027-000001",
+                "PJ-2027-000001",
+                "SJ-2027-000002"
+            ]
+        );
+        assert_eq!(
+            ledger.get_journal_entry_by_id(4).unwrap().reference(),
+            Some("SJ-2027-000001")
+        );
+
+        // A rejected entry does not leave a gap in the sequence
+        let mut duplicate = sale(date(2026, 7, 4), "SJ");
+        duplicate.set_id(1);
+        assert_eq!(
+            ledger.post_journal_entry(duplicate).unwrap_err(),
+            LedgerError::DuplicateJournalEntryId(1)
+        );
+        let reversal_id = ledger.reverse_journal_entry(1, date(2026, 7, 5)).unwrap();
+        let reversal = ledger
+            .get_journal_entry_by_reference("SJ-2027-000003")
+            .unwrap();
+        assert_eq!(reversal.id(), reversal_id);
+        assert_eq!(reversal.reversal_of(), Some(1));
+
+        // Reloaded entries keep their references and the sequences carry on after them
+        let mut reloaded = Ledger::new(2, date(2026, 6, 1), date(2026, 7, 31));
+        reloaded.set_fiscal_year_start_month(7);
+        let mut out_of_range = ledger.journal_entries().clone();
+        out_of_range.push(sale(date(2026, 8, 1), "SJ"));
+        assert_eq!(
+            reloaded.set_journal_entries(out_of_range),
+            Err(LedgerError::DateOutOfRange(date(2026, 8, 1)))
+        );
+        assert_eq!(reloaded.number_of_journal_entries(), 0);
+
+        // Loaded references must carry on the sequence of their journal and year
+        let gap = ledger
+            .get_journal_entry_by_reference("SJ-2027-000002")
+            .unwrap()
+            .clone();
+        assert_eq!(
+            reloaded.set_journal_entries(vec![gap]),
+            Err(LedgerError::ReferenceOutOfSequence(
+                "SJ-2027-000002".to_owned()
+            ))
+        );
+        reloaded
+            .set_journal_entries(ledger.journal_entries().clone())
+            .unwrap();
+        assert_eq!(
+            reloaded.set_journal_entries(Vec::new()),
+            Err(LedgerError::LedgerNotEmpty(2))
+        );
+        assert!(reloaded.verify_chain().is_ok());
+        let posted = reloaded
+            .post_new_journal_entry(sale(date(2026, 7, 6), "SJ"))
+            .unwrap();
+        assert_eq!(posted.reference(), Some("SJ-2027-000004"));
+        assert_eq!(posted.id(), 16);
+    }
+
+    #[test]
+    fn test_id_sequence_reserves_ids() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+
+        // Ids taken from the ledger's sequence are never allocated again
+        let generated = JournalEntry::from_lines(
+            ledger.id_sequence(),
+            date(2024, 1, 10),
+            "Cash sale",
+            &[
+                (&cash, 50.0, EntryType::Debit),
+                (&sales, 50.0, EntryType::Credit),
+            ],
+        );
+        assert_eq!(generated.id(), 1);
+        let posted_id = ledger
+            .post_new_journal_entry(generated.clone())
+            .unwrap()
+            .id();
+        assert_eq!(posted_id, 4);
+        assert_eq!(ledger.post_journal_entry(generated).unwrap().id(), 1);
+        assert_eq!(ledger.id_sequence().peek(), 7);
+    }
+
+    #[test]
+    fn test_loaded_sealed_entries_need_a_recorded_date() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.set_clock(|| date(2024, 2, 1));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 10),
+            "Cash sale",
+            &[
+                (&cash, 50.0, EntryType::Debit),
+                (&sales, 50.0, EntryType::Credit),
+            ],
+        ));
+
+        let mut undated = ledger.journal_entries().clone();
+        undated[0].set_recorded_date(None);
+        let mut reloaded = Ledger::new(2, date(2024, 1, 1), date(2024, 12, 31));
+        assert_eq!(
+            reloaded.set_journal_entries(undated),
+            Err(LedgerError::MissingRecordedDate(1))
+        );
+
+        reloaded
+            .set_journal_entries(ledger.journal_entries().clone())
+            .unwrap();
+        assert_eq!(
+            reloaded.journal_entries()[0].recorded_date(),
+            Some(date(2024, 2, 1))
+        );
+        assert_eq!(
+            reloaded
+                .as_recorded_at(date(2024, 2, 1))
+                .number_of_journal_entries(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_post_rejects_unbalanced_and_out_of_range_entries() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 1, 31));
+
+        let unbalanced = journal_entry(
+            1,
+            date(2024, 1, 10),
+            "Cash sale",
+            &[
+                (&chart.account("Cash"), 100.0, EntryType::Debit),
+                (&chart.account("Sales Revenue"), 90.0, EntryType::Credit),
+            ],
+        );
+        assert_eq!(
+            ledger.post_journal_entry(unbalanced).unwrap_err(),
+            LedgerError::UnbalancedJournalEntry(1)
+        );
+
+        let out_of_range = journal_entry(
+            1,
+            date(2024, 2, 1),
+            "Cash sale",
+            &[
+                (&chart.account("Cash"), 100.0, EntryType::Debit),
+                (&chart.account("Sales Revenue"), 100.0, EntryType::Credit),
+            ],
+        );
+        assert_eq!(
+            ledger.post_journal_entry(out_of_range).unwrap_err(),
+            LedgerError::DateOutOfRange(date(2024, 2, 1))
+        );
+
+        assert_eq!(ledger.number_of_journal_entries(), 0);
+        assert_eq!(ledger.chain_head(), None);
+    }
+
+    ///
+    /// A cash sale of `amount` with id `id`
+    ///
+    fn cash_sale(
+        chart: &TestChart,
+        id: usize,
+        date_of_entry: DateTime<Utc>,
+        amount: f64,
+    ) -> JournalEntry {
+        journal_entry(
+            id,
+            date_of_entry,
+            "Cash sale",
+            &[
+                (&chart.account("Cash"), amount, EntryType::Debit),
+                (&chart.account("Sales Revenue"), amount, EntryType::Credit),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_reversals_past_the_to_date_wait_until_it_covers_them() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 1, 31));
+        let mut sale = cash_sale(&chart, 1, date(2024, 1, 31), 400.0);
+        sale.set_auto_reversing(true);
+        ledger.add_journal_entry(sale);
+
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+        let scheduled = ledger.get_reversal_of(1).unwrap();
+        assert_eq!(scheduled.date_of_entry(), date(2024, 2, 1));
+        let reversal_id = scheduled.id();
+        assert_eq!(ledger.scheduled_reversals().len(), 1);
+
+        // Extending the ledger short of the reversal's date keeps it scheduled
+        ledger.set_to_date(date(2024, 1, 31));
+        assert_eq!(ledger.scheduled_reversals().len(), 1);
+
+        ledger.set_to_date(date(2024, 2, 29));
+        assert!(ledger.scheduled_reversals().is_empty());
+        assert_eq!(ledger.number_of_journal_entries(), 2);
+        let reversal = ledger.get_journal_entry_by_id(reversal_id).unwrap();
+        assert_eq!(reversal.reversal_of(), Some(1));
+        assert!(ledger.verify_chain().is_ok());
+
+        // Posted once, however often the ledger is extended
+        ledger.set_to_date(date(2024, 3, 31));
+        assert_eq!(ledger.number_of_journal_entries(), 2);
+    }
+
+    #[test]
+    fn test_voided_entries_cannot_be_corrected() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        ledger.add_journal_entry(cash_sale(&chart, 1, date(2024, 6, 5), 80.0));
+        ledger.void_journal_entry(1).unwrap();
+
+        assert_eq!(
+            ledger.void_journal_entry(1),
+            Err(LedgerError::AlreadyVoided(1))
+        );
+        assert_eq!(
+            ledger.reverse_journal_entry(1, date(2024, 6, 10)),
+            Err(LedgerError::AlreadyVoided(1))
+        );
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+        assert_eq!(ledger.active_journal_entries().count(), 0);
+    }
+
+    #[test]
+    fn test_reversed_entries_cannot_be_reversed_or_voided() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        ledger.add_journal_entry(cash_sale(&chart, 1, date(2024, 6, 5), 80.0));
+        let reversal_id = ledger.reverse_journal_entry(1, date(2024, 6, 10)).unwrap();
 
+        assert_eq!(
+            ledger.reverse_journal_entry(1, date(2024, 6, 11)),
+            Err(LedgerError::AlreadyReversed(1))
+        );
+        assert_eq!(
+            ledger.void_journal_entry(1),
+            Err(LedgerError::AlreadyReversed(1))
+        );
         assert_eq!(ledger.number_of_journal_entries(), 2);
+        assert!(!ledger
+            .get_journal_entry_by_id(reversal_id)
+            .unwrap()
+            .is_voided());
+    }
+
+    #[test]
+    fn test_idempotency_key_posted_with_other_content() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        let mut sale = cash_sale(&chart, 1, date(2024, 6, 5), 80.0);
+        sale.set_idempotency_key(Some("order-7"));
+        ledger.add_journal_entry(sale);
+
+        let mut redated = cash_sale(&chart, 2, date(2024, 6, 6), 80.0);
+        redated.set_idempotency_key(Some("order-7"));
+        assert_eq!(
+            ledger.post_journal_entry(redated).unwrap_err(),
+            LedgerError::IdempotencyConflict("order-7".to_owned())
+        );
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+        assert!(ledger.get_journal_entry_by_id(2).is_none());
+
+        // The conflicting entry's ids stay free
+        ledger
+            .post_journal_entry(cash_sale(&chart, 2, date(2024, 6, 6), 80.0))
+            .unwrap();
+        assert!(ledger.verify_chain().is_ok());
+    }
+
+    #[test]
+    fn test_duplicate_ids_and_references_are_rejected() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        ledger.add_journal_entry(cash_sale(&chart, 1, date(2024, 6, 5), 80.0));
+        ledger
+            .add_draft(cash_sale(&chart, 2, date(2024, 6, 6), 20.0))
+            .unwrap();
+
+        assert_eq!(
+            ledger
+                .post_journal_entry(cash_sale(&chart, 1, date(2024, 6, 7), 10.0))
+                .unwrap_err(),
+            LedgerError::DuplicateJournalEntryId(1)
+        );
+        // Drafts hold on to their ids
+        assert_eq!(
+            ledger
+                .post_journal_entry(cash_sale(&chart, 2, date(2024, 6, 7), 10.0))
+                .unwrap_err(),
+            LedgerError::DuplicateJournalEntryId(2)
+        );
+
+        // Entry 3 keeps the transaction entry ids of entry 1
+        let mut reused_lines = cash_sale(&chart, 1, date(2024, 6, 7), 10.0);
+        reused_lines.set_id(3);
+        assert_eq!(
+            ledger.post_journal_entry(reused_lines).unwrap_err(),
+            LedgerError::DuplicateTransactionEntryId(100)
+        );
+        assert_eq!(ledger.number_of_journal_entries(), 1);
+
+        // Two ledgers number their references independently
+        let referenced = |ledger: &mut Ledger, id| {
+            let mut sale = cash_sale(&chart, id, date(2024, 6, 7), 10.0);
+            sale.set_journal_type(Some("SJ"));
+            ledger.post_journal_entry(sale).unwrap().clone()
+        };
+        let first = referenced(&mut Ledger::new(2, date(2024, 6, 1), date(2024, 6, 30)), 4);
+        let second = referenced(&mut Ledger::new(3, date(2024, 6, 1), date(2024, 6, 30)), 5);
+        assert_eq!(
+            Ledger::new(4, date(2024, 6, 1), date(2024, 6, 30))
+                .set_journal_entries(vec![first, second]),
+            Err(LedgerError::DuplicateReference("SJ-2024-000001".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_drafts_keep_ids_of_their_own() {
+        let chart = test_chart();
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+        ledger.add_journal_entry(cash_sale(&chart, 1, date(2024, 6, 5), 80.0));
+        ledger
+            .add_draft(cash_sale(&chart, 2, date(2024, 6, 6), 20.0))
+            .unwrap();
+
+        // An edited draft cannot take the ids of a posted entry
+        let mut reused_id = ledger.get_draft_by_id(2).unwrap().clone();
+        reused_id.set_id(1);
+        assert_eq!(
+            ledger.update_draft(2, reused_id),
+            Err(LedgerError::DuplicateJournalEntryId(1))
+        );
+        let mut reused_lines = cash_sale(&chart, 1, date(2024, 6, 6), 20.0);
+        reused_lines.set_id(2);
+        assert_eq!(
+            ledger.update_draft(2, reused_lines),
+            Err(LedgerError::DuplicateTransactionEntryId(100))
+        );
+
+        // A draft that fails to post is kept, then posts under its own ids
+        let mut conflicting = ledger.get_draft_by_id(2).unwrap().clone();
+        conflicting.set_idempotency_key(Some("order-7"));
+        let mut sale = cash_sale(&chart, 3, date(2024, 6, 7), 10.0);
+        sale.set_idempotency_key(Some("order-7"));
+        ledger.add_journal_entry(sale);
+        ledger.update_draft(2, conflicting).unwrap();
+        assert_eq!(
+            ledger.post_draft(2),
+            Err(LedgerError::IdempotencyConflict("order-7".to_owned()))
+        );
+        assert!(ledger.get_draft_by_id(2).is_some());
+
+        let mut renumbered = ledger.get_draft_by_id(2).unwrap().clone();
+        renumbered.set_idempotency_key(None);
+        renumbered.set_id(4);
+        ledger.update_draft(2, renumbered).unwrap();
+        assert!(ledger.get_draft_by_id(2).is_none());
+        assert_eq!(ledger.post_draft(4), Ok(()));
+        assert!(ledger.drafts().is_empty());
+        assert_eq!(ledger.number_of_journal_entries(), 3);
+    }
+
+    #[test]
+    fn test_entries_balance_to_the_cent() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let mut ledger = Ledger::new(1, date(2024, 6, 1), date(2024, 6, 30));
+
+        let split = journal_entry(
+            1,
+            date(2024, 6, 5),
+            "Split sale",
+            &[
+                (&cash, 0.3, EntryType::Debit),
+                (&sales, 0.1, EntryType::Credit),
+                (&sales, 0.2, EntryType::Credit),
+            ],
+        );
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert!(split.validate());
+        ledger.add_journal_entry(split);
+
+        let off_by_a_cent = journal_entry(
+            2,
+            date(2024, 6, 5),
+            "Split sale",
+            &[
+                (&cash, 0.31, EntryType::Debit),
+                (&sales, 0.1, EntryType::Credit),
+                (&sales, 0.2, EntryType::Credit),
+            ],
+        );
+        assert_eq!(
+            ledger.post_journal_entry(off_by_a_cent).unwrap_err(),
+            LedgerError::UnbalancedJournalEntry(2)
+        );
     }
 }
@@ -1,15 +1,40 @@
+pub mod accounting_equation;
 pub mod accounting_tree;
+pub mod audit;
 pub mod balance_sheet;
+pub mod balance_sheet_layout;
+pub mod bank_import;
+pub mod bank_reconciliation;
+pub mod budget;
 pub mod cashflow_statement;
+pub mod comparative_balance_sheet;
+pub mod consolidation;
+pub mod debt;
+pub mod dimensions;
+pub mod fixed_assets;
+pub mod hash_chain;
 pub mod income_statement;
+pub mod inventory;
 pub mod ledger;
+pub mod payables;
+pub mod payroll;
+pub mod period;
+pub mod ratios;
+pub mod receivables;
+pub mod recurring;
+pub mod subledger;
+pub mod tax;
+pub mod trial_balance;
+
+#[cfg(test)]
+mod test_utils;
 
 // let acc: Account = Account::new("Cash", asset);asset
 
-// let cash_entry = TransactionEntry::new(acc, 
+// let cash_entry = TransactionEntry::new(acc,
 //     400.0, EntryType::Credit, Utc::now(),
 //     "Electricity expense");
-    
+
 // let expense_acc: Account = Account::new("Utilities Expenses", expense);
 // let expense_entry: TransactionEntry = TransactionEntry::new(expense_acc, 400.0, EntryType::Debit, Utc::now(),
 //  "Electricity expense");
@@ -32,7 +57,6 @@ pub mod ledger;
 
 // println!("{:?}", entry);
 
-
 // #[derive(Debug)]
 // struct Account {
 //     name: String,
@@ -65,14 +89,14 @@ pub mod ledger;
 
 //     ///
 //     /// Get the `PrimaryAccountType` of this account
-//     /// 
+//     ///
 //     fn primary_account(&self) -> &Option<PrimaryAccountType> {
 //         let mut parent_tag= self.account_tag.as_ref();
 
 //         while parent_tag.level() != 1 {
 //             let parent_tag = parent_tag.parent();
 //         }
-        
+
 //         return &None;
 //     }
 // }
@@ -0,0 +1,1132 @@
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTree};
+use crate::journal::ledger::{round_to_cents, EntryType, IdSequence, JournalEntry, Ledger};
+use crate::journal::period::ReportingPeriod;
+use crate::journal::subledger::{
+    control_balance, positive, AgingBuckets, AgingReport, AgingRow, Application,
+    ControlReconciliation, SubledgerError,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Display;
+use std::str::FromStr;
+
+///
+/// `Vendor` owed amounts recorded in the `AccountsPayable` subledger
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vendor {
+    id: usize,
+    name: String,
+}
+
+impl Vendor {
+    pub fn new(id: usize, name: &str) -> Self {
+        Vendor {
+            id,
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+///
+/// `PaymentTerms` of a bill, e.g. `2/10 net 30`: a 2% discount when paid within
+/// 10 days, otherwise the full amount is due within 30 days
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaymentTerms {
+    discount_percentage: f64,
+    discount_days: i64,
+    net_days: i64,
+}
+
+impl PaymentTerms {
+    pub fn new(discount_percentage: f64, discount_days: i64, net_days: i64) -> Self {
+        PaymentTerms {
+            discount_percentage,
+            discount_days,
+            net_days,
+        }
+    }
+
+    ///
+    /// Terms without an early payment discount
+    ///
+    pub fn net(net_days: i64) -> Self {
+        PaymentTerms::new(0f64, 0, net_days)
+    }
+
+    pub fn discount_percentage(&self) -> f64 {
+        self.discount_percentage
+    }
+
+    pub fn discount_days(&self) -> i64 {
+        self.discount_days
+    }
+
+    pub fn net_days(&self) -> i64 {
+        self.net_days
+    }
+
+    pub fn due_date(&self, bill_date: DateTime<Utc>) -> DateTime<Utc> {
+        bill_date + Duration::days(self.net_days)
+    }
+
+    ///
+    /// Get the last day the discount can be taken, if the terms have one
+    ///
+    pub fn discount_date(&self, bill_date: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (self.discount_percentage > 0f64).then(|| bill_date + Duration::days(self.discount_days))
+    }
+
+    ///
+    /// Get the discount on `amount` when paying on `payment_date`
+    ///
+    pub fn discount(
+        &self,
+        amount: f64,
+        bill_date: DateTime<Utc>,
+        payment_date: DateTime<Utc>,
+    ) -> f64 {
+        match self.discount_date(bill_date) {
+            Some(discount_date) if payment_date <= discount_date => {
+                round_to_cents(amount * self.discount_percentage / 100f64)
+            }
+            _ => 0f64,
+        }
+    }
+}
+
+impl FromStr for PaymentTerms {
+    type Err = String;
+
+    ///
+    /// Parse terms written as `2/10 net 30`, `2/10, n/30`, `net 30` or `n/30`
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lowercase = s.to_lowercase();
+        let numbers: Vec<&str> = lowercase
+            .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|n| !n.is_empty())
+            .collect();
+        let has_net = lowercase.contains("net") || lowercase.contains("n/");
+        let invalid = || format!("invalid payment terms {:?}", s);
+
+        match (numbers.as_slice(), has_net) {
+            ([percentage, discount_days, net_days], true) => Ok(PaymentTerms::new(
+                percentage.parse().map_err(|_| invalid())?,
+                discount_days.parse().map_err(|_| invalid())?,
+                net_days.parse().map_err(|_| invalid())?,
+            )),
+            ([net_days], true) => Ok(PaymentTerms::net(net_days.parse().map_err(|_| invalid())?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for PaymentTerms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.discount_percentage > 0f64 {
+            write!(
+                f,
+                "{}/{} net {}",
+                self.discount_percentage, self.discount_days, self.net_days
+            )
+        } else {
+            write!(f, "net {}", self.net_days)
+        }
+    }
+}
+
+///
+/// `Bill` received from a vendor. Each line is charged to an expense (or asset) account.
+///
+#[derive(Debug, Clone)]
+pub struct Bill {
+    id: usize,
+    vendor_id: usize,
+    date: DateTime<Utc>,
+    terms: PaymentTerms,
+    description: String,
+    lines: Vec<(AccountNodeRef, f64)>,
+}
+
+impl Bill {
+    pub fn new(
+        id: usize,
+        vendor_id: usize,
+        date: DateTime<Utc>,
+        terms: PaymentTerms,
+        description: &str,
+    ) -> Self {
+        Bill {
+            id,
+            vendor_id,
+            date,
+            terms,
+            description: description.to_owned(),
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn add_line(&mut self, account: &AccountNodeRef, amount: f64) {
+        self.lines.push((account.clone(), amount));
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn vendor_id(&self) -> usize {
+        self.vendor_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn terms(&self) -> &PaymentTerms {
+        &self.terms
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn lines(&self) -> &Vec<(AccountNodeRef, f64)> {
+        &self.lines
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.lines.iter().map(|(_, amount)| amount).sum()
+    }
+
+    pub fn due_date(&self) -> DateTime<Utc> {
+        self.terms.due_date(self.date)
+    }
+
+    pub fn discount_date(&self) -> Option<DateTime<Utc>> {
+        self.terms.discount_date(self.date)
+    }
+}
+
+///
+/// `VendorPayment` made to a vendor and applied to some of its bills.
+/// The applications settle `amount + discount`: the cash paid plus any early payment
+/// discount taken.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorPayment {
+    id: usize,
+    vendor_id: usize,
+    date: DateTime<Utc>,
+    amount: f64,
+    discount: f64,
+    description: String,
+    applications: Vec<Application>,
+}
+
+impl VendorPayment {
+    pub fn new(
+        id: usize,
+        vendor_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+        description: &str,
+    ) -> Self {
+        VendorPayment {
+            id,
+            vendor_id,
+            date,
+            amount,
+            discount: 0f64,
+            description: description.to_owned(),
+            applications: Vec::new(),
+        }
+    }
+
+    ///
+    /// Settle `amount` of the bill `bill_id` on the payment date
+    ///
+    pub fn add_application(&mut self, bill_id: usize, amount: f64) {
+        self.applications
+            .push(Application::new(bill_id, amount, self.date));
+    }
+
+    pub fn set_discount(&mut self, discount: f64) {
+        self.discount = discount;
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn vendor_id(&self) -> usize {
+        self.vendor_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    ///
+    /// Get the cash paid
+    ///
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn discount(&self) -> f64 {
+        self.discount
+    }
+
+    ///
+    /// Get the amount taken off the vendor's balance: the cash paid plus the discount
+    ///
+    pub fn settled_amount(&self) -> f64 {
+        self.amount + self.discount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn applications(&self) -> &Vec<Application> {
+        &self.applications
+    }
+
+    ///
+    /// Get the part of the settled amount not applied to any bill as of a date
+    ///
+    pub fn unapplied_amount(&self, as_of: DateTime<Utc>) -> f64 {
+        if self.date > as_of {
+            return 0f64;
+        }
+
+        self.settled_amount()
+            - self
+                .applications
+                .iter()
+                .filter(|a| a.date() <= &as_of)
+                .map(|a| a.amount())
+                .sum::<f64>()
+    }
+}
+
+///
+/// A bill selected for payment by a payment run
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentProposal {
+    bill_id: usize,
+    vendor_id: usize,
+    due_date: DateTime<Utc>,
+    open_amount: f64,
+    discount: f64,
+}
+
+impl PaymentProposal {
+    pub fn bill_id(&self) -> usize {
+        self.bill_id
+    }
+
+    pub fn vendor_id(&self) -> usize {
+        self.vendor_id
+    }
+
+    pub fn due_date(&self) -> &DateTime<Utc> {
+        &self.due_date
+    }
+
+    pub fn open_amount(&self) -> f64 {
+        self.open_amount
+    }
+
+    pub fn discount(&self) -> f64 {
+        self.discount
+    }
+
+    ///
+    /// Get the cash to pay: the open amount less the discount
+    ///
+    pub fn payment(&self) -> f64 {
+        self.open_amount - self.discount
+    }
+}
+
+///
+/// `PaymentRun` holds the payments made by `AccountsPayable::payment_run`, one per vendor,
+/// and the journal entries to add to the `Ledger`
+///
+#[derive(Debug, Clone)]
+pub struct PaymentRun {
+    date: DateTime<Utc>,
+    proposals: Vec<PaymentProposal>,
+    payments: Vec<VendorPayment>,
+    journal_entries: Vec<JournalEntry>,
+}
+
+impl PaymentRun {
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn proposals(&self) -> &Vec<PaymentProposal> {
+        &self.proposals
+    }
+
+    pub fn payments(&self) -> &Vec<VendorPayment> {
+        &self.payments
+    }
+
+    pub fn journal_entries(&self) -> &Vec<JournalEntry> {
+        &self.journal_entries
+    }
+
+    pub fn total_paid(&self) -> f64 {
+        self.payments.iter().map(|p| p.amount()).sum()
+    }
+
+    pub fn total_discount(&self) -> f64 {
+        self.payments.iter().map(|p| p.discount()).sum()
+    }
+}
+
+///
+/// A bill or payment on a `VendorStatement`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorStatementLine {
+    date: DateTime<Utc>,
+    document_id: usize,
+    description: String,
+    charge: f64,
+    payment: f64,
+    balance: f64,
+}
+
+impl VendorStatementLine {
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn document_id(&self) -> usize {
+        self.document_id
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    ///
+    /// Get the amount billed
+    ///
+    pub fn charge(&self) -> f64 {
+        self.charge
+    }
+
+    ///
+    /// Get the amount settled, discount included
+    ///
+    pub fn payment(&self) -> f64 {
+        self.payment
+    }
+
+    ///
+    /// Get the running balance owed to the vendor
+    ///
+    pub fn balance(&self) -> f64 {
+        self.balance
+    }
+}
+
+///
+/// `VendorStatement` listing a vendor's bills and payments over a period
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct VendorStatement {
+    vendor: Vendor,
+    period: ReportingPeriod,
+    opening_balance: f64,
+    lines: Vec<VendorStatementLine>,
+}
+
+impl VendorStatement {
+    pub fn vendor(&self) -> &Vendor {
+        &self.vendor
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn opening_balance(&self) -> f64 {
+        self.opening_balance
+    }
+
+    pub fn lines(&self) -> &Vec<VendorStatementLine> {
+        &self.lines
+    }
+
+    pub fn closing_balance(&self) -> f64 {
+        self.lines
+            .last()
+            .map(|line| line.balance)
+            .unwrap_or(self.opening_balance)
+    }
+}
+
+impl Display for VendorStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", self.vendor.name(), self.period)?;
+        writeln!(f, "{:<50}{:>36.2}", "Opening balance", self.opening_balance)?;
+        for line in self.lines.iter() {
+            writeln!(
+                f,
+                "{}  {:<6}{:<32}{:>12.2}{:>12.2}{:>12.2}",
+                line.date.format("%Y-%m-%d"),
+                line.document_id,
+                line.description,
+                line.charge,
+                line.payment,
+                line.balance
+            )?;
+        }
+        write!(
+            f,
+            "{:<50}{:>36.2}",
+            "Closing balance",
+            self.closing_balance()
+        )
+    }
+}
+
+///
+/// `AccountsPayable` subledger tracking the open bills of each vendor behind an
+/// accounts payable control account. Posting a document records it and returns
+/// the `JournalEntry` to add to the `Ledger`.
+/// Bills and payments share a single id space.
+///
+#[derive(Debug, Clone)]
+pub struct AccountsPayable {
+    control_account: AccountNodeRef,
+    discount_account: Option<AccountNodeRef>,
+    vendors: Vec<Vendor>,
+    bills: Vec<Bill>,
+    payments: Vec<VendorPayment>,
+}
+
+impl AccountsPayable {
+    pub fn new(control_account: AccountNodeRef) -> Self {
+        AccountsPayable {
+            control_account,
+            discount_account: None,
+            vendors: Vec::new(),
+            bills: Vec::new(),
+            payments: Vec::new(),
+        }
+    }
+
+    pub fn control_account(&self) -> AccountNodeRef {
+        self.control_account.clone()
+    }
+
+    ///
+    /// Get the account credited with the early payment discounts taken
+    ///
+    pub fn discount_account(&self) -> Option<AccountNodeRef> {
+        self.discount_account.clone()
+    }
+
+    pub fn set_discount_account(&mut self, discount_account: Option<AccountNodeRef>) {
+        self.discount_account = discount_account;
+    }
+
+    pub fn add_vendor(&mut self, vendor: Vendor) -> Result<(), SubledgerError> {
+        if self.get_vendor(vendor.id()).is_some() {
+            return Err(SubledgerError::DuplicateParty(vendor.id()));
+        }
+
+        self.vendors.push(vendor);
+        Ok(())
+    }
+
+    pub fn vendors(&self) -> &Vec<Vendor> {
+        &self.vendors
+    }
+
+    pub fn get_vendor(&self, id: usize) -> Option<&Vendor> {
+        self.vendors.iter().find(|v| v.id() == id)
+    }
+
+    pub fn bills(&self) -> &Vec<Bill> {
+        &self.bills
+    }
+
+    pub fn get_bill(&self, id: usize) -> Option<&Bill> {
+        self.bills.iter().find(|b| b.id() == id)
+    }
+
+    pub fn payments(&self) -> &Vec<VendorPayment> {
+        &self.payments
+    }
+
+    fn check_new_document(&self, id: usize, vendor_id: usize) -> Result<(), SubledgerError> {
+        if self.get_vendor(vendor_id).is_none() {
+            return Err(SubledgerError::UnknownParty(vendor_id));
+        }
+
+        if self.bills.iter().any(|b| b.id() == id) || self.payments.iter().any(|p| p.id() == id) {
+            return Err(SubledgerError::DuplicateDocument(id));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Record a bill, debiting the account of each line and crediting the control account
+    ///
+    pub fn post_bill(
+        &mut self,
+        bill: Bill,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, SubledgerError> {
+        self.check_new_document(bill.id(), bill.vendor_id())?;
+        for (_, amount) in bill.lines() {
+            positive(*amount)?;
+        }
+        let amount = positive(bill.amount())?;
+
+        let mut lines: Vec<(&AccountNodeRef, f64, EntryType)> = bill
+            .lines()
+            .iter()
+            .map(|(account, amount)| (account, *amount, EntryType::Debit))
+            .collect();
+        lines.push((&self.control_account, amount, EntryType::Credit));
+
+        let journal_entry = JournalEntry::from_lines(
+            ledger.id_sequence(),
+            *bill.date(),
+            bill.description(),
+            &lines,
+        );
+
+        self.bills.push(bill);
+        Ok(journal_entry)
+    }
+
+    ///
+    /// Record a payment and its applications, debiting the control account with the
+    /// settled amount and crediting `cash_account` and the discount account
+    ///
+    pub fn post_payment(
+        &mut self,
+        payment: VendorPayment,
+        cash_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, SubledgerError> {
+        self.check_payment(&payment)?;
+
+        Ok(self.record_payment(payment, cash_account, ledger.id_sequence()))
+    }
+
+    ///
+    /// Check that a payment can be recorded: its applications must not settle more
+    /// than is still owed on the bills, counting every payment already recorded
+    ///
+    fn check_payment(&self, payment: &VendorPayment) -> Result<(), SubledgerError> {
+        self.check_new_document(payment.id(), payment.vendor_id())?;
+        positive(payment.amount())?;
+
+        for application in payment.applications() {
+            positive(application.amount())?;
+
+            let bill = self
+                .get_bill(application.document_id())
+                .filter(|bill| bill.vendor_id() == payment.vendor_id())
+                .ok_or(SubledgerError::UnknownDocument(application.document_id()))?;
+            if application.date() < bill.date() {
+                return Err(SubledgerError::EarlyApplication(bill.id()));
+            }
+
+            let open_amount = self.open_amount(bill.id(), DateTime::<Utc>::MAX_UTC);
+            let applied: f64 = payment
+                .applications()
+                .iter()
+                .filter(|a| a.document_id() == bill.id())
+                .map(|a| a.amount())
+                .sum();
+
+            if applied > open_amount + DEFAULT_TOLERANCE {
+                return Err(SubledgerError::OverApplied {
+                    document_id: bill.id(),
+                    open_amount,
+                    applied,
+                });
+            }
+        }
+
+        let unapplied_amount = payment.unapplied_amount(DateTime::<Utc>::MAX_UTC);
+        if unapplied_amount < -DEFAULT_TOLERANCE {
+            return Err(SubledgerError::OverApplied {
+                document_id: payment.id(),
+                open_amount: payment.settled_amount(),
+                applied: payment.settled_amount() - unapplied_amount,
+            });
+        }
+
+        if payment.discount() < 0f64 {
+            return Err(SubledgerError::InvalidAmount(payment.discount()));
+        } else if payment.discount() > 0f64 && self.discount_account.is_none() {
+            return Err(SubledgerError::MissingAccount(
+                "purchase discounts".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Record a payment that passed `check_payment` and build its journal entry
+    ///
+    fn record_payment(
+        &mut self,
+        payment: VendorPayment,
+        cash_account: &AccountNodeRef,
+        ids: &mut IdSequence,
+    ) -> JournalEntry {
+        let mut lines = vec![
+            (
+                &self.control_account,
+                payment.settled_amount(),
+                EntryType::Debit,
+            ),
+            (cash_account, payment.amount(), EntryType::Credit),
+        ];
+        if let Some(discount_account) = self
+            .discount_account
+            .as_ref()
+            .filter(|_| payment.discount() > 0f64)
+        {
+            lines.push((discount_account, payment.discount(), EntryType::Credit));
+        }
+
+        let journal_entry =
+            JournalEntry::from_lines(ids, *payment.date(), payment.description(), &lines);
+
+        self.payments.push(payment);
+        journal_entry
+    }
+
+    ///
+    /// Select the open bills dated by `run_date` and due by `pay_through`, taking the
+    /// early payment discount when `run_date` is within the discount period.
+    /// The open amounts count every payment recorded, as `post_payment` does, so
+    /// a bill already paid after `run_date` is not proposed again.
+    ///
+    pub fn propose_payments(
+        &self,
+        run_date: DateTime<Utc>,
+        pay_through: DateTime<Utc>,
+    ) -> Vec<PaymentProposal> {
+        self.bills
+            .iter()
+            .filter(|bill| bill.date() <= &run_date && bill.due_date() <= pay_through)
+            .filter_map(|bill| {
+                let open_amount = self.open_amount(bill.id(), DateTime::<Utc>::MAX_UTC);
+                (open_amount > DEFAULT_TOLERANCE).then(|| PaymentProposal {
+                    bill_id: bill.id(),
+                    vendor_id: bill.vendor_id(),
+                    due_date: bill.due_date(),
+                    open_amount,
+                    discount: if open_amount == bill.amount() {
+                        bill.terms().discount(open_amount, *bill.date(), run_date)
+                    } else {
+                        0f64
+                    },
+                })
+            })
+            .collect()
+    }
+
+    ///
+    /// Pay the bills proposed by `propose_payments`, making one payment per vendor.
+    /// The payments take the ids following the highest document id of the subledger.
+    /// Every payment is checked before any is recorded, so a failing run records nothing.
+    ///
+    pub fn payment_run(
+        &mut self,
+        run_date: DateTime<Utc>,
+        pay_through: DateTime<Utc>,
+        cash_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<PaymentRun, SubledgerError> {
+        let proposals = self.propose_payments(run_date, pay_through);
+
+        let mut next_document_id = self
+            .bills
+            .iter()
+            .map(|b| b.id())
+            .chain(self.payments.iter().map(|p| p.id()))
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let mut payments: Vec<VendorPayment> = Vec::new();
+        for vendor in self.vendors.iter() {
+            let vendor_proposals: Vec<&PaymentProposal> = proposals
+                .iter()
+                .filter(|p| p.vendor_id() == vendor.id())
+                .collect();
+            if vendor_proposals.is_empty() {
+                continue;
+            }
+
+            let mut payment = VendorPayment::new(
+                next_document_id,
+                vendor.id(),
+                run_date,
+                vendor_proposals.iter().map(|p| p.payment()).sum(),
+                &format!(
+                    "Payment run {} - {}",
+                    run_date.format("%Y-%m-%d"),
+                    vendor.name()
+                ),
+            );
+            payment.set_discount(vendor_proposals.iter().map(|p| p.discount()).sum());
+            for proposal in vendor_proposals {
+                payment.add_application(proposal.bill_id(), proposal.open_amount());
+            }
+
+            next_document_id += 1;
+            payments.push(payment);
+        }
+
+        for payment in payments.iter() {
+            self.check_payment(payment)?;
+        }
+
+        let journal_entries = payments
+            .iter()
+            .map(|payment| self.record_payment(payment.clone(), cash_account, ledger.id_sequence()))
+            .collect();
+
+        Ok(PaymentRun {
+            date: run_date,
+            proposals,
+            payments,
+            journal_entries,
+        })
+    }
+
+    ///
+    /// Get what is still owed on a bill from the payments applied up to `as_of`
+    ///
+    pub fn open_amount(&self, bill_id: usize, as_of: DateTime<Utc>) -> f64 {
+        let Some(bill) = self.get_bill(bill_id) else {
+            return 0f64;
+        };
+        if bill.date() > &as_of {
+            return 0f64;
+        }
+
+        let settled: f64 = self
+            .payments
+            .iter()
+            .flat_map(|p| p.applications())
+            .filter(|a| a.document_id() == bill_id && a.date() <= &as_of)
+            .map(|a| a.amount())
+            .sum();
+
+        bill.amount() - settled
+    }
+
+    pub fn open_bills(&self, vendor_id: usize, as_of: DateTime<Utc>) -> Vec<&Bill> {
+        self.bills
+            .iter()
+            .filter(|b| {
+                b.vendor_id() == vendor_id && self.open_amount(b.id(), as_of) > DEFAULT_TOLERANCE
+            })
+            .collect()
+    }
+
+    ///
+    /// Get the payments made to a vendor and not applied to any bill as of a date
+    ///
+    pub fn unapplied_amount(&self, vendor_id: usize, as_of: DateTime<Utc>) -> f64 {
+        self.payments
+            .iter()
+            .filter(|p| p.vendor_id() == vendor_id)
+            .map(|p| p.unapplied_amount(as_of))
+            .sum()
+    }
+
+    pub fn vendor_balance(&self, vendor_id: usize, as_of: DateTime<Utc>) -> f64 {
+        let open: f64 = self
+            .bills
+            .iter()
+            .filter(|b| b.vendor_id() == vendor_id)
+            .map(|b| self.open_amount(b.id(), as_of))
+            .sum();
+
+        open - self.unapplied_amount(vendor_id, as_of)
+    }
+
+    ///
+    /// Get what is owed to all vendors as of a date
+    ///
+    pub fn balance(&self, as_of: DateTime<Utc>) -> f64 {
+        self.vendors
+            .iter()
+            .map(|v| self.vendor_balance(v.id(), as_of))
+            .sum()
+    }
+
+    ///
+    /// Age the open bills by days past their due date
+    ///
+    pub fn aging(&self, as_of: DateTime<Utc>, buckets: &AgingBuckets) -> AgingReport {
+        let rows = self
+            .vendors
+            .iter()
+            .map(|vendor| {
+                let mut row =
+                    AgingRow::new(vendor.id(), vendor.name(), buckets.number_of_buckets());
+
+                for bill in self.open_bills(vendor.id(), as_of) {
+                    let days_past_due = (as_of - bill.due_date()).num_days();
+                    row.add(
+                        buckets.bucket(days_past_due),
+                        self.open_amount(bill.id(), as_of),
+                    );
+                }
+                row.add_unapplied(self.unapplied_amount(vendor.id(), as_of));
+
+                row
+            })
+            .collect();
+
+        AgingReport::new(as_of, buckets, rows)
+    }
+
+    ///
+    /// Build the statement of a vendor's bills and payments dated within `period`
+    ///
+    pub fn vendor_statement(
+        &self,
+        vendor_id: usize,
+        period: ReportingPeriod,
+    ) -> Result<VendorStatement, SubledgerError> {
+        let vendor = self
+            .get_vendor(vendor_id)
+            .ok_or(SubledgerError::UnknownParty(vendor_id))?
+            .clone();
+
+        let before_period = |date: &DateTime<Utc>| date < &period.from_date();
+        let opening_balance = self
+            .bills
+            .iter()
+            .filter(|b| b.vendor_id() == vendor_id && before_period(b.date()))
+            .map(|b| b.amount())
+            .sum::<f64>()
+            - self
+                .payments
+                .iter()
+                .filter(|p| p.vendor_id() == vendor_id && before_period(p.date()))
+                .map(|p| p.settled_amount())
+                .sum::<f64>();
+
+        let mut documents: Vec<(DateTime<Utc>, usize, String, f64, f64)> = self
+            .bills
+            .iter()
+            .filter(|b| b.vendor_id() == vendor_id && period.contains(*b.date()))
+            .map(|b| {
+                (
+                    *b.date(),
+                    b.id(),
+                    b.description().to_owned(),
+                    b.amount(),
+                    0f64,
+                )
+            })
+            .chain(
+                self.payments
+                    .iter()
+                    .filter(|p| p.vendor_id() == vendor_id && period.contains(*p.date()))
+                    .map(|p| {
+                        (
+                            *p.date(),
+                            p.id(),
+                            p.description().to_owned(),
+                            0f64,
+                            p.settled_amount(),
+                        )
+                    }),
+            )
+            .collect();
+        documents.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut balance = opening_balance;
+        let lines = documents
+            .into_iter()
+            .map(|(date, document_id, description, charge, payment)| {
+                balance += charge - payment;
+                VendorStatementLine {
+                    date,
+                    document_id,
+                    description,
+                    charge,
+                    payment,
+                    balance,
+                }
+            })
+            .collect();
+
+        Ok(VendorStatement {
+            vendor,
+            period,
+            opening_balance,
+            lines,
+        })
+    }
+
+    ///
+    /// Compare the subledger balance to the control account balance in the `ledger`
+    ///
+    pub fn reconcile(
+        &self,
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        as_of: DateTime<Utc>,
+    ) -> Result<ControlReconciliation, SubledgerError> {
+        Ok(ControlReconciliation::new(
+            as_of,
+            self.balance(as_of),
+            control_balance(accounting_tree, ledger, &self.control_account, as_of)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AccountsPayable, Bill, PaymentTerms, Vendor, VendorPayment};
+    use crate::journal::ledger::Ledger;
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::subledger::{AgingBuckets, SubledgerError};
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_payment_terms() {
+        let terms: PaymentTerms = "2/10 net 30".parse().unwrap();
+
+        assert_eq!(terms, PaymentTerms::new(2.0, 10, 30));
+        assert_eq!("2/10, n/30".parse::<PaymentTerms>().unwrap(), terms);
+        assert_eq!(
+            "Net 45".parse::<PaymentTerms>().unwrap(),
+            PaymentTerms::net(45)
+        );
+        assert!("COD".parse::<PaymentTerms>().is_err());
+        assert_eq!(terms.to_string(), "2/10 net 30");
+
+        assert_eq!(terms.due_date(date(2024, 1, 1)), date(2024, 1, 31));
+        assert_eq!(
+            terms.discount(1_000.0, date(2024, 1, 1), date(2024, 1, 11)),
+            20.0
+        );
+        assert_eq!(
+            terms.discount(1_000.0, date(2024, 1, 1), date(2024, 1, 12)),
+            0.0
+        );
+    }
+
+    #[test]
+    fn test_accounts_payable_payment_run() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let rent = chart.account("Rent Expense");
+        let inventory = chart.account("Inventory");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        let mut payables = AccountsPayable::new(chart.account("Accounts Payable"));
+        payables.add_vendor(Vendor::new(1, "Supplies Co")).unwrap();
+        payables.add_vendor(Vendor::new(2, "Landlord")).unwrap();
+
+        for (id, vendor_id, day, terms, account, amount) in [
+            (1, 1, date(2024, 1, 1), "2/10 net 30", &inventory, 1_000.0),
+            (2, 1, date(2024, 1, 20), "net 30", &inventory, 500.0),
+            (3, 2, date(2024, 1, 10), "net 15", &rent, 200.0),
+        ] {
+            let mut bill = Bill::new(id, vendor_id, day, terms.parse().unwrap(), "Bill");
+            bill.add_line(account, amount);
+            let journal_entry = payables.post_bill(bill, &mut ledger).unwrap();
+            ledger.add_journal_entry(journal_entry);
+        }
+
+        let proposals = payables.propose_payments(date(2024, 1, 10), date(2024, 1, 31));
+        assert_eq!(proposals.len(), 2);
+        assert_eq!(proposals[0].discount(), 20.0);
+
+        assert_eq!(
+            payables
+                .payment_run(date(2024, 1, 10), date(2024, 1, 31), &cash, &mut ledger)
+                .unwrap_err(),
+            SubledgerError::MissingAccount("purchase discounts".to_owned())
+        );
+        assert!(payables.payments().is_empty());
+        payables.set_discount_account(Some(chart.account("Cost of Goods Sold")));
+
+        let run = payables
+            .payment_run(date(2024, 1, 10), date(2024, 1, 31), &cash, &mut ledger)
+            .unwrap();
+        assert_eq!(run.payments().len(), 2);
+        assert_eq!(run.total_paid(), 1_180.0);
+        assert_eq!(run.total_discount(), 20.0);
+        assert!(run.journal_entries().iter().all(|j| j.validate()));
+        for journal_entry in run.journal_entries() {
+            ledger.add_journal_entry(journal_entry.clone());
+        }
+
+        let mut overpayment = VendorPayment::new(10, 1, date(2024, 2, 1), 600.0, "Cheque");
+        overpayment.add_application(2, 600.0);
+        assert_eq!(
+            payables
+                .post_payment(overpayment, &cash, &mut ledger)
+                .unwrap_err(),
+            SubledgerError::OverApplied {
+                document_id: 2,
+                open_amount: 500.0,
+                applied: 600.0
+            }
+        );
+
+        let aging = payables.aging(date(2024, 3, 1), &AgingBuckets::default());
+        assert_eq!(
+            aging.row(1).unwrap().buckets(),
+            &vec![0.0, 500.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(aging.total(), 500.0);
+
+        let statement = payables
+            .vendor_statement(
+                1,
+                ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap(),
+            )
+            .unwrap();
+        assert_eq!(statement.lines().len(), 3);
+        assert_eq!(statement.lines()[1].payment(), 1_000.0);
+        assert_eq!(statement.closing_balance(), 500.0);
+
+        let reconciliation = payables
+            .reconcile(&chart.tree(), &ledger, date(2024, 3, 1))
+            .unwrap();
+        assert_eq!(reconciliation.control_balance(), 500.0);
+        assert!(reconciliation.is_reconciled());
+    }
+}
@@ -0,0 +1,764 @@
+use crate::journal::accounting_tree::AccountNodeRef;
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError,
+};
+use chrono::{DateTime, Datelike, Utc};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// Errors raised by the `Payroll`
+///
+#[derive(Debug, PartialEq)]
+pub enum PayrollError {
+    DuplicateEmployee(usize),
+    UnknownEmployee(usize),
+    DuplicateRun(usize),
+    UnknownRun(usize),
+    ///
+    /// The liabilities of the run were already remitted
+    ///
+    AlreadyRemitted(usize),
+    ///
+    /// An employee is paid more than once in a run
+    ///
+    DuplicatePayslip(usize),
+    ///
+    /// The withholdings of an employee exceed their gross pay
+    ///
+    NegativeNetPay {
+        employee_id: usize,
+        net_pay: f64,
+    },
+    ///
+    /// Gross pay must be positive
+    ///
+    InvalidAmount(f64),
+    ///
+    /// The ledger rejected the journal entry of a run or remittance
+    ///
+    Ledger(LedgerError),
+}
+
+impl Display for PayrollError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PayrollError::DuplicateEmployee(id) => write!(f, "Employee {} already exists.", id),
+            PayrollError::UnknownEmployee(id) => write!(f, "Employee {} does not exist.", id),
+            PayrollError::DuplicateRun(id) => write!(f, "Payroll run {} already exists.", id),
+            PayrollError::UnknownRun(id) => write!(f, "Payroll run {} does not exist.", id),
+            PayrollError::AlreadyRemitted(id) => {
+                write!(f, "Payroll run {} has already been remitted.", id)
+            }
+            PayrollError::DuplicatePayslip(id) => {
+                write!(f, "Employee {} is paid more than once in the run.", id)
+            }
+            PayrollError::NegativeNetPay {
+                employee_id,
+                net_pay,
+            } => write!(
+                f,
+                "Employee {} would have a net pay of {:.2}.",
+                employee_id, net_pay
+            ),
+            PayrollError::InvalidAmount(amount) => {
+                write!(f, "Amount {:.2} must be positive.", amount)
+            }
+            PayrollError::Ledger(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for PayrollError {}
+
+impl From<LedgerError> for PayrollError {
+    fn from(error: LedgerError) -> Self {
+        PayrollError::Ledger(error)
+    }
+}
+
+///
+/// `Employee` and the gross pay of a regular pay period
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Employee {
+    id: usize,
+    name: String,
+    gross_pay: f64,
+}
+
+impl Employee {
+    pub fn new(id: usize, name: &str, gross_pay: f64) -> Self {
+        Employee {
+            id,
+            name: name.to_owned(),
+            gross_pay,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn gross_pay(&self) -> f64 {
+        self.gross_pay
+    }
+
+    pub fn set_gross_pay(&mut self, gross_pay: f64) {
+        self.gross_pay = gross_pay;
+    }
+}
+
+///
+/// How the amount of a `PayrollRule` is computed from gross pay
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleBasis {
+    ///
+    /// A share of gross pay (`0.062` for 6.2%)
+    ///
+    Percentage(f64),
+    ///
+    /// The same amount every run, capped at gross pay
+    ///
+    Fixed(f64),
+}
+
+///
+/// Who bears a `PayrollRule`
+///
+#[derive(Debug, Clone)]
+pub enum RuleKind {
+    ///
+    /// Withheld from the employee's gross pay
+    ///
+    Withholding,
+    ///
+    /// Paid by the employer on top of gross pay and charged to `expense_account`
+    ///
+    EmployerContribution { expense_account: AccountNodeRef },
+}
+
+///
+/// `PayrollRule`: a withholding or an employer contribution owed to `liability_account`
+/// until it is remitted
+///
+#[derive(Debug, Clone)]
+pub struct PayrollRule {
+    name: String,
+    kind: RuleKind,
+    basis: RuleBasis,
+    liability_account: AccountNodeRef,
+    wage_base_limit: Option<f64>,
+}
+
+impl PayrollRule {
+    pub fn new(
+        name: &str,
+        kind: RuleKind,
+        basis: RuleBasis,
+        liability_account: AccountNodeRef,
+    ) -> Self {
+        PayrollRule {
+            name: name.to_owned(),
+            kind,
+            basis,
+            liability_account,
+            wage_base_limit: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &RuleKind {
+        &self.kind
+    }
+
+    pub fn basis(&self) -> RuleBasis {
+        self.basis
+    }
+
+    pub fn liability_account(&self) -> AccountNodeRef {
+        self.liability_account.clone()
+    }
+
+    pub fn wage_base_limit(&self) -> Option<f64> {
+        self.wage_base_limit
+    }
+
+    ///
+    /// Only apply the rule to the first `wage_base_limit` of an employee's gross pay
+    /// in a calendar year, as with unemployment taxes
+    ///
+    pub fn set_wage_base_limit(&mut self, wage_base_limit: Option<f64>) {
+        self.wage_base_limit = wage_base_limit;
+    }
+
+    ///
+    /// Get the amount of the rule on `gross_pay` for an employee already paid
+    /// `year_to_date` in the calendar year
+    ///
+    pub fn amount(&self, gross_pay: f64, year_to_date: f64) -> f64 {
+        let subject = match self.wage_base_limit {
+            Some(limit) => gross_pay.min((limit - year_to_date).max(0f64)),
+            None => gross_pay,
+        };
+
+        round_to_cents(match self.basis {
+            RuleBasis::Percentage(rate) => subject * rate,
+            RuleBasis::Fixed(amount) => amount.min(subject),
+        })
+    }
+}
+
+///
+/// The amount of a rule on a `Payslip`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayslipLine {
+    rule_name: String,
+    amount: f64,
+}
+
+impl PayslipLine {
+    pub fn rule_name(&self) -> &str {
+        &self.rule_name
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+}
+
+///
+/// An employee's pay in a `PayrollRun`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Payslip {
+    employee_id: usize,
+    gross_pay: f64,
+    withholdings: Vec<PayslipLine>,
+    contributions: Vec<PayslipLine>,
+}
+
+impl Payslip {
+    pub fn employee_id(&self) -> usize {
+        self.employee_id
+    }
+
+    pub fn gross_pay(&self) -> f64 {
+        self.gross_pay
+    }
+
+    pub fn withholdings(&self) -> &Vec<PayslipLine> {
+        &self.withholdings
+    }
+
+    ///
+    /// Get the employer contributions on top of gross pay
+    ///
+    pub fn contributions(&self) -> &Vec<PayslipLine> {
+        &self.contributions
+    }
+
+    pub fn total_withholdings(&self) -> f64 {
+        round_to_cents(self.withholdings.iter().map(|line| line.amount).sum())
+    }
+
+    pub fn total_contributions(&self) -> f64 {
+        round_to_cents(self.contributions.iter().map(|line| line.amount).sum())
+    }
+
+    pub fn net_pay(&self) -> f64 {
+        round_to_cents(self.gross_pay - self.total_withholdings())
+    }
+}
+
+///
+/// `PayrollRun` pays a set of employees on a date
+///
+#[derive(Debug, Clone)]
+pub struct PayrollRun {
+    id: usize,
+    date: DateTime<Utc>,
+    payslips: Vec<Payslip>,
+    liabilities: Vec<(AccountNodeRef, f64)>,
+    remitted: bool,
+}
+
+impl PayrollRun {
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn payslips(&self) -> &Vec<Payslip> {
+        &self.payslips
+    }
+
+    pub fn payslip(&self, employee_id: usize) -> Option<&Payslip> {
+        self.payslips
+            .iter()
+            .find(|payslip| payslip.employee_id == employee_id)
+    }
+
+    ///
+    /// Get the withholdings and employer contributions owed, by liability account
+    ///
+    pub fn liabilities(&self) -> &Vec<(AccountNodeRef, f64)> {
+        &self.liabilities
+    }
+
+    pub fn is_remitted(&self) -> bool {
+        self.remitted
+    }
+
+    pub fn total_gross_pay(&self) -> f64 {
+        round_to_cents(self.payslips.iter().map(|payslip| payslip.gross_pay).sum())
+    }
+
+    pub fn total_net_pay(&self) -> f64 {
+        round_to_cents(self.payslips.iter().map(|payslip| payslip.net_pay()).sum())
+    }
+}
+
+///
+/// Add `amount` to the total of `account`
+///
+fn add_to_account(totals: &mut Vec<(AccountNodeRef, f64)>, account: &AccountNodeRef, amount: f64) {
+    match totals.iter_mut().find(|(a, _)| Rc::ptr_eq(a, account)) {
+        Some((_, total)) => *total = round_to_cents(*total + amount),
+        None => totals.push((account.clone(), amount)),
+    }
+}
+
+///
+/// `Payroll` computes the pay of its employees with its rules and posts the runs
+/// and the remittance of the liabilities they create
+///
+#[derive(Debug, Clone)]
+pub struct Payroll {
+    wages_expense: AccountNodeRef,
+    cash: AccountNodeRef,
+    employees: Vec<Employee>,
+    rules: Vec<PayrollRule>,
+    runs: Vec<PayrollRun>,
+}
+
+impl Payroll {
+    pub fn new(wages_expense: AccountNodeRef, cash: AccountNodeRef) -> Self {
+        Payroll {
+            wages_expense,
+            cash,
+            employees: Vec::new(),
+            rules: Vec::new(),
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn add_employee(&mut self, employee: Employee) -> Result<(), PayrollError> {
+        if self.get_employee(employee.id).is_some() {
+            return Err(PayrollError::DuplicateEmployee(employee.id));
+        }
+
+        self.employees.push(employee);
+
+        Ok(())
+    }
+
+    pub fn employees(&self) -> &Vec<Employee> {
+        &self.employees
+    }
+
+    pub fn get_employee(&self, id: usize) -> Option<&Employee> {
+        self.employees.iter().find(|employee| employee.id == id)
+    }
+
+    pub fn get_employee_mut(&mut self, id: usize) -> Option<&mut Employee> {
+        self.employees.iter_mut().find(|employee| employee.id == id)
+    }
+
+    pub fn add_rule(&mut self, rule: PayrollRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &Vec<PayrollRule> {
+        &self.rules
+    }
+
+    pub fn runs(&self) -> &Vec<PayrollRun> {
+        &self.runs
+    }
+
+    pub fn get_run(&self, id: usize) -> Option<&PayrollRun> {
+        self.runs.iter().find(|run| run.id == id)
+    }
+
+    ///
+    /// Get the gross pay of an employee in the calendar year of `date` up to `date`,
+    /// including the runs already made on that date
+    ///
+    pub fn year_to_date_gross_pay(&self, employee_id: usize, date: DateTime<Utc>) -> f64 {
+        round_to_cents(
+            self.runs
+                .iter()
+                .filter(|run| run.date.year() == date.year() && run.date <= date)
+                .filter_map(|run| run.payslip(employee_id))
+                .map(|payslip| payslip.gross_pay)
+                .sum(),
+        )
+    }
+
+    ///
+    /// Pay every employee their regular gross pay
+    ///
+    pub fn run(
+        &mut self,
+        run_id: usize,
+        date: DateTime<Utc>,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, PayrollError> {
+        let gross_pays: Vec<(usize, f64)> = self
+            .employees
+            .iter()
+            .map(|employee| (employee.id, employee.gross_pay))
+            .collect();
+
+        self.run_with(run_id, date, &gross_pays, ledger)
+    }
+
+    ///
+    /// Pay the `(employee id, gross pay)` of `gross_pays`. The journal entry debits wages
+    /// expense with the gross pay and the employer expense accounts with the contributions,
+    /// credits the liability accounts with the withholdings and contributions, and credits
+    /// cash with the net pay. Gross pays are taken to the cent.
+    /// The wage base limits count the earlier runs of the year, those made on `date` included.
+    /// The run is only recorded once `ledger` accepts its journal entry, which is returned.
+    ///
+    pub fn run_with(
+        &mut self,
+        run_id: usize,
+        date: DateTime<Utc>,
+        gross_pays: &[(usize, f64)],
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, PayrollError> {
+        if self.get_run(run_id).is_some() {
+            return Err(PayrollError::DuplicateRun(run_id));
+        }
+
+        let mut payslips = Vec::new();
+        let mut expenses: Vec<(AccountNodeRef, f64)> = Vec::new();
+        let mut liabilities: Vec<(AccountNodeRef, f64)> = Vec::new();
+
+        for (employee_id, gross_pay) in gross_pays {
+            let gross_pay = round_to_cents(*gross_pay);
+            if self.get_employee(*employee_id).is_none() {
+                return Err(PayrollError::UnknownEmployee(*employee_id));
+            }
+            if gross_pay <= 0f64 {
+                return Err(PayrollError::InvalidAmount(gross_pay));
+            }
+            if payslips
+                .iter()
+                .any(|payslip: &Payslip| payslip.employee_id == *employee_id)
+            {
+                return Err(PayrollError::DuplicatePayslip(*employee_id));
+            }
+
+            let year_to_date = self.year_to_date_gross_pay(*employee_id, date);
+            let mut payslip = Payslip {
+                employee_id: *employee_id,
+                gross_pay,
+                withholdings: Vec::new(),
+                contributions: Vec::new(),
+            };
+            add_to_account(&mut expenses, &self.wages_expense, gross_pay);
+
+            for rule in self.rules.iter() {
+                let amount = rule.amount(gross_pay, year_to_date);
+                if amount == 0f64 {
+                    continue;
+                }
+
+                let line = PayslipLine {
+                    rule_name: rule.name.clone(),
+                    amount,
+                };
+                match &rule.kind {
+                    RuleKind::Withholding => payslip.withholdings.push(line),
+                    RuleKind::EmployerContribution { expense_account } => {
+                        add_to_account(&mut expenses, expense_account, amount);
+                        payslip.contributions.push(line);
+                    }
+                }
+                add_to_account(&mut liabilities, &rule.liability_account, amount);
+            }
+
+            if payslip.net_pay() < 0f64 {
+                return Err(PayrollError::NegativeNetPay {
+                    employee_id: *employee_id,
+                    net_pay: payslip.net_pay(),
+                });
+            }
+            payslips.push(payslip);
+        }
+
+        let run = PayrollRun {
+            id: run_id,
+            date,
+            payslips,
+            liabilities,
+            remitted: false,
+        };
+
+        let net_pay = run.total_net_pay();
+        let mut lines: Vec<(&AccountNodeRef, f64, EntryType)> = expenses
+            .iter()
+            .map(|(account, amount)| (account, *amount, EntryType::Debit))
+            .collect();
+        lines.extend(
+            run.liabilities
+                .iter()
+                .map(|(account, amount)| (account, *amount, EntryType::Credit)),
+        );
+        lines.push((&self.cash, net_pay, EntryType::Credit));
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Payroll run {}", run_id),
+            &lines,
+        );
+        let posted = ledger.post_new_journal_entry(journal_entry)?.clone();
+        self.runs.push(run);
+
+        Ok(posted)
+    }
+
+    ///
+    /// Pay the withholdings and contributions of a run to the authorities and funds:
+    /// debit each liability account, credit cash. The run is only marked as remitted
+    /// once `ledger` accepts the journal entry, which is returned.
+    ///
+    pub fn remit(
+        &mut self,
+        run_id: usize,
+        date: DateTime<Utc>,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, PayrollError> {
+        let run = self
+            .runs
+            .iter_mut()
+            .find(|run| run.id == run_id)
+            .ok_or(PayrollError::UnknownRun(run_id))?;
+        if run.remitted {
+            return Err(PayrollError::AlreadyRemitted(run_id));
+        }
+
+        let total = round_to_cents(run.liabilities.iter().map(|(_, amount)| amount).sum());
+        let mut lines: Vec<(&AccountNodeRef, f64, EntryType)> = run
+            .liabilities
+            .iter()
+            .map(|(account, amount)| (account, *amount, EntryType::Debit))
+            .collect();
+        lines.push((&self.cash, total, EntryType::Credit));
+
+        let journal_entry = JournalEntry::from_lines(
+            &mut IdSequence::new(1),
+            date,
+            &format!("Payroll remittance for run {}", run_id),
+            &lines,
+        );
+        let posted = ledger.post_new_journal_entry(journal_entry)?.clone();
+        run.remitted = true;
+
+        Ok(posted)
+    }
+
+    ///
+    /// Get the liabilities of the runs not remitted yet, by liability account
+    ///
+    pub fn outstanding_liabilities(&self) -> Vec<(AccountNodeRef, f64)> {
+        let mut outstanding = Vec::new();
+
+        for run in self.runs.iter().filter(|run| !run.remitted) {
+            for (account, amount) in run.liabilities.iter() {
+                add_to_account(&mut outstanding, account, *amount);
+            }
+        }
+
+        outstanding
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Employee, Payroll, PayrollError, PayrollRule, RuleBasis, RuleKind};
+    use crate::journal::ledger::{EntryType, Ledger, LedgerError};
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_payroll_run_and_remittance() {
+        let chart = test_chart();
+        let mut payroll = Payroll::new(chart.account("Wages Expense"), chart.account("Cash"));
+        payroll
+            .add_employee(Employee::new(1, "Ada", 4_000.0))
+            .unwrap();
+        payroll
+            .add_employee(Employee::new(2, "Grace", 3_000.0))
+            .unwrap();
+
+        payroll.add_rule(PayrollRule::new(
+            "Federal income tax",
+            RuleKind::Withholding,
+            RuleBasis::Percentage(0.1),
+            chart.account("Federal Income Tax Payable"),
+        ));
+        payroll.add_rule(PayrollRule::new(
+            "Health flex",
+            RuleKind::Withholding,
+            RuleBasis::Fixed(100.0),
+            chart.account("Health/Child Flex Payable"),
+        ));
+        let mut unemployment = PayrollRule::new(
+            "Federal unemployment tax",
+            RuleKind::EmployerContribution {
+                expense_account: chart.account("Payroll Tax Expense"),
+            },
+            RuleBasis::Percentage(0.006),
+            chart.account("Federal Unemployment Tax Payable"),
+        );
+        unemployment.set_wage_base_limit(Some(7_000.0));
+        payroll.add_rule(unemployment);
+        payroll.add_rule(PayrollRule::new(
+            "Health insurance",
+            RuleKind::EmployerContribution {
+                expense_account: chart.account("Employee Benefits Expense"),
+            },
+            RuleBasis::Fixed(250.0),
+            chart.account("Health/Child Flex Payable"),
+        ));
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        let journal_entry = payroll.run(1, date(2024, 1, 31), &mut ledger).unwrap();
+        assert!(journal_entry.validate());
+        assert_eq!(journal_entry.total_debit(), 7_542.0);
+
+        let run = payroll.get_run(1).unwrap();
+        assert_eq!(run.payslip(1).unwrap().net_pay(), 3_500.0);
+        assert_eq!(run.total_net_pay(), 6_100.0);
+        let cash = journal_entry
+            .transaction_entries()
+            .iter()
+            .find(|entry| entry.account_name() == "Cash")
+            .unwrap();
+        assert_eq!(cash.amount(), 6_100.0);
+        assert_eq!(cash.entry_type(), &EntryType::Credit);
+
+        // Ada's unemployment tax stops at the 7000 wage base
+        payroll.run(2, date(2024, 2, 29), &mut ledger).unwrap();
+        let run = payroll.get_run(2).unwrap();
+        let unemployment_tax =
+            |employee_id: usize| run.payslip(employee_id).unwrap().contributions()[0].amount();
+        assert_eq!(unemployment_tax(1), 18.0);
+        assert_eq!(unemployment_tax(2), 18.0);
+
+        // An off-cycle bonus on payday counts the regular run of the day
+        payroll
+            .run_with(3, date(2024, 2, 29), &[(2, 2_000.0)], &mut ledger)
+            .unwrap();
+        let run = payroll.get_run(3).unwrap();
+        assert_eq!(run.payslip(2).unwrap().contributions()[0].amount(), 6.0);
+
+        assert_eq!(
+            payroll
+                .run_with(
+                    4,
+                    date(2024, 3, 15),
+                    &[(1, 1_000.0), (1, 1_000.0)],
+                    &mut ledger
+                )
+                .unwrap_err(),
+            PayrollError::DuplicatePayslip(1)
+        );
+        assert_eq!(
+            payroll
+                .run_with(4, date(2024, 3, 15), &[(2, 50.0)], &mut ledger)
+                .unwrap_err(),
+            PayrollError::NegativeNetPay {
+                employee_id: 2,
+                net_pay: -5.0
+            }
+        );
+
+        let remittance = payroll.remit(1, date(2024, 2, 15), &mut ledger).unwrap();
+        assert!(remittance.validate());
+        assert_eq!(remittance.total_credit(), 1_442.0);
+        assert_eq!(
+            payroll
+                .remit(1, date(2024, 2, 15), &mut ledger)
+                .unwrap_err(),
+            PayrollError::AlreadyRemitted(1)
+        );
+        assert_eq!(payroll.outstanding_liabilities().len(), 3);
+    }
+
+    #[test]
+    fn test_generated_entries_post() {
+        let chart = test_chart();
+        let mut payroll = Payroll::new(chart.account("Wages Expense"), chart.account("Cash"));
+        payroll
+            .add_employee(Employee::new(1, "Ada", 4_000.0))
+            .unwrap();
+        payroll.add_rule(PayrollRule::new(
+            "Federal income tax",
+            RuleKind::Withholding,
+            RuleBasis::Percentage(0.1137),
+            chart.account("Federal Income Tax Payable"),
+        ));
+        payroll.add_rule(PayrollRule::new(
+            "Federal unemployment tax",
+            RuleKind::EmployerContribution {
+                expense_account: chart.account("Payroll Tax Expense"),
+            },
+            RuleBasis::Percentage(0.0063),
+            chart.account("Federal Unemployment Tax Payable"),
+        ));
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        for run_id in 1..=500 {
+            let gross_pay = 100.0 + run_id as f64 * 1.373;
+            payroll
+                .run_with(run_id, date(2024, 1, 31), &[(1, gross_pay)], &mut ledger)
+                .unwrap();
+            payroll
+                .remit(run_id, date(2024, 2, 15), &mut ledger)
+                .unwrap();
+        }
+        assert_eq!(ledger.number_of_journal_entries(), 1_000);
+
+        // A run or remittance the ledger rejects is not recorded
+        assert_eq!(
+            payroll
+                .run_with(501, date(2025, 1, 31), &[(1, 100.0)], &mut ledger)
+                .unwrap_err(),
+            PayrollError::Ledger(LedgerError::DateOutOfRange(date(2025, 1, 31)))
+        );
+        assert!(payroll.get_run(501).is_none());
+        payroll
+            .run_with(501, date(2024, 12, 31), &[(1, 100.0)], &mut ledger)
+            .unwrap();
+        assert!(payroll.remit(501, date(2025, 1, 15), &mut ledger).is_err());
+        assert_eq!(payroll.outstanding_liabilities().len(), 2);
+    }
+}
@@ -0,0 +1,81 @@
+use crate::journal::balance_sheet::BalanceSheetError;
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+
+///
+/// `ReportingPeriod` is the span of time a financial statement is built for.
+/// It is either an explicit date range or everything up to and including an as-of date.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportingPeriod {
+    Range {
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    },
+    AsOf(DateTime<Utc>),
+}
+
+impl ReportingPeriod {
+    ///
+    /// Create a `ReportingPeriod` covering `from_date` to `to_date` inclusive.
+    /// Returns `BalanceSheetError::InvalidPeriod` when `from_date` is after `to_date`.
+    ///
+    pub fn range(
+        from_date: DateTime<Utc>,
+        to_date: DateTime<Utc>,
+    ) -> Result<Self, BalanceSheetError> {
+        if from_date > to_date {
+            return Err(BalanceSheetError::InvalidPeriod { from_date, to_date });
+        }
+
+        Ok(ReportingPeriod::Range { from_date, to_date })
+    }
+
+    ///
+    /// Create a `ReportingPeriod` covering everything up to and including `date`
+    ///
+    pub fn as_of(date: DateTime<Utc>) -> Self {
+        ReportingPeriod::AsOf(date)
+    }
+
+    ///
+    /// Get the first instant covered by the period
+    ///
+    pub fn from_date(&self) -> DateTime<Utc> {
+        match self {
+            ReportingPeriod::Range { from_date, .. } => *from_date,
+            ReportingPeriod::AsOf(_) => DateTime::<Utc>::MIN_UTC,
+        }
+    }
+
+    ///
+    /// Get the last instant covered by the period
+    ///
+    pub fn to_date(&self) -> DateTime<Utc> {
+        match self {
+            ReportingPeriod::Range { to_date, .. } => *to_date,
+            ReportingPeriod::AsOf(date) => *date,
+        }
+    }
+
+    ///
+    /// Check whether `date` falls within the period
+    ///
+    pub fn contains(&self, date: DateTime<Utc>) -> bool {
+        date >= self.from_date() && date <= self.to_date()
+    }
+}
+
+impl Display for ReportingPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReportingPeriod::Range { from_date, to_date } => write!(
+                f,
+                "{} - {}",
+                from_date.format("%Y-%m-%d"),
+                to_date.format("%Y-%m-%d")
+            ),
+            ReportingPeriod::AsOf(date) => write!(f, "As of {}", date.format("%Y-%m-%d")),
+        }
+    }
+}
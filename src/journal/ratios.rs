@@ -0,0 +1,519 @@
+use crate::journal::accounting_tree::SubtotalNode;
+use crate::journal::balance_sheet::BalanceSheetSnapshot;
+use crate::journal::income_statement::IncomeStatementSnapshot;
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+///
+/// An amount a ratio is computed from, found on the balance sheet or the income statement
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RatioInput {
+    CurrentAssets,
+    CurrentLiabilities,
+    Inventory,
+    Receivables,
+    Payables,
+    TotalAssets,
+    TotalLiabilities,
+    Equity,
+    Revenue,
+    CostOfGoodsSold,
+}
+
+impl RatioInput {
+    ///
+    /// Check whether the input is read from the income statement rather than the balance sheet
+    ///
+    pub fn is_income_statement_input(&self) -> bool {
+        matches!(self, RatioInput::Revenue | RatioInput::CostOfGoodsSold)
+    }
+}
+
+///
+/// `RatioMapping` maps every `RatioInput` to the accounts (names or paths) whose total it is
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatioMapping {
+    inputs: HashMap<RatioInput, Vec<String>>,
+}
+
+impl RatioMapping {
+    ///
+    /// Create a mapping with no inputs mapped
+    ///
+    pub fn new() -> Self {
+        RatioMapping {
+            inputs: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Map `input` to the total of `accounts`
+    ///
+    pub fn set(&mut self, input: RatioInput, accounts: Vec<&str>) {
+        self.inputs.insert(
+            input,
+            accounts.iter().map(|account| account.to_string()).collect(),
+        );
+    }
+
+    ///
+    /// Remove the mapping of `input`, making the ratios that need it not computable
+    ///
+    pub fn remove(&mut self, input: RatioInput) {
+        self.inputs.remove(&input);
+    }
+
+    ///
+    /// Get the accounts `input` is mapped to
+    ///
+    pub fn get(&self, input: RatioInput) -> Option<&Vec<String>> {
+        self.inputs.get(&input)
+    }
+}
+
+impl Default for RatioMapping {
+    ///
+    /// Mapping that follows the names of the accounts in `data/account_types.txt`
+    ///
+    fn default() -> Self {
+        let mut mapping = RatioMapping::new();
+        mapping.set(RatioInput::CurrentAssets, vec!["Current Assets"]);
+        mapping.set(RatioInput::CurrentLiabilities, vec!["Current Liabilities"]);
+        mapping.set(RatioInput::Inventory, vec!["Inventory"]);
+        mapping.set(RatioInput::Receivables, vec!["Accounts Receivable"]);
+        mapping.set(RatioInput::Payables, vec!["Accounts Payable"]);
+        mapping.set(RatioInput::TotalAssets, vec!["Assets"]);
+        mapping.set(RatioInput::TotalLiabilities, vec!["Liabilities"]);
+        mapping.set(RatioInput::Equity, vec!["Equity"]);
+        mapping.set(RatioInput::Revenue, vec!["Revenue"]);
+        mapping.set(RatioInput::CostOfGoodsSold, vec!["Cost of Goods Sold"]);
+        mapping
+    }
+}
+
+///
+/// The ratios computed by a `RatioAnalysis`
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RatioKind {
+    CurrentRatio,
+    QuickRatio,
+    DebtToEquity,
+    GrossMargin,
+    ReturnOnAssets,
+    ReturnOnEquity,
+    InventoryTurnover,
+    DaysSalesOutstanding,
+    DaysPayablesOutstanding,
+}
+
+impl RatioKind {
+    pub fn all() -> Vec<RatioKind> {
+        vec![
+            RatioKind::CurrentRatio,
+            RatioKind::QuickRatio,
+            RatioKind::DebtToEquity,
+            RatioKind::GrossMargin,
+            RatioKind::ReturnOnAssets,
+            RatioKind::ReturnOnEquity,
+            RatioKind::InventoryTurnover,
+            RatioKind::DaysSalesOutstanding,
+            RatioKind::DaysPayablesOutstanding,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RatioKind::CurrentRatio => "Current Ratio",
+            RatioKind::QuickRatio => "Quick Ratio",
+            RatioKind::DebtToEquity => "Debt to Equity",
+            RatioKind::GrossMargin => "Gross Margin",
+            RatioKind::ReturnOnAssets => "Return on Assets",
+            RatioKind::ReturnOnEquity => "Return on Equity",
+            RatioKind::InventoryTurnover => "Inventory Turnover",
+            RatioKind::DaysSalesOutstanding => "Days Sales Outstanding",
+            RatioKind::DaysPayablesOutstanding => "Days Payables Outstanding",
+        }
+    }
+}
+
+///
+/// Value of a ratio, or the reason it could not be computed
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum RatioValue {
+    Computed(f64),
+    NotComputable(String),
+}
+
+impl RatioValue {
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            RatioValue::Computed(value) => Some(*value),
+            RatioValue::NotComputable(_) => None,
+        }
+    }
+
+    pub fn is_computable(&self) -> bool {
+        matches!(self, RatioValue::Computed(_))
+    }
+}
+
+///
+/// A named set of ratios, in the order of `RatioKind::all`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct RatioSet {
+    ratios: Vec<(RatioKind, RatioValue)>,
+}
+
+impl RatioSet {
+    pub fn ratios(&self) -> &Vec<(RatioKind, RatioValue)> {
+        &self.ratios
+    }
+
+    pub fn get(&self, kind: RatioKind) -> Option<&RatioValue> {
+        self.ratios
+            .iter()
+            .find(|(ratio_kind, _)| *ratio_kind == kind)
+            .map(|(_, value)| value)
+    }
+
+    ///
+    /// Get a ratio by its name, ignoring ASCII case
+    ///
+    pub fn get_by_name(&self, name: &str) -> Option<&RatioValue> {
+        self.ratios
+            .iter()
+            .find(|(kind, _)| kind.name().eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+}
+
+impl Display for RatioSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (kind, value) in self.ratios.iter() {
+            match value {
+                RatioValue::Computed(value) => writeln!(f, "{:<28}{:>12.4}", kind.name(), value)?,
+                RatioValue::NotComputable(reason) => {
+                    writeln!(f, "{:<28}{:>12} ({})", kind.name(), "n/a", reason)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///
+/// `RatioAnalysis` computes financial ratios from a built balance sheet and income statement
+///
+#[derive(Debug, Clone, Default)]
+pub struct RatioAnalysis {
+    mapping: RatioMapping,
+}
+
+impl RatioAnalysis {
+    pub fn new(mapping: RatioMapping) -> Self {
+        RatioAnalysis { mapping }
+    }
+
+    pub fn mapping(&self) -> &RatioMapping {
+        &self.mapping
+    }
+
+    pub fn set_mapping(&mut self, mapping: RatioMapping) {
+        self.mapping = mapping;
+    }
+
+    ///
+    /// Compute every ratio of `RatioKind::all`.
+    /// Days based ratios use the number of days covered by the income statement, and
+    /// are not computable when it covers no bounded date range.
+    ///
+    pub fn compute(
+        &self,
+        balance_sheet: &BalanceSheetSnapshot,
+        income_statement: &IncomeStatementSnapshot,
+    ) -> RatioSet {
+        let input = |input: RatioInput| self.input(input, balance_sheet, income_statement);
+        let days = match income_statement.period() {
+            ReportingPeriod::Range { from_date, to_date }
+                if *from_date != DateTime::<Utc>::MIN_UTC =>
+            {
+                Ok(((*to_date - *from_date).num_days() + 1) as f64)
+            }
+            _ => Err("the income statement covers no bounded date range".to_owned()),
+        };
+        let times_days = |amount: f64| days.clone().map(|days| amount * days);
+
+        let ratios = RatioKind::all()
+            .into_iter()
+            .map(|kind| {
+                let value = match kind {
+                    RatioKind::CurrentRatio => divide(
+                        input(RatioInput::CurrentAssets),
+                        input(RatioInput::CurrentLiabilities),
+                    ),
+                    RatioKind::QuickRatio => divide(
+                        subtract(
+                            input(RatioInput::CurrentAssets),
+                            input(RatioInput::Inventory),
+                        ),
+                        input(RatioInput::CurrentLiabilities),
+                    ),
+                    RatioKind::DebtToEquity => divide(
+                        input(RatioInput::TotalLiabilities),
+                        input(RatioInput::Equity),
+                    ),
+                    RatioKind::GrossMargin => divide(
+                        subtract(
+                            input(RatioInput::Revenue),
+                            input(RatioInput::CostOfGoodsSold),
+                        ),
+                        input(RatioInput::Revenue),
+                    ),
+                    RatioKind::ReturnOnAssets => divide(
+                        Ok(income_statement.net_income()),
+                        input(RatioInput::TotalAssets),
+                    ),
+                    RatioKind::ReturnOnEquity => {
+                        divide(Ok(income_statement.net_income()), input(RatioInput::Equity))
+                    }
+                    RatioKind::InventoryTurnover => divide(
+                        input(RatioInput::CostOfGoodsSold),
+                        input(RatioInput::Inventory),
+                    ),
+                    RatioKind::DaysSalesOutstanding => divide(
+                        input(RatioInput::Receivables).and_then(times_days),
+                        input(RatioInput::Revenue),
+                    ),
+                    RatioKind::DaysPayablesOutstanding => divide(
+                        input(RatioInput::Payables).and_then(times_days),
+                        input(RatioInput::CostOfGoodsSold),
+                    ),
+                };
+
+                (kind, value)
+            })
+            .collect();
+
+        RatioSet { ratios }
+    }
+
+    ///
+    /// Total of the accounts `input` is mapped to, or the reason it cannot be found
+    ///
+    fn input(
+        &self,
+        input: RatioInput,
+        balance_sheet: &BalanceSheetSnapshot,
+        income_statement: &IncomeStatementSnapshot,
+    ) -> Result<f64, String> {
+        let accounts = match self.mapping.get(input) {
+            None => return Err(format!("no accounts are mapped to {:?}", input)),
+            Some(accounts) if accounts.is_empty() => {
+                return Err(format!("no accounts are mapped to {:?}", input))
+            }
+            Some(accounts) => accounts,
+        };
+
+        let root: &SubtotalNode = if input.is_income_statement_input() {
+            income_statement.root()
+        } else {
+            balance_sheet.root()
+        };
+
+        let mut total = 0f64;
+        for account in accounts {
+            match root.lookup(account) {
+                None => return Err(format!("account {:?} was not found", account)),
+                Some(node) => total += node.amount(),
+            }
+        }
+
+        Ok(total)
+    }
+}
+
+fn subtract(lhs: Result<f64, String>, rhs: Result<f64, String>) -> Result<f64, String> {
+    Ok(lhs? - rhs?)
+}
+
+fn divide(numerator: Result<f64, String>, denominator: Result<f64, String>) -> RatioValue {
+    match (numerator, denominator) {
+        (Err(reason), _) | (_, Err(reason)) => RatioValue::NotComputable(reason),
+        (Ok(_), Ok(0f64)) => RatioValue::NotComputable("the denominator is zero".to_owned()),
+        (Ok(numerator), Ok(denominator)) => RatioValue::Computed(numerator / denominator),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RatioAnalysis, RatioInput, RatioKind, RatioMapping, RatioValue};
+    use crate::journal::balance_sheet::BalanceSheet;
+    use crate::journal::income_statement::IncomeStatement;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+    use chrono::{DateTime, Utc};
+
+    #[test]
+    fn test_ratio_analysis() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let receivables = chart.account("Accounts Receivable");
+        let inventory = chart.account("Inventory");
+        let payables = chart.account("Accounts Payable");
+        let stock = chart.account("Common Stock");
+        let sales = chart.account("Sales Revenue");
+        let cogs = chart.account("Cost of Goods Sold");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 1),
+            "Share capital",
+            &[
+                (&cash, 1_000.0, EntryType::Debit),
+                (&stock, 1_000.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 2, 1),
+            "Inventory on credit",
+            &[
+                (&inventory, 600.0, EntryType::Debit),
+                (&payables, 600.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 3, 1),
+            "Credit sale",
+            &[
+                (&receivables, 732.0, EntryType::Debit),
+                (&sales, 732.0, EntryType::Credit),
+                (&cogs, 300.0, EntryType::Debit),
+                (&inventory, 300.0, EntryType::Credit),
+            ],
+        ));
+
+        let balance_sheet = BalanceSheet::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger.clone(),
+        )
+        .build()
+        .unwrap();
+        let income_statement = IncomeStatement::new(
+            1,
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger,
+        )
+        .build()
+        .unwrap();
+
+        let ratios = RatioAnalysis::default().compute(&balance_sheet, &income_statement);
+
+        // Current assets: 1000 + 732 + 300, current liabilities: 600
+        assert_eq!(
+            ratios.get(RatioKind::CurrentRatio),
+            Some(&RatioValue::Computed(2_032.0 / 600.0))
+        );
+        assert_eq!(
+            ratios.get(RatioKind::QuickRatio).unwrap().value(),
+            Some(1_732.0 / 600.0)
+        );
+        assert_eq!(
+            ratios.get(RatioKind::GrossMargin).unwrap().value(),
+            Some(432.0 / 732.0)
+        );
+        assert_eq!(
+            ratios.get(RatioKind::ReturnOnEquity).unwrap().value(),
+            Some(432.0 / 1_000.0)
+        );
+        assert_eq!(
+            ratios.get(RatioKind::InventoryTurnover).unwrap().value(),
+            Some(1.0)
+        );
+        // 2024 is a leap year
+        assert_eq!(
+            ratios
+                .get_by_name("days sales outstanding")
+                .unwrap()
+                .value(),
+            Some(366.0)
+        );
+
+        let mut mapping = RatioMapping::default();
+        mapping.remove(RatioInput::Inventory);
+        mapping.set(RatioInput::Payables, vec!["Trade Creditors"]);
+
+        let ratios = RatioAnalysis::new(mapping).compute(&balance_sheet, &income_statement);
+        assert!(!ratios.get(RatioKind::QuickRatio).unwrap().is_computable());
+        assert_eq!(
+            ratios.get(RatioKind::DaysPayablesOutstanding),
+            Some(&RatioValue::NotComputable(
+                "account \"Trade Creditors\" was not found".to_owned()
+            ))
+        );
+        assert!(ratios.get(RatioKind::CurrentRatio).unwrap().is_computable());
+        assert!(ratios.to_string().contains("n/a"));
+    }
+
+    #[test]
+    fn test_days_ratios_need_a_bounded_period() {
+        let chart = test_chart();
+        let (receivables, sales) = (
+            chart.account("Accounts Receivable"),
+            chart.account("Sales Revenue"),
+        );
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 3, 1),
+            "Credit sale",
+            &[
+                (&receivables, 500.0, EntryType::Debit),
+                (&sales, 500.0, EntryType::Credit),
+            ],
+        ));
+
+        let balance_sheet = BalanceSheet::new(
+            1,
+            DateTime::<Utc>::MIN_UTC,
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger.clone(),
+        )
+        .build()
+        .unwrap();
+        let income_statement = IncomeStatement::new(
+            1,
+            DateTime::<Utc>::MIN_UTC,
+            date(2024, 12, 31),
+            chart.tree(),
+            ledger,
+        )
+        .build()
+        .unwrap();
+
+        let ratios = RatioAnalysis::default().compute(&balance_sheet, &income_statement);
+        assert_eq!(
+            ratios.get(RatioKind::DaysSalesOutstanding),
+            Some(&RatioValue::NotComputable(
+                "the income statement covers no bounded date range".to_owned()
+            ))
+        );
+        assert!(ratios.get(RatioKind::GrossMargin).unwrap().is_computable());
+    }
+}
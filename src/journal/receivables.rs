@@ -0,0 +1,731 @@
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTree};
+use crate::journal::ledger::{EntryType, JournalEntry, Ledger};
+use crate::journal::subledger::{
+    control_balance, positive, AgingBuckets, AgingReport, AgingRow, Application,
+    ControlReconciliation, SubledgerError,
+};
+use chrono::{DateTime, Utc};
+
+///
+/// `Customer` owing amounts recorded in the `AccountsReceivable` subledger
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Customer {
+    id: usize,
+    name: String,
+}
+
+impl Customer {
+    pub fn new(id: usize, name: &str) -> Self {
+        Customer {
+            id,
+            name: name.to_owned(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+///
+/// `Invoice` billed to a customer
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invoice {
+    id: usize,
+    customer_id: usize,
+    date: DateTime<Utc>,
+    due_date: DateTime<Utc>,
+    amount: f64,
+    description: String,
+}
+
+impl Invoice {
+    pub fn new(
+        id: usize,
+        customer_id: usize,
+        date: DateTime<Utc>,
+        due_date: DateTime<Utc>,
+        amount: f64,
+        description: &str,
+    ) -> Self {
+        Invoice {
+            id,
+            customer_id,
+            date,
+            due_date,
+            amount,
+            description: description.to_owned(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn customer_id(&self) -> usize {
+        self.customer_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn due_date(&self) -> &DateTime<Utc> {
+        &self.due_date
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+///
+/// `CustomerPayment` received from a customer and applied to some of its invoices.
+/// Whatever is not applied stays on the customer's account.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomerPayment {
+    id: usize,
+    customer_id: usize,
+    date: DateTime<Utc>,
+    amount: f64,
+    description: String,
+    applications: Vec<Application>,
+}
+
+impl CustomerPayment {
+    pub fn new(
+        id: usize,
+        customer_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+        description: &str,
+    ) -> Self {
+        CustomerPayment {
+            id,
+            customer_id,
+            date,
+            amount,
+            description: description.to_owned(),
+            applications: Vec::new(),
+        }
+    }
+
+    ///
+    /// Apply `amount` of the payment to the invoice `invoice_id` on the payment date
+    ///
+    pub fn add_application(&mut self, invoice_id: usize, amount: f64) {
+        self.applications
+            .push(Application::new(invoice_id, amount, self.date));
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn customer_id(&self) -> usize {
+        self.customer_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn applications(&self) -> &Vec<Application> {
+        &self.applications
+    }
+
+    ///
+    /// Get the part of the payment not applied to any invoice as of a date
+    ///
+    pub fn unapplied_amount(&self, as_of: DateTime<Utc>) -> f64 {
+        if self.date > as_of {
+            return 0f64;
+        }
+
+        self.amount
+            - self
+                .applications
+                .iter()
+                .filter(|a| a.date() <= &as_of)
+                .map(|a| a.amount())
+                .sum::<f64>()
+    }
+}
+
+///
+/// `CreditNote` reducing what a customer owes, optionally against one of its invoices
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreditNote {
+    id: usize,
+    customer_id: usize,
+    date: DateTime<Utc>,
+    amount: f64,
+    description: String,
+    invoice_id: Option<usize>,
+}
+
+impl CreditNote {
+    pub fn new(
+        id: usize,
+        customer_id: usize,
+        date: DateTime<Utc>,
+        amount: f64,
+        description: &str,
+        invoice_id: Option<usize>,
+    ) -> Self {
+        CreditNote {
+            id,
+            customer_id,
+            date,
+            amount,
+            description: description.to_owned(),
+            invoice_id,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn customer_id(&self) -> usize {
+        self.customer_id
+    }
+
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn invoice_id(&self) -> Option<usize> {
+        self.invoice_id
+    }
+}
+
+///
+/// `AccountsReceivable` subledger tracking the open items of each customer behind
+/// an accounts receivable control account. Posting a document records it and returns
+/// the `JournalEntry` to add to the `Ledger`.
+/// Invoices, payments and credit notes share a single id space.
+///
+#[derive(Debug, Clone)]
+pub struct AccountsReceivable {
+    control_account: AccountNodeRef,
+    customers: Vec<Customer>,
+    invoices: Vec<Invoice>,
+    payments: Vec<CustomerPayment>,
+    credit_notes: Vec<CreditNote>,
+}
+
+impl AccountsReceivable {
+    pub fn new(control_account: AccountNodeRef) -> Self {
+        AccountsReceivable {
+            control_account,
+            customers: Vec::new(),
+            invoices: Vec::new(),
+            payments: Vec::new(),
+            credit_notes: Vec::new(),
+        }
+    }
+
+    pub fn control_account(&self) -> AccountNodeRef {
+        self.control_account.clone()
+    }
+
+    pub fn add_customer(&mut self, customer: Customer) -> Result<(), SubledgerError> {
+        if self.get_customer(customer.id()).is_some() {
+            return Err(SubledgerError::DuplicateParty(customer.id()));
+        }
+
+        self.customers.push(customer);
+        Ok(())
+    }
+
+    pub fn customers(&self) -> &Vec<Customer> {
+        &self.customers
+    }
+
+    pub fn get_customer(&self, id: usize) -> Option<&Customer> {
+        self.customers.iter().find(|c| c.id() == id)
+    }
+
+    pub fn invoices(&self) -> &Vec<Invoice> {
+        &self.invoices
+    }
+
+    pub fn get_invoice(&self, id: usize) -> Option<&Invoice> {
+        self.invoices.iter().find(|i| i.id() == id)
+    }
+
+    pub fn payments(&self) -> &Vec<CustomerPayment> {
+        &self.payments
+    }
+
+    pub fn credit_notes(&self) -> &Vec<CreditNote> {
+        &self.credit_notes
+    }
+
+    fn check_new_document(&self, id: usize, customer_id: usize) -> Result<(), SubledgerError> {
+        if self.get_customer(customer_id).is_none() {
+            return Err(SubledgerError::UnknownParty(customer_id));
+        }
+
+        if self.invoices.iter().any(|i| i.id() == id)
+            || self.payments.iter().any(|p| p.id() == id)
+            || self.credit_notes.iter().any(|c| c.id() == id)
+        {
+            return Err(SubledgerError::DuplicateDocument(id));
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Check that `applications` can be made to the open invoices of `customer_id`
+    ///
+    fn check_applications(
+        &self,
+        customer_id: usize,
+        applications: &[Application],
+    ) -> Result<(), SubledgerError> {
+        for application in applications {
+            positive(application.amount())?;
+
+            let invoice = self
+                .get_invoice(application.document_id())
+                .filter(|invoice| invoice.customer_id() == customer_id)
+                .ok_or(SubledgerError::UnknownDocument(application.document_id()))?;
+            if application.date() < invoice.date() {
+                return Err(SubledgerError::EarlyApplication(invoice.id()));
+            }
+
+            let open_amount = self.open_amount(invoice.id(), DateTime::<Utc>::MAX_UTC);
+            let applied: f64 = applications
+                .iter()
+                .filter(|a| a.document_id() == invoice.id())
+                .map(|a| a.amount())
+                .sum();
+
+            if applied > open_amount + DEFAULT_TOLERANCE {
+                return Err(SubledgerError::OverApplied {
+                    document_id: invoice.id(),
+                    open_amount,
+                    applied,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Record an invoice, debiting the control account and crediting `revenue_account`
+    ///
+    pub fn post_invoice(
+        &mut self,
+        invoice: Invoice,
+        revenue_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, SubledgerError> {
+        self.check_new_document(invoice.id(), invoice.customer_id())?;
+        let amount = positive(invoice.amount())?;
+
+        let journal_entry = JournalEntry::from_lines(
+            ledger.id_sequence(),
+            *invoice.date(),
+            invoice.description(),
+            &[
+                (&self.control_account, amount, EntryType::Debit),
+                (revenue_account, amount, EntryType::Credit),
+            ],
+        );
+
+        self.invoices.push(invoice);
+        Ok(journal_entry)
+    }
+
+    ///
+    /// Record a payment and its applications, debiting `cash_account` and crediting
+    /// the control account
+    ///
+    pub fn post_payment(
+        &mut self,
+        payment: CustomerPayment,
+        cash_account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, SubledgerError> {
+        self.check_new_document(payment.id(), payment.customer_id())?;
+        let amount = positive(payment.amount())?;
+        self.check_applications(payment.customer_id(), payment.applications())?;
+
+        let unapplied_amount = payment.unapplied_amount(DateTime::<Utc>::MAX_UTC);
+        if unapplied_amount < -DEFAULT_TOLERANCE {
+            return Err(SubledgerError::OverApplied {
+                document_id: payment.id(),
+                open_amount: amount,
+                applied: amount - unapplied_amount,
+            });
+        }
+
+        let journal_entry = JournalEntry::from_lines(
+            ledger.id_sequence(),
+            *payment.date(),
+            payment.description(),
+            &[
+                (cash_account, amount, EntryType::Debit),
+                (&self.control_account, amount, EntryType::Credit),
+            ],
+        );
+
+        self.payments.push(payment);
+        Ok(journal_entry)
+    }
+
+    ///
+    /// Apply the unapplied part of an already posted payment to an invoice on `date`.
+    /// The aging as of earlier dates is left as it was.
+    /// No journal entry is needed as the cash already went through the control account.
+    ///
+    pub fn apply_payment(
+        &mut self,
+        payment_id: usize,
+        invoice_id: usize,
+        amount: f64,
+        date: DateTime<Utc>,
+    ) -> Result<(), SubledgerError> {
+        let payment = self
+            .payments
+            .iter()
+            .find(|p| p.id() == payment_id)
+            .ok_or(SubledgerError::UnknownDocument(payment_id))?;
+        if date < *payment.date() {
+            return Err(SubledgerError::EarlyApplication(payment_id));
+        }
+
+        let unapplied_amount = payment.unapplied_amount(DateTime::<Utc>::MAX_UTC);
+        if amount > unapplied_amount + DEFAULT_TOLERANCE {
+            return Err(SubledgerError::OverApplied {
+                document_id: payment_id,
+                open_amount: unapplied_amount,
+                applied: amount,
+            });
+        }
+        let application = Application::new(invoice_id, amount, date);
+        self.check_applications(payment.customer_id(), std::slice::from_ref(&application))?;
+
+        self.payments
+            .iter_mut()
+            .find(|p| p.id() == payment_id)
+            .unwrap()
+            .applications
+            .push(application);
+
+        Ok(())
+    }
+
+    ///
+    /// Record a credit note, debiting `account` (sales returns, allowances, ...) and
+    /// crediting the control account
+    ///
+    pub fn post_credit_note(
+        &mut self,
+        credit_note: CreditNote,
+        account: &AccountNodeRef,
+        ledger: &mut Ledger,
+    ) -> Result<JournalEntry, SubledgerError> {
+        self.check_new_document(credit_note.id(), credit_note.customer_id())?;
+        let amount = positive(credit_note.amount())?;
+        if let Some(invoice_id) = credit_note.invoice_id() {
+            self.check_applications(
+                credit_note.customer_id(),
+                &[Application::new(invoice_id, amount, *credit_note.date())],
+            )?;
+        }
+
+        let journal_entry = JournalEntry::from_lines(
+            ledger.id_sequence(),
+            *credit_note.date(),
+            credit_note.description(),
+            &[
+                (account, amount, EntryType::Debit),
+                (&self.control_account, amount, EntryType::Credit),
+            ],
+        );
+
+        self.credit_notes.push(credit_note);
+        Ok(journal_entry)
+    }
+
+    ///
+    /// Get what is still owed on an invoice from the payments applied and the credit
+    /// notes dated up to `as_of`
+    ///
+    pub fn open_amount(&self, invoice_id: usize, as_of: DateTime<Utc>) -> f64 {
+        let Some(invoice) = self.get_invoice(invoice_id) else {
+            return 0f64;
+        };
+        if invoice.date() > &as_of {
+            return 0f64;
+        }
+
+        let paid: f64 = self
+            .payments
+            .iter()
+            .flat_map(|p| p.applications())
+            .filter(|a| a.document_id() == invoice_id && a.date() <= &as_of)
+            .map(|a| a.amount())
+            .sum();
+        let credited: f64 = self
+            .credit_notes
+            .iter()
+            .filter(|c| c.date() <= &as_of && c.invoice_id() == Some(invoice_id))
+            .map(|c| c.amount())
+            .sum();
+
+        invoice.amount() - paid - credited
+    }
+
+    ///
+    /// Get the invoices of a customer with something still owed as of a date
+    ///
+    pub fn open_invoices(&self, customer_id: usize, as_of: DateTime<Utc>) -> Vec<&Invoice> {
+        self.invoices
+            .iter()
+            .filter(|i| {
+                i.customer_id() == customer_id
+                    && self.open_amount(i.id(), as_of) > DEFAULT_TOLERANCE
+            })
+            .collect()
+    }
+
+    ///
+    /// Get the payments and credit notes of a customer not applied to any invoice
+    /// as of a date
+    ///
+    pub fn unapplied_amount(&self, customer_id: usize, as_of: DateTime<Utc>) -> f64 {
+        let payments: f64 = self
+            .payments
+            .iter()
+            .filter(|p| p.customer_id() == customer_id)
+            .map(|p| p.unapplied_amount(as_of))
+            .sum();
+        let credit_notes: f64 = self
+            .credit_notes
+            .iter()
+            .filter(|c| {
+                c.customer_id() == customer_id && c.date() <= &as_of && c.invoice_id().is_none()
+            })
+            .map(|c| c.amount())
+            .sum();
+
+        payments + credit_notes
+    }
+
+    pub fn customer_balance(&self, customer_id: usize, as_of: DateTime<Utc>) -> f64 {
+        let open: f64 = self
+            .invoices
+            .iter()
+            .filter(|i| i.customer_id() == customer_id)
+            .map(|i| self.open_amount(i.id(), as_of))
+            .sum();
+
+        open - self.unapplied_amount(customer_id, as_of)
+    }
+
+    ///
+    /// Get what all customers owe as of a date
+    ///
+    pub fn balance(&self, as_of: DateTime<Utc>) -> f64 {
+        self.customers
+            .iter()
+            .map(|c| self.customer_balance(c.id(), as_of))
+            .sum()
+    }
+
+    ///
+    /// Age the open invoices by days past their due date
+    ///
+    pub fn aging(&self, as_of: DateTime<Utc>, buckets: &AgingBuckets) -> AgingReport {
+        let rows = self
+            .customers
+            .iter()
+            .map(|customer| {
+                let mut row =
+                    AgingRow::new(customer.id(), customer.name(), buckets.number_of_buckets());
+
+                for invoice in self.open_invoices(customer.id(), as_of) {
+                    let days_past_due = (as_of - *invoice.due_date()).num_days();
+                    row.add(
+                        buckets.bucket(days_past_due),
+                        self.open_amount(invoice.id(), as_of),
+                    );
+                }
+                row.add_unapplied(self.unapplied_amount(customer.id(), as_of));
+
+                row
+            })
+            .collect();
+
+        AgingReport::new(as_of, buckets, rows)
+    }
+
+    ///
+    /// Compare the subledger balance to the control account balance in the `ledger`
+    ///
+    pub fn reconcile(
+        &self,
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        as_of: DateTime<Utc>,
+    ) -> Result<ControlReconciliation, SubledgerError> {
+        Ok(ControlReconciliation::new(
+            as_of,
+            self.balance(as_of),
+            control_balance(accounting_tree, ledger, &self.control_account, as_of)?,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AccountsReceivable, CreditNote, Customer, CustomerPayment, Invoice};
+    use crate::journal::ledger::Ledger;
+    use crate::journal::subledger::{AgingBuckets, SubledgerError};
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_accounts_receivable_aging_reconciles() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let sales = chart.account("Sales Revenue");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        let mut receivables = AccountsReceivable::new(chart.account("Accounts Receivable"));
+        receivables.add_customer(Customer::new(1, "Acme")).unwrap();
+        receivables
+            .add_customer(Customer::new(2, "Globex"))
+            .unwrap();
+        assert_eq!(
+            receivables.add_customer(Customer::new(2, "Initech")),
+            Err(SubledgerError::DuplicateParty(2))
+        );
+
+        for (id, customer_id, day, due, amount) in [
+            (1, 1, date(2024, 1, 1), date(2024, 1, 31), 1_000.0),
+            (2, 1, date(2024, 2, 1), date(2024, 3, 2), 500.0),
+            (3, 2, date(2024, 1, 1), date(2024, 1, 15), 300.0),
+        ] {
+            let invoice = Invoice::new(id, customer_id, day, due, amount, "Goods");
+            let journal_entry = receivables
+                .post_invoice(invoice, &sales, &mut ledger)
+                .unwrap();
+            ledger.add_journal_entry(journal_entry);
+        }
+
+        let mut payment = CustomerPayment::new(4, 1, date(2024, 2, 10), 600.0, "Acme payment");
+        payment.add_application(1, 700.0);
+        assert_eq!(
+            receivables
+                .post_payment(payment.clone(), &cash, &mut ledger)
+                .unwrap_err(),
+            SubledgerError::OverApplied {
+                document_id: 4,
+                open_amount: 600.0,
+                applied: 700.0
+            }
+        );
+
+        let mut payment = CustomerPayment::new(4, 1, date(2024, 2, 10), 600.0, "Acme payment");
+        payment.add_application(1, 600.0);
+        let journal_entry = receivables
+            .post_payment(payment, &cash, &mut ledger)
+            .unwrap();
+        ledger.add_journal_entry(journal_entry);
+
+        let credit_note = CreditNote::new(5, 1, date(2024, 2, 15), 100.0, "Damaged", Some(2));
+        let journal_entry = receivables
+            .post_credit_note(credit_note, &sales, &mut ledger)
+            .unwrap();
+        ledger.add_journal_entry(journal_entry);
+
+        let on_account = CustomerPayment::new(6, 2, date(2024, 3, 1), 50.0, "Globex payment");
+        let journal_entry = receivables
+            .post_payment(on_account, &cash, &mut ledger)
+            .unwrap();
+        ledger.add_journal_entry(journal_entry);
+
+        assert_eq!(receivables.open_amount(1, date(2024, 2, 9)), 1_000.0);
+        assert_eq!(receivables.open_amount(1, date(2024, 4, 20)), 400.0);
+
+        let aging = receivables.aging(date(2024, 4, 20), &AgingBuckets::default());
+        assert_eq!(
+            aging.row(1).unwrap().buckets(),
+            &vec![0.0, 0.0, 400.0, 400.0, 0.0]
+        );
+        assert_eq!(
+            aging.row(2).unwrap().buckets(),
+            &vec![0.0, 0.0, 0.0, 0.0, 300.0]
+        );
+        assert_eq!(aging.row(2).unwrap().total(), 250.0);
+        assert_eq!(aging.total(), 1_050.0);
+
+        let reconciliation = receivables
+            .reconcile(&chart.tree(), &ledger, date(2024, 4, 20))
+            .unwrap();
+        assert_eq!(reconciliation.control_balance(), 1_050.0);
+        assert!(reconciliation.is_reconciled());
+
+        assert_eq!(
+            receivables.apply_payment(6, 3, 50.0, date(2024, 2, 28)),
+            Err(SubledgerError::EarlyApplication(6))
+        );
+        receivables
+            .apply_payment(6, 3, 50.0, date(2024, 4, 21))
+            .unwrap();
+        assert_eq!(receivables.open_amount(3, date(2024, 4, 21)), 250.0);
+        assert_eq!(receivables.unapplied_amount(2, date(2024, 4, 21)), 0.0);
+        assert_eq!(receivables.open_amount(3, date(2024, 4, 20)), 300.0);
+        assert_eq!(receivables.unapplied_amount(2, date(2024, 4, 20)), 50.0);
+        assert_eq!(
+            receivables
+                .aging(date(2024, 4, 20), &AgingBuckets::default())
+                .total(),
+            1_050.0
+        );
+        assert_eq!(
+            receivables.apply_payment(6, 3, 1.0, date(2024, 4, 21)),
+            Err(SubledgerError::OverApplied {
+                document_id: 6,
+                open_amount: 0.0,
+                applied: 1.0
+            })
+        );
+    }
+}
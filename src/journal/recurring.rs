@@ -0,0 +1,633 @@
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTreeNode};
+use crate::journal::ledger::{
+    round_to_cents, EntryType, IdSequence, JournalEntry, Ledger, LedgerError,
+};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc, Weekday};
+use std::error::Error;
+use std::fmt::Display;
+
+///
+/// Errors raised by the `RecurringEntries` generator
+///
+#[derive(Debug, PartialEq)]
+pub enum RecurringError {
+    DuplicateTemplate(usize),
+    UnknownTemplate(usize),
+    ///
+    /// The lines of a template do not balance on an occurrence
+    ///
+    Unbalanced {
+        template_id: usize,
+        date: DateTime<Utc>,
+    },
+    ///
+    /// The ledger rejected an occurrence
+    ///
+    Ledger(LedgerError),
+}
+
+impl Display for RecurringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecurringError::DuplicateTemplate(id) => write!(f, "Template {} already exists.", id),
+            RecurringError::UnknownTemplate(id) => write!(f, "Template {} does not exist.", id),
+            RecurringError::Unbalanced { template_id, date } => write!(
+                f,
+                "Template {} does not balance on {}.",
+                template_id,
+                date.format("%Y-%m-%d")
+            ),
+            RecurringError::Ledger(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for RecurringError {}
+
+impl From<LedgerError> for RecurringError {
+    fn from(error: LedgerError) -> Self {
+        RecurringError::Ledger(error)
+    }
+}
+
+///
+/// When a `RecurringTemplate` falls due
+///
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recurrence {
+    ///
+    /// Every month on the given day, or on the last day of shorter months
+    ///
+    MonthlyOnDay(u32),
+    Weekly(Weekday),
+    EndOfMonth,
+    ///
+    /// An explicit list of dates
+    ///
+    Custom(Vec<DateTime<Utc>>),
+}
+
+///
+/// Get the last day of a month
+///
+fn last_day_of_month(year: i32, month: u32) -> u32 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|first| first.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(28)
+}
+
+impl Recurrence {
+    ///
+    /// Get the dates the recurrence falls on from `from_date` to `to_date`, both included.
+    /// Generated dates keep the time of day of `from_date`.
+    ///
+    pub fn dates(&self, from_date: DateTime<Utc>, to_date: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let mut dates = Vec::new();
+        let time = from_date.time();
+
+        match self {
+            Recurrence::MonthlyOnDay(_) | Recurrence::EndOfMonth => {
+                let (mut year, mut month) = (from_date.year(), from_date.month());
+
+                loop {
+                    let last_day = last_day_of_month(year, month);
+                    let day = match self {
+                        Recurrence::MonthlyOnDay(day) => (*day).clamp(1, last_day),
+                        _ => last_day,
+                    };
+                    let date = Utc.from_utc_datetime(
+                        &NaiveDate::from_ymd_opt(year, month, day)
+                            .unwrap_or_default()
+                            .and_time(time),
+                    );
+                    if date > to_date {
+                        break;
+                    }
+                    if date >= from_date {
+                        dates.push(date);
+                    }
+
+                    (year, month) = if month == 12 {
+                        (year + 1, 1)
+                    } else {
+                        (year, month + 1)
+                    };
+                }
+            }
+            Recurrence::Weekly(weekday) => {
+                let offset = (7 + weekday.num_days_from_monday() as i64
+                    - from_date.weekday().num_days_from_monday() as i64)
+                    % 7;
+                let mut date = from_date + Duration::days(offset);
+
+                while date <= to_date {
+                    dates.push(date);
+                    date += Duration::days(7);
+                }
+            }
+            Recurrence::Custom(custom_dates) => {
+                dates = custom_dates
+                    .iter()
+                    .filter(|date| **date >= from_date && **date <= to_date)
+                    .cloned()
+                    .collect();
+                dates.sort();
+            }
+        }
+
+        dates
+    }
+}
+
+///
+/// How the amount of a template line is computed on each occurrence
+///
+#[derive(Debug, Clone)]
+pub enum AmountFormula {
+    Fixed(f64),
+    ///
+    /// `base` increased by `rate` (`0.03` for 3%) every `every` occurrences,
+    /// compounded; for instance a rent indexed every twelve months
+    ///
+    Escalating {
+        base: f64,
+        rate: f64,
+        every: usize,
+    },
+    ///
+    /// `rate` times the balance of `account` before the occurrence date, including the
+    /// occurrences generated before it but not posted yet
+    ///
+    PercentOfBalance {
+        account: AccountNodeRef,
+        rate: f64,
+    },
+    ///
+    /// Computed from the occurrence number (starting at `0`) and date
+    ///
+    Custom(fn(usize, DateTime<Utc>) -> f64),
+}
+
+impl AmountFormula {
+    ///
+    /// Get the amount of occurrence `occurrence` dated `date`, rounded to cents.
+    /// `pending` holds the journal entries generated for `ledger` but not posted yet.
+    ///
+    pub fn amount(
+        &self,
+        occurrence: usize,
+        date: DateTime<Utc>,
+        ledger: &Ledger,
+        pending: &[JournalEntry],
+    ) -> f64 {
+        let amount = match self {
+            AmountFormula::Fixed(amount) => *amount,
+            AmountFormula::Escalating { base, rate, every } => {
+                let steps = occurrence / (*every).max(1);
+                base * (1f64 + rate).powi(steps as i32)
+            }
+            AmountFormula::PercentOfBalance { account, rate } => {
+                let name = account.borrow().name().to_owned();
+                let balance: f64 = ledger
+                    .active_journal_entries()
+                    .chain(pending.iter())
+                    .filter(|journal_entry| journal_entry.date_of_entry() < date)
+                    .flat_map(|journal_entry| journal_entry.transaction_entries())
+                    .filter(|entry| entry.account_name() == name)
+                    .filter_map(|entry| entry.signed_amount())
+                    .sum();
+
+                balance * rate
+            }
+            AmountFormula::Custom(formula) => formula(occurrence, date),
+        };
+
+        round_to_cents(amount)
+    }
+}
+
+///
+/// A line of a `RecurringTemplate`
+///
+#[derive(Debug, Clone)]
+pub struct TemplateLine {
+    account: AccountNodeRef,
+    entry_type: EntryType,
+    formula: AmountFormula,
+}
+
+impl TemplateLine {
+    pub fn new(account: AccountNodeRef, entry_type: EntryType, formula: AmountFormula) -> Self {
+        TemplateLine {
+            account,
+            entry_type,
+            formula,
+        }
+    }
+
+    pub fn account(&self) -> AccountNodeRef {
+        self.account.clone()
+    }
+
+    pub fn entry_type(&self) -> &EntryType {
+        &self.entry_type
+    }
+
+    pub fn formula(&self) -> &AmountFormula {
+        &self.formula
+    }
+}
+
+///
+/// `RecurringTemplate`: a `JournalEntry` posted on every date of its `Recurrence`
+/// from `start_date` until the optional `end_date`
+///
+#[derive(Debug, Clone)]
+pub struct RecurringTemplate {
+    id: usize,
+    description: String,
+    recurrence: Recurrence,
+    start_date: DateTime<Utc>,
+    end_date: Option<DateTime<Utc>>,
+    lines: Vec<TemplateLine>,
+}
+
+impl RecurringTemplate {
+    pub fn new(
+        id: usize,
+        description: &str,
+        recurrence: Recurrence,
+        start_date: DateTime<Utc>,
+    ) -> Self {
+        RecurringTemplate {
+            id,
+            description: description.to_owned(),
+            recurrence,
+            start_date,
+            end_date: None,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn recurrence(&self) -> &Recurrence {
+        &self.recurrence
+    }
+
+    pub fn start_date(&self) -> &DateTime<Utc> {
+        &self.start_date
+    }
+
+    pub fn end_date(&self) -> Option<&DateTime<Utc>> {
+        self.end_date.as_ref()
+    }
+
+    pub fn set_end_date(&mut self, end_date: Option<DateTime<Utc>>) {
+        self.end_date = end_date;
+    }
+
+    pub fn lines(&self) -> &Vec<TemplateLine> {
+        &self.lines
+    }
+
+    pub fn add_line(
+        &mut self,
+        account: &AccountNodeRef,
+        entry_type: EntryType,
+        formula: AmountFormula,
+    ) {
+        self.lines
+            .push(TemplateLine::new(account.clone(), entry_type, formula));
+    }
+
+    ///
+    /// Get the description of the journal entries generated from the template
+    ///
+    pub fn entry_description(&self) -> String {
+        format!("{} [recurring {}]", self.description, self.id)
+    }
+
+    ///
+    /// Get the idempotency key of the occurrence dated `date`, which keeps it from
+    /// being posted twice
+    ///
+    pub fn idempotency_key(&self, date: DateTime<Utc>) -> String {
+        format!("recurring:{}:{}", self.id, date.format("%Y-%m-%d"))
+    }
+
+    ///
+    /// Get the dates the template falls due on up to `through`
+    ///
+    pub fn occurrences(&self, through: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        let to_date = match self.end_date {
+            Some(end_date) if end_date < through => end_date,
+            _ => through,
+        };
+
+        self.recurrence.dates(self.start_date, to_date)
+    }
+
+    ///
+    /// Build the `JournalEntry` of occurrence `occurrence` dated `date`, given the
+    /// journal entries generated for `ledger` but not posted yet.
+    /// Lines whose amount is zero are left out.
+    ///
+    pub fn journal_entry(
+        &self,
+        occurrence: usize,
+        date: DateTime<Utc>,
+        ledger: &Ledger,
+        pending: &[JournalEntry],
+        ids: &mut IdSequence,
+    ) -> Result<JournalEntry, RecurringError> {
+        let amounts: Vec<(&AccountNodeRef, f64, EntryType)> = self
+            .lines
+            .iter()
+            .map(|line| {
+                (
+                    &line.account,
+                    line.formula.amount(occurrence, date, ledger, pending),
+                    line.entry_type,
+                )
+            })
+            .filter(|(_, amount, _)| *amount != 0f64)
+            .collect();
+
+        let balance: f64 = amounts
+            .iter()
+            .map(|(_, amount, entry_type)| match entry_type {
+                EntryType::Debit => *amount,
+                EntryType::Credit => -amount,
+            })
+            .sum();
+        if amounts.is_empty() || round_to_cents(balance) != 0f64 {
+            return Err(RecurringError::Unbalanced {
+                template_id: self.id,
+                date,
+            });
+        }
+
+        let mut journal_entry =
+            JournalEntry::from_lines(ids, date, &self.entry_description(), &amounts);
+        journal_entry.set_idempotency_key(Some(&self.idempotency_key(date)));
+
+        Ok(journal_entry)
+    }
+}
+
+///
+/// `RecurringEntries` holds the recurring templates and materializes their due
+/// occurrences into a `Ledger`
+///
+#[derive(Debug, Clone, Default)]
+pub struct RecurringEntries {
+    templates: Vec<RecurringTemplate>,
+}
+
+impl RecurringEntries {
+    pub fn new() -> Self {
+        RecurringEntries::default()
+    }
+
+    pub fn add_template(&mut self, template: RecurringTemplate) -> Result<(), RecurringError> {
+        if self.get_template(template.id).is_some() {
+            return Err(RecurringError::DuplicateTemplate(template.id));
+        }
+
+        self.templates.push(template);
+
+        Ok(())
+    }
+
+    pub fn remove_template(&mut self, id: usize) -> Result<RecurringTemplate, RecurringError> {
+        let index = self
+            .templates
+            .iter()
+            .position(|template| template.id == id)
+            .ok_or(RecurringError::UnknownTemplate(id))?;
+
+        Ok(self.templates.remove(index))
+    }
+
+    pub fn templates(&self) -> &Vec<RecurringTemplate> {
+        &self.templates
+    }
+
+    pub fn get_template(&self, id: usize) -> Option<&RecurringTemplate> {
+        self.templates.iter().find(|template| template.id == id)
+    }
+
+    pub fn get_template_mut(&mut self, id: usize) -> Option<&mut RecurringTemplate> {
+        self.templates.iter_mut().find(|template| template.id == id)
+    }
+
+    ///
+    /// Post to `ledger` every occurrence due up to `through` that it does not hold yet.
+    /// Occurrences outside the dates of the ledger are left for the ledger covering them.
+    /// An occurrence is already posted when the ledger holds its idempotency key, so
+    /// running the generator again never posts twice.
+    /// The occurrences are built in date order, each seeing the ones before it, and
+    /// nothing is posted if one does not balance.
+    /// Returns the ids the ledger gave the journal entries added.
+    ///
+    pub fn generate(
+        &self,
+        ledger: &mut Ledger,
+        through: DateTime<Utc>,
+    ) -> Result<Vec<usize>, RecurringError> {
+        let (from_date, to_date) = (*ledger.from_date(), (*ledger.to_date()).min(through));
+
+        let mut due: Vec<(DateTime<Utc>, &RecurringTemplate, usize)> = Vec::new();
+        for template in self.templates.iter() {
+            for (occurrence, date) in template.occurrences(to_date).into_iter().enumerate() {
+                if date >= from_date {
+                    due.push((date, template, occurrence));
+                }
+            }
+        }
+        due.sort_by_key(|(date, _, _)| *date);
+
+        let mut journal_entries: Vec<JournalEntry> = Vec::new();
+        for (date, template, occurrence) in due {
+            let key = template.idempotency_key(date);
+            let posted = ledger.get_journal_entry_by_idempotency_key(&key).is_some()
+                || journal_entries
+                    .iter()
+                    .any(|journal_entry| journal_entry.idempotency_key() == Some(key.as_str()));
+            if !posted {
+                let journal_entry = template.journal_entry(
+                    occurrence,
+                    date,
+                    ledger,
+                    &journal_entries,
+                    &mut IdSequence::new(1),
+                )?;
+                journal_entries.push(journal_entry);
+            }
+        }
+
+        let mut posted_ids = Vec::new();
+        for journal_entry in journal_entries {
+            posted_ids.push(ledger.post_new_journal_entry(journal_entry)?.id());
+        }
+
+        Ok(posted_ids)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AmountFormula, Recurrence, RecurringEntries, RecurringError, RecurringTemplate};
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+    use chrono::Weekday;
+
+    #[test]
+    fn test_recurrence_dates() {
+        let from = date(2024, 1, 15);
+        let to = date(2024, 4, 30);
+
+        assert_eq!(
+            Recurrence::MonthlyOnDay(31).dates(from, to),
+            vec![
+                date(2024, 1, 31),
+                date(2024, 2, 29),
+                date(2024, 3, 31),
+                date(2024, 4, 30)
+            ]
+        );
+        assert_eq!(
+            Recurrence::MonthlyOnDay(10).dates(from, to)[0],
+            date(2024, 2, 10)
+        );
+        assert_eq!(Recurrence::EndOfMonth.dates(from, to).len(), 4);
+
+        // 2024-01-15 is a Monday
+        let fridays = Recurrence::Weekly(Weekday::Fri).dates(from, date(2024, 2, 2));
+        assert_eq!(
+            fridays,
+            vec![date(2024, 1, 19), date(2024, 1, 26), date(2024, 2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_generate_is_idempotent() {
+        let chart = test_chart();
+        let mut rent = RecurringTemplate::new(
+            1,
+            "Office rent",
+            Recurrence::MonthlyOnDay(1),
+            date(2024, 1, 1),
+        );
+        rent.set_end_date(Some(date(2025, 12, 31)));
+        let formula = AmountFormula::Escalating {
+            base: 1_000.0,
+            rate: 0.05,
+            every: 12,
+        };
+        rent.add_line(
+            &chart.account("Rent Expense"),
+            EntryType::Debit,
+            formula.clone(),
+        );
+        rent.add_line(&chart.account("Cash"), EntryType::Credit, formula);
+
+        let mut recurring = RecurringEntries::new();
+        recurring.add_template(rent).unwrap();
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2025, 12, 31));
+
+        let posted = recurring.generate(&mut ledger, date(2024, 3, 15)).unwrap();
+        assert_eq!(posted.len(), 3);
+
+        let posted = recurring.generate(&mut ledger, date(2024, 3, 31)).unwrap();
+        assert!(posted.is_empty());
+
+        recurring.generate(&mut ledger, date(2026, 6, 30)).unwrap();
+        assert_eq!(ledger.number_of_journal_entries(), 24);
+        assert!(ledger
+            .get_journal_entry_by_idempotency_key("recurring:1:2025-12-01")
+            .is_some());
+
+        let january_2025 = ledger.get_journal_entries_by_date(date(2025, 1, 1));
+        assert_eq!(january_2025[0].total_debit(), 1_050.0);
+
+        let mut unbalanced =
+            RecurringTemplate::new(2, "Unbalanced", Recurrence::EndOfMonth, date(2024, 1, 1));
+        unbalanced.add_line(
+            &chart.account("Rent Expense"),
+            EntryType::Debit,
+            AmountFormula::Fixed(10.0),
+        );
+        recurring.add_template(unbalanced).unwrap();
+        assert_eq!(
+            recurring.generate(&mut ledger, date(2024, 1, 31)),
+            Err(RecurringError::Unbalanced {
+                template_id: 2,
+                date: date(2024, 1, 31)
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_within_the_ledger_dates_on_pending_balances() {
+        let chart = test_chart();
+        let (cash, accrued) = (chart.account("Cash"), chart.account("Accrued Liabilities"));
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 1),
+            "Borrowing",
+            &[
+                (&cash, 1_000.0, EntryType::Debit),
+                (&accrued, 1_000.0, EntryType::Credit),
+            ],
+        ));
+
+        let mut interest = RecurringTemplate::new(
+            1,
+            "Compound interest",
+            Recurrence::EndOfMonth,
+            date(2023, 11, 1),
+        );
+        let formula = AmountFormula::PercentOfBalance {
+            account: accrued.clone(),
+            rate: 0.01,
+        };
+        interest.add_line(
+            &chart.account("Interest Expense"),
+            EntryType::Debit,
+            formula.clone(),
+        );
+        interest.add_line(&accrued, EntryType::Credit, formula);
+
+        let mut recurring = RecurringEntries::new();
+        recurring.add_template(interest).unwrap();
+
+        let posted = recurring.generate(&mut ledger, date(2024, 3, 31)).unwrap();
+        let amounts: Vec<f64> = posted
+            .iter()
+            .map(|id| ledger.get_journal_entry_by_id(*id).unwrap().total_debit())
+            .collect();
+        assert_eq!(amounts, vec![10.0, 10.1, 10.2]);
+
+        let posted = recurring.generate(&mut ledger, date(2025, 3, 1)).unwrap();
+        assert_eq!(posted.len(), 9);
+        assert_eq!(ledger.number_of_journal_entries(), 13);
+    }
+}
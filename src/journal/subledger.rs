@@ -0,0 +1,439 @@
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTree, AccountTreeNode};
+use crate::journal::balance_sheet::{build_subtotals, BalanceSheetError};
+use crate::journal::ledger::{Ledger, TransactionEntry};
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// Errors raised by the receivables and payables subledgers
+///
+#[derive(Debug, PartialEq)]
+pub enum SubledgerError {
+    ///
+    /// A customer or vendor with the same id already exists
+    ///
+    DuplicateParty(usize),
+    ///
+    /// No customer or vendor has the id
+    ///
+    UnknownParty(usize),
+    ///
+    /// An invoice, bill, payment or credit note with the same id already exists
+    ///
+    DuplicateDocument(usize),
+    ///
+    /// No open invoice or bill of the party has the id
+    ///
+    UnknownDocument(usize),
+    ///
+    /// More than the open amount of a document was applied to it
+    ///
+    OverApplied {
+        document_id: usize,
+        open_amount: f64,
+        applied: f64,
+    },
+    ///
+    /// An amount was applied to a document before the document or the payment was dated
+    ///
+    EarlyApplication(usize),
+    ///
+    /// Amounts must be positive
+    ///
+    InvalidAmount(f64),
+    ///
+    /// An account needed to post a document was not set
+    ///
+    MissingAccount(String),
+    ///
+    /// The control account balance could not be computed
+    ///
+    Statement(BalanceSheetError),
+}
+
+impl Display for SubledgerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SubledgerError::DuplicateParty(id) => write!(f, "Party {} already exists.", id),
+            SubledgerError::UnknownParty(id) => write!(f, "Party {} does not exist.", id),
+            SubledgerError::DuplicateDocument(id) => write!(f, "Document {} already exists.", id),
+            SubledgerError::UnknownDocument(id) => {
+                write!(f, "Document {} is not an open document of the party.", id)
+            }
+            SubledgerError::OverApplied {
+                document_id,
+                open_amount,
+                applied,
+            } => write!(
+                f,
+                "Cannot apply {:.2} to document {} with an open amount of {:.2}.",
+                applied, document_id, open_amount
+            ),
+            SubledgerError::EarlyApplication(id) => write!(
+                f,
+                "Cannot apply an amount to document {} before it is dated.",
+                id
+            ),
+            SubledgerError::InvalidAmount(amount) => {
+                write!(f, "Amount {:.2} must be positive.", amount)
+            }
+            SubledgerError::MissingAccount(account) => {
+                write!(f, "No {} account is set.", account)
+            }
+            SubledgerError::Statement(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for SubledgerError {}
+
+impl From<BalanceSheetError> for SubledgerError {
+    fn from(error: BalanceSheetError) -> Self {
+        SubledgerError::Statement(error)
+    }
+}
+
+///
+/// Check that an amount is positive
+///
+pub(crate) fn positive(amount: f64) -> Result<f64, SubledgerError> {
+    if amount > 0f64 {
+        Ok(amount)
+    } else {
+        Err(SubledgerError::InvalidAmount(amount))
+    }
+}
+
+///
+/// An amount applied to an invoice or a bill on a date
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct Application {
+    document_id: usize,
+    amount: f64,
+    date: DateTime<Utc>,
+}
+
+impl Application {
+    pub fn new(document_id: usize, amount: f64, date: DateTime<Utc>) -> Self {
+        Application {
+            document_id,
+            amount,
+            date,
+        }
+    }
+
+    pub fn document_id(&self) -> usize {
+        self.document_id
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    ///
+    /// Get the date the amount was applied, from which it reduces the open amount
+    ///
+    pub fn date(&self) -> &DateTime<Utc> {
+        &self.date
+    }
+}
+
+///
+/// `AgingBuckets` group open items by how many days past due they are.
+/// The boundaries are the last day of each bucket; a final bucket holds
+/// everything older than the last boundary.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgingBuckets {
+    boundaries: Vec<i64>,
+}
+
+impl Default for AgingBuckets {
+    ///
+    /// Current, 1-30, 31-60, 61-90 and 90+ days past due
+    ///
+    fn default() -> Self {
+        AgingBuckets::new(vec![0, 30, 60, 90])
+    }
+}
+
+impl AgingBuckets {
+    pub fn new(mut boundaries: Vec<i64>) -> Self {
+        boundaries.sort();
+        boundaries.dedup();
+
+        AgingBuckets { boundaries }
+    }
+
+    pub fn boundaries(&self) -> &Vec<i64> {
+        &self.boundaries
+    }
+
+    pub fn number_of_buckets(&self) -> usize {
+        self.boundaries.len() + 1
+    }
+
+    ///
+    /// Get the bucket of an item `days_past_due` days past its due date
+    ///
+    pub fn bucket(&self, days_past_due: i64) -> usize {
+        self.boundaries
+            .iter()
+            .position(|boundary| days_past_due <= *boundary)
+            .unwrap_or(self.boundaries.len())
+    }
+
+    pub fn labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        let mut previous: Option<i64> = None;
+
+        for boundary in self.boundaries.iter() {
+            labels.push(match previous {
+                None if *boundary <= 0 => "Current".to_owned(),
+                None => format!("0-{}", boundary),
+                Some(previous) => format!("{}-{}", previous + 1, boundary),
+            });
+            previous = Some(*boundary);
+        }
+        labels.push(format!("{}+", previous.unwrap_or(0)));
+
+        labels
+    }
+}
+
+///
+/// A customer's or vendor's line of an `AgingReport`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgingRow {
+    party_id: usize,
+    party_name: String,
+    buckets: Vec<f64>,
+    unapplied: f64,
+}
+
+impl AgingRow {
+    pub(crate) fn new(party_id: usize, party_name: &str, buckets: usize) -> Self {
+        AgingRow {
+            party_id,
+            party_name: party_name.to_owned(),
+            buckets: vec![0f64; buckets],
+            unapplied: 0f64,
+        }
+    }
+
+    pub(crate) fn add(&mut self, bucket: usize, amount: f64) {
+        self.buckets[bucket] += amount;
+    }
+
+    pub(crate) fn add_unapplied(&mut self, amount: f64) {
+        self.unapplied += amount;
+    }
+
+    pub fn party_id(&self) -> usize {
+        self.party_id
+    }
+
+    pub fn party_name(&self) -> &str {
+        &self.party_name
+    }
+
+    pub fn buckets(&self) -> &Vec<f64> {
+        &self.buckets
+    }
+
+    ///
+    /// Get the payments and credits not applied to any document, as a positive amount
+    ///
+    pub fn unapplied(&self) -> f64 {
+        self.unapplied
+    }
+
+    ///
+    /// Get the open documents less the unapplied amounts
+    ///
+    pub fn total(&self) -> f64 {
+        self.buckets.iter().sum::<f64>() - self.unapplied
+    }
+}
+
+///
+/// `AgingReport` of the open items of a subledger as of a date
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgingReport {
+    as_of: DateTime<Utc>,
+    labels: Vec<String>,
+    rows: Vec<AgingRow>,
+}
+
+impl AgingReport {
+    pub(crate) fn new(as_of: DateTime<Utc>, buckets: &AgingBuckets, rows: Vec<AgingRow>) -> Self {
+        AgingReport {
+            as_of,
+            labels: buckets.labels(),
+            rows,
+        }
+    }
+
+    pub fn as_of(&self) -> &DateTime<Utc> {
+        &self.as_of
+    }
+
+    pub fn labels(&self) -> &Vec<String> {
+        &self.labels
+    }
+
+    pub fn rows(&self) -> &Vec<AgingRow> {
+        &self.rows
+    }
+
+    pub fn row(&self, party_id: usize) -> Option<&AgingRow> {
+        self.rows.iter().find(|row| row.party_id == party_id)
+    }
+
+    ///
+    /// Get the total of each bucket over all parties
+    ///
+    pub fn bucket_totals(&self) -> Vec<f64> {
+        (0..self.labels.len())
+            .map(|bucket| self.rows.iter().map(|row| row.buckets[bucket]).sum())
+            .collect()
+    }
+
+    pub fn unapplied(&self) -> f64 {
+        self.rows.iter().map(|row| row.unapplied).sum()
+    }
+
+    pub fn total(&self) -> f64 {
+        self.rows.iter().map(|row| row.total()).sum()
+    }
+}
+
+impl Display for AgingReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Aging as of {}", self.as_of.format("%Y-%m-%d"))?;
+
+        write!(f, "{:<25}", "")?;
+        for label in self.labels.iter() {
+            write!(f, "{:>12}", label)?;
+        }
+        writeln!(f, "{:>12}{:>12}", "Unapplied", "Total")?;
+
+        let write_row = |f: &mut std::fmt::Formatter<'_>,
+                         name: &str,
+                         buckets: &[f64],
+                         unapplied: f64,
+                         total| {
+            write!(f, "{:<25}", name)?;
+            for amount in buckets {
+                write!(f, "{:>12.2}", amount)?;
+            }
+            writeln!(f, "{:>12.2}{:>12.2}", -unapplied, total)
+        };
+
+        for row in self.rows.iter() {
+            write_row(f, &row.party_name, &row.buckets, row.unapplied, row.total())?;
+        }
+        write_row(
+            f,
+            "Total",
+            &self.bucket_totals(),
+            self.unapplied(),
+            self.total(),
+        )
+    }
+}
+
+///
+/// Comparison of a subledger's total to the balance of its control account
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlReconciliation {
+    as_of: DateTime<Utc>,
+    subledger_balance: f64,
+    control_balance: f64,
+}
+
+impl ControlReconciliation {
+    pub(crate) fn new(as_of: DateTime<Utc>, subledger_balance: f64, control_balance: f64) -> Self {
+        ControlReconciliation {
+            as_of,
+            subledger_balance,
+            control_balance,
+        }
+    }
+
+    pub fn as_of(&self) -> &DateTime<Utc> {
+        &self.as_of
+    }
+
+    pub fn subledger_balance(&self) -> f64 {
+        self.subledger_balance
+    }
+
+    pub fn control_balance(&self) -> f64 {
+        self.control_balance
+    }
+
+    ///
+    /// Get the control account balance less the subledger total
+    ///
+    pub fn difference(&self) -> f64 {
+        self.control_balance - self.subledger_balance
+    }
+
+    pub fn is_reconciled(&self) -> bool {
+        self.difference().abs() <= DEFAULT_TOLERANCE
+    }
+}
+
+///
+/// Get the balance of `control_account` from the journal entries of `ledger`
+/// dated up to `as_of`
+///
+pub fn control_balance(
+    accounting_tree: &AccountTree,
+    ledger: &Ledger,
+    control_account: &AccountNodeRef,
+    as_of: DateTime<Utc>,
+) -> Result<f64, SubledgerError> {
+    let transaction_entries: Vec<&Rc<TransactionEntry>> = ledger
+        .active_journal_entries()
+        .filter(|journal_entry| journal_entry.date_of_entry() <= as_of)
+        .flat_map(|journal_entry| journal_entry.transaction_entries())
+        .collect();
+
+    let root = build_subtotals(accounting_tree, transaction_entries)?;
+    let account_name = control_account.borrow().name().to_owned();
+
+    root.find(&account_name)
+        .map(|node| node.amount())
+        .ok_or(SubledgerError::Statement(
+            BalanceSheetError::UnknownAccount(account_name),
+        ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::AgingBuckets;
+
+    #[test]
+    fn test_aging_buckets() {
+        let buckets = AgingBuckets::default();
+
+        assert_eq!(
+            buckets.labels(),
+            vec!["Current", "1-30", "31-60", "61-90", "90+"]
+        );
+        assert_eq!(buckets.bucket(-5), 0);
+        assert_eq!(buckets.bucket(0), 0);
+        assert_eq!(buckets.bucket(30), 1);
+        assert_eq!(buckets.bucket(31), 2);
+        assert_eq!(buckets.bucket(91), 4);
+    }
+}
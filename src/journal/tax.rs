@@ -0,0 +1,689 @@
+use crate::journal::accounting_tree::{AccountNodeRef, AccountTreeNode};
+use crate::journal::ledger::{round_to_cents, EntryType, JournalEntry, Ledger, TransactionEntry};
+use crate::journal::period::ReportingPeriod;
+use chrono::{DateTime, Utc};
+use std::error::Error;
+use std::fmt::Display;
+use std::rc::Rc;
+
+///
+/// Errors raised by the `TaxEngine`
+///
+#[derive(Debug, PartialEq)]
+pub enum TaxError {
+    DuplicateTaxCode(String),
+    UnknownTaxCode(String),
+    ///
+    /// The tax code has no rate in effect on the date
+    ///
+    NoRate {
+        code: String,
+        date: DateTime<Utc>,
+    },
+}
+
+impl Display for TaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaxError::DuplicateTaxCode(code) => write!(f, "Tax code {} already exists.", code),
+            TaxError::UnknownTaxCode(code) => write!(f, "Tax code {} does not exist.", code),
+            TaxError::NoRate { code, date } => write!(
+                f,
+                "Tax code {} has no rate in effect on {}.",
+                code,
+                date.format("%Y-%m-%d")
+            ),
+        }
+    }
+}
+
+impl Error for TaxError {}
+
+///
+/// Whether a tax code applies to sales or to purchases
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TaxDirection {
+    ///
+    /// Tax collected on sales, owed to the tax authority
+    ///
+    Output,
+    ///
+    /// Tax paid on purchases, recoverable from the tax authority
+    ///
+    Input,
+}
+
+///
+/// Whether the amounts of taxed lines include the tax
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pricing {
+    Exclusive,
+    Inclusive,
+}
+
+///
+/// A rate (`0.2` for 20%) in effect from `effective_from`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxRate {
+    rate: f64,
+    effective_from: DateTime<Utc>,
+}
+
+impl TaxRate {
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn effective_from(&self) -> &DateTime<Utc> {
+        &self.effective_from
+    }
+}
+
+///
+/// `TaxCode` and the history of its rates
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxCode {
+    code: String,
+    name: String,
+    direction: TaxDirection,
+    rates: Vec<TaxRate>,
+}
+
+impl TaxCode {
+    pub fn new(code: &str, name: &str, direction: TaxDirection) -> Self {
+        TaxCode {
+            code: code.to_owned(),
+            name: name.to_owned(),
+            direction,
+            rates: Vec::new(),
+        }
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn direction(&self) -> TaxDirection {
+        self.direction
+    }
+
+    pub fn rates(&self) -> &Vec<TaxRate> {
+        &self.rates
+    }
+
+    ///
+    /// Add a rate in effect from `effective_from` until the next rate
+    ///
+    pub fn add_rate(&mut self, rate: f64, effective_from: DateTime<Utc>) {
+        self.rates.push(TaxRate {
+            rate,
+            effective_from,
+        });
+        self.rates.sort_by_key(|rate| rate.effective_from);
+    }
+
+    ///
+    /// Get the rate in effect on `date`
+    ///
+    pub fn rate_on(&self, date: DateTime<Utc>) -> Option<f64> {
+        self.rates
+            .iter()
+            .rev()
+            .find(|rate| rate.effective_from <= date)
+            .map(|rate| rate.rate)
+    }
+}
+
+///
+/// Split an amount into its taxable base and its tax
+///
+pub fn split_tax(amount: f64, rate: f64, pricing: Pricing) -> (f64, f64) {
+    match pricing {
+        Pricing::Exclusive => (round_to_cents(amount), round_to_cents(amount * rate)),
+        Pricing::Inclusive => {
+            let base = round_to_cents(amount / (1f64 + rate));
+            (base, round_to_cents(amount - base))
+        }
+    }
+}
+
+///
+/// A revenue or expense line of a taxed sale or purchase
+///
+#[derive(Debug, Clone)]
+pub struct TaxedLine {
+    account: AccountNodeRef,
+    amount: f64,
+    entry_type: EntryType,
+    tax_code: Option<String>,
+}
+
+impl TaxedLine {
+    pub fn new(
+        account: &AccountNodeRef,
+        amount: f64,
+        entry_type: EntryType,
+        tax_code: Option<&str>,
+    ) -> Self {
+        TaxedLine {
+            account: account.clone(),
+            amount,
+            entry_type,
+            tax_code: tax_code.map(|code| code.to_owned()),
+        }
+    }
+
+    pub fn account(&self) -> AccountNodeRef {
+        self.account.clone()
+    }
+
+    pub fn amount(&self) -> f64 {
+        self.amount
+    }
+
+    pub fn entry_type(&self) -> &EntryType {
+        &self.entry_type
+    }
+
+    pub fn tax_code(&self) -> Option<&str> {
+        self.tax_code.as_deref()
+    }
+}
+
+///
+/// `TaxEngine` holds the tax codes and splits the tax out of taxed sales and
+/// purchases into the output tax (liability) and input tax (asset) accounts
+///
+#[derive(Debug, Clone)]
+pub struct TaxEngine {
+    tax_codes: Vec<TaxCode>,
+    output_tax_account: AccountNodeRef,
+    input_tax_account: AccountNodeRef,
+}
+
+impl TaxEngine {
+    pub fn new(output_tax_account: AccountNodeRef, input_tax_account: AccountNodeRef) -> Self {
+        TaxEngine {
+            tax_codes: Vec::new(),
+            output_tax_account,
+            input_tax_account,
+        }
+    }
+
+    pub fn output_tax_account(&self) -> AccountNodeRef {
+        self.output_tax_account.clone()
+    }
+
+    pub fn input_tax_account(&self) -> AccountNodeRef {
+        self.input_tax_account.clone()
+    }
+
+    pub fn add_tax_code(&mut self, tax_code: TaxCode) -> Result<(), TaxError> {
+        if self.get_tax_code(&tax_code.code).is_some() {
+            return Err(TaxError::DuplicateTaxCode(tax_code.code));
+        }
+
+        self.tax_codes.push(tax_code);
+
+        Ok(())
+    }
+
+    pub fn tax_codes(&self) -> &Vec<TaxCode> {
+        &self.tax_codes
+    }
+
+    pub fn get_tax_code(&self, code: &str) -> Option<&TaxCode> {
+        self.tax_codes.iter().find(|tax_code| tax_code.code == code)
+    }
+
+    pub fn get_tax_code_mut(&mut self, code: &str) -> Option<&mut TaxCode> {
+        self.tax_codes
+            .iter_mut()
+            .find(|tax_code| tax_code.code == code)
+    }
+
+    fn tax_account(&self, direction: TaxDirection) -> &AccountNodeRef {
+        match direction {
+            TaxDirection::Output => &self.output_tax_account,
+            TaxDirection::Input => &self.input_tax_account,
+        }
+    }
+
+    ///
+    /// Build the `JournalEntry` of a taxed sale or purchase. Each line with a tax code
+    /// is followed by its tax on the output or input tax account, on the same side, and
+    /// `counter_account` (receivable, payable or cash) takes the balance. Sales credit
+    /// revenue; crediting a purchase line or debiting a sales line records a return.
+    ///
+    pub fn journal_entry(
+        &self,
+        ledger: &mut Ledger,
+        date_of_entry: DateTime<Utc>,
+        description: &str,
+        counter_account: &AccountNodeRef,
+        lines: &[TaxedLine],
+        pricing: Pricing,
+    ) -> Result<JournalEntry, TaxError> {
+        // (account, amount, entry type, tax code)
+        let mut postings: Vec<(&AccountNodeRef, f64, EntryType, Option<&str>)> = Vec::new();
+
+        for line in lines {
+            match line.tax_code.as_deref() {
+                None => postings.push((
+                    &line.account,
+                    round_to_cents(line.amount),
+                    line.entry_type,
+                    None,
+                )),
+                Some(code) => {
+                    let tax_code = self
+                        .get_tax_code(code)
+                        .ok_or(TaxError::UnknownTaxCode(code.to_owned()))?;
+                    let rate = tax_code.rate_on(date_of_entry).ok_or(TaxError::NoRate {
+                        code: code.to_owned(),
+                        date: date_of_entry,
+                    })?;
+                    let (base, tax) = split_tax(line.amount, rate, pricing);
+
+                    postings.push((&line.account, base, line.entry_type, Some(code)));
+                    if tax != 0f64 {
+                        postings.push((
+                            self.tax_account(tax_code.direction),
+                            tax,
+                            line.entry_type,
+                            Some(code),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Every amount is in cents, so summing whole cents balances the entry exactly
+        let balance_in_cents: i64 = postings
+            .iter()
+            .map(|(_, amount, entry_type, _)| {
+                let cents = (amount * 100f64).round() as i64;
+                match entry_type {
+                    EntryType::Debit => cents,
+                    EntryType::Credit => -cents,
+                }
+            })
+            .sum();
+        let balance = balance_in_cents as f64 / 100f64;
+        if balance > 0f64 {
+            postings.push((counter_account, balance, EntryType::Credit, None));
+        } else if balance < 0f64 {
+            postings.push((counter_account, -balance, EntryType::Debit, None));
+        }
+
+        let ids = ledger.id_sequence();
+        let mut journal_entry = JournalEntry::new(ids.next_id(), date_of_entry, description);
+        for (account, amount, entry_type, tax_code) in postings {
+            let mut transaction_entry = TransactionEntry::new(
+                ids.next_id(),
+                account.clone(),
+                amount,
+                entry_type,
+                date_of_entry,
+                description,
+            );
+            transaction_entry.set_tax_code(tax_code);
+            journal_entry.add_transaction_entry(Rc::new(transaction_entry));
+        }
+
+        Ok(journal_entry)
+    }
+}
+
+///
+/// A tax code's line of a `TaxReturn`
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxReturnLine {
+    code: String,
+    direction: TaxDirection,
+    taxable_base: f64,
+    tax: f64,
+    journal_entry_ids: Vec<usize>,
+}
+
+impl TaxReturnLine {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn direction(&self) -> TaxDirection {
+        self.direction
+    }
+
+    ///
+    /// Get the taxable base, net of returns
+    ///
+    pub fn taxable_base(&self) -> f64 {
+        round_to_cents(self.taxable_base)
+    }
+
+    pub fn tax(&self) -> f64 {
+        round_to_cents(self.tax)
+    }
+
+    ///
+    /// Get the ids of the journal entries behind the line
+    ///
+    pub fn journal_entry_ids(&self) -> &Vec<usize> {
+        &self.journal_entry_ids
+    }
+}
+
+///
+/// `TaxReturn` summarizes the taxable bases and the tax of each tax code over a period
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxReturn {
+    period: ReportingPeriod,
+    lines: Vec<TaxReturnLine>,
+}
+
+impl TaxReturn {
+    ///
+    /// Build the tax return of the journal entries of `ledger` within `period`
+    /// from the transaction entries that carry a tax code of `tax_engine`
+    ///
+    pub fn build(
+        tax_engine: &TaxEngine,
+        ledger: &Ledger,
+        period: &ReportingPeriod,
+    ) -> Result<Self, TaxError> {
+        let output_account = tax_engine.output_tax_account.borrow().name().to_owned();
+        let input_account = tax_engine.input_tax_account.borrow().name().to_owned();
+        let mut lines: Vec<TaxReturnLine> = Vec::new();
+
+        let journal_entries = ledger
+            .active_journal_entries()
+            .filter(|journal_entry| period.contains(journal_entry.date_of_entry()));
+        for journal_entry in journal_entries {
+            for entry in journal_entry.transaction_entries() {
+                let Some(code) = entry.tax_code() else {
+                    continue;
+                };
+                let direction = tax_engine
+                    .get_tax_code(code)
+                    .ok_or(TaxError::UnknownTaxCode(code.to_owned()))?
+                    .direction;
+
+                // Output tax and sales are credits, input tax and purchases debits
+                let amount = match (direction, entry.entry_type()) {
+                    (TaxDirection::Output, EntryType::Credit)
+                    | (TaxDirection::Input, EntryType::Debit) => entry.amount(),
+                    _ => -entry.amount(),
+                };
+                let account_name = entry.account_name();
+                let is_tax = account_name == output_account || account_name == input_account;
+
+                let index = match lines.iter().position(|line| line.code == code) {
+                    Some(index) => index,
+                    None => {
+                        lines.push(TaxReturnLine {
+                            code: code.to_owned(),
+                            direction,
+                            taxable_base: 0f64,
+                            tax: 0f64,
+                            journal_entry_ids: Vec::new(),
+                        });
+                        lines.len() - 1
+                    }
+                };
+                let line = &mut lines[index];
+                if is_tax {
+                    line.tax += amount;
+                } else {
+                    line.taxable_base += amount;
+                }
+                if !line.journal_entry_ids.contains(&journal_entry.id()) {
+                    line.journal_entry_ids.push(journal_entry.id());
+                }
+            }
+        }
+        lines.sort_by(|a, b| a.code.cmp(&b.code));
+
+        Ok(TaxReturn {
+            period: *period,
+            lines,
+        })
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn lines(&self) -> &Vec<TaxReturnLine> {
+        &self.lines
+    }
+
+    pub fn line(&self, code: &str) -> Option<&TaxReturnLine> {
+        self.lines.iter().find(|line| line.code == code)
+    }
+
+    fn total(&self, direction: TaxDirection) -> f64 {
+        round_to_cents(
+            self.lines
+                .iter()
+                .filter(|line| line.direction == direction)
+                .map(|line| line.tax)
+                .sum(),
+        )
+    }
+
+    pub fn output_tax(&self) -> f64 {
+        self.total(TaxDirection::Output)
+    }
+
+    pub fn input_tax(&self) -> f64 {
+        self.total(TaxDirection::Input)
+    }
+
+    ///
+    /// Get the tax due: positive when owed, negative when refundable
+    ///
+    pub fn net_tax(&self) -> f64 {
+        round_to_cents(self.output_tax() - self.input_tax())
+    }
+}
+
+impl Display for TaxReturn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Tax return {}", self.period)?;
+        writeln!(
+            f,
+            "{:<12}{:<10}{:>15}{:>15}",
+            "Code", "", "Taxable base", "Tax"
+        )?;
+
+        for line in self.lines.iter() {
+            let direction = match line.direction {
+                TaxDirection::Output => "Output",
+                TaxDirection::Input => "Input",
+            };
+            writeln!(
+                f,
+                "{:<12}{:<10}{:>15.2}{:>15.2}",
+                line.code,
+                direction,
+                line.taxable_base(),
+                line.tax()
+            )?;
+        }
+
+        let net_tax = self.net_tax();
+        let label = if net_tax < 0f64 { "Refundable" } else { "Due" };
+        writeln!(f, "{:<37}{:>15.2}", label, net_tax.abs())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_tax, Pricing, TaxCode, TaxDirection, TaxEngine, TaxReturn, TaxedLine};
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, test_chart};
+
+    #[test]
+    fn test_split_tax() {
+        assert_eq!(split_tax(100.0, 0.2, Pricing::Exclusive), (100.0, 20.0));
+        assert_eq!(split_tax(120.0, 0.2, Pricing::Inclusive), (100.0, 20.0));
+        assert_eq!(split_tax(10.0, 0.07, Pricing::Inclusive), (9.35, 0.65));
+    }
+
+    #[test]
+    fn test_tax_return() {
+        let chart = test_chart();
+        let mut engine = TaxEngine::new(
+            chart.account("VAT Payable"),
+            chart.account("VAT Receivable"),
+        );
+
+        let mut sales = TaxCode::new("S", "Standard rate sales", TaxDirection::Output);
+        sales.add_rate(0.2, date(2020, 1, 1));
+        sales.add_rate(0.1, date(2024, 3, 1));
+        engine.add_tax_code(sales).unwrap();
+        let mut purchases = TaxCode::new("P", "Standard rate purchases", TaxDirection::Input);
+        purchases.add_rate(0.2, date(2020, 1, 1));
+        engine.add_tax_code(purchases).unwrap();
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 3, 31));
+
+        let sale = engine
+            .journal_entry(
+                &mut ledger,
+                date(2024, 1, 10),
+                "Invoice 1",
+                &chart.account("Accounts Receivable"),
+                &[TaxedLine::new(
+                    &chart.account("Sales Revenue"),
+                    1_000.0,
+                    EntryType::Credit,
+                    Some("S"),
+                )],
+                Pricing::Exclusive,
+            )
+            .unwrap();
+        assert!(sale.validate());
+        assert_eq!(sale.total_debit(), 1_200.0);
+        let sale_id = sale.id();
+        ledger.add_journal_entry(sale);
+
+        let purchase = engine
+            .journal_entry(
+                &mut ledger,
+                date(2024, 1, 20),
+                "Bill 1",
+                &chart.account("Accounts Payable"),
+                &[
+                    TaxedLine::new(
+                        &chart.account("Rent Expense"),
+                        600.0,
+                        EntryType::Debit,
+                        Some("P"),
+                    ),
+                    TaxedLine::new(
+                        &chart.account("Wages Expense"),
+                        50.0,
+                        EntryType::Debit,
+                        None,
+                    ),
+                ],
+                Pricing::Inclusive,
+            )
+            .unwrap();
+        assert!(purchase.validate());
+        let purchase_id = purchase.id();
+        ledger.add_journal_entry(purchase);
+
+        // The new rate applies to the March return of goods
+        let credit_note = engine
+            .journal_entry(
+                &mut ledger,
+                date(2024, 3, 5),
+                "Credit note 1",
+                &chart.account("Accounts Receivable"),
+                &[TaxedLine::new(
+                    &chart.account("Sales Revenue"),
+                    100.0,
+                    EntryType::Debit,
+                    Some("S"),
+                )],
+                Pricing::Exclusive,
+            )
+            .unwrap();
+        assert_eq!(credit_note.total_credit(), 110.0);
+        ledger.add_journal_entry(credit_note);
+
+        let quarter = ReportingPeriod::range(date(2024, 1, 1), date(2024, 3, 31)).unwrap();
+        let tax_return = TaxReturn::build(&engine, &ledger, &quarter).unwrap();
+
+        let output = tax_return.line("S").unwrap();
+        assert_eq!(output.taxable_base(), 900.0);
+        assert_eq!(output.tax(), 190.0);
+        assert_eq!(output.journal_entry_ids().len(), 2);
+        assert!(output.journal_entry_ids().contains(&sale_id));
+
+        let input = tax_return.line("P").unwrap();
+        assert_eq!(input.taxable_base(), 500.0);
+        assert_eq!(input.tax(), 100.0);
+        assert_eq!(input.journal_entry_ids(), &vec![purchase_id]);
+
+        assert_eq!(tax_return.net_tax(), 90.0);
+    }
+
+    #[test]
+    fn test_generated_entries_post() {
+        let chart = test_chart();
+        let mut engine = TaxEngine::new(
+            chart.account("VAT Payable"),
+            chart.account("VAT Receivable"),
+        );
+        let mut sales = TaxCode::new("S", "Reduced rate sales", TaxDirection::Output);
+        sales.add_rate(0.07, date(2020, 1, 1));
+        engine.add_tax_code(sales).unwrap();
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        for cents in 1..=500 {
+            let amount = cents as f64 * 1.37 / 100f64;
+            for pricing in [Pricing::Exclusive, Pricing::Inclusive] {
+                let journal_entry = engine
+                    .journal_entry(
+                        &mut ledger,
+                        date(2024, 1, 10),
+                        "Till sale",
+                        &chart.account("Cash"),
+                        &[
+                            TaxedLine::new(
+                                &chart.account("Sales Revenue"),
+                                amount,
+                                EntryType::Credit,
+                                Some("S"),
+                            ),
+                            TaxedLine::new(
+                                &chart.account("Sales Revenue"),
+                                0.1,
+                                EntryType::Credit,
+                                None,
+                            ),
+                        ],
+                        pricing,
+                    )
+                    .unwrap();
+                ledger.post_journal_entry(journal_entry).unwrap();
+            }
+        }
+        assert_eq!(ledger.number_of_journal_entries(), 1_000);
+    }
+}
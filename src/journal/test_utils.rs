@@ -0,0 +1,200 @@
+use crate::journal::accounting_tree::{
+    AccountNode, AccountNodeRef, AccountTagNode, AccountTree, ActionType, ParentNodeT,
+    PrimaryAccountType, RootNode, RootNodeRef,
+};
+use crate::journal::ledger::{EntryType, JournalEntry, TransactionEntry};
+use chrono::{DateTime, TimeZone, Utc};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+///
+/// An `AccountTree` together with its terminal accounts indexed by name
+///
+pub(crate) struct TestChart {
+    tree: AccountTree,
+    accounts: HashMap<String, AccountNodeRef>,
+}
+
+impl TestChart {
+    pub(crate) fn tree(&self) -> AccountTree {
+        self.tree.clone()
+    }
+
+    pub(crate) fn account(&self, name: &str) -> AccountNodeRef {
+        self.accounts
+            .get(name)
+            .unwrap_or_else(|| panic!("No account named {:?} in the test chart", name))
+            .clone()
+    }
+}
+
+///
+/// `(primary type, debit normal, [(tag, [accounts])])` tuples describing a three level chart
+///
+pub(crate) type ChartSpec<'a> = [(&'a str, bool, &'a [(&'a str, &'a [&'a str])])];
+
+///
+/// Build a three level chart from a `ChartSpec`
+///
+pub(crate) fn build_chart(spec: &ChartSpec) -> TestChart {
+    let root: RootNodeRef = Rc::new(RefCell::new(RootNode::new()));
+    let mut accounts = HashMap::new();
+
+    for (primary_name, debit_normal, tags) in spec {
+        let primary_type = if *debit_normal {
+            PrimaryAccountType::new(primary_name, ActionType::Increase, ActionType::Decrease)
+        } else {
+            PrimaryAccountType::new(primary_name, ActionType::Decrease, ActionType::Increase)
+        };
+
+        let primary_node = Rc::new(RefCell::new(AccountTagNode::new(
+            1,
+            primary_name,
+            Some(root.clone()),
+            Some(Rc::new(primary_type)),
+        )));
+        root.borrow_mut().add_child(primary_node.clone());
+
+        for (tag_name, account_names) in tags.iter() {
+            let tag_node = Rc::new(RefCell::new(AccountTagNode::new(
+                2,
+                tag_name,
+                Some(primary_node.clone()),
+                None,
+            )));
+            primary_node.borrow_mut().add_child(tag_node.clone());
+
+            for account_name in account_names.iter() {
+                let account = Rc::new(RefCell::new(AccountNode::new(
+                    3,
+                    account_name,
+                    Some(tag_node.clone()),
+                )));
+                tag_node.borrow_mut().add_child(account.clone());
+                accounts.insert(account_name.to_string(), account);
+            }
+        }
+    }
+
+    TestChart {
+        tree: AccountTree::new(root),
+        accounts,
+    }
+}
+
+///
+/// A small chart of accounts with the usual primary account types
+///
+pub(crate) fn test_chart() -> TestChart {
+    build_chart(&[
+        (
+            "Assets",
+            true,
+            &[
+                (
+                    "Current Assets",
+                    &["Cash", "Accounts Receivable", "Inventory", "VAT Receivable"],
+                ),
+                (
+                    "Non Current Assets",
+                    &["Equipment", "Accumulated Depreciation"],
+                ),
+            ],
+        ),
+        (
+            "Liabilities",
+            false,
+            &[
+                (
+                    "Current Liabilities",
+                    &[
+                        "Accounts Payable",
+                        "Accrued Liabilities",
+                        "VAT Payable",
+                        "Federal Income Tax Payable",
+                        "Federal Unemployment Tax Payable",
+                        "Health/Child Flex Payable",
+                        "Short Term Loan",
+                    ],
+                ),
+                (
+                    "Non Current Liabilities",
+                    &[
+                        "Notes Payable",
+                        "Bonds Payable",
+                        "Discount on Bonds Payable",
+                    ],
+                ),
+            ],
+        ),
+        (
+            "Equity",
+            false,
+            &[
+                ("Owner's Equity", &["Common Stock", "Retained Earnings"]),
+                (
+                    "Group Equity",
+                    &["Translation Adjustment", "Non-Controlling Interest"],
+                ),
+            ],
+        ),
+        (
+            "Revenue",
+            false,
+            &[
+                ("Operating Revenue", &["Sales Revenue"]),
+                ("Other Income", &["Gain on Disposal"]),
+            ],
+        ),
+        (
+            "Expenses",
+            true,
+            &[
+                (
+                    "Operating Expenses",
+                    &[
+                        "Cost of Goods Sold",
+                        "Rent Expense",
+                        "Wages Expense",
+                        "Depreciation Expense",
+                        "Payroll Tax Expense",
+                        "Employee Benefits Expense",
+                    ],
+                ),
+                (
+                    "Other Expenses",
+                    &["Interest Expense", "Non-Controlling Interest in Income"],
+                ),
+            ],
+        ),
+    ])
+}
+
+pub(crate) fn date(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+}
+
+///
+/// Build a `JournalEntry` from `(account, amount, entry type)` lines.
+/// Transaction entry ids are derived from the journal entry id.
+///
+pub(crate) fn journal_entry(
+    id: usize,
+    date_of_entry: DateTime<Utc>,
+    description: &str,
+    lines: &[(&AccountNodeRef, f64, EntryType)],
+) -> JournalEntry {
+    let mut journal_entry = JournalEntry::new(id, date_of_entry, description);
+
+    for (index, (account, amount, entry_type)) in lines.iter().enumerate() {
+        journal_entry.add_transaction_entry(Rc::new(TransactionEntry::new(
+            id * 100 + index,
+            (*account).clone(),
+            *amount,
+            *entry_type,
+            date_of_entry,
+            description,
+        )));
+    }
+
+    journal_entry
+}
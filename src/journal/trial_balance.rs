@@ -0,0 +1,396 @@
+use crate::journal::accounting_equation::DEFAULT_TOLERANCE;
+use crate::journal::accounting_tree::{AccountTree, SubtotalNode};
+use crate::journal::balance_sheet::BalanceSheetError;
+use crate::journal::dimensions::{dimension_values, DimensionFilter};
+use crate::journal::ledger::{EntryType, JournalEntry, Ledger};
+use crate::journal::period::ReportingPeriod;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+///
+/// A line of a `TrialBalance`. Balances are signed with debits positive and credits negative.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrialBalanceRow {
+    account_name: String,
+    unadjusted: f64,
+    adjustments: f64,
+}
+
+impl TrialBalanceRow {
+    pub fn account_name(&self) -> &str {
+        &self.account_name
+    }
+
+    ///
+    /// Get the balance before the adjusting entries
+    ///
+    pub fn unadjusted(&self) -> f64 {
+        self.unadjusted
+    }
+
+    ///
+    /// Get the net of the adjusting entries
+    ///
+    pub fn adjustments(&self) -> f64 {
+        self.adjustments
+    }
+
+    pub fn adjusted(&self) -> f64 {
+        self.unadjusted + self.adjustments
+    }
+}
+
+///
+/// Split a signed balance into its debit and credit columns
+///
+fn columns(balance: f64) -> (f64, f64) {
+    if balance >= 0f64 {
+        (balance, 0f64)
+    } else {
+        (0f64, -balance)
+    }
+}
+
+///
+/// `TrialBalance` lists the balance of every account with activity over a period,
+/// in the order of the `AccountTree`: unadjusted, the adjusting entries, and adjusted
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrialBalance {
+    period: ReportingPeriod,
+    rows: Vec<TrialBalanceRow>,
+}
+
+impl TrialBalance {
+    ///
+    /// Build the trial balance of the journal entries of `ledger` within `period`.
+    /// Adjusting entries, including auto-reversing entries and their reversals, go
+    /// to the adjustments column.
+    ///
+    pub fn build(
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        period: &ReportingPeriod,
+    ) -> Result<Self, BalanceSheetError> {
+        Self::build_filtered(accounting_tree, ledger, period, &DimensionFilter::new())
+    }
+
+    ///
+    /// Build the trial balance of the transaction entries `filter` selects
+    ///
+    pub fn build_filtered(
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        period: &ReportingPeriod,
+        filter: &DimensionFilter,
+    ) -> Result<Self, BalanceSheetError> {
+        let mut unadjusted: HashMap<String, f64> = HashMap::new();
+        let mut adjustments: HashMap<String, f64> = HashMap::new();
+
+        let journal_entries = ledger
+            .active_journal_entries()
+            .filter(|journal_entry| period.contains(journal_entry.date_of_entry()));
+        for journal_entry in journal_entries {
+            let balances = if journal_entry.is_adjusting() {
+                &mut adjustments
+            } else {
+                &mut unadjusted
+            };
+            add_balances(balances, journal_entry, filter);
+        }
+
+        // Leaves of the tree in depth first order
+        let account_names: Vec<String> =
+            SubtotalNode::from_tree(accounting_tree.root(), &HashMap::new())
+                .leaves()
+                .iter()
+                .map(|leaf| leaf.name().to_owned())
+                .collect();
+
+        for account_name in unadjusted.keys().chain(adjustments.keys()) {
+            if !account_names.contains(account_name) {
+                return Err(BalanceSheetError::UnknownAccount(account_name.to_owned()));
+            }
+        }
+
+        let rows = account_names
+            .into_iter()
+            .filter(|name| unadjusted.contains_key(name) || adjustments.contains_key(name))
+            .map(|name| TrialBalanceRow {
+                unadjusted: unadjusted.get(&name).cloned().unwrap_or(0f64),
+                adjustments: adjustments.get(&name).cloned().unwrap_or(0f64),
+                account_name: name,
+            })
+            .collect();
+
+        Ok(TrialBalance {
+            period: *period,
+            rows,
+        })
+    }
+
+    ///
+    /// Build a trial balance for each value `dimension` takes in `ledger`
+    ///
+    pub fn build_grouped(
+        accounting_tree: &AccountTree,
+        ledger: &Ledger,
+        period: &ReportingPeriod,
+        dimension: &str,
+    ) -> Result<Vec<(Option<String>, Self)>, BalanceSheetError> {
+        dimension_values(ledger, dimension)
+            .into_iter()
+            .map(|value| {
+                let filter = DimensionFilter::new().and(dimension, value.as_deref());
+                Self::build_filtered(accounting_tree, ledger, period, &filter)
+                    .map(|trial_balance| (value, trial_balance))
+            })
+            .collect()
+    }
+
+    pub fn period(&self) -> &ReportingPeriod {
+        &self.period
+    }
+
+    pub fn rows(&self) -> &Vec<TrialBalanceRow> {
+        &self.rows
+    }
+
+    pub fn row(&self, account_name: &str) -> Option<&TrialBalanceRow> {
+        self.rows
+            .iter()
+            .find(|row| row.account_name.eq_ignore_ascii_case(account_name))
+    }
+
+    ///
+    /// Get the total debits and credits of the unadjusted balances
+    ///
+    pub fn unadjusted_totals(&self) -> (f64, f64) {
+        self.totals(|row| row.unadjusted)
+    }
+
+    ///
+    /// Get the total debits and credits of the adjusted balances
+    ///
+    pub fn adjusted_totals(&self) -> (f64, f64) {
+        self.totals(|row| row.adjusted())
+    }
+
+    fn totals(&self, balance: impl Fn(&TrialBalanceRow) -> f64) -> (f64, f64) {
+        self.rows
+            .iter()
+            .fold((0f64, 0f64), |(debits, credits), row| {
+                let (debit, credit) = columns(balance(row));
+                (debits + debit, credits + credit)
+            })
+    }
+
+    ///
+    /// Check that the adjusted debits equal the adjusted credits
+    ///
+    pub fn is_balanced(&self) -> bool {
+        let (debits, credits) = self.adjusted_totals();
+
+        (debits - credits).abs() <= DEFAULT_TOLERANCE
+    }
+}
+
+fn add_balances(
+    balances: &mut HashMap<String, f64>,
+    journal_entry: &JournalEntry,
+    filter: &DimensionFilter,
+) {
+    for entry in journal_entry
+        .transaction_entries()
+        .iter()
+        .filter(|entry| filter.matches(entry))
+    {
+        let amount = match entry.entry_type() {
+            EntryType::Debit => entry.amount(),
+            EntryType::Credit => -entry.amount(),
+        };
+
+        *balances.entry(entry.account_name()).or_insert(0f64) += amount;
+    }
+}
+
+impl Display for TrialBalance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Trial balance {}", self.period)?;
+        writeln!(
+            f,
+            "{:<30}{:>24}{:>24}{:>24}",
+            "", "Unadjusted", "Adjustments", "Adjusted"
+        )?;
+        writeln!(
+            f,
+            "{:<30}{:>12}{:>12}{:>12}{:>12}{:>12}{:>12}",
+            "Account", "Debit", "Credit", "Debit", "Credit", "Debit", "Credit"
+        )?;
+
+        let write_columns = |f: &mut std::fmt::Formatter<'_>, balance: f64| {
+            let (debit, credit) = columns(balance);
+            write!(f, "{:>12.2}{:>12.2}", debit, credit)
+        };
+
+        for row in self.rows.iter() {
+            write!(f, "{:<30}", row.account_name)?;
+            write_columns(f, row.unadjusted)?;
+            write_columns(f, row.adjustments)?;
+            write_columns(f, row.adjusted())?;
+            writeln!(f)?;
+        }
+
+        let (unadjusted_debits, unadjusted_credits) = self.unadjusted_totals();
+        let (adjusted_debits, adjusted_credits) = self.adjusted_totals();
+        writeln!(
+            f,
+            "{:<30}{:>12.2}{:>12.2}{:>24}{:>12.2}{:>12.2}",
+            "Total", unadjusted_debits, unadjusted_credits, "", adjusted_debits, adjusted_credits
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TrialBalance;
+    use crate::journal::ledger::{EntryType, Ledger};
+    use crate::journal::period::ReportingPeriod;
+    use crate::journal::test_utils::{date, journal_entry, test_chart};
+
+    #[test]
+    fn test_auto_reversing_accrual() {
+        let chart = test_chart();
+        let cash = chart.account("Cash");
+        let wages = chart.account("Wages Expense");
+        let accrued = chart.account("Accrued Liabilities");
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 1, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 1, 15),
+            "January wages",
+            &[
+                (&wages, 1_000.0, EntryType::Debit),
+                (&cash, 1_000.0, EntryType::Credit),
+            ],
+        ));
+
+        let mut accrual = journal_entry(
+            2,
+            date(2024, 1, 31),
+            "Accrued wages",
+            &[
+                (&wages, 400.0, EntryType::Debit),
+                (&accrued, 400.0, EntryType::Credit),
+            ],
+        );
+        accrual.set_auto_reversing(true);
+        ledger.add_journal_entry(accrual);
+
+        // The reversal falls after the ledger's dates and waits until they cover it
+        assert_eq!(ledger.number_of_journal_entries(), 2);
+        assert_eq!(ledger.scheduled_reversals().len(), 1);
+
+        let january = ReportingPeriod::range(date(2024, 1, 1), date(2024, 1, 31)).unwrap();
+        let trial_balance = TrialBalance::build(&chart.tree(), &ledger, &january).unwrap();
+        let wages_row = trial_balance.row("Wages Expense").unwrap();
+        assert_eq!(wages_row.unadjusted(), 1_000.0);
+        assert_eq!(wages_row.adjustments(), 400.0);
+        assert_eq!(wages_row.adjusted(), 1_400.0);
+        assert_eq!(trial_balance.adjusted_totals(), (1_400.0, 1_400.0));
+        assert!(trial_balance.is_balanced());
+
+        ledger.set_to_date(date(2024, 2, 29));
+        assert!(ledger.scheduled_reversals().is_empty());
+
+        let reversal = ledger.get_reversal_of(2).unwrap();
+        assert_eq!(reversal.date_of_entry(), date(2024, 2, 1));
+        assert_eq!(reversal.total_debit(), 400.0);
+        assert!(reversal.is_adjusting());
+
+        let february = ReportingPeriod::range(date(2024, 2, 1), date(2024, 2, 29)).unwrap();
+        let trial_balance = TrialBalance::build(&chart.tree(), &ledger, &february).unwrap();
+        assert_eq!(
+            trial_balance
+                .row("Accrued Liabilities")
+                .unwrap()
+                .adjustments(),
+            400.0
+        );
+        assert_eq!(
+            trial_balance.row("Wages Expense").unwrap().adjusted(),
+            -400.0
+        );
+
+        // Voiding the accrual voids its reversal too
+        ledger.void_journal_entry(2).unwrap();
+        assert!(ledger.get_reversal_of(2).unwrap().is_voided());
+        let trial_balance = TrialBalance::build(&chart.tree(), &ledger, &february).unwrap();
+        assert!(trial_balance.row("Accrued Liabilities").is_none());
+    }
+
+    #[test]
+    fn test_restated_period_as_recorded() {
+        let chart = test_chart();
+        let (cash, sales) = (chart.account("Cash"), chart.account("Sales Revenue"));
+        let (wages, accrued) = (
+            chart.account("Wages Expense"),
+            chart.account("Accrued Liabilities"),
+        );
+
+        let mut ledger = Ledger::new(1, date(2024, 1, 1), date(2024, 12, 31));
+        ledger.set_clock(|| date(2024, 3, 31));
+        ledger.add_journal_entry(journal_entry(
+            1,
+            date(2024, 3, 10),
+            "Cash sale",
+            &[
+                (&cash, 900.0, EntryType::Debit),
+                (&sales, 900.0, EntryType::Credit),
+            ],
+        ));
+        ledger.add_journal_entry(journal_entry(
+            2,
+            date(2024, 3, 20),
+            "Cash sale",
+            &[
+                (&cash, 100.0, EntryType::Debit),
+                (&sales, 100.0, EntryType::Credit),
+            ],
+        ));
+
+        // March is restated in April: wages left out and a sale posted twice
+        ledger.set_clock(|| date(2024, 4, 15));
+        ledger.add_journal_entry(journal_entry(
+            3,
+            date(2024, 3, 31),
+            "Unrecorded March wages",
+            &[
+                (&wages, 250.0, EntryType::Debit),
+                (&accrued, 250.0, EntryType::Credit),
+            ],
+        ));
+        ledger.void_journal_entry(2).unwrap();
+        assert_eq!(
+            ledger
+                .get_journal_entries_recorded_between(date(2024, 4, 1), date(2024, 4, 30))
+                .len(),
+            1
+        );
+
+        let march = ReportingPeriod::range(date(2024, 3, 1), date(2024, 3, 31)).unwrap();
+        let restated = TrialBalance::build(&chart.tree(), &ledger, &march).unwrap();
+        assert_eq!(restated.row("Sales Revenue").unwrap().adjusted(), -900.0);
+        assert_eq!(restated.row("Wages Expense").unwrap().adjusted(), 250.0);
+
+        let as_closed = ledger.as_recorded_at(date(2024, 4, 1));
+        assert_eq!(as_closed.number_of_journal_entries(), 2);
+        assert!(as_closed.verify_chain().is_ok());
+        let original = TrialBalance::build(&chart.tree(), &as_closed, &march).unwrap();
+        assert_eq!(original.row("Sales Revenue").unwrap().adjusted(), -1_000.0);
+        assert!(original.row("Wages Expense").is_none());
+        assert!(original.is_balanced());
+    }
+}
@@ -1 +1 @@
-pub mod journal;
\ No newline at end of file
+pub mod journal;